@@ -3,11 +3,15 @@ use std::collections::BTreeMap;
 use melior::{
     dialect::{
         arith, cf, func,
-        llvm::{self, r#type::pointer, AllocaOptions, LoadStoreOptions},
+        llvm::{
+            self,
+            r#type::{array, pointer},
+            AllocaOptions, LoadStoreOptions,
+        },
     },
     ir::{
-        attribute::{IntegerAttribute, TypeAttribute},
-        r#type::IntegerType,
+        attribute::{DenseElementsAttribute, IntegerAttribute, TypeAttribute},
+        r#type::{IntegerType, RankedTensorType},
         Block, BlockRef, Location, Module, Region, Value,
     },
     Context as MeliorContext,
@@ -15,41 +19,70 @@ use melior::{
 
 use crate::{
     constants::{
-        CALLDATA_PTR_GLOBAL, CALLDATA_SIZE_GLOBAL, GAS_COUNTER_GLOBAL, MAX_STACK_SIZE,
-        MEMORY_PTR_GLOBAL, MEMORY_SIZE_GLOBAL, STACK_BASEPTR_GLOBAL, STACK_PTR_GLOBAL,
+        gas_cost::GasSchedule, CALLDATA_PTR_GLOBAL, CALLDATA_SIZE_GLOBAL, CODE_GLOBAL,
+        GAS_COUNTER_GLOBAL, MAX_STACK_SIZE, MEMORY_PTR_GLOBAL, MEMORY_SIZE_GLOBAL,
+        STACK_BASEPTR_GLOBAL, STACK_PTR_GLOBAL,
     },
     errors::CodegenError,
-    program::{Operation, Program},
+    program::{Opcode, Program},
+    spec::Spec,
     syscall::{self, ExitStatusCode},
-    utils::{get_remaining_gas, integer_constant_from_u8, llvm_mlir},
+    utils::{integer_constant_from_u8, llvm_mlir},
 };
 
+/// How many 32-byte values an operation can pass by pointer to the syscalls at once,
+/// which is enough for the four topics of LOG4.
+pub(crate) const U256_SLOTS: usize = 4;
+
 #[derive(Debug, Clone)]
 pub(crate) struct OperationCtx<'c> {
     /// The MLIR context.
     pub mlir_context: &'c MeliorContext,
     /// The program IR.
     pub program: &'c Program,
+    /// The spec the program is compiled for.
+    pub spec: Spec,
+    /// The static cost of each opcode.
+    pub gas_schedule: GasSchedule,
+    /// The most values the stack can hold.
+    pub stack_size: u32,
+    /// Whether ADD, SUB and MUL report their overflows to the syscall context.
+    pub instrument_overflow: bool,
     /// The syscall context to be passed to syscalls.
     pub syscall_ctx: Value<'c, 'c>,
-    /// Reference to the revert block.
-    /// This block takes care of reverts.
-    pub revert_block: BlockRef<'c, 'c>,
+    /// Whether a tracer is installed, failures are captured, opcodes counted or a step
+    /// limit is set in the syscall context, as an `i1`.
+    /// It's read once at the start, so steps aren't reported at all otherwise.
+    pub tracing_flag: Value<'c, 'c>,
+    /// Reference to the error block.
+    /// This block takes care of exceptional halts, such as stack errors or running out of gas.
+    pub error_block: BlockRef<'c, 'c>,
     /// Reference to the jump table block.
     /// This block receives the PC as an argument and jumps to the block corresponding to that PC,
-    /// or reverts in case the destination is not a JUMPDEST.
+    /// or halts in case the destination is not a JUMPDEST.
     pub jumptable_block: BlockRef<'c, 'c>,
     /// Blocks to jump to. These are registered dynamically as JUMPDESTs are processed.
     pub jumpdest_blocks: BTreeMap<usize, BlockRef<'c, 'c>>,
+    /// Slots for the 32-byte values passed by pointer to the syscalls, such as storage keys,
+    /// addresses or log topics. They're allocated once in the setup block, so operations
+    /// inside loops don't keep growing the native stack.
+    pub u256_slots: [Value<'c, 'c>; U256_SLOTS],
+    /// Slot for the gas a nested call or creation gives back, allocated like [`Self::u256_slots`].
+    pub gas_slot: Value<'c, 'c>,
 }
 
 impl<'c> OperationCtx<'c> {
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn new(
         context: &'c MeliorContext,
         module: &'c Module,
         region: &'c Region,
         setup_block: &'c Block<'c>,
         program: &'c Program,
+        spec: Spec,
+        gas_schedule: GasSchedule,
+        stack_size: u32,
+        instrument_overflow: bool,
     ) -> Result<Self, CodegenError> {
         let location = Location::unknown(context);
         let ptr_type = pointer(context, 0);
@@ -59,66 +92,98 @@ impl<'c> OperationCtx<'c> {
         let initial_gas = setup_block.add_argument(uint64, location);
 
         // Append setup code to be run at the start
-        generate_stack_setup_code(context, module, setup_block)?;
+        generate_stack_setup_code(context, module, setup_block, syscall_ctx, stack_size)?;
         generate_memory_setup_code(context, module, setup_block)?;
         generate_calldata_setup_code(context, module, setup_block)?;
+        generate_code_setup_code(context, module, program)?;
         generate_gas_counter_setup_code(context, module, setup_block, initial_gas)?;
+        let u256_slots = (0..U256_SLOTS)
+            .map(|_| generate_slot(context, setup_block, 256))
+            .collect::<Result<Vec<_>, _>>()?
+            .try_into()
+            .expect("one slot was generated for each");
+        let gas_slot = generate_slot(context, setup_block, 64)?;
 
         syscall::mlir::declare_syscalls(context, module);
 
+        let is_tracing =
+            syscall::mlir::is_tracing_syscall(context, syscall_ctx, setup_block, location)?;
+        let zero = setup_block
+            .append_operation(arith::constant(
+                context,
+                integer_constant_from_u8(context, 0).into(),
+                location,
+            ))
+            .result(0)?
+            .into();
+        let tracing_flag = setup_block
+            .append_operation(arith::cmpi(
+                context,
+                arith::CmpiPredicate::Ne,
+                is_tracing,
+                zero,
+                location,
+            ))
+            .result(0)?
+            .into();
+
         // Generate helper blocks
-        let revert_block = region.append_block(generate_revert_block(context, syscall_ctx)?);
+        let error_block = region.append_block(generate_error_block(context, syscall_ctx)?);
         let jumptable_block = region.append_block(create_jumptable_landing_block(context));
 
         let op_ctx = OperationCtx {
             mlir_context: context,
             program,
+            spec,
+            gas_schedule,
+            stack_size,
+            instrument_overflow,
             syscall_ctx,
-            revert_block,
+            tracing_flag,
+            error_block,
             jumptable_block,
             jumpdest_blocks: Default::default(),
+            u256_slots,
+            gas_slot,
         };
         Ok(op_ctx)
     }
 
+    /// Returns the static cost of `opcode` in the program's gas schedule.
+    pub(crate) fn gas_cost(&self, opcode: Opcode) -> i64 {
+        self.gas_schedule.cost(opcode as u8)
+    }
+
     /// Populate the jumptable block with a dynamic dispatch according to the
-    /// received PC.
+    /// received PC. Any PC that isn't a JUMPDEST falls through to the error block.
     pub(crate) fn populate_jumptable(&self) -> Result<(), CodegenError> {
         let context = self.mlir_context;
-        let program = self.program;
         let start_block = self.jumptable_block;
 
         let location = Location::unknown(context);
         let uint256 = IntegerType::new(context, 256);
 
         // The block receives a single argument: the value to switch on
-        // TODO: move to program module
-        let jumpdest_pcs: Vec<i64> = program
-            .operations
-            .iter()
-            .filter_map(|op| match op {
-                Operation::Jumpdest { pc } => Some(*pc as i64),
-                _ => None,
-            })
-            .collect();
-
         let arg = start_block.argument(0)?;
 
-        let case_destinations: Vec<_> = self
+        // NOTE: the case values and destinations must come from the same
+        // (ordered) map, so each JUMPDEST's PC is paired with its own block.
+        // This also covers a JUMPDEST at PC 0 and several adjacent JUMPDESTs.
+        let (jumpdest_pcs, case_destinations): (Vec<i64>, Vec<_>) = self
             .jumpdest_blocks
-            .values()
-            .map(|b| {
+            .iter()
+            .map(|(pc, b)| {
                 let x: (&Block, &[Value]) = (b, &[]);
-                x
+                (*pc as i64, x)
             })
-            .collect();
+            .unzip();
 
         let op = start_block.append_operation(cf::switch(
             context,
             &jumpdest_pcs,
             arg.into(),
             uint256.into(),
-            (&self.revert_block, &[]),
+            (&self.error_block, &[]),
             &case_destinations,
             location,
         )?);
@@ -131,6 +196,10 @@ impl<'c> OperationCtx<'c> {
     /// Registers a block as a valid jump destination.
     // TODO: move into jumptable module
     pub(crate) fn register_jump_destination(&mut self, pc: usize, block: BlockRef<'c, 'c>) {
+        debug_assert!(
+            self.program.valid_jumpdests.contains(&pc),
+            "{pc} isn't the offset of a JUMPDEST opcode"
+        );
         self.jumpdest_blocks.insert(pc, block);
     }
 
@@ -190,10 +259,51 @@ fn generate_gas_counter_setup_code<'c>(
     Ok(())
 }
 
+/// Allocates a slot for an integer of `bits` bits, to be passed to the syscalls by pointer
+/// by every operation.
+fn generate_slot<'c>(
+    context: &'c MeliorContext,
+    block: &'c Block<'c>,
+    bits: u32,
+) -> Result<Value<'c, 'c>, CodegenError> {
+    let location = Location::unknown(context);
+    let ptr_type = pointer(context, 0);
+    let uint32 = IntegerType::new(context, 32);
+    let uint64 = IntegerType::new(context, 64);
+    let slot_type = IntegerType::new(context, bits);
+
+    let number_of_elements = block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint32.into(), 1).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    // NOTE: the syscalls receive a `U256`, which is 16-byte aligned
+    let slot_ptr = block
+        .append_operation(llvm::alloca(
+            context,
+            number_of_elements,
+            ptr_type,
+            location,
+            AllocaOptions::new()
+                .align(IntegerAttribute::new(uint64.into(), 16).into())
+                .elem_type(Some(TypeAttribute::new(slot_type.into()))),
+        ))
+        .result(0)?
+        .into();
+
+    Ok(slot_ptr)
+}
+
 fn generate_stack_setup_code<'c>(
     context: &'c MeliorContext,
     module: &'c Module,
     block: &'c Block<'c>,
+    syscall_ctx: Value<'c, 'c>,
+    stack_size: u32,
 ) -> Result<(), CodegenError> {
     let location = Location::unknown(context);
     let ptr_type = pointer(context, 0);
@@ -217,25 +327,27 @@ fn generate_stack_setup_code<'c>(
 
     let uint256 = IntegerType::new(context, 256);
 
-    // Allocate stack memory
-    let stack_size = block
+    // Allocate stack memory. It always fits MAX_STACK_SIZE values, since that's how many
+    // the initial stack can have, even if a smaller stack is configured.
+    // It's owned by the syscall context, since a big stack wouldn't fit in the native one.
+    let allocated_size = stack_size.max(MAX_STACK_SIZE as u32);
+    let allocated_size = block
         .append_operation(arith::constant(
             context,
-            IntegerAttribute::new(uint256.into(), MAX_STACK_SIZE as i64).into(),
+            IntegerAttribute::new(IntegerType::new(context, 32).into(), allocated_size as i64)
+                .into(),
             location,
         ))
         .result(0)?
         .into();
 
-    let stack_baseptr = block
-        .append_operation(llvm::alloca(
-            context,
-            stack_size,
-            ptr_type,
-            location,
-            AllocaOptions::new().elem_type(Some(TypeAttribute::new(uint256.into()))),
-        ))
-        .result(0)?;
+    let stack_baseptr = syscall::mlir::allocate_stack_syscall(
+        context,
+        syscall_ctx,
+        block,
+        allocated_size,
+        location,
+    )?;
 
     // Populate the globals with the allocated stack memory
     let stack_baseptr_ptr = block
@@ -256,6 +368,25 @@ fn generate_stack_setup_code<'c>(
     ));
     assert!(res.verify());
 
+    // Seed the stack with the initial values from the syscall context, if any
+    let initial_stack_size = syscall::mlir::copy_initial_stack_syscall(
+        context,
+        syscall_ctx,
+        block,
+        stack_baseptr.into(),
+        location,
+    )?;
+    let stack_ptr = block
+        .append_operation(llvm::get_element_ptr_dynamic(
+            context,
+            stack_baseptr.into(),
+            &[initial_stack_size],
+            uint256.into(),
+            ptr_type,
+            location,
+        ))
+        .result(0)?;
+
     let stackptr_ptr = block
         .append_operation(llvm_mlir::addressof(
             context,
@@ -267,7 +398,7 @@ fn generate_stack_setup_code<'c>(
 
     let res = block.append_operation(llvm::store(
         context,
-        stack_baseptr.into(),
+        stack_ptr.into(),
         stackptr_ptr.into(),
         location,
         LoadStoreOptions::default(),
@@ -388,6 +519,42 @@ fn generate_calldata_setup_code<'c>(
     Ok(())
 }
 
+/// Embeds the program's bytecode in the module as a constant global,
+/// so it can be read by CODECOPY.
+fn generate_code_setup_code<'c>(
+    context: &'c MeliorContext,
+    module: &'c Module,
+    program: &Program,
+) -> Result<(), CodegenError> {
+    let location = Location::unknown(context);
+    let uint8 = IntegerType::new(context, 8).into();
+
+    let bytecode = program.to_bytecode();
+    // Empty code runs as an immediate STOP, so there's no CODECOPY to read the global.
+    // It's left out instead of being emitted as a zero-length array.
+    if bytecode.is_empty() {
+        return Ok(());
+    }
+
+    let bytes: Vec<_> = bytecode
+        .iter()
+        .map(|byte| IntegerAttribute::new(uint8, *byte as i64).into())
+        .collect();
+    let tensor_type = RankedTensorType::new(&[bytecode.len() as u64], uint8, None);
+    let value = DenseElementsAttribute::new(tensor_type.into(), &bytes)?;
+
+    let res = module.body().append_operation(llvm_mlir::global_constant(
+        context,
+        CODE_GLOBAL,
+        array(uint8, bytecode.len() as u32),
+        value.into(),
+        location,
+    ));
+    assert!(res.verify());
+
+    Ok(())
+}
+
 /// Create the jumptable landing block. This is the main entrypoint
 /// for JUMP and JUMPI operations.
 fn create_jumptable_landing_block(context: &MeliorContext) -> Block {
@@ -396,17 +563,19 @@ fn create_jumptable_landing_block(context: &MeliorContext) -> Block {
     Block::new(&[(uint256.into(), location)])
 }
 
-pub fn generate_revert_block<'c>(
+/// Generates the block for exceptional halts (stack underflow or overflow, out of gas,
+/// invalid operations, etc.). Unlike REVERT, these consume all the remaining gas.
+pub fn generate_error_block<'c>(
     context: &'c MeliorContext,
     syscall_ctx: Value<'c, 'c>,
 ) -> Result<Block<'c>, CodegenError> {
     let location = Location::unknown(context);
     let uint32 = IntegerType::new(context, 32).into();
+    let uint64 = IntegerType::new(context, 64).into();
 
-    let revert_block = Block::new(&[]);
-    let remaining_gas = get_remaining_gas(context, &revert_block)?;
+    let error_block = Block::new(&[]);
 
-    let zero_constant = revert_block
+    let zero_constant = error_block
         .append_operation(arith::constant(
             context,
             IntegerAttribute::new(uint32, 0).into(),
@@ -415,7 +584,17 @@ pub fn generate_revert_block<'c>(
         .result(0)?
         .into();
 
-    let reason = revert_block
+    // All the remaining gas is consumed
+    let zero_gas = error_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint64, 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let reason = error_block
         .append_operation(arith::constant(
             context,
             integer_constant_from_u8(context, ExitStatusCode::Error.to_u8()).into(),
@@ -427,17 +606,17 @@ pub fn generate_revert_block<'c>(
     syscall::mlir::write_result_syscall(
         context,
         syscall_ctx,
-        &revert_block,
+        &error_block,
         zero_constant,
         zero_constant,
-        remaining_gas,
+        zero_gas,
         reason,
         location,
     );
 
-    revert_block.append_operation(func::r#return(&[reason], location));
+    error_block.append_operation(func::r#return(&[reason], location));
 
-    Ok(revert_block)
+    Ok(error_block)
 }
 
 // Syscall MLIR wrappers
@@ -463,6 +642,30 @@ impl<'c> OperationCtx<'c> {
         )
     }
 
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn trace_step_syscall(
+        &'c self,
+        block: &'c Block,
+        pc: Value<'c, 'c>,
+        opcode: Value<'c, 'c>,
+        gas: Value<'c, 'c>,
+        stack_baseptr: Value<'c, 'c>,
+        stack_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        syscall::mlir::trace_step_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            pc,
+            opcode,
+            gas,
+            stack_baseptr,
+            stack_ptr,
+            location,
+        )
+    }
+
     pub(crate) fn get_calldata_size_syscall(
         &'c self,
         block: &'c Block,
@@ -497,7 +700,7 @@ impl<'c> OperationCtx<'c> {
         data: Value<'c, 'c>,
         size: Value<'c, 'c>,
         location: Location<'c>,
-    ) {
+    ) -> Result<Value, CodegenError> {
         syscall::mlir::append_log_syscall(
             self.mlir_context,
             self.syscall_ctx,
@@ -505,7 +708,7 @@ impl<'c> OperationCtx<'c> {
             data,
             size,
             location,
-        );
+        )
     }
 
     pub(crate) fn append_log_with_one_topic_syscall(
@@ -515,7 +718,7 @@ impl<'c> OperationCtx<'c> {
         size: Value<'c, 'c>,
         topic: Value<'c, 'c>,
         location: Location<'c>,
-    ) {
+    ) -> Result<Value, CodegenError> {
         syscall::mlir::append_log_with_one_topic_syscall(
             self.mlir_context,
             self.syscall_ctx,
@@ -524,7 +727,7 @@ impl<'c> OperationCtx<'c> {
             size,
             topic,
             location,
-        );
+        )
     }
 
     pub(crate) fn append_log_with_two_topics_syscall(
@@ -535,7 +738,7 @@ impl<'c> OperationCtx<'c> {
         topic1_ptr: Value<'c, 'c>,
         topic2_ptr: Value<'c, 'c>,
         location: Location<'c>,
-    ) {
+    ) -> Result<Value, CodegenError> {
         syscall::mlir::append_log_with_two_topics_syscall(
             self.mlir_context,
             self.syscall_ctx,
@@ -545,7 +748,7 @@ impl<'c> OperationCtx<'c> {
             topic1_ptr,
             topic2_ptr,
             location,
-        );
+        )
     }
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn append_log_with_three_topics_syscall(
@@ -557,7 +760,7 @@ impl<'c> OperationCtx<'c> {
         topic2_ptr: Value<'c, 'c>,
         topic3_ptr: Value<'c, 'c>,
         location: Location<'c>,
-    ) {
+    ) -> Result<Value, CodegenError> {
         syscall::mlir::append_log_with_three_topics_syscall(
             self.mlir_context,
             self.syscall_ctx,
@@ -568,7 +771,7 @@ impl<'c> OperationCtx<'c> {
             topic2_ptr,
             topic3_ptr,
             location,
-        );
+        )
     }
     #[allow(clippy::too_many_arguments)]
     pub(crate) fn append_log_with_four_topics_syscall(
@@ -581,7 +784,7 @@ impl<'c> OperationCtx<'c> {
         topic3_ptr: Value<'c, 'c>,
         topic4_ptr: Value<'c, 'c>,
         location: Location<'c>,
-    ) {
+    ) -> Result<Value, CodegenError> {
         syscall::mlir::append_log_with_four_topics_syscall(
             self.mlir_context,
             self.syscall_ctx,
@@ -593,7 +796,7 @@ impl<'c> OperationCtx<'c> {
             topic3_ptr,
             topic4_ptr,
             location,
-        );
+        )
     }
     #[allow(unused)]
     pub(crate) fn get_calldata_ptr_syscall(
@@ -608,4 +811,537 @@ impl<'c> OperationCtx<'c> {
             location,
         )
     }
+
+    pub(crate) fn keccak256_syscall(
+        &'c self,
+        block: &'c Block,
+        offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        hash_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::keccak256_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            offset,
+            size,
+            hash_ptr,
+            location,
+        )
+    }
+
+    pub(crate) fn storage_read_syscall(
+        &'c self,
+        block: &'c Block,
+        key_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        gas_remaining: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::storage_read_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            key_ptr,
+            value_ptr,
+            gas_remaining,
+            location,
+        )
+    }
+
+    pub(crate) fn storage_write_syscall(
+        &'c self,
+        block: &'c Block,
+        key_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        gas_remaining: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::storage_write_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            key_ptr,
+            value_ptr,
+            gas_remaining,
+            location,
+        )
+    }
+
+    pub(crate) fn transient_read_syscall(
+        &'c self,
+        block: &'c Block,
+        key_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::transient_read_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            key_ptr,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn transient_write_syscall(
+        &'c self,
+        block: &'c Block,
+        key_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::transient_write_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            key_ptr,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_callvalue_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_callvalue_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_caller_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_caller_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_origin_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_origin_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_address_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_address_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_returndata_size_syscall(
+        &'c self,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::get_returndata_size_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            location,
+        )
+    }
+
+    pub(crate) fn copy_returndata_syscall(
+        &'c self,
+        block: &'c Block,
+        dest_offset: Value<'c, 'c>,
+        offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        syscall::mlir::copy_returndata_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            dest_offset,
+            offset,
+            size,
+            location,
+        )
+    }
+
+    pub(crate) fn get_call_gas_syscall(
+        &'c self,
+        block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::get_call_gas_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            address_ptr,
+            value_ptr,
+            location,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn call_syscall(
+        &'c self,
+        block: &'c Block,
+        gas: Value<'c, 'c>,
+        address_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        args_offset: Value<'c, 'c>,
+        args_size: Value<'c, 'c>,
+        ret_offset: Value<'c, 'c>,
+        ret_size: Value<'c, 'c>,
+        gas_remaining_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::call_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            gas,
+            address_ptr,
+            value_ptr,
+            args_offset,
+            args_size,
+            ret_offset,
+            ret_size,
+            gas_remaining_ptr,
+            location,
+        )
+    }
+
+    pub(crate) fn is_static_syscall(
+        &'c self,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::is_static_syscall(self.mlir_context, self.syscall_ctx, block, location)
+    }
+
+    pub(crate) fn get_gasprice_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_gasprice_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_block_hash_syscall(
+        &'c self,
+        block: &'c Block,
+        number_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_block_hash_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            number_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_coinbase_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_coinbase_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_timestamp_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_timestamp_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_block_number_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_block_number_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_gaslimit_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_gaslimit_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_chainid_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_chainid_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_prevrandao_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_prevrandao_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_basefee_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_basefee_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_balance_syscall(
+        &'c self,
+        block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        balance_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::get_balance_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            address_ptr,
+            balance_ptr,
+            location,
+        )
+    }
+
+    pub(crate) fn get_self_balance_syscall(
+        &'c self,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::get_self_balance_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            value_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn on_overflow_syscall(
+        &'c self,
+        block: &'c Block,
+        opcode: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::on_overflow_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            opcode,
+            location,
+        );
+    }
+
+    pub(crate) fn get_selfdestruct_gas_syscall(
+        &'c self,
+        block: &'c Block,
+        beneficiary_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::get_selfdestruct_gas_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            beneficiary_ptr,
+            location,
+        )
+    }
+
+    pub(crate) fn selfdestruct_syscall(
+        &'c self,
+        block: &'c Block,
+        beneficiary_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::selfdestruct_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            beneficiary_ptr,
+            location,
+        );
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn create_syscall(
+        &'c self,
+        block: &'c Block,
+        offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        salt_ptr: Option<Value<'c, 'c>>,
+        gas: Value<'c, 'c>,
+        gas_remaining_ptr: Value<'c, 'c>,
+        address_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        syscall::mlir::create_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            offset,
+            size,
+            value_ptr,
+            salt_ptr,
+            gas,
+            gas_remaining_ptr,
+            address_ptr,
+            location,
+        );
+    }
+
+    pub(crate) fn get_ext_code_size_syscall(
+        &'c self,
+        block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        size_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::get_ext_code_size_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            address_ptr,
+            size_ptr,
+            location,
+        )
+    }
+
+    pub(crate) fn get_ext_code_hash_syscall(
+        &'c self,
+        block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        hash_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::get_ext_code_hash_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            address_ptr,
+            hash_ptr,
+            location,
+        )
+    }
+
+    pub(crate) fn copy_ext_code_syscall(
+        &'c self,
+        block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        code_offset_ptr: Value<'c, 'c>,
+        dest_offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value, CodegenError> {
+        syscall::mlir::copy_ext_code_syscall(
+            self.mlir_context,
+            self.syscall_ctx,
+            block,
+            address_ptr,
+            code_offset_ptr,
+            dest_offset,
+            size,
+            location,
+        )
+    }
 }
@@ -9,11 +9,13 @@ use crate::errors::CodegenError;
 use crate::module::MLIRModule;
 use crate::program::Program;
 use llvm_sys::{
+    bit_writer::LLVMWriteBitcodeToFile,
     core::{
         LLVMContextCreate, LLVMContextDispose, LLVMDisposeMessage, LLVMDisposeModule,
         LLVMPrintModuleToFile,
     },
     error::LLVMGetErrorMessage,
+    prelude::LLVMModuleRef,
     target_machine::{
         LLVMCodeGenFileType, LLVMCodeGenOptLevel, LLVMCodeModel, LLVMCreateTargetMachine,
         LLVMDisposeTargetMachine, LLVMGetDefaultTargetTriple, LLVMGetHostCPUFeatures,
@@ -178,6 +180,49 @@ pub fn compile_to_object(
     }
 }
 
+/// Converts a module to LLVM bitcode, which can be cached and later run with
+/// [`Executor::from_bitcode`](crate::executor::Executor::from_bitcode) without
+/// lowering the MLIR again.
+/// The bitcode will be written to the specified target path.
+///
+/// Returns the path to the bitcode.
+pub fn compile_to_bitcode(
+    module: &MLIRModule<'_>,
+    output_file: impl AsRef<Path>,
+) -> Result<PathBuf, CodegenError> {
+    let target_file = output_file.as_ref().with_extension("bc");
+
+    unsafe {
+        let llvm_context = LLVMContextCreate();
+
+        let op = module.melior_module.as_operation().to_raw();
+
+        let llvm_module: LLVMModuleRef =
+            mlirTranslateModuleToLLVMIR(op, llvm_context as *mut _) as *mut _;
+        if llvm_module.is_null() {
+            LLVMContextDispose(llvm_context);
+            return Err(CodegenError::LLVMCompileError(
+                "failed to translate the module to LLVM IR".to_string(),
+            ));
+        }
+
+        let filename = CString::new(target_file.as_os_str().to_string_lossy().as_bytes()).unwrap();
+        let failed = LLVMWriteBitcodeToFile(llvm_module, filename.as_ptr()) != 0;
+
+        LLVMDisposeModule(llvm_module);
+        LLVMContextDispose(llvm_context);
+
+        if failed {
+            return Err(CodegenError::LLVMCompileError(format!(
+                "failed to write bitcode to {}",
+                target_file.display()
+            )));
+        }
+    }
+
+    Ok(target_file)
+}
+
 /// Links object file to produce an executable binary
 // Taken from cairo_native
 pub fn link_binary(
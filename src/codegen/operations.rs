@@ -8,23 +8,29 @@ use melior::{
     },
     ir::{
         attribute::IntegerAttribute, r#type::IntegerType, Attribute, Block, BlockRef, Location,
-        Region,
+        Region, Value,
     },
 };
 
 use super::context::OperationCtx;
 use crate::{
-    constants::{gas_cost, MEMORY_PTR_GLOBAL, MEMORY_SIZE_GLOBAL},
+    constants::{
+        gas_cost, CODE_GLOBAL, MAX_INITCODE_SIZE, MEMORY_PTR_GLOBAL, MEMORY_SIZE_GLOBAL,
+        STACK_BASEPTR_GLOBAL,
+    },
     errors::CodegenError,
-    program::Operation,
+    program::{Opcode, Operation},
+    spec::Spec,
     syscall::ExitStatusCode,
     utils::{
-        allocate_and_store_value, check_if_zero, check_stack_has_at_least,
-        check_stack_has_space_for, compare_values, compute_log_dynamic_gas,
-        constant_value_from_i64, consume_gas, consume_gas_as_value, extend_memory,
-        get_nth_from_stack, get_remaining_gas, get_stack_pointer, inc_stack_pointer,
-        integer_constant_from_i64, llvm_mlir, return_empty_result, return_result_from_stack,
-        stack_pop, stack_push, swap_stack_elements,
+        check_if_zero, check_is_not_static, check_is_signed_division_overflow, check_memory_range,
+        check_stack_has_at_least, check_stack_has_space_for, compare_values,
+        compute_log_dynamic_gas, constant_value_from_i64, consume_gas, consume_gas_as_value,
+        copy_zero_padded, extend_memory, extend_memory_for_copy, get_nth_from_stack,
+        get_remaining_gas, get_stack_pointer, inc_stack_pointer, integer_constant_from_i64,
+        integer_constant_from_u8, integer_constant_from_usize, llvm_mlir, return_empty_result,
+        return_result_from_stack, return_unused_gas, stack_pop, stack_push, store_in_slot,
+        swap_endianness, swap_stack_elements, zero_offset_if_empty,
     },
 };
 
@@ -37,10 +43,13 @@ pub fn generate_code_for_op<'c>(
     region: &'c Region<'c>,
     op: Operation,
 ) -> Result<(BlockRef<'c, 'c>, BlockRef<'c, 'c>), CodegenError> {
+    if !op_ctx.spec.is_operation_enabled(&op) {
+        return codegen_invalid(op_ctx, region);
+    }
     match op {
         Operation::Stop => codegen_stop(op_ctx, region),
-        Operation::Push0 => codegen_push(op_ctx, region, BigUint::ZERO, true),
-        Operation::Push((_, x)) => codegen_push(op_ctx, region, x, false),
+        Operation::Push0 => codegen_push(op_ctx, region, BigUint::ZERO, 0),
+        Operation::Push((n, x)) => codegen_push(op_ctx, region, x, n),
         Operation::Add => codegen_add(op_ctx, region),
         Operation::Mul => codegen_mul(op_ctx, region),
         Operation::Sub => codegen_sub(op_ctx, region),
@@ -61,10 +70,12 @@ pub fn generate_code_for_op<'c>(
         Operation::And => codegen_and(op_ctx, region),
         Operation::Or => codegen_or(op_ctx, region),
         Operation::Xor => codegen_xor(op_ctx, region),
+        Operation::Not => codegen_not(op_ctx, region),
         Operation::Byte => codegen_byte(op_ctx, region),
         Operation::Shr => codegen_shr(op_ctx, region),
         Operation::Shl => codegen_shl(op_ctx, region),
         Operation::Sar => codegen_sar(op_ctx, region),
+        Operation::Keccak256 => codegen_keccak256(op_ctx, region),
         Operation::Codesize => codegen_codesize(op_ctx, region),
         Operation::Pop => codegen_pop(op_ctx, region),
         Operation::Mload => codegen_mload(op_ctx, region),
@@ -81,12 +92,153 @@ pub fn generate_code_for_op<'c>(
         Operation::Revert => codegen_revert(op_ctx, region),
         Operation::Mstore => codegen_mstore(op_ctx, region),
         Operation::Mstore8 => codegen_mstore8(op_ctx, region),
+        Operation::Sload => codegen_sload(op_ctx, region),
+        Operation::Sstore => codegen_sstore(op_ctx, region),
         Operation::Log(x) => codegen_log(op_ctx, region, x),
         Operation::CalldataLoad => codegen_calldataload(op_ctx, region),
         Operation::CallDataSize => codegen_calldatasize(op_ctx, region),
+        Operation::CallValue => codegen_callvalue(op_ctx, region),
+        Operation::Caller => codegen_caller(op_ctx, region),
+        Operation::Origin => codegen_origin(op_ctx, region),
+        Operation::Address => codegen_address(op_ctx, region),
+        Operation::CalldataCopy => codegen_calldatacopy(op_ctx, region),
+        Operation::CodeCopy => codegen_codecopy(op_ctx, region),
+        Operation::ReturnDataSize => codegen_returndatasize(op_ctx, region),
+        Operation::ReturnDataCopy => codegen_returndatacopy(op_ctx, region),
+        Operation::Call => codegen_call(op_ctx, region),
+        Operation::GasPrice => codegen_gasprice(op_ctx, region),
+        Operation::Coinbase => codegen_coinbase(op_ctx, region),
+        Operation::Timestamp => codegen_timestamp(op_ctx, region),
+        Operation::Number => codegen_number(op_ctx, region),
+        Operation::GasLimit => codegen_gaslimit(op_ctx, region),
+        Operation::ChainId => codegen_chainid(op_ctx, region),
+        Operation::PrevRandao => codegen_prevrandao(op_ctx, region),
+        Operation::BaseFee => codegen_basefee(op_ctx, region),
+        Operation::Balance => codegen_balance(op_ctx, region),
+        Operation::SelfBalance => codegen_selfbalance(op_ctx, region),
+        Operation::Invalid => codegen_invalid(op_ctx, region),
+        Operation::Tload => codegen_tload(op_ctx, region),
+        Operation::Tstore => codegen_tstore(op_ctx, region),
+        Operation::SelfDestruct => codegen_selfdestruct(op_ctx, region),
+        Operation::Create => codegen_create(op_ctx, region, false),
+        Operation::Create2 => codegen_create(op_ctx, region, true),
+        Operation::ExtCodeSize => codegen_extcodesize(op_ctx, region),
+        Operation::ExtCodeCopy => codegen_extcodecopy(op_ctx, region),
+        Operation::ExtCodeHash => codegen_extcodehash(op_ctx, region),
+        Operation::BlockHash => codegen_blockhash(op_ctx, region),
     }
 }
 
+/// Generates a block that reports the step to the syscall context when a tracer is installed,
+/// failures are captured, opcodes counted or a step limit set, and then continues to
+/// `op_block`, which holds the code of the operation at `pc`.
+/// If the step limit is reached instead, it halts with an error.
+/// Returns the generated block, which should be jumped to instead of `op_block`.
+pub fn generate_trace_step<'c>(
+    op_ctx: &OperationCtx<'c>,
+    region: &'c Region<'c>,
+    op_block: BlockRef<'c, 'c>,
+    pc: usize,
+    opcode: u8,
+) -> Result<BlockRef<'c, 'c>, CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let ptr_type = pointer(context, 0);
+
+    let trace_block = region.append_block(Block::new(&[]));
+
+    // Only call the syscall when a tracer was installed, failures are captured,
+    // opcodes counted or a step limit set
+    start_block.append_operation(cf::cond_br(
+        context,
+        op_ctx.tracing_flag,
+        &trace_block,
+        &op_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let pc = trace_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(IntegerType::new(context, 64).into(), pc as i64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let opcode = trace_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(IntegerType::new(context, 8).into(), opcode as i64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let gas = get_remaining_gas(context, &trace_block)?;
+
+    let stack_baseptr_ptr = trace_block
+        .append_operation(llvm_mlir::addressof(
+            context,
+            STACK_BASEPTR_GLOBAL,
+            ptr_type,
+            location,
+        ))
+        .result(0)?;
+    let stack_baseptr = trace_block
+        .append_operation(llvm::load(
+            context,
+            stack_baseptr_ptr.into(),
+            ptr_type,
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+    let stack_ptr = get_stack_pointer(context, &trace_block)?;
+
+    let limit_reached = op_ctx.trace_step_syscall(
+        &trace_block,
+        pc,
+        opcode,
+        gas,
+        stack_baseptr,
+        stack_ptr,
+        location,
+    )?;
+    let zero = trace_block
+        .append_operation(arith::constant(
+            context,
+            integer_constant_from_u8(context, 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let limit_reached = trace_block
+        .append_operation(arith::cmpi(
+            context,
+            arith::CmpiPredicate::Ne,
+            limit_reached,
+            zero,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    trace_block.append_operation(cf::cond_br(
+        context,
+        limit_reached,
+        &op_ctx.error_block,
+        &op_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    Ok(start_block)
+}
+
 fn codegen_calldatasize<'c, 'r>(
     op_ctx: &mut OperationCtx<'c>,
     region: &'r Region<'c>,
@@ -95,7 +247,7 @@ fn codegen_calldatasize<'c, 'r>(
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::CALLDATASIZE)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CALLDATASIZE))?;
 
     let ok_block = region.append_block(Block::new(&[]));
 
@@ -103,7 +255,7 @@ fn codegen_calldatasize<'c, 'r>(
         context,
         gas_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -128,10 +280,12 @@ fn codegen_exp<'c, 'r>(
     let start_block = region.append_block(Block::new(&[]));
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+    let uint64 = IntegerType::new(context, 64);
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::EXP)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::EXP))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -142,23 +296,148 @@ fn codegen_exp<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
     ));
 
-    let lhs = stack_pop(context, &ok_block)?;
-    let rhs = stack_pop(context, &ok_block)?;
+    let base = stack_pop(context, &ok_block)?;
+    let exponent = stack_pop(context, &ok_block)?;
 
-    let result = ok_block
-        .append_operation(ods::math::ipowi(context, rhs, lhs, location).into())
+    // dynamic_gas = 50 * exponent_byte_size
+    // where exponent_byte_size = (256 - leading_zeros(exponent) + 7) / 8
+    let leading_zeros = ok_block
+        .append_operation(llvm::intr_ctlz(
+            context,
+            exponent,
+            false,
+            uint256.into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let constant_256 = constant_value_from_i64(context, &ok_block, 256)?;
+    let exponent_bits = ok_block
+        .append_operation(arith::subi(constant_256, leading_zeros, location))
+        .result(0)?
+        .into();
+    let constant_7 = constant_value_from_i64(context, &ok_block, 7)?;
+    let exponent_bits_plus_7 = ok_block
+        .append_operation(arith::addi(exponent_bits, constant_7, location))
+        .result(0)?
+        .into();
+    let constant_8 = constant_value_from_i64(context, &ok_block, 8)?;
+    let exponent_byte_size = ok_block
+        .append_operation(arith::divui(exponent_bits_plus_7, constant_8, location))
+        .result(0)?
+        .into();
+    let gas_per_byte = constant_value_from_i64(context, &ok_block, gas_cost::EXP_PER_BYTE)?;
+    let dynamic_gas = ok_block
+        .append_operation(arith::muli(exponent_byte_size, gas_per_byte, location))
+        .result(0)?
+        .into();
+    let dynamic_gas = ok_block
+        .append_operation(arith::trunci(dynamic_gas, uint64.into(), location))
         .result(0)?
         .into();
+    let dynamic_gas_flag = consume_gas_as_value(context, &ok_block, dynamic_gas)?;
 
-    stack_push(context, &ok_block, result)?;
+    // The result is computed by square-and-multiply, iterating over the bits
+    // of the exponent. Multiplications wrap around at 2^256, as the EVM expects.
+    let loop_header_block = region.append_block(Block::new(&[
+        (uint256.into(), location),
+        (uint256.into(), location),
+        (uint256.into(), location),
+    ]));
+    let loop_body_block = region.append_block(Block::new(&[]));
+    let end_block = region.append_block(Block::new(&[]));
 
-    Ok((start_block, ok_block))
+    let one = constant_value_from_i64(context, &ok_block, 1)?;
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        dynamic_gas_flag,
+        &loop_header_block,
+        &op_ctx.error_block,
+        &[one, base, exponent],
+        &[],
+        location,
+    ));
+
+    /******************** loop_header_block *******************/
+
+    let result = loop_header_block.argument(0)?.into();
+    let base = loop_header_block.argument(1)?.into();
+    let exponent = loop_header_block.argument(2)?.into();
+
+    let zero = constant_value_from_i64(context, &loop_header_block, 0)?;
+    let exponent_is_zero = compare_values(
+        context,
+        &loop_header_block,
+        CmpiPredicate::Eq,
+        exponent,
+        zero,
+    )?;
+
+    loop_header_block.append_operation(cf::cond_br(
+        context,
+        exponent_is_zero,
+        &end_block,
+        &loop_body_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    /******************** loop_body_block *******************/
+
+    // If the lowest bit of the exponent is set, multiply the result by the base
+    let lowest_bit = loop_body_block
+        .append_operation(arith::andi(exponent, one, location))
+        .result(0)?
+        .into();
+    let lowest_bit_is_set = compare_values(
+        context,
+        &loop_body_block,
+        CmpiPredicate::Ne,
+        lowest_bit,
+        zero,
+    )?;
+    let multiplied_result = loop_body_block
+        .append_operation(arith::muli(result, base, location))
+        .result(0)?
+        .into();
+    let new_result = loop_body_block
+        .append_operation(arith::select(
+            lowest_bit_is_set,
+            multiplied_result,
+            result,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let new_base = loop_body_block
+        .append_operation(arith::muli(base, base, location))
+        .result(0)?
+        .into();
+    let new_exponent = loop_body_block
+        .append_operation(arith::shrui(exponent, one, location))
+        .result(0)?
+        .into();
+
+    loop_body_block.append_operation(cf::br(
+        &loop_header_block,
+        &[new_result, new_base, new_exponent],
+        location,
+    ));
+
+    /******************** end_block *******************/
+
+    stack_push(context, &end_block, result)?;
+
+    Ok((start_block, end_block))
 }
 
 fn codegen_iszero<'c, 'r>(
@@ -171,7 +450,7 @@ fn codegen_iszero<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 1)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::ISZERO)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::ISZERO))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -183,7 +462,7 @@ fn codegen_iszero<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -225,7 +504,7 @@ fn codegen_and<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::AND)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::AND))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -237,7 +516,7 @@ fn codegen_and<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -266,7 +545,7 @@ fn codegen_gt<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::GT)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::GT))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -277,7 +556,7 @@ fn codegen_gt<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -312,7 +591,7 @@ fn codegen_or<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::OR)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::OR))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -324,7 +603,7 @@ fn codegen_or<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -353,7 +632,7 @@ fn codegen_lt<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::LT)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::LT))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -365,7 +644,7 @@ fn codegen_lt<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -400,7 +679,7 @@ fn codegen_sgt<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SGT)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SGT))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -412,7 +691,7 @@ fn codegen_sgt<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -447,7 +726,7 @@ fn codegen_eq<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::EQ)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::EQ))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -458,7 +737,7 @@ fn codegen_eq<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -487,19 +766,15 @@ fn codegen_push<'c, 'r>(
     op_ctx: &mut OperationCtx<'c>,
     region: &'r Region<'c>,
     value_to_push: BigUint,
-    is_zero: bool,
+    push_size: u8,
 ) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
     let start_block = region.append_block(Block::new(&[]));
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
 
     // Check there's enough space in stack
-    let flag = check_stack_has_space_for(context, &start_block, 1)?;
-    let gas_cost = if is_zero {
-        gas_cost::PUSH0
-    } else {
-        gas_cost::PUSHN
-    };
+    let flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+    let gas_cost = op_ctx.gas_schedule.cost(Opcode::PUSH0 as u8 + push_size);
     let gas_flag = consume_gas(context, &start_block, gas_cost)?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -512,7 +787,7 @@ fn codegen_push<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -534,7 +809,10 @@ fn codegen_dup<'c, 'r>(
     region: &'r Region<'c>,
     nth: u8,
 ) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
-    debug_assert!(nth > 0 && nth <= 16);
+    if !(1..=16).contains(&nth) {
+        // There's no such opcode, so it halts like INVALID
+        return codegen_invalid(op_ctx, region);
+    }
     let start_block = region.append_block(Block::new(&[]));
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
@@ -542,7 +820,8 @@ fn codegen_dup<'c, 'r>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, nth as u32)?;
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::DUPN)?;
+    let gas_cost = op_ctx.gas_schedule.cost(Opcode::DUP1 as u8 + nth - 1);
+    let gas_flag = consume_gas(context, &start_block, gas_cost)?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -554,7 +833,7 @@ fn codegen_dup<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -572,7 +851,10 @@ fn codegen_swap<'c, 'r>(
     region: &'r Region<'c>,
     nth: u8,
 ) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
-    debug_assert!(nth > 0 && nth <= 16);
+    if !(1..=16).contains(&nth) {
+        // There's no such opcode, so it halts like INVALID
+        return codegen_invalid(op_ctx, region);
+    }
     let start_block = region.append_block(Block::new(&[]));
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
@@ -580,7 +862,8 @@ fn codegen_swap<'c, 'r>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, (nth + 1) as u32)?;
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SWAPN)?;
+    let gas_cost = op_ctx.gas_schedule.cost(Opcode::SWAP1 as u8 + nth - 1);
+    let gas_flag = consume_gas(context, &start_block, gas_cost)?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -593,7 +876,7 @@ fn codegen_swap<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -604,6 +887,46 @@ fn codegen_swap<'c, 'r>(
     Ok((start_block, ok_block))
 }
 
+/// Generates a call reporting an overflow of `opcode` to the syscall context when the
+/// `overflow` flag is set. The operation's result is left as is, wrapped around.
+/// Returns the unterminated block to continue in.
+fn report_overflow<'c, 'r>(
+    op_ctx: &OperationCtx<'c>,
+    region: &'r Region<'c>,
+    block: BlockRef<'c, 'r>,
+    overflow: Value,
+    opcode: Opcode,
+) -> Result<BlockRef<'c, 'r>, CodegenError> {
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    let overflow_block = region.append_block(Block::new(&[]));
+    let continue_block = region.append_block(Block::new(&[]));
+
+    block.append_operation(cf::cond_br(
+        context,
+        overflow,
+        &overflow_block,
+        &continue_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let opcode = overflow_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(IntegerType::new(context, 8).into(), opcode as i64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    op_ctx.on_overflow_syscall(&overflow_block, opcode, location);
+    overflow_block.append_operation(cf::br(&continue_block, &[], location));
+
+    Ok(continue_block)
+}
+
 fn codegen_add<'c, 'r>(
     op_ctx: &mut OperationCtx<'c>,
     region: &'r Region<'c>,
@@ -615,7 +938,7 @@ fn codegen_add<'c, 'r>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::ADD)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::ADD))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -628,7 +951,7 @@ fn codegen_add<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -644,7 +967,15 @@ fn codegen_add<'c, 'r>(
 
     stack_push(context, &ok_block, result)?;
 
-    Ok((start_block, ok_block))
+    if !op_ctx.instrument_overflow {
+        return Ok((start_block, ok_block));
+    }
+
+    // The sum wrapped around if it's lower than one of the operands
+    let overflow = compare_values(context, &ok_block, CmpiPredicate::Ult, result, lhs)?;
+    let end_block = report_overflow(op_ctx, region, ok_block, overflow, Opcode::ADD)?;
+
+    Ok((start_block, end_block))
 }
 
 fn codegen_sub<'c, 'r>(
@@ -658,7 +989,7 @@ fn codegen_sub<'c, 'r>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SUB)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SUB))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -671,7 +1002,7 @@ fn codegen_sub<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -687,7 +1018,61 @@ fn codegen_sub<'c, 'r>(
 
     stack_push(context, &ok_block, result)?;
 
-    Ok((start_block, ok_block))
+    if !op_ctx.instrument_overflow {
+        return Ok((start_block, ok_block));
+    }
+
+    // The difference wrapped around if the subtrahend is greater than the minuend
+    let overflow = compare_values(context, &ok_block, CmpiPredicate::Ult, lhs, rhs)?;
+    let end_block = report_overflow(op_ctx, region, ok_block, overflow, Opcode::SUB)?;
+
+    Ok((start_block, end_block))
+}
+
+/// Generates the zero denominator check shared by the division and modulo operations.
+/// When `den` is zero, 0 is pushed to the stack, as the EVM defines it. Otherwise
+/// `non_zero_path` generates the operation starting in the given block, pushes the result,
+/// and returns the unterminated block it ends in.
+/// Returns the unterminated block both paths join in.
+fn with_zero_denominator_guard<'c, 'r, F>(
+    op_ctx: &OperationCtx<'c>,
+    region: &'r Region<'c>,
+    block: BlockRef<'c, 'r>,
+    den: Value,
+    non_zero_path: F,
+) -> Result<BlockRef<'c, 'r>, CodegenError>
+where
+    F: FnOnce(BlockRef<'c, 'r>) -> Result<BlockRef<'c, 'r>, CodegenError>,
+{
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    let den_is_zero = check_if_zero(context, &block, &den)?;
+    let den_zero_bloq = region.append_block(Block::new(&[]));
+    let den_not_zero_bloq = region.append_block(Block::new(&[]));
+    let return_block = region.append_block(Block::new(&[]));
+
+    // Denominator is zero path
+    let zero_value = constant_value_from_i64(context, &den_zero_bloq, 0i64)?;
+    stack_push(context, &den_zero_bloq, zero_value)?;
+    den_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+
+    // Denominator is not zero path
+    let last_block = non_zero_path(den_not_zero_bloq)?;
+    last_block.append_operation(cf::br(&return_block, &[], location));
+
+    // Branch to den_zero if den_is_zero == true; else branch to den_not_zero
+    block.append_operation(cf::cond_br(
+        context,
+        den_is_zero,
+        &den_zero_bloq,
+        &den_not_zero_bloq,
+        &[],
+        &[],
+        location,
+    ));
+
+    Ok(return_block)
 }
 
 fn codegen_div<'c, 'r>(
@@ -702,7 +1087,7 @@ fn codegen_div<'c, 'r>(
     let stack_size_flag = check_stack_has_at_least(context, &start_block, 2)?;
 
     // Check there's enough gas to compute the operation
-    let gas_flag = consume_gas(context, &start_block, gas_cost::DIV)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::DIV))?;
 
     let ok_flag = start_block
         .append_operation(arith::andi(stack_size_flag, gas_flag, location))
@@ -715,7 +1100,7 @@ fn codegen_div<'c, 'r>(
         context,
         ok_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -724,35 +1109,15 @@ fn codegen_div<'c, 'r>(
     let num = stack_pop(context, &ok_block)?;
     let den = stack_pop(context, &ok_block)?;
 
-    let den_is_zero = check_if_zero(context, &ok_block, &den)?;
-    let den_zero_bloq = region.append_block(Block::new(&[]));
-    let den_not_zero_bloq = region.append_block(Block::new(&[]));
-    let return_block = region.append_block(Block::new(&[]));
-
-    // Denominator is zero path
-    let zero_value = constant_value_from_i64(context, &den_zero_bloq, 0i64)?;
-    stack_push(context, &den_zero_bloq, zero_value)?;
-    den_zero_bloq.append_operation(cf::br(&return_block, &[], location));
-
-    // Denominator is not zero path
-    let result = den_not_zero_bloq
-        .append_operation(arith::divui(num, den, location))
-        .result(0)?
-        .into();
-
-    stack_push(context, &den_not_zero_bloq, result)?;
-    den_not_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+    let return_block = with_zero_denominator_guard(op_ctx, region, ok_block, den, |block| {
+        let result = block
+            .append_operation(arith::divui(num, den, location))
+            .result(0)?
+            .into();
 
-    // Branch to den_zero if den_is_zero == true; else branch to den_not_zero
-    ok_block.append_operation(cf::cond_br(
-        context,
-        den_is_zero,
-        &den_zero_bloq,
-        &den_not_zero_bloq,
-        &[],
-        &[],
-        location,
-    ));
+        stack_push(context, &block, result)?;
+        Ok(block)
+    })?;
 
     Ok((start_block, return_block))
 }
@@ -767,7 +1132,7 @@ fn codegen_sdiv<'c, 'r>(
 
     // Check there's enough elements in stack
     let stack_size_flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SDIV)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SDIV))?;
 
     let ok_flag = start_block
         .append_operation(arith::andi(stack_size_flag, gas_flag, location))
@@ -780,7 +1145,7 @@ fn codegen_sdiv<'c, 'r>(
         context,
         ok_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -788,35 +1153,36 @@ fn codegen_sdiv<'c, 'r>(
 
     let num = stack_pop(context, &ok_block)?;
     let den = stack_pop(context, &ok_block)?;
-    let den_is_zero = check_if_zero(context, &ok_block, &den)?;
-    let den_zero_bloq = region.append_block(Block::new(&[]));
-    let den_not_zero_bloq = region.append_block(Block::new(&[]));
-    let return_block = region.append_block(Block::new(&[]));
+    let return_block = with_zero_denominator_guard(op_ctx, region, ok_block, den, |block| {
+        let is_overflow = check_is_signed_division_overflow(context, &block, num, den)?;
+        let overflow_bloq = region.append_block(Block::new(&[]));
+        let no_overflow_bloq = region.append_block(Block::new(&[]));
+        let result_block = region.append_block(Block::new(&[]));
 
-    // Denominator is zero path
-    let zero_value = constant_value_from_i64(context, &den_zero_bloq, 0i64)?;
-    stack_push(context, &den_zero_bloq, zero_value)?;
-    den_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+        block.append_operation(cf::cond_br(
+            context,
+            is_overflow,
+            &overflow_bloq,
+            &no_overflow_bloq,
+            &[],
+            &[],
+            location,
+        ));
 
-    // Denominator is not zero path
-    let result = den_not_zero_bloq
-        .append_operation(ods::llvm::sdiv(context, num, den, location).into())
-        .result(0)?
-        .into();
+        // INT256_MIN / -1 overflows, and the result is INT256_MIN
+        stack_push(context, &overflow_bloq, num)?;
+        overflow_bloq.append_operation(cf::br(&result_block, &[], location));
 
-    stack_push(context, &den_not_zero_bloq, result)?;
-    den_not_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+        let result = no_overflow_bloq
+            .append_operation(ods::llvm::sdiv(context, num, den, location).into())
+            .result(0)?
+            .into();
 
-    // Branch to den_zero if den_is_zero == true; else branch to den_not_zero
-    ok_block.append_operation(cf::cond_br(
-        context,
-        den_is_zero,
-        &den_zero_bloq,
-        &den_not_zero_bloq,
-        &[],
-        &[],
-        location,
-    ));
+        stack_push(context, &no_overflow_bloq, result)?;
+        no_overflow_bloq.append_operation(cf::br(&result_block, &[], location));
+
+        Ok(result_block)
+    })?;
 
     Ok((start_block, return_block))
 }
@@ -832,7 +1198,7 @@ fn codegen_mul<'c, 'r>(
     // Check there's enough elements in stack
     let stack_size_flag = check_stack_has_at_least(context, &start_block, 2)?;
     // Check there's enough gas to compute the operation
-    let gas_flag = consume_gas(context, &start_block, gas_cost::MUL)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::MUL))?;
 
     let ok_flag = start_block
         .append_operation(arith::andi(stack_size_flag, gas_flag, location))
@@ -845,7 +1211,7 @@ fn codegen_mul<'c, 'r>(
         context,
         ok_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -861,20 +1227,45 @@ fn codegen_mul<'c, 'r>(
 
     stack_push(context, &ok_block, result)?;
 
-    Ok((start_block, ok_block))
-}
+    if !op_ctx.instrument_overflow {
+        return Ok((start_block, ok_block));
+    }
 
-fn codegen_mod<'c, 'r>(
-    op_ctx: &mut OperationCtx<'c>,
-    region: &'r Region<'c>,
-) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
-    let start_block = region.append_block(Block::new(&[]));
+    // The product wrapped around if dividing it by a (non-zero) operand doesn't give back
+    // the other one. The division is by 1 when the operand is zero, to avoid dividing by 0.
+    let zero = constant_value_from_i64(context, &ok_block, 0)?;
+    let one = constant_value_from_i64(context, &ok_block, 1)?;
+    let lhs_is_zero = compare_values(context, &ok_block, CmpiPredicate::Eq, lhs, zero)?;
+    let divisor = ok_block
+        .append_operation(arith::select(lhs_is_zero, one, lhs, location))
+        .result(0)?
+        .into();
+    let quotient = ok_block
+        .append_operation(arith::divui(result, divisor, location))
+        .result(0)?
+        .into();
+    let quotient_differs = compare_values(context, &ok_block, CmpiPredicate::Ne, quotient, rhs)?;
+    let lhs_is_not_zero = compare_values(context, &ok_block, CmpiPredicate::Ne, lhs, zero)?;
+    let overflow = ok_block
+        .append_operation(arith::andi(lhs_is_not_zero, quotient_differs, location))
+        .result(0)?
+        .into();
+    let end_block = report_overflow(op_ctx, region, ok_block, overflow, Opcode::MUL)?;
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_mod<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::MOD)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::MOD))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -886,7 +1277,7 @@ fn codegen_mod<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -895,42 +1286,15 @@ fn codegen_mod<'c, 'r>(
     let num = stack_pop(context, &ok_block)?;
     let den = stack_pop(context, &ok_block)?;
 
-    let den_is_zero = check_if_zero(context, &ok_block, &den)?;
-    let den_zero_bloq = region.append_block(Block::new(&[]));
-    let den_not_zero_bloq = region.append_block(Block::new(&[]));
-    let return_block = region.append_block(Block::new(&[]));
-
-    let constant_value = den_zero_bloq
-        .append_operation(arith::constant(
-            context,
-            integer_constant_from_i64(context, 0i64).into(),
-            location,
-        ))
-        .result(0)?
-        .into();
-
-    stack_push(context, &den_zero_bloq, constant_value)?;
-
-    den_zero_bloq.append_operation(cf::br(&return_block, &[], location));
-
-    let mod_result = den_not_zero_bloq
-        .append_operation(arith::remui(num, den, location))
-        .result(0)?
-        .into();
-
-    stack_push(context, &den_not_zero_bloq, mod_result)?;
-
-    den_not_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+    let return_block = with_zero_denominator_guard(op_ctx, region, ok_block, den, |block| {
+        let mod_result = block
+            .append_operation(arith::remui(num, den, location))
+            .result(0)?
+            .into();
 
-    ok_block.append_operation(cf::cond_br(
-        context,
-        den_is_zero,
-        &den_zero_bloq,
-        &den_not_zero_bloq,
-        &[],
-        &[],
-        location,
-    ));
+        stack_push(context, &block, mod_result)?;
+        Ok(block)
+    })?;
 
     Ok((start_block, return_block))
 }
@@ -945,7 +1309,7 @@ fn codegen_smod<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SMOD)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SMOD))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -957,7 +1321,7 @@ fn codegen_smod<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -966,42 +1330,39 @@ fn codegen_smod<'c, 'r>(
     let num = stack_pop(context, &ok_block)?;
     let den = stack_pop(context, &ok_block)?;
 
-    let den_is_zero = check_if_zero(context, &ok_block, &den)?;
-    let den_zero_bloq = region.append_block(Block::new(&[]));
-    let den_not_zero_bloq = region.append_block(Block::new(&[]));
-    let return_block = region.append_block(Block::new(&[]));
+    let return_block = with_zero_denominator_guard(op_ctx, region, ok_block, den, |block| {
+        let is_overflow = check_is_signed_division_overflow(context, &block, num, den)?;
+        let overflow_bloq = region.append_block(Block::new(&[]));
+        let no_overflow_bloq = region.append_block(Block::new(&[]));
+        let result_block = region.append_block(Block::new(&[]));
 
-    let constant_value = den_zero_bloq
-        .append_operation(arith::constant(
+        block.append_operation(cf::cond_br(
             context,
-            integer_constant_from_i64(context, 0i64).into(),
+            is_overflow,
+            &overflow_bloq,
+            &no_overflow_bloq,
+            &[],
+            &[],
             location,
-        ))
-        .result(0)?
-        .into();
+        ));
 
-    stack_push(context, &den_zero_bloq, constant_value)?;
+        // INT256_MIN % -1 is 0, but `srem` is undefined for it
+        let zero_value = constant_value_from_i64(context, &overflow_bloq, 0i64)?;
+        stack_push(context, &overflow_bloq, zero_value)?;
 
-    den_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+        overflow_bloq.append_operation(cf::br(&result_block, &[], location));
 
-    let mod_result = den_not_zero_bloq
-        .append_operation(ods::llvm::srem(context, num, den, location).into())
-        .result(0)?
-        .into();
+        let mod_result = no_overflow_bloq
+            .append_operation(ods::llvm::srem(context, num, den, location).into())
+            .result(0)?
+            .into();
 
-    stack_push(context, &den_not_zero_bloq, mod_result)?;
+        stack_push(context, &no_overflow_bloq, mod_result)?;
 
-    den_not_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+        no_overflow_bloq.append_operation(cf::br(&result_block, &[], location));
 
-    ok_block.append_operation(cf::cond_br(
-        context,
-        den_is_zero,
-        &den_zero_bloq,
-        &den_not_zero_bloq,
-        &[],
-        &[],
-        location,
-    ));
+        Ok(result_block)
+    })?;
 
     Ok((start_block, return_block))
 }
@@ -1016,7 +1377,7 @@ fn codegen_addmod<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 3)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::ADDMOD)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::ADDMOD))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -1028,7 +1389,7 @@ fn codegen_addmod<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1038,65 +1399,39 @@ fn codegen_addmod<'c, 'r>(
     let b = stack_pop(context, &ok_block)?;
     let den = stack_pop(context, &ok_block)?;
 
-    let den_is_zero = check_if_zero(context, &ok_block, &den)?;
-    let den_zero_bloq = region.append_block(Block::new(&[]));
-    let den_not_zero_bloq = region.append_block(Block::new(&[]));
-    let return_block = region.append_block(Block::new(&[]));
-
-    let constant_value = den_zero_bloq
-        .append_operation(arith::constant(
-            context,
-            integer_constant_from_i64(context, 0i64).into(),
-            location,
-        ))
-        .result(0)?
-        .into();
-
-    stack_push(context, &den_zero_bloq, constant_value)?;
-
-    den_zero_bloq.append_operation(cf::br(&return_block, &[], location));
-    let uint256 = IntegerType::new(context, 256).into();
-    let uint257 = IntegerType::new(context, 257).into();
-
-    // extend the operands to 257 bits before the addition
-    let extended_a = den_not_zero_bloq
-        .append_operation(arith::extui(a, uint257, location))
-        .result(0)?
-        .into();
-    let extended_b = den_not_zero_bloq
-        .append_operation(arith::extui(b, uint257, location))
-        .result(0)?
-        .into();
-    let extended_den = den_not_zero_bloq
-        .append_operation(arith::extui(den, uint257, location))
-        .result(0)?
-        .into();
-    let add_result = den_not_zero_bloq
-        .append_operation(arith::addi(extended_a, extended_b, location))
-        .result(0)?
-        .into();
-    let mod_result = den_not_zero_bloq
-        .append_operation(arith::remui(add_result, extended_den, location))
-        .result(0)?
-        .into();
-    let truncated_result = den_not_zero_bloq
-        .append_operation(arith::trunci(mod_result, uint256, location))
-        .result(0)?
-        .into();
-
-    stack_push(context, &den_not_zero_bloq, truncated_result)?;
+    let return_block = with_zero_denominator_guard(op_ctx, region, ok_block, den, |block| {
+        let uint256 = IntegerType::new(context, 256).into();
+        let uint257 = IntegerType::new(context, 257).into();
 
-    den_not_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+        // extend the operands to 257 bits before the addition
+        let extended_a = block
+            .append_operation(arith::extui(a, uint257, location))
+            .result(0)?
+            .into();
+        let extended_b = block
+            .append_operation(arith::extui(b, uint257, location))
+            .result(0)?
+            .into();
+        let extended_den = block
+            .append_operation(arith::extui(den, uint257, location))
+            .result(0)?
+            .into();
+        let add_result = block
+            .append_operation(arith::addi(extended_a, extended_b, location))
+            .result(0)?
+            .into();
+        let mod_result = block
+            .append_operation(arith::remui(add_result, extended_den, location))
+            .result(0)?
+            .into();
+        let truncated_result = block
+            .append_operation(arith::trunci(mod_result, uint256, location))
+            .result(0)?
+            .into();
 
-    ok_block.append_operation(cf::cond_br(
-        context,
-        den_is_zero,
-        &den_zero_bloq,
-        &den_not_zero_bloq,
-        &[],
-        &[],
-        location,
-    ));
+        stack_push(context, &block, truncated_result)?;
+        Ok(block)
+    })?;
 
     Ok((start_block, return_block))
 }
@@ -1111,7 +1446,7 @@ fn codegen_mulmod<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 3)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::MULMOD)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::MULMOD))?;
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
@@ -1123,7 +1458,7 @@ fn codegen_mulmod<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1133,69 +1468,88 @@ fn codegen_mulmod<'c, 'r>(
     let b = stack_pop(context, &ok_block)?;
     let den = stack_pop(context, &ok_block)?;
 
-    let den_is_zero = check_if_zero(context, &ok_block, &den)?;
-    let den_zero_bloq = region.append_block(Block::new(&[]));
-    let den_not_zero_bloq = region.append_block(Block::new(&[]));
-    let return_block = region.append_block(Block::new(&[]));
+    let return_block = with_zero_denominator_guard(op_ctx, region, ok_block, den, |block| {
+        let uint256 = IntegerType::new(context, 256).into();
+        let uint512 = IntegerType::new(context, 512).into();
 
-    let constant_value = den_zero_bloq
-        .append_operation(arith::constant(
-            context,
-            integer_constant_from_i64(context, 0i64).into(),
-            location,
-        ))
-        .result(0)?
-        .into();
+        // extend the operands to 512 bits before the multiplication
+        let extended_a = block
+            .append_operation(arith::extui(a, uint512, location))
+            .result(0)?
+            .into();
+        let extended_b = block
+            .append_operation(arith::extui(b, uint512, location))
+            .result(0)?
+            .into();
+        let extended_den = block
+            .append_operation(arith::extui(den, uint512, location))
+            .result(0)?
+            .into();
 
-    stack_push(context, &den_zero_bloq, constant_value)?;
+        let mul_result = block
+            .append_operation(arith::muli(extended_a, extended_b, location))
+            .result(0)?
+            .into();
+        let mod_result = block
+            .append_operation(arith::remui(mul_result, extended_den, location))
+            .result(0)?
+            .into();
+        let truncated_result = block
+            .append_operation(arith::trunci(mod_result, uint256, location))
+            .result(0)?
+            .into();
 
-    den_zero_bloq.append_operation(cf::br(&return_block, &[], location));
+        stack_push(context, &block, truncated_result)?;
+        Ok(block)
+    })?;
 
-    let uint256 = IntegerType::new(context, 256).into();
-    let uint512 = IntegerType::new(context, 512).into();
+    Ok((start_block, return_block))
+}
 
-    // extend the operands to 512 bits before the multiplication
-    let extended_a = den_not_zero_bloq
-        .append_operation(arith::extui(a, uint512, location))
-        .result(0)?
-        .into();
-    let extended_b = den_not_zero_bloq
-        .append_operation(arith::extui(b, uint512, location))
-        .result(0)?
-        .into();
-    let extended_den = den_not_zero_bloq
-        .append_operation(arith::extui(den, uint512, location))
-        .result(0)?
-        .into();
+fn codegen_xor<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
 
-    let mul_result = den_not_zero_bloq
-        .append_operation(arith::muli(extended_a, extended_b, location))
-        .result(0)?
-        .into();
-    let mod_result = den_not_zero_bloq
-        .append_operation(arith::remui(mul_result, extended_den, location))
-        .result(0)?
-        .into();
-    let truncated_result = den_not_zero_bloq
-        .append_operation(arith::trunci(mod_result, uint256, location))
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 2)?;
+
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::XOR))?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
         .result(0)?
         .into();
 
-    stack_push(context, &den_not_zero_bloq, truncated_result)?;
-    den_not_zero_bloq.append_operation(cf::br(&return_block, &[], location));
-    ok_block.append_operation(cf::cond_br(
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
         context,
-        den_is_zero,
-        &den_zero_bloq,
-        &den_not_zero_bloq,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
     ));
-    Ok((start_block, return_block))
+
+    let lhs = stack_pop(context, &ok_block)?;
+    let rhs = stack_pop(context, &ok_block)?;
+
+    let result = ok_block
+        .append_operation(arith::xori(lhs, rhs, location))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, result)?;
+
+    Ok((start_block, ok_block))
 }
 
-fn codegen_xor<'c, 'r>(
+fn codegen_not<'c, 'r>(
     op_ctx: &mut OperationCtx<'c>,
     region: &'r Region<'c>,
 ) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
@@ -1204,9 +1558,9 @@ fn codegen_xor<'c, 'r>(
     let location = Location::unknown(context);
 
     // Check there's enough elements in stack
-    let flag = check_stack_has_at_least(context, &start_block, 2)?;
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::XOR)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::NOT))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -1219,17 +1573,26 @@ fn codegen_xor<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
     ));
 
-    let lhs = stack_pop(context, &ok_block)?;
-    let rhs = stack_pop(context, &ok_block)?;
+    let value = stack_pop(context, &ok_block)?;
+
+    // -1 is all ones in two's complement, so xor-ing with it flips every bit
+    let all_ones = ok_block
+        .append_operation(arith::constant(
+            context,
+            Attribute::parse(context, "-1 : i256").unwrap(),
+            location,
+        ))
+        .result(0)?
+        .into();
 
     let result = ok_block
-        .append_operation(arith::xori(lhs, rhs, location))
+        .append_operation(arith::xori(value, all_ones, location))
         .result(0)?
         .into();
 
@@ -1250,7 +1613,7 @@ fn codegen_shr<'c, 'r>(
     // Check there's enough elements in stack
     let mut flag = check_stack_has_at_least(context, &start_block, 2)?;
 
-    let gas_flag = consume_gas(context, &start_block, 3)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SHR))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -1263,7 +1626,7 @@ fn codegen_shr<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1272,16 +1635,16 @@ fn codegen_shr<'c, 'r>(
     let shift = stack_pop(context, &ok_block)?;
     let value = stack_pop(context, &ok_block)?;
 
-    let value_255 = ok_block
+    let value_256 = ok_block
         .append_operation(arith::constant(
             context,
-            IntegerAttribute::new(uint256.into(), 255_i64).into(),
+            IntegerAttribute::new(uint256.into(), 256_i64).into(),
             location,
         ))
         .result(0)?
         .into();
 
-    flag = compare_values(context, &ok_block, CmpiPredicate::Ult, shift, value_255)?;
+    flag = compare_values(context, &ok_block, CmpiPredicate::Ult, shift, value_256)?;
 
     let ok_ok_block = region.append_block(Block::new(&[]));
     let altv_block = region.append_block(Block::new(&[]));
@@ -1298,7 +1661,7 @@ fn codegen_shr<'c, 'r>(
         location,
     ));
 
-    // if shift is less than 255
+    // if shift is less than 256
     let result = ok_ok_block
         .append_operation(arith::shrui(value, shift, location))
         .result(0)?
@@ -1308,7 +1671,7 @@ fn codegen_shr<'c, 'r>(
 
     ok_ok_block.append_operation(cf::br(&empty_block, &[], location));
 
-    // if shift is greater than 255
+    // if shift is 256 or greater
     let result = altv_block
         .append_operation(arith::constant(
             context,
@@ -1337,7 +1700,7 @@ fn codegen_shl<'c, 'r>(
     // Check there's enough elements in stack
     let mut flag = check_stack_has_at_least(context, &start_block, 2)?;
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SHL)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SHL))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -1350,7 +1713,7 @@ fn codegen_shl<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1359,16 +1722,16 @@ fn codegen_shl<'c, 'r>(
     let shift = stack_pop(context, &ok_block)?;
     let value = stack_pop(context, &ok_block)?;
 
-    let value_255 = ok_block
+    let value_256 = ok_block
         .append_operation(arith::constant(
             context,
-            IntegerAttribute::new(uint256.into(), 255_i64).into(),
+            IntegerAttribute::new(uint256.into(), 256_i64).into(),
             location,
         ))
         .result(0)?
         .into();
 
-    flag = compare_values(context, &ok_block, CmpiPredicate::Ult, shift, value_255)?;
+    flag = compare_values(context, &ok_block, CmpiPredicate::Ult, shift, value_256)?;
 
     let ok_ok_block = region.append_block(Block::new(&[]));
     let altv_block = region.append_block(Block::new(&[]));
@@ -1385,7 +1748,7 @@ fn codegen_shl<'c, 'r>(
         location,
     ));
 
-    // if shift is less than 255
+    // if shift is less than 256
     let result = ok_ok_block
         .append_operation(arith::shli(value, shift, location))
         .result(0)?
@@ -1395,7 +1758,7 @@ fn codegen_shl<'c, 'r>(
 
     ok_ok_block.append_operation(cf::br(&empty_block, &[], location));
 
-    // if shift is greater than 255
+    // if shift is 256 or greater
     let result = altv_block
         .append_operation(arith::constant(
             context,
@@ -1423,7 +1786,7 @@ fn codegen_pop<'c, 'r>(
     // Check there's at least 1 element in stack
     let flag = check_stack_has_at_least(context, &start_block, 1)?;
 
-    let gas_flag = consume_gas(context, &start_block, gas_cost::POP)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::POP))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -1436,7 +1799,7 @@ fn codegen_pop<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1460,13 +1823,21 @@ fn codegen_mload<'c, 'r>(
     let ptr_type = pointer(context, 0);
 
     let stack_flag = check_stack_has_at_least(context, &start_block, 1)?;
+    // Check there's enough gas for the load itself, even if memory isn't extended
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::MLOAD))?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, stack_flag, location))
+        .result(0)?
+        .into();
+
     let ok_block = region.append_block(Block::new(&[]));
 
     start_block.append_operation(cf::cond_br(
         context,
-        stack_flag,
+        condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1474,13 +1845,17 @@ fn codegen_mload<'c, 'r>(
 
     let offset = stack_pop(context, &ok_block)?;
 
+    let value_size_u256 = constant_value_from_i64(context, &ok_block, 32)?;
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, &ok_block, &in_bounds_block, offset, value_size_u256)?;
+
     // Compute required memory size
-    let offset = ok_block
+    let offset = in_bounds_block
         .append_operation(arith::trunci(offset, uint32.into(), location))
         .result(0)
         .unwrap()
         .into();
-    let value_size = ok_block
+    let value_size = in_bounds_block
         .append_operation(arith::constant(
             context,
             IntegerAttribute::new(uint32.into(), 32).into(),
@@ -1488,7 +1863,7 @@ fn codegen_mload<'c, 'r>(
         ))
         .result(0)?
         .into();
-    let required_size = ok_block
+    let required_size = in_bounds_block
         .append_operation(arith::addi(offset, value_size, location))
         .result(0)?
         .into();
@@ -1497,11 +1872,11 @@ fn codegen_mload<'c, 'r>(
 
     extend_memory(
         op_ctx,
-        &ok_block,
+        &in_bounds_block,
         &memory_access_block,
         region,
         required_size,
-        gas_cost::MLOAD,
+        0,
     )?;
 
     // Memory access
@@ -1537,6 +1912,8 @@ fn codegen_mload<'c, 'r>(
         .result(0)?
         .into();
 
+    // The memory is a byte buffer allocated by the syscall context, which makes no alignment
+    // guarantees, so even word-aligned offsets can only assume byte alignment
     let read_value = memory_access_block
         .append_operation(llvm::load(
             context,
@@ -1549,17 +1926,8 @@ fn codegen_mload<'c, 'r>(
         .result(0)?
         .into();
 
-    // check system endianness before pushing the value
-    let read_value = if cfg!(target_endian = "little") {
-        // if the system is little endian, we convert the value to big endian
-        memory_access_block
-            .append_operation(llvm::intr_bswap(read_value, uint256.into(), location))
-            .result(0)?
-            .into()
-    } else {
-        // if the system is big endian, there is no need to convert the value
-        read_value
-    };
+    // the memory is big endian, so we convert the value to the native byte order
+    let read_value = swap_endianness(context, &memory_access_block, read_value)?;
 
     stack_push(context, &memory_access_block, read_value)?;
 
@@ -1576,9 +1944,9 @@ fn codegen_codesize<'c, 'r>(
     let uint256 = IntegerType::new(context, 256);
 
     // Check there's stack overflow
-    let stack_flag = check_stack_has_space_for(context, &start_block, 1)?;
+    let stack_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
     // Check there's enough gas
-    let gas_flag = consume_gas(context, &start_block, gas_cost::CODESIZE)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CODESIZE))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, stack_flag, location))
@@ -1591,7 +1959,7 @@ fn codegen_codesize<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1622,7 +1990,7 @@ fn codegen_sar<'c, 'r>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
     // Check there's enough gas
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SAR)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SAR))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -1635,7 +2003,7 @@ fn codegen_sar<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1644,31 +2012,48 @@ fn codegen_sar<'c, 'r>(
     let shift = stack_pop(context, &ok_block)?;
     let value = stack_pop(context, &ok_block)?;
 
-    // max_shift = 255
-    let max_shift = ok_block
-        .append_operation(arith::constant(
-            context,
-            integer_constant_from_i64(context, 255).into(),
-            location,
-        ))
-        .result(0)?
-        .into();
+    let value_256 = constant_value_from_i64(context, &ok_block, 256)?;
+    let is_shift_in_range =
+        compare_values(context, &ok_block, CmpiPredicate::Ult, shift, value_256)?;
+
+    let in_range_block = region.append_block(Block::new(&[]));
+    let out_of_range_block = region.append_block(Block::new(&[]));
+    // to unify the blocks after the branching
+    let end_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        is_shift_in_range,
+        &in_range_block,
+        &out_of_range_block,
+        &[],
+        &[],
+        location,
+    ));
 
-    // if shift > 255  then after applying the `shrsi` operation the result will be poisoned
-    // to avoid the poisoning we set shift = min(shift, 255)
-    let shift = ok_block
-        .append_operation(arith::minui(shift, max_shift, location))
+    // if shift is less than 256
+    let result = in_range_block
+        .append_operation(arith::shrsi(value, shift, location))
         .result(0)?
         .into();
 
-    let result = ok_block
-        .append_operation(arith::shrsi(value, shift, location))
+    stack_push(context, &in_range_block, result)?;
+
+    in_range_block.append_operation(cf::br(&end_block, &[], location));
+
+    // if shift is 256 or greater, every bit is shifted out and the result is
+    // 0 or -1 (all ones) depending on the sign bit, which is what shifting by 255 gives
+    let max_shift = constant_value_from_i64(context, &out_of_range_block, 255)?;
+    let result = out_of_range_block
+        .append_operation(arith::shrsi(value, max_shift, location))
         .result(0)?
         .into();
 
-    stack_push(context, &ok_block, result)?;
+    stack_push(context, &out_of_range_block, result)?;
 
-    Ok((start_block, ok_block))
+    out_of_range_block.append_operation(cf::br(&end_block, &[], location));
+
+    Ok((start_block, end_block))
 }
 
 fn codegen_byte<'c, 'r>(
@@ -1682,7 +2067,7 @@ fn codegen_byte<'c, 'r>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
     // Check there's enough gas
-    let gas_flag = consume_gas(context, &start_block, gas_cost::BYTE)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::BYTE))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -1703,7 +2088,7 @@ fn codegen_byte<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1715,22 +2100,16 @@ fn codegen_byte<'c, 'r>(
     const BITS_PER_BYTE: u8 = 8;
     const MAX_SHIFT: u8 = 31;
 
-    let constant_bits_per_byte = constant_value_from_i64(context, &ok_block, BITS_PER_BYTE as i64)?;
-    let constant_max_shift_in_bits =
-        constant_value_from_i64(context, &ok_block, (MAX_SHIFT * BITS_PER_BYTE) as i64)?;
-
-    let offset_in_bits = ok_block
-        .append_operation(arith::muli(offset, constant_bits_per_byte, location))
-        .result(0)?
-        .into();
+    let constant_max_shift = constant_value_from_i64(context, &ok_block, MAX_SHIFT as i64)?;
 
     // compare  offset > max_shift?
+    // This is done before converting the offset to bits, since that could overflow
     let is_offset_out_of_bounds = ok_block
         .append_operation(arith::cmpi(
             context,
             arith::CmpiPredicate::Ugt,
-            offset_in_bits,
-            constant_max_shift_in_bits,
+            offset,
+            constant_max_shift,
             location,
         ))
         .result(0)?
@@ -1777,6 +2156,19 @@ fn codegen_byte<'c, 'r>(
     //------------------------------
     // result = 0x00000000000000FF
 
+    let constant_bits_per_byte =
+        constant_value_from_i64(context, &offset_ok_block, BITS_PER_BYTE as i64)?;
+    let constant_max_shift_in_bits = constant_value_from_i64(
+        context,
+        &offset_ok_block,
+        (MAX_SHIFT * BITS_PER_BYTE) as i64,
+    )?;
+
+    let offset_in_bits = offset_ok_block
+        .append_operation(arith::muli(offset, constant_bits_per_byte, location))
+        .result(0)?
+        .into();
+
     // compute how many bits the value has to be shifted
     // shift_right_in_bits = max_shift - offset
     let shift_right_in_bits = offset_ok_block
@@ -1826,7 +2218,7 @@ fn codegen_jumpdest<'c>(
     let location = Location::unknown(context);
 
     // Check there's enough gas to compute the operation
-    let gas_flag = consume_gas(context, &landing_block, gas_cost::JUMPDEST)?;
+    let gas_flag = consume_gas(context, &landing_block, op_ctx.gas_cost(Opcode::JUMPDEST))?;
 
     let ok_block = region.append_block(Block::new(&[]));
 
@@ -1834,7 +2226,7 @@ fn codegen_jumpdest<'c>(
         context,
         gas_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1857,7 +2249,7 @@ fn codegen_jumpi<'c, 'r: 'c>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
     // Check there's enough gas
-    let gas_flag = consume_gas(context, &start_block, gas_cost::JUMPI)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::JUMPI))?;
 
     let ok_block = region.append_block(Block::new(&[]));
 
@@ -1870,7 +2262,7 @@ fn codegen_jumpi<'c, 'r: 'c>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1918,7 +2310,7 @@ fn codegen_jump<'c, 'r: 'c>(
     op_ctx: &mut OperationCtx<'c>,
     region: &'r Region<'c>,
 ) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
-    // it reverts if Counter offset is not a JUMPDEST.
+    // it halts, consuming all the gas, if Counter offset is not a JUMPDEST.
     // The error is generated even if the JUMP would not have been done
 
     let start_block = region.append_block(Block::new(&[]));
@@ -1928,7 +2320,7 @@ fn codegen_jump<'c, 'r: 'c>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 1)?;
     // Check there's enough gas
-    let gas_flag = consume_gas(context, &start_block, gas_cost::JUMP)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::JUMP))?;
 
     let ok_block = region.append_block(Block::new(&[]));
 
@@ -1941,7 +2333,7 @@ fn codegen_jump<'c, 'r: 'c>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1969,8 +2361,8 @@ fn codegen_pc<'c>(
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
 
-    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::PC)?;
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::PC))?;
 
     let ok_flag = start_block
         .append_operation(arith::andi(stack_size_flag, gas_flag, location))
@@ -1983,16 +2375,17 @@ fn codegen_pc<'c>(
         context,
         ok_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
     ));
 
+    // Casting the PC to an i64 could make it negative
     let pc_value = ok_block
         .append_operation(arith::constant(
             context,
-            integer_constant_from_i64(context, pc as i64).into(),
+            integer_constant_from_usize(context, pc),
             location,
         ))
         .result(0)?
@@ -2015,8 +2408,8 @@ fn codegen_msize<'c>(
     let uint32 = IntegerType::new(context, 32).into();
     let uint256 = IntegerType::new(context, 256).into();
 
-    let stack_flag = check_stack_has_space_for(context, &start_block, 1)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::MSIZE)?;
+    let stack_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::MSIZE))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, stack_flag, location))
@@ -2029,13 +2422,14 @@ fn codegen_msize<'c>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
     ));
 
-    // Get address of memory size global
+    // Get address of memory size global, which holds the word-aligned logical size,
+    // regardless of the capacity of the syscall context's memory
     let memory_ptr = ok_block
         .append_operation(llvm_mlir::addressof(
             context,
@@ -2083,7 +2477,7 @@ fn codegen_return<'c>(
         context,
         flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2119,7 +2513,7 @@ fn codegen_revert<'c>(
         context,
         flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2147,17 +2541,33 @@ fn codegen_stop<'c, 'r>(
     Ok((start_block, empty_block))
 }
 
-fn codegen_signextend<'c, 'r>(
+/// Generates an exceptional halt, which consumes all the remaining gas.
+/// Used for INVALID, and for operations the active spec doesn't support yet.
+fn codegen_invalid<'c, 'r>(
     op_ctx: &mut OperationCtx<'c>,
     region: &'r Region<'c>,
 ) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
     let start_block = region.append_block(Block::new(&[]));
-    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(op_ctx.mlir_context);
+
+    start_block.append_operation(cf::br(&op_ctx.error_block, &[], location));
+
+    let empty_block = region.append_block(Block::new(&[]));
+
+    Ok((start_block, empty_block))
+}
+
+fn codegen_signextend<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
 
     // Check there's enough elements in stack
     let stack_size_flag = check_stack_has_at_least(context, &start_block, 2)?;
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SIGNEXTEND)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SIGNEXTEND))?;
 
     // Check there's enough gas to perform the operation
     let ok_flag = start_block
@@ -2171,7 +2581,7 @@ fn codegen_signextend<'c, 'r>(
         context,
         ok_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2182,23 +2592,43 @@ fn codegen_signextend<'c, 'r>(
 
     // Constant definition
     let max_byte_size = constant_value_from_i64(context, &ok_block, 31)?;
-    let bits_per_byte = constant_value_from_i64(context, &ok_block, 8)?;
-    let sign_bit_position_on_byte = constant_value_from_i64(context, &ok_block, 7)?;
-    let max_bits = constant_value_from_i64(context, &ok_block, 255)?;
 
-    // byte_size = min(max_byte_size, byte_size)
-    let byte_size = ok_block
-        .append_operation(arith::minui(byte_size, max_byte_size, location))
-        .result(0)?
-        .into();
+    // If the byte index is out of range, the value is left unchanged
+    let is_in_range = compare_values(
+        context,
+        &ok_block,
+        CmpiPredicate::Ule,
+        byte_size,
+        max_byte_size,
+    )?;
+
+    let extend_block = region.append_block(Block::new(&[]));
+    let unchanged_block = region.append_block(Block::new(&[]));
+    // to unify the blocks after the branching
+    let end_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        is_in_range,
+        &extend_block,
+        &unchanged_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // if byte_size is less than 32
+    let bits_per_byte = constant_value_from_i64(context, &extend_block, 8)?;
+    let sign_bit_position_on_byte = constant_value_from_i64(context, &extend_block, 7)?;
+    let max_bits = constant_value_from_i64(context, &extend_block, 255)?;
 
     // bits_to_shift = max_bits - byte_size * bits_per_byte + sign_bit_position_on_byte
-    let byte_number_in_bits = ok_block
+    let byte_number_in_bits = extend_block
         .append_operation(arith::muli(byte_size, bits_per_byte, location))
         .result(0)?
         .into();
 
-    let value_size_in_bits = ok_block
+    let value_size_in_bits = extend_block
         .append_operation(arith::addi(
             byte_number_in_bits,
             sign_bit_position_on_byte,
@@ -2207,28 +2637,35 @@ fn codegen_signextend<'c, 'r>(
         .result(0)?
         .into();
 
-    let bits_to_shift = ok_block
+    let bits_to_shift = extend_block
         .append_operation(arith::subi(max_bits, value_size_in_bits, location))
         .result(0)?
         .into();
 
     // value_to_extend << bits_to_shift
-    let left_shifted_value = ok_block
+    let left_shifted_value = extend_block
         .append_operation(ods::llvm::shl(context, value_to_extend, bits_to_shift, location).into())
         .result(0)?
         .into();
 
     // value_to_extend >> bits_to_shift  (sign extended)
-    let result = ok_block
+    let result = extend_block
         .append_operation(
             ods::llvm::ashr(context, left_shifted_value, bits_to_shift, location).into(),
         )
         .result(0)?
         .into();
 
-    stack_push(context, &ok_block, result)?;
+    stack_push(context, &extend_block, result)?;
 
-    Ok((start_block, ok_block))
+    extend_block.append_operation(cf::br(&end_block, &[], location));
+
+    // if byte_size is 32 or greater
+    stack_push(context, &unchanged_block, value_to_extend)?;
+
+    unchanged_block.append_operation(cf::br(&end_block, &[], location));
+
+    Ok((start_block, end_block))
 }
 
 fn codegen_gas<'c, 'r>(
@@ -2240,10 +2677,10 @@ fn codegen_gas<'c, 'r>(
     let location = Location::unknown(context);
 
     // Check there's at least space for one element in the stack
-    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1)?;
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
 
     // Check there's enough gas to compute the operation
-    let gas_flag = consume_gas(context, &start_block, gas_cost::GAS)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::GAS))?;
 
     let ok_flag = start_block
         .append_operation(arith::andi(stack_size_flag, gas_flag, location))
@@ -2256,12 +2693,13 @@ fn codegen_gas<'c, 'r>(
         context,
         ok_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
     ));
 
+    // The counter was already decremented, so this is the gas left after paying for GAS
     let gas = get_remaining_gas(context, &ok_block)?;
 
     let gas_extended = ok_block
@@ -2290,7 +2728,7 @@ fn codegen_slt<'c, 'r>(
     let stack_size_flag = check_stack_has_at_least(context, &start_block, 2)?;
 
     // Check there's enough gas to compute the operation
-    let gas_flag = consume_gas(context, &start_block, gas_cost::SLT)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SLT))?;
 
     let ok_flag = start_block
         .append_operation(arith::andi(stack_size_flag, gas_flag, location))
@@ -2303,7 +2741,7 @@ fn codegen_slt<'c, 'r>(
         context,
         ok_flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2341,14 +2779,21 @@ fn codegen_mstore<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
+    // Check there's enough gas for the store itself, even if memory isn't extended
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::MSTORE))?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
 
     let ok_block = region.append_block(Block::new(&[]));
 
     start_block.append_operation(cf::cond_br(
         context,
-        flag,
+        condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2357,8 +2802,12 @@ fn codegen_mstore<'c, 'r>(
     let offset = stack_pop(context, &ok_block)?;
     let value = stack_pop(context, &ok_block)?;
 
+    let value_size_u256 = constant_value_from_i64(context, &ok_block, 32)?;
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, &ok_block, &in_bounds_block, offset, value_size_u256)?;
+
     // truncate offset to 32 bits
-    let offset = ok_block
+    let offset = in_bounds_block
         .append_operation(arith::trunci(offset, uint32.into(), location))
         .result(0)
         .unwrap()
@@ -2366,7 +2815,7 @@ fn codegen_mstore<'c, 'r>(
 
     let value_width_in_bytes = 32;
     // value_size = 32
-    let value_size = ok_block
+    let value_size = in_bounds_block
         .append_operation(arith::constant(
             context,
             IntegerAttribute::new(uint32.into(), value_width_in_bytes).into(),
@@ -2376,7 +2825,7 @@ fn codegen_mstore<'c, 'r>(
         .into();
 
     // required_size = offset + value_size
-    let required_size = ok_block
+    let required_size = in_bounds_block
         .append_operation(arith::addi(offset, value_size, location))
         .result(0)?
         .into();
@@ -2385,11 +2834,11 @@ fn codegen_mstore<'c, 'r>(
 
     extend_memory(
         op_ctx,
-        &ok_block,
+        &in_bounds_block,
         &memory_access_block,
         region,
         required_size,
-        gas_cost::MSTORE,
+        0,
     )?;
 
     // Memory access
@@ -2426,21 +2875,10 @@ fn codegen_mstore<'c, 'r>(
         .result(0)?
         .into();
 
-    let uint256 = IntegerType::new(context, 256);
-
-    // check system endianness before storing the value
-    let value = if cfg!(target_endian = "little") {
-        // if the system is little endian, we convert the value to big endian
-        memory_access_block
-            .append_operation(llvm::intr_bswap(value, uint256.into(), location))
-            .result(0)?
-            .into()
-    } else {
-        // if the system is big endian, there is no need to convert the value
-        value
-    };
+    // the memory is big endian, so we convert the value from the native byte order
+    let value = swap_endianness(context, &memory_access_block, value)?;
 
-    // store the value in the memory
+    // store the value in the memory, which is only byte-aligned (see MLOAD)
     memory_access_block.append_operation(llvm::store(
         context,
         value,
@@ -2466,14 +2904,21 @@ fn codegen_mstore8<'c, 'r>(
 
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 2)?;
+    // Check there's enough gas for the store itself, even if memory isn't extended
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::MSTORE8))?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
 
     let ok_block = region.append_block(Block::new(&[]));
 
     start_block.append_operation(cf::cond_br(
         context,
-        flag,
+        condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2482,8 +2927,12 @@ fn codegen_mstore8<'c, 'r>(
     let offset = stack_pop(context, &ok_block)?;
     let value = stack_pop(context, &ok_block)?;
 
+    let value_size_u256 = constant_value_from_i64(context, &ok_block, 1)?;
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, &ok_block, &in_bounds_block, offset, value_size_u256)?;
+
     // truncate value to the least significative byte of the 32-byte value
-    let value = ok_block
+    let value = in_bounds_block
         .append_operation(arith::trunci(
             value,
             r#IntegerType::new(context, 8).into(),
@@ -2493,7 +2942,7 @@ fn codegen_mstore8<'c, 'r>(
         .into();
 
     // truncate offset to 32 bits
-    let offset = ok_block
+    let offset = in_bounds_block
         .append_operation(arith::trunci(offset, uint32.into(), location))
         .result(0)
         .unwrap()
@@ -2501,7 +2950,7 @@ fn codegen_mstore8<'c, 'r>(
 
     let value_width_in_bytes = 1;
     // value_size = 1
-    let value_size = ok_block
+    let value_size = in_bounds_block
         .append_operation(arith::constant(
             context,
             IntegerAttribute::new(uint32.into(), value_width_in_bytes).into(),
@@ -2511,7 +2960,7 @@ fn codegen_mstore8<'c, 'r>(
         .into();
 
     // required_size = offset + size
-    let required_size = ok_block
+    let required_size = in_bounds_block
         .append_operation(arith::addi(offset, value_size, location))
         .result(0)?
         .into();
@@ -2520,11 +2969,11 @@ fn codegen_mstore8<'c, 'r>(
 
     extend_memory(
         op_ctx,
-        &ok_block,
+        &in_bounds_block,
         &memory_access_block,
         region,
         required_size,
-        gas_cost::MSTORE8,
+        0,
     )?;
 
     // Memory access
@@ -2592,7 +3041,7 @@ fn codegen_mcopy<'c, 'r>(
         context,
         flag,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2603,36 +3052,49 @@ fn codegen_mcopy<'c, 'r>(
     // where to copy from
     let offset = stack_pop(context, &ok_block)?;
     let size = stack_pop(context, &ok_block)?;
+    let offset = zero_offset_if_empty(context, &ok_block, offset, size)?;
+    let dest_offset = zero_offset_if_empty(context, &ok_block, dest_offset, size)?;
+
+    let src_in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, &ok_block, &src_in_bounds_block, offset, size)?;
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(
+        op_ctx,
+        &src_in_bounds_block,
+        &in_bounds_block,
+        dest_offset,
+        size,
+    )?;
 
     // truncate offset and dest_offset to 32 bits
-    let offset = ok_block
+    let offset = in_bounds_block
         .append_operation(arith::trunci(offset, uint32.into(), location))
         .result(0)?
         .into();
 
-    let dest_offset = ok_block
+    let dest_offset = in_bounds_block
         .append_operation(arith::trunci(dest_offset, uint32.into(), location))
         .result(0)?
         .into();
 
-    let size = ok_block
+    let size = in_bounds_block
         .append_operation(arith::trunci(size, uint32.into(), location))
         .result(0)?
         .into();
 
     // required_size = offset + size
-    let src_required_size = ok_block
+    let src_required_size = in_bounds_block
         .append_operation(arith::addi(offset, size, location))
         .result(0)?
         .into();
 
     // dest_required_size = dest_offset + size
-    let dest_required_size = ok_block
+    let dest_required_size = in_bounds_block
         .append_operation(arith::addi(dest_offset, size, location))
         .result(0)?
         .into();
 
-    let required_size = ok_block
+    let required_size = in_bounds_block
         .append_operation(arith::maxui(
             src_required_size,
             dest_required_size,
@@ -2645,11 +3107,11 @@ fn codegen_mcopy<'c, 'r>(
 
     extend_memory(
         op_ctx,
-        &ok_block,
+        &in_bounds_block,
         &memory_access_block,
         region,
         required_size,
-        gas_cost::MCOPY,
+        op_ctx.gas_cost(Opcode::MCOPY),
     )?;
 
     // Memory access
@@ -2728,7 +3190,7 @@ fn codegen_calldataload<'c, 'r>(
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, 1)?;
     // Check there's enough gas
-    let gas_flag = consume_gas(context, &start_block, gas_cost::CALLDATALOAD)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CALLDATALOAD))?;
 
     let condition = start_block
         .append_operation(arith::andi(gas_flag, flag, location))
@@ -2741,7 +3203,7 @@ fn codegen_calldataload<'c, 'r>(
         context,
         condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2779,12 +3241,94 @@ fn codegen_calldataload<'c, 'r>(
         .result(0)?
         .into();
 
+    let in_range_block = region.append_block(Block::new(&[]));
+    let boundary_block = region.append_block(Block::new(&[]));
     let offset_ok_block = region.append_block(Block::new(&[]));
     let offset_bad_block = region.append_block(Block::new(&[]));
     let end_block = region.append_block(Block::new(&[]));
 
+    // the whole word is in range if offset + 32 <= calldata_size, computed as
+    // calldata_size >= 32 && offset <= calldata_size - 32 to avoid overflowing
+    let calldata_has_a_word = ok_block
+        .append_operation(arith::cmpi(
+            context,
+            arith::CmpiPredicate::Uge,
+            calldata_size,
+            max_slice_width,
+            location,
+        ))
+        .result(0)?
+        .into();
+    let last_word_offset = ok_block
+        .append_operation(arith::subi(calldata_size, max_slice_width, location))
+        .result(0)?
+        .into();
+    let offset_is_before_last_word = ok_block
+        .append_operation(arith::cmpi(
+            context,
+            arith::CmpiPredicate::Ule,
+            offset,
+            last_word_offset,
+            location,
+        ))
+        .result(0)?
+        .into();
+    let word_in_range = ok_block
+        .append_operation(arith::andi(
+            calldata_has_a_word,
+            offset_is_before_last_word,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    // if offset + 32 <= calldata_size => in_range_block
+    // else => boundary_block
+    ok_block.append_operation(cf::cond_br(
+        context,
+        word_in_range,
+        &in_range_block,
+        &boundary_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    /******************** in_range_block *******************/
+
+    // the whole word is in the calldata, so it's loaded directly
+    let calldata_ptr_at_offset = in_range_block
+        .append_operation(llvm::get_element_ptr_dynamic(
+            context,
+            calldata_ptr,
+            &[offset],
+            uint8.into(),
+            ptr_type,
+            location,
+        ))
+        .result(0)?
+        .into();
+    let calldata_word = in_range_block
+        .append_operation(llvm::load(
+            context,
+            calldata_ptr_at_offset,
+            uint256.into(),
+            location,
+            LoadStoreOptions::new()
+                .align(IntegerAttribute::new(IntegerType::new(context, 64).into(), 1).into()),
+        ))
+        .result(0)?
+        .into();
+    let calldata_word = swap_endianness(context, &in_range_block, calldata_word)?;
+    stack_push(context, &in_range_block, calldata_word)?;
+    in_range_block.append_operation(cf::br(&end_block, &[], location));
+
+    /******************** in_range_block *******************/
+
+    /******************** boundary_block *******************/
+
     // offset < calldata_size =>  offset_ok
-    let offset_ok = ok_block
+    let offset_ok = boundary_block
         .append_operation(arith::cmpi(
             context,
             arith::CmpiPredicate::Ult,
@@ -2797,7 +3341,7 @@ fn codegen_calldataload<'c, 'r>(
 
     // if offset < calldata_size => offset_ok_block
     // else => offset_bad_block
-    ok_block.append_operation(cf::cond_br(
+    boundary_block.append_operation(cf::cond_br(
         context,
         offset_ok,
         &offset_ok_block,
@@ -2807,6 +3351,8 @@ fn codegen_calldataload<'c, 'r>(
         location,
     ));
 
+    /******************** boundary_block *******************/
+
     /******************** offset_bad_block *******************/
 
     // offset >= calldata_size => push 0
@@ -2870,19 +3416,11 @@ fn codegen_calldataload<'c, 'r>(
     // increment the stack pointer so calldata[offset..len] is placed at the top of the stack
     inc_stack_pointer(context, &offset_ok_block)?;
 
-    // if the system is little endian, we have to convert the result to big endian
-    // pop calldata_slice, change to big endian and push it again
-    if cfg!(target_endian = "little") {
-        // pop the slice
-        let calldata_slice = stack_pop(context, &offset_ok_block)?;
-        // convert it to big endian
-        let calldata_slice = offset_ok_block
-            .append_operation(llvm::intr_bswap(calldata_slice, uint256.into(), location))
-            .result(0)?
-            .into();
-        // push it back on the stack
-        stack_push(context, &offset_ok_block, calldata_slice)?;
-    }
+    // the calldata is big endian, so we have to convert the result to the native byte order
+    // pop calldata_slice, convert it and push it again
+    let calldata_slice = stack_pop(context, &offset_ok_block)?;
+    let calldata_slice = swap_endianness(context, &offset_ok_block, calldata_slice)?;
+    stack_push(context, &offset_ok_block, calldata_slice)?;
 
     offset_ok_block.append_operation(cf::br(&end_block, &[], location));
 
@@ -2896,8 +3434,12 @@ fn codegen_log<'c, 'r>(
     region: &'r Region<'c>,
     nth: u8,
 ) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
-    debug_assert!(nth <= 4);
-    // TODO: check if the current execution context is from a STATICCALL (since Byzantium fork).
+    // Unlike DUP and SWAP, an out-of-range LOG is rejected here instead of halting:
+    // the number of topics also decides which log syscall gets emitted, so there's
+    // nothing meaningful to lower it to, and the error points the caller to its pc
+    if nth > 4 {
+        return Err(CodegenError::InvalidOperation(format!("LOG{nth}")));
+    }
     let start_block = region.append_block(Block::new(&[]));
     let context = &op_ctx.mlir_context;
     let location = Location::unknown(context);
@@ -2905,14 +3447,20 @@ fn codegen_log<'c, 'r>(
     let required_elements = 2 + nth;
     // Check there's enough elements in stack
     let flag = check_stack_has_at_least(context, &start_block, required_elements.into())?;
+    // Logs can't be emitted inside a STATICCALL (since Byzantium fork)
+    let not_static_flag = check_is_not_static(op_ctx, &start_block)?;
+    let condition = start_block
+        .append_operation(arith::andi(flag, not_static_flag, location))
+        .result(0)?
+        .into();
 
     let ok_block = region.append_block(Block::new(&[]));
 
     start_block.append_operation(cf::cond_br(
         context,
-        flag,
+        condition,
         &ok_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -2920,89 +3468,2450 @@ fn codegen_log<'c, 'r>(
 
     let offset_u256 = stack_pop(context, &ok_block)?;
     let size_u256 = stack_pop(context, &ok_block)?;
+    let offset_u256 = zero_offset_if_empty(context, &ok_block, offset_u256, size_u256)?;
+
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, &ok_block, &in_bounds_block, offset_u256, size_u256)?;
 
-    let offset = ok_block
+    let offset = in_bounds_block
         .append_operation(arith::trunci(offset_u256, uint32.into(), location))
         .result(0)?
         .into();
-    let size = ok_block
+    let size = in_bounds_block
         .append_operation(arith::trunci(size_u256, uint32.into(), location))
         .result(0)?
         .into();
 
     // required_size = offset + value_size
-    let required_size = ok_block
+    let required_size = in_bounds_block
         .append_operation(arith::addi(offset, size, location))
         .result(0)?
         .into();
 
+    let dynamic_gas = compute_log_dynamic_gas(op_ctx, &in_bounds_block, nth, size_u256, location)?;
+    let dynamic_gas_flag = consume_gas_as_value(context, &in_bounds_block, dynamic_gas)?;
+
+    let memory_extension_block = region.append_block(Block::new(&[]));
     let log_block = region.append_block(Block::new(&[]));
-    let dynamic_gas = compute_log_dynamic_gas(op_ctx, &ok_block, nth, size_u256, location)?;
-    consume_gas_as_value(context, &ok_block, dynamic_gas)?;
+
+    in_bounds_block.append_operation(cf::cond_br(
+        context,
+        dynamic_gas_flag,
+        &memory_extension_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
     extend_memory(
         op_ctx,
-        &ok_block,
+        &memory_extension_block,
         &log_block,
         region,
         required_size,
-        gas_cost::LOG,
+        op_ctx.gas_schedule.cost(Opcode::LOG0 as u8 + nth),
     )?;
 
+    // The topics are popped after offset and size, so the topmost one is the
+    // first topic of the log: `topic_pointers[0]` is topic1, and so on.
     let mut topic_pointers = vec![];
-    for _i in 0..nth {
+    for i in 0..nth as usize {
         let topic = stack_pop(context, &log_block)?;
-        let topic_ptr = allocate_and_store_value(op_ctx, &log_block, topic, location)?;
+        let topic_ptr = store_in_slot(op_ctx, &log_block, i, topic, location)?;
         topic_pointers.push(topic_ptr);
     }
 
-    match nth {
-        0 => {
-            op_ctx.append_log_syscall(&log_block, offset, size, location);
-        }
-        1 => {
-            op_ctx.append_log_with_one_topic_syscall(
-                &log_block,
-                offset,
-                size,
-                topic_pointers[0],
-                location,
-            );
-        }
-        2 => {
-            op_ctx.append_log_with_two_topics_syscall(
-                &log_block,
-                offset,
-                size,
-                topic_pointers[0],
-                topic_pointers[1],
-                location,
-            );
-        }
-        3 => {
-            op_ctx.append_log_with_three_topics_syscall(
-                &log_block,
-                offset,
-                size,
-                topic_pointers[0],
-                topic_pointers[1],
-                topic_pointers[2],
-                location,
-            );
-        }
-        4 => {
-            op_ctx.append_log_with_four_topics_syscall(
-                &log_block,
-                offset,
-                size,
-                topic_pointers[0],
-                topic_pointers[1],
-                topic_pointers[2],
-                topic_pointers[3],
-                location,
-            );
-        }
+    let appended = match nth {
+        0 => op_ctx.append_log_syscall(&log_block, offset, size, location)?,
+        1 => op_ctx.append_log_with_one_topic_syscall(
+            &log_block,
+            offset,
+            size,
+            topic_pointers[0],
+            location,
+        )?,
+        2 => op_ctx.append_log_with_two_topics_syscall(
+            &log_block,
+            offset,
+            size,
+            topic_pointers[0],
+            topic_pointers[1],
+            location,
+        )?,
+        3 => op_ctx.append_log_with_three_topics_syscall(
+            &log_block,
+            offset,
+            size,
+            topic_pointers[0],
+            topic_pointers[1],
+            topic_pointers[2],
+            location,
+        )?,
+        4 => op_ctx.append_log_with_four_topics_syscall(
+            &log_block,
+            offset,
+            size,
+            topic_pointers[0],
+            topic_pointers[1],
+            topic_pointers[2],
+            topic_pointers[3],
+            location,
+        )?,
         _ => unreachable!("nth should satisfy 0 <= nth <= 4"),
-    }
+    };
+
+    let zero = log_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(IntegerType::new(context, 8).into(), 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let appended_flag = compare_values(context, &log_block, CmpiPredicate::Ne, appended, zero)?;
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    log_block.append_operation(cf::cond_br(
+        context,
+        appended_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_keccak256<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+    let uint64 = IntegerType::new(context, 64);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 2)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::KECCAK256))?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let offset_u256 = stack_pop(context, &ok_block)?;
+    let size_u256 = stack_pop(context, &ok_block)?;
+    let offset_u256 = zero_offset_if_empty(context, &ok_block, offset_u256, size_u256)?;
+
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, &ok_block, &in_bounds_block, offset_u256, size_u256)?;
+
+    // dynamic_gas = 6 * ((size + 31) / 32)
+    let constant_31 = constant_value_from_i64(context, &in_bounds_block, 31)?;
+    let size_plus_31 = in_bounds_block
+        .append_operation(arith::addi(size_u256, constant_31, location))
+        .result(0)?
+        .into();
+    let constant_32 = constant_value_from_i64(context, &in_bounds_block, 32)?;
+    let size_in_words = in_bounds_block
+        .append_operation(arith::divui(size_plus_31, constant_32, location))
+        .result(0)?
+        .into();
+    let gas_per_word =
+        constant_value_from_i64(context, &in_bounds_block, gas_cost::KECCAK256_PER_WORD)?;
+    let dynamic_gas = in_bounds_block
+        .append_operation(arith::muli(size_in_words, gas_per_word, location))
+        .result(0)?
+        .into();
+    let dynamic_gas = in_bounds_block
+        .append_operation(arith::trunci(dynamic_gas, uint64.into(), location))
+        .result(0)?
+        .into();
+    let dynamic_gas_flag = consume_gas_as_value(context, &in_bounds_block, dynamic_gas)?;
+
+    let memory_extension_block = region.append_block(Block::new(&[]));
+
+    in_bounds_block.append_operation(cf::cond_br(
+        context,
+        dynamic_gas_flag,
+        &memory_extension_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let offset = memory_extension_block
+        .append_operation(arith::trunci(offset_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+    let size = memory_extension_block
+        .append_operation(arith::trunci(size_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    // required_size = offset + size
+    let required_size = memory_extension_block
+        .append_operation(arith::addi(offset, size, location))
+        .result(0)?
+        .into();
+
+    let hash_block = region.append_block(Block::new(&[]));
+
+    extend_memory(
+        op_ctx,
+        &memory_extension_block,
+        &hash_block,
+        region,
+        required_size,
+        0,
+    )?;
+
+    // Allocate a slot for the syscall to write the hash into
+    let hash_ptr = op_ctx.u256_slots[0];
+
+    let hashed = op_ctx.keccak256_syscall(&hash_block, offset, size, hash_ptr, location)?;
+    let zero = hash_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(IntegerType::new(context, 8).into(), 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let hashed_flag = compare_values(context, &hash_block, CmpiPredicate::Ne, hashed, zero)?;
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    hash_block.append_operation(cf::cond_br(
+        context,
+        hashed_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
 
-    Ok((start_block, log_block))
+    let hash = end_block
+        .append_operation(llvm::load(
+            context,
+            hash_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &end_block, hash)?;
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_sload<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let key = stack_pop(context, &ok_block)?;
+    let key_ptr = store_in_slot(op_ctx, &ok_block, 0, key, location)?;
+    let value_ptr = op_ctx.u256_slots[1];
+
+    // The gas cost depends on whether the slot was already accessed.
+    // It isn't read if there's not enough gas left to pay for it
+    let remaining_gas = get_remaining_gas(context, &ok_block)?;
+    let dynamic_gas =
+        op_ctx.storage_read_syscall(&ok_block, key_ptr, value_ptr, remaining_gas, location)?;
+    let gas_flag = consume_gas_as_value(context, &ok_block, dynamic_gas)?;
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        gas_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let value = end_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &end_block, value)?;
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_sstore<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint64 = IntegerType::new(context, 64);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 2)?;
+
+    // Check the remaining gas is above the SSTORE sentry (EIP-2200)
+    let remaining_gas = get_remaining_gas(context, &start_block)?;
+    let min_remaining_gas = start_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint64.into(), gas_cost::SSTORE_MIN_REMAINING_GAS).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let gas_sentry_flag = compare_values(
+        context,
+        &start_block,
+        CmpiPredicate::Ugt,
+        remaining_gas,
+        min_remaining_gas,
+    )?;
+
+    // Storage can't be modified inside a STATICCALL
+    let not_static_flag = check_is_not_static(op_ctx, &start_block)?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_sentry_flag, flag, location))
+        .result(0)?
+        .into();
+    let condition = start_block
+        .append_operation(arith::andi(condition, not_static_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let key = stack_pop(context, &ok_block)?;
+    let value = stack_pop(context, &ok_block)?;
+    let key_ptr = store_in_slot(op_ctx, &ok_block, 0, key, location)?;
+    let value_ptr = store_in_slot(op_ctx, &ok_block, 1, value, location)?;
+
+    // The gas cost depends on the current and original values of the slot.
+    // Nothing is written if there's not enough gas left to pay for it
+    let remaining_gas = get_remaining_gas(context, &ok_block)?;
+    let dynamic_gas =
+        op_ctx.storage_write_syscall(&ok_block, key_ptr, value_ptr, remaining_gas, location)?;
+    let gas_flag = consume_gas_as_value(context, &ok_block, dynamic_gas)?;
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        gas_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_tload<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+    // Check there's enough gas
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::TLOAD))?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let key = stack_pop(context, &ok_block)?;
+    let key_ptr = store_in_slot(op_ctx, &ok_block, 0, key, location)?;
+    let value_ptr = op_ctx.u256_slots[1];
+
+    op_ctx.transient_read_syscall(&ok_block, key_ptr, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_tstore<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 2)?;
+    // Check there's enough gas
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::TSTORE))?;
+    // Transient storage can't be modified inside a STATICCALL either
+    let not_static_flag = check_is_not_static(op_ctx, &start_block)?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+    let condition = start_block
+        .append_operation(arith::andi(condition, not_static_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let key = stack_pop(context, &ok_block)?;
+    let value = stack_pop(context, &ok_block)?;
+    let key_ptr = store_in_slot(op_ctx, &ok_block, 0, key, location)?;
+    let value_ptr = store_in_slot(op_ctx, &ok_block, 1, value, location)?;
+
+    op_ctx.transient_write_syscall(&ok_block, key_ptr, value_ptr, location);
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_callvalue<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CALLVALUE))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the call value using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_callvalue_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_caller<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CALLER))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the caller address using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_caller_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_origin<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::ORIGIN))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the origin address using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_origin_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_address<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::ADDRESS))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the contract address using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_address_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_calldatacopy<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint8 = IntegerType::new(context, 8);
+    let uint32 = IntegerType::new(context, 32);
+    let uint256 = IntegerType::new(context, 256);
+    let ptr_type = pointer(context, 0);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 3)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CALLDATACOPY))?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // where to copy to in memory
+    let dest_offset_u256 = stack_pop(context, &ok_block)?;
+    // where to copy from in calldata
+    let offset = stack_pop(context, &ok_block)?;
+    let size_u256 = stack_pop(context, &ok_block)?;
+    let dest_offset_u256 = zero_offset_if_empty(context, &ok_block, dest_offset_u256, size_u256)?;
+
+    let copy_block = region.append_block(Block::new(&[]));
+
+    extend_memory_for_copy(
+        op_ctx,
+        &ok_block,
+        &copy_block,
+        region,
+        dest_offset_u256,
+        size_u256,
+    )?;
+
+    let dest_offset = copy_block
+        .append_operation(arith::trunci(dest_offset_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    // Memory access
+    let memory_ptr_ptr = copy_block
+        .append_operation(llvm_mlir::addressof(
+            context,
+            MEMORY_PTR_GLOBAL,
+            ptr_type,
+            location,
+        ))
+        .result(0)?;
+
+    let memory_ptr = copy_block
+        .append_operation(llvm::load(
+            context,
+            memory_ptr_ptr.into(),
+            ptr_type,
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    // memory_destination = memory_ptr + dest_offset
+    let destination = copy_block
+        .append_operation(llvm::get_element_ptr_dynamic(
+            context,
+            memory_ptr,
+            &[dest_offset],
+            uint8.into(),
+            ptr_type,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let calldata_ptr = op_ctx.get_calldata_ptr_syscall(&copy_block, location)?;
+    let calldata_size_u32 = op_ctx.get_calldata_size_syscall(&copy_block, location)?;
+    let calldata_size = copy_block
+        .append_operation(arith::extui(calldata_size_u32, uint256.into(), location))
+        .result(0)?
+        .into();
+
+    // copy calldata[offset..offset + size] to memory[dest_offset..dest_offset + size]
+    copy_zero_padded(
+        context,
+        &copy_block,
+        destination,
+        calldata_ptr,
+        calldata_size,
+        offset,
+        size_u256,
+    )?;
+
+    Ok((start_block, copy_block))
+}
+
+fn codegen_codecopy<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint8 = IntegerType::new(context, 8);
+    let uint32 = IntegerType::new(context, 32);
+    let uint256 = IntegerType::new(context, 256);
+    let ptr_type = pointer(context, 0);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 3)?;
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CODECOPY))?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // where to copy to in memory
+    let dest_offset_u256 = stack_pop(context, &ok_block)?;
+    // where to copy from in the bytecode
+    let offset = stack_pop(context, &ok_block)?;
+    let size_u256 = stack_pop(context, &ok_block)?;
+    let dest_offset_u256 = zero_offset_if_empty(context, &ok_block, dest_offset_u256, size_u256)?;
+
+    let copy_block = region.append_block(Block::new(&[]));
+
+    extend_memory_for_copy(
+        op_ctx,
+        &ok_block,
+        &copy_block,
+        region,
+        dest_offset_u256,
+        size_u256,
+    )?;
+
+    let dest_offset = copy_block
+        .append_operation(arith::trunci(dest_offset_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    // Memory access
+    let memory_ptr_ptr = copy_block
+        .append_operation(llvm_mlir::addressof(
+            context,
+            MEMORY_PTR_GLOBAL,
+            ptr_type,
+            location,
+        ))
+        .result(0)?;
+
+    let memory_ptr = copy_block
+        .append_operation(llvm::load(
+            context,
+            memory_ptr_ptr.into(),
+            ptr_type,
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    // memory_destination = memory_ptr + dest_offset
+    let destination = copy_block
+        .append_operation(llvm::get_element_ptr_dynamic(
+            context,
+            memory_ptr,
+            &[dest_offset],
+            uint8.into(),
+            ptr_type,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let code_ptr = copy_block
+        .append_operation(llvm_mlir::addressof(
+            context,
+            CODE_GLOBAL,
+            ptr_type,
+            location,
+        ))
+        .result(0)?
+        .into();
+    let code_size = copy_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint256.into(), op_ctx.program.code_size as i64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    // copy code[offset..offset + size] to memory[dest_offset..dest_offset + size]
+    copy_zero_padded(
+        context,
+        &copy_block,
+        destination,
+        code_ptr,
+        code_size,
+        offset,
+        size_u256,
+    )?;
+
+    Ok((start_block, copy_block))
+}
+
+fn codegen_returndatasize<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(
+        context,
+        &start_block,
+        op_ctx.gas_cost(Opcode::RETURNDATASIZE),
+    )?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the return data size using a syscall
+    let uint256 = IntegerType::new(context, 256).into();
+    let returndata_size = op_ctx.get_returndata_size_syscall(&ok_block, location)?;
+    let extended_size = ok_block
+        .append_operation(arith::extui(returndata_size, uint256, location))
+        .result(0)?
+        .into();
+    stack_push(context, &ok_block, extended_size)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_returndatacopy<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 3)?;
+    let gas_flag = consume_gas(
+        context,
+        &start_block,
+        op_ctx.gas_cost(Opcode::RETURNDATACOPY),
+    )?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // where to copy to in memory
+    let dest_offset_u256 = stack_pop(context, &ok_block)?;
+    // where to copy from in the return data
+    let offset_u256 = stack_pop(context, &ok_block)?;
+    let size_u256 = stack_pop(context, &ok_block)?;
+    let dest_offset_u256 = zero_offset_if_empty(context, &ok_block, dest_offset_u256, size_u256)?;
+
+    let returndata_size = op_ctx.get_returndata_size_syscall(&ok_block, location)?;
+    let returndata_size = ok_block
+        .append_operation(arith::extui(returndata_size, uint256.into(), location))
+        .result(0)?
+        .into();
+
+    // Unlike CALLDATACOPY, reading past the end of the return data is an error.
+    // offset <= returndata_size && size <= returndata_size - offset
+    let offset_flag = compare_values(
+        context,
+        &ok_block,
+        CmpiPredicate::Ule,
+        offset_u256,
+        returndata_size,
+    )?;
+    let available_bytes = ok_block
+        .append_operation(arith::subi(returndata_size, offset_u256, location))
+        .result(0)?
+        .into();
+    let size_flag = compare_values(
+        context,
+        &ok_block,
+        CmpiPredicate::Ule,
+        size_u256,
+        available_bytes,
+    )?;
+    let bounds_flag = ok_block
+        .append_operation(arith::andi(offset_flag, size_flag, location))
+        .result(0)?
+        .into();
+
+    let bounds_ok_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        bounds_flag,
+        &bounds_ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let copy_block = region.append_block(Block::new(&[]));
+
+    extend_memory_for_copy(
+        op_ctx,
+        &bounds_ok_block,
+        &copy_block,
+        region,
+        dest_offset_u256,
+        size_u256,
+    )?;
+
+    let dest_offset = copy_block
+        .append_operation(arith::trunci(dest_offset_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+    let offset = copy_block
+        .append_operation(arith::trunci(offset_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+    let size = copy_block
+        .append_operation(arith::trunci(size_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    let copied =
+        op_ctx.copy_returndata_syscall(&copy_block, dest_offset, offset, size, location)?;
+    let zero = copy_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(IntegerType::new(context, 8).into(), 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let copied_flag = compare_values(context, &copy_block, CmpiPredicate::Ne, copied, zero)?;
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    copy_block.append_operation(cf::cond_br(
+        context,
+        copied_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_call<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+    let uint64 = IntegerType::new(context, 64);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 7)?;
+    // Check there's enough gas
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CALL))?;
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let gas = stack_pop(context, &ok_block)?;
+    let address = stack_pop(context, &ok_block)?;
+    let value = stack_pop(context, &ok_block)?;
+    let args_offset = stack_pop(context, &ok_block)?;
+    let args_size = stack_pop(context, &ok_block)?;
+    let ret_offset = stack_pop(context, &ok_block)?;
+    let ret_size = stack_pop(context, &ok_block)?;
+    let args_offset = zero_offset_if_empty(context, &ok_block, args_offset, args_size)?;
+    let ret_offset = zero_offset_if_empty(context, &ok_block, ret_offset, ret_size)?;
+
+    let args_in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(
+        op_ctx,
+        &ok_block,
+        &args_in_bounds_block,
+        args_offset,
+        args_size,
+    )?;
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(
+        op_ctx,
+        &args_in_bounds_block,
+        &in_bounds_block,
+        ret_offset,
+        ret_size,
+    )?;
+
+    // Transferring value has an extra cost
+    let zero = constant_value_from_i64(context, &in_bounds_block, 0)?;
+    let is_value_transfer =
+        compare_values(context, &in_bounds_block, CmpiPredicate::Ne, value, zero)?;
+    let value_transfer_cost = in_bounds_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint64.into(), gas_cost::CALL_VALUE_TRANSFER).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let no_cost = in_bounds_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint64.into(), 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let value_transfer_cost = in_bounds_block
+        .append_operation(arith::select(
+            is_value_transfer,
+            value_transfer_cost,
+            no_cost,
+            location,
+        ))
+        .result(0)?
+        .into();
+    // Accessing the callee, and creating it if it's empty, have a dynamic cost
+    let address_ptr = store_in_slot(op_ctx, &in_bounds_block, 0, address, location)?;
+    let value_ptr = store_in_slot(op_ctx, &in_bounds_block, 1, value, location)?;
+    let dynamic_gas =
+        op_ctx.get_call_gas_syscall(&in_bounds_block, address_ptr, value_ptr, location)?;
+    let dynamic_gas = in_bounds_block
+        .append_operation(arith::addi(value_transfer_cost, dynamic_gas, location))
+        .result(0)?
+        .into();
+    let value_transfer_gas_flag = consume_gas_as_value(context, &in_bounds_block, dynamic_gas)?;
+
+    // Value can't be transferred inside a STATICCALL
+    let not_static_flag = check_is_not_static(op_ctx, &in_bounds_block)?;
+    let no_value_transfer =
+        compare_values(context, &in_bounds_block, CmpiPredicate::Eq, value, zero)?;
+    let value_transfer_allowed = in_bounds_block
+        .append_operation(arith::ori(not_static_flag, no_value_transfer, location))
+        .result(0)?
+        .into();
+    let condition = in_bounds_block
+        .append_operation(arith::andi(
+            value_transfer_gas_flag,
+            value_transfer_allowed,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let memory_extension_block = region.append_block(Block::new(&[]));
+
+    in_bounds_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &memory_extension_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // truncate the memory offsets and sizes to 32 bits
+    let args_offset = memory_extension_block
+        .append_operation(arith::trunci(args_offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let args_size = memory_extension_block
+        .append_operation(arith::trunci(args_size, uint32.into(), location))
+        .result(0)?
+        .into();
+    let ret_offset = memory_extension_block
+        .append_operation(arith::trunci(ret_offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let ret_size = memory_extension_block
+        .append_operation(arith::trunci(ret_size, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    // required_size = max(args_offset + args_size, ret_offset + ret_size)
+    let args_required_size = memory_extension_block
+        .append_operation(arith::addi(args_offset, args_size, location))
+        .result(0)?
+        .into();
+    let ret_required_size = memory_extension_block
+        .append_operation(arith::addi(ret_offset, ret_size, location))
+        .result(0)?
+        .into();
+    let required_size = memory_extension_block
+        .append_operation(arith::maxui(
+            args_required_size,
+            ret_required_size,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    let call_block = region.append_block(Block::new(&[]));
+
+    extend_memory(
+        op_ctx,
+        &memory_extension_block,
+        &call_block,
+        region,
+        required_size,
+        0,
+    )?;
+
+    // All but one 64th of the remaining gas can be given to the callee (EIP-150)
+    let remaining_gas = get_remaining_gas(context, &call_block)?;
+    let constant_64 = call_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint64.into(), 64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let one_64th = call_block
+        .append_operation(arith::divui(remaining_gas, constant_64, location))
+        .result(0)?
+        .into();
+    let max_callee_gas = call_block
+        .append_operation(arith::subi(remaining_gas, one_64th, location))
+        .result(0)?
+        .into();
+    let max_callee_gas = call_block
+        .append_operation(arith::extui(max_callee_gas, uint256.into(), location))
+        .result(0)?
+        .into();
+    // callee_gas = min(gas, max_callee_gas)
+    let callee_gas = call_block
+        .append_operation(arith::minui(gas, max_callee_gas, location))
+        .result(0)?
+        .into();
+    let callee_gas = call_block
+        .append_operation(arith::trunci(callee_gas, uint64.into(), location))
+        .result(0)?
+        .into();
+    // this always succeeds, since callee_gas <= remaining_gas
+    consume_gas_as_value(context, &call_block, callee_gas)?;
+
+    let gas_remaining_ptr = op_ctx.gas_slot;
+
+    let success = op_ctx.call_syscall(
+        &call_block,
+        callee_gas,
+        address_ptr,
+        value_ptr,
+        args_offset,
+        args_size,
+        ret_offset,
+        ret_size,
+        gas_remaining_ptr,
+        location,
+    )?;
+
+    // Give back the gas the callee didn't use
+    let callee_gas_remaining = call_block
+        .append_operation(llvm::load(
+            context,
+            gas_remaining_ptr,
+            uint64.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+    return_unused_gas(context, &call_block, callee_gas_remaining)?;
+
+    let success = call_block
+        .append_operation(arith::extui(success, uint256.into(), location))
+        .result(0)?
+        .into();
+    stack_push(context, &call_block, success)?;
+
+    Ok((start_block, call_block))
+}
+
+fn codegen_gasprice<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::GASPRICE))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the gas price using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_gasprice_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_blockhash<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+    // Check there's enough gas
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::BLOCKHASH))?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let number = stack_pop(context, &ok_block)?;
+    let number_ptr = store_in_slot(op_ctx, &ok_block, 0, number, location)?;
+
+    // The syscall replaces the block number with the block's hash
+    op_ctx.get_block_hash_syscall(&ok_block, number_ptr, location);
+
+    let hash = ok_block
+        .append_operation(llvm::load(
+            context,
+            number_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, hash)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_coinbase<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::COINBASE))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the coinbase address using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_coinbase_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_timestamp<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::TIMESTAMP))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the block timestamp using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_timestamp_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_number<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::NUMBER))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the block number using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_block_number_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_gaslimit<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::GASLIMIT))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the block gas limit using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_gaslimit_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_chainid<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::CHAINID))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the chain ID using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_chainid_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_prevrandao<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::PREVRANDAO))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the block randomness using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_prevrandao_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_basefee<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::BASEFEE))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the block base fee using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_basefee_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_balance<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let address = stack_pop(context, &ok_block)?;
+    let address_ptr = store_in_slot(op_ctx, &ok_block, 0, address, location)?;
+    let balance_ptr = op_ctx.u256_slots[1];
+
+    // The gas cost depends on whether the account was already accessed
+    let dynamic_gas = op_ctx.get_balance_syscall(&ok_block, address_ptr, balance_ptr, location)?;
+    let gas_flag = consume_gas_as_value(context, &ok_block, dynamic_gas)?;
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        gas_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let balance = end_block
+        .append_operation(llvm::load(
+            context,
+            balance_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &end_block, balance)?;
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_selfbalance<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's at least space for one element in the stack
+    let stack_size_flag = check_stack_has_space_for(context, &start_block, 1, op_ctx.stack_size)?;
+
+    // Check there's enough gas to compute the operation
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SELFBALANCE))?;
+
+    let ok_flag = start_block
+        .append_operation(arith::andi(stack_size_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        ok_flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // Get the contract's balance using a syscall
+    let value_ptr = op_ctx.u256_slots[0];
+    op_ctx.get_self_balance_syscall(&ok_block, value_ptr, location);
+
+    let value = ok_block
+        .append_operation(llvm::load(
+            context,
+            value_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &ok_block, value)?;
+
+    Ok((start_block, ok_block))
+}
+
+fn codegen_selfdestruct<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+    // Check there's enough gas
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(Opcode::SELFDESTRUCT))?;
+    // Accounts can't be destroyed inside a STATICCALL
+    let not_static_flag = check_is_not_static(op_ctx, &start_block)?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+    let condition = start_block
+        .append_operation(arith::andi(condition, not_static_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let beneficiary = stack_pop(context, &ok_block)?;
+    let beneficiary_ptr = store_in_slot(op_ctx, &ok_block, 0, beneficiary, location)?;
+
+    // The gas cost depends on whether the beneficiary was already accessed, and if it's empty
+    let dynamic_gas = op_ctx.get_selfdestruct_gas_syscall(&ok_block, beneficiary_ptr, location)?;
+    let gas_flag = consume_gas_as_value(context, &ok_block, dynamic_gas)?;
+
+    let destruct_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        gas_flag,
+        &destruct_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    op_ctx.selfdestruct_syscall(&destruct_block, beneficiary_ptr, location);
+
+    // The execution stops right after the destruction
+    return_empty_result(op_ctx, &destruct_block, ExitStatusCode::Stop, location)?;
+
+    let empty_block = region.append_block(Block::new(&[]));
+
+    Ok((start_block, empty_block))
+}
+
+fn codegen_create<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+    is_create2: bool,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+    let uint64 = IntegerType::new(context, 64);
+    let uint256 = IntegerType::new(context, 256);
+
+    let stack_items = if is_create2 { 4 } else { 3 };
+    let opcode = if is_create2 {
+        Opcode::CREATE2
+    } else {
+        Opcode::CREATE
+    };
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, stack_items)?;
+    // Check there's enough gas
+    let gas_flag = consume_gas(context, &start_block, op_ctx.gas_cost(opcode))?;
+    // Contracts can't be created inside a STATICCALL
+    let not_static_flag = check_is_not_static(op_ctx, &start_block)?;
+
+    let condition = start_block
+        .append_operation(arith::andi(gas_flag, flag, location))
+        .result(0)?
+        .into();
+    let condition = start_block
+        .append_operation(arith::andi(condition, not_static_flag, location))
+        .result(0)?
+        .into();
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let value = stack_pop(context, &ok_block)?;
+    let offset = stack_pop(context, &ok_block)?;
+    let size = stack_pop(context, &ok_block)?;
+    let salt = if is_create2 {
+        Some(stack_pop(context, &ok_block)?)
+    } else {
+        None
+    };
+    let offset = zero_offset_if_empty(context, &ok_block, offset, size)?;
+
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, &ok_block, &in_bounds_block, offset, size)?;
+
+    // The init code is paid per word from Shanghai, and CREATE2 also pays for hashing it
+    let mut gas_per_word = 0;
+    if op_ctx.spec.enabled(Spec::Shanghai) {
+        gas_per_word += gas_cost::INIT_CODE_WORD_COST as i64;
+    }
+    if is_create2 {
+        gas_per_word += gas_cost::KECCAK256_PER_WORD;
+    }
+    // dynamic_gas = gas_per_word * ((size + 31) / 32)
+    let constant_31 = constant_value_from_i64(context, &in_bounds_block, 31)?;
+    let size_plus_31 = in_bounds_block
+        .append_operation(arith::addi(size, constant_31, location))
+        .result(0)?
+        .into();
+    let constant_32 = constant_value_from_i64(context, &in_bounds_block, 32)?;
+    let size_in_words = in_bounds_block
+        .append_operation(arith::divui(size_plus_31, constant_32, location))
+        .result(0)?
+        .into();
+    let gas_per_word = constant_value_from_i64(context, &in_bounds_block, gas_per_word)?;
+    let dynamic_gas = in_bounds_block
+        .append_operation(arith::muli(size_in_words, gas_per_word, location))
+        .result(0)?
+        .into();
+    let dynamic_gas = in_bounds_block
+        .append_operation(arith::trunci(dynamic_gas, uint64.into(), location))
+        .result(0)?
+        .into();
+    let mut condition = consume_gas_as_value(context, &in_bounds_block, dynamic_gas)?;
+
+    // The init code's size is limited from Shanghai
+    if op_ctx.spec.enabled(Spec::Shanghai) {
+        let max_size =
+            constant_value_from_i64(context, &in_bounds_block, MAX_INITCODE_SIZE as i64)?;
+        let size_flag = compare_values(
+            context,
+            &in_bounds_block,
+            CmpiPredicate::Ule,
+            size,
+            max_size,
+        )?;
+        condition = in_bounds_block
+            .append_operation(arith::andi(condition, size_flag, location))
+            .result(0)?
+            .into();
+    }
+
+    let memory_extension_block = region.append_block(Block::new(&[]));
+
+    in_bounds_block.append_operation(cf::cond_br(
+        context,
+        condition,
+        &memory_extension_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    // truncate the memory offset and size to 32 bits
+    let offset = memory_extension_block
+        .append_operation(arith::trunci(offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let size = memory_extension_block
+        .append_operation(arith::trunci(size, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    // required_size = offset + size
+    let required_size = memory_extension_block
+        .append_operation(arith::addi(offset, size, location))
+        .result(0)?
+        .into();
+
+    let create_block = region.append_block(Block::new(&[]));
+
+    extend_memory(
+        op_ctx,
+        &memory_extension_block,
+        &create_block,
+        region,
+        required_size,
+        0,
+    )?;
+
+    // All but one 64th of the remaining gas is given to the init code (EIP-150)
+    let remaining_gas = get_remaining_gas(context, &create_block)?;
+    let constant_64 = create_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint64.into(), 64).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let one_64th = create_block
+        .append_operation(arith::divui(remaining_gas, constant_64, location))
+        .result(0)?
+        .into();
+    let callee_gas = create_block
+        .append_operation(arith::subi(remaining_gas, one_64th, location))
+        .result(0)?
+        .into();
+    // this always succeeds, since callee_gas <= remaining_gas
+    consume_gas_as_value(context, &create_block, callee_gas)?;
+
+    let value_ptr = store_in_slot(op_ctx, &create_block, 0, value, location)?;
+    let salt_ptr = salt
+        .map(|salt| store_in_slot(op_ctx, &create_block, 1, salt, location))
+        .transpose()?;
+    let address_ptr = op_ctx.u256_slots[2];
+    let gas_remaining_ptr = op_ctx.gas_slot;
+
+    op_ctx.create_syscall(
+        &create_block,
+        offset,
+        size,
+        value_ptr,
+        salt_ptr,
+        callee_gas,
+        gas_remaining_ptr,
+        address_ptr,
+        location,
+    );
+
+    // Give back the gas the init code didn't use
+    let callee_gas_remaining = create_block
+        .append_operation(llvm::load(
+            context,
+            gas_remaining_ptr,
+            uint64.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+    return_unused_gas(context, &create_block, callee_gas_remaining)?;
+
+    // The address is 0 if the creation failed
+    let address = create_block
+        .append_operation(llvm::load(
+            context,
+            address_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+    stack_push(context, &create_block, address)?;
+
+    Ok((start_block, create_block))
+}
+
+fn codegen_extcodesize<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let address = stack_pop(context, &ok_block)?;
+    let address_ptr = store_in_slot(op_ctx, &ok_block, 0, address, location)?;
+    let size_ptr = op_ctx.u256_slots[1];
+
+    // The gas cost depends on whether the account was already accessed
+    let dynamic_gas =
+        op_ctx.get_ext_code_size_syscall(&ok_block, address_ptr, size_ptr, location)?;
+    let gas_flag = consume_gas_as_value(context, &ok_block, dynamic_gas)?;
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        gas_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let size = end_block
+        .append_operation(llvm::load(
+            context,
+            size_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &end_block, size)?;
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_extcodehash<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint256 = IntegerType::new(context, 256);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let address = stack_pop(context, &ok_block)?;
+    let address_ptr = store_in_slot(op_ctx, &ok_block, 0, address, location)?;
+    let hash_ptr = op_ctx.u256_slots[1];
+
+    // The gas cost depends on whether the account was already accessed
+    let dynamic_gas =
+        op_ctx.get_ext_code_hash_syscall(&ok_block, address_ptr, hash_ptr, location)?;
+    let gas_flag = consume_gas_as_value(context, &ok_block, dynamic_gas)?;
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    ok_block.append_operation(cf::cond_br(
+        context,
+        gas_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let hash = end_block
+        .append_operation(llvm::load(
+            context,
+            hash_ptr,
+            uint256.into(),
+            location,
+            LoadStoreOptions::default(),
+        ))
+        .result(0)?
+        .into();
+
+    stack_push(context, &end_block, hash)?;
+
+    Ok((start_block, end_block))
+}
+
+fn codegen_extcodecopy<'c, 'r>(
+    op_ctx: &mut OperationCtx<'c>,
+    region: &'r Region<'c>,
+) -> Result<(BlockRef<'c, 'r>, BlockRef<'c, 'r>), CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = &op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+
+    // Check there's enough elements in stack
+    let flag = check_stack_has_at_least(context, &start_block, 4)?;
+
+    let ok_block = region.append_block(Block::new(&[]));
+
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &ok_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let address = stack_pop(context, &ok_block)?;
+    // where to copy to in memory
+    let dest_offset_u256 = stack_pop(context, &ok_block)?;
+    // where to copy from in the account's code
+    let code_offset = stack_pop(context, &ok_block)?;
+    let size_u256 = stack_pop(context, &ok_block)?;
+    let dest_offset_u256 = zero_offset_if_empty(context, &ok_block, dest_offset_u256, size_u256)?;
+
+    let copy_block = region.append_block(Block::new(&[]));
+
+    extend_memory_for_copy(
+        op_ctx,
+        &ok_block,
+        &copy_block,
+        region,
+        dest_offset_u256,
+        size_u256,
+    )?;
+
+    let dest_offset = copy_block
+        .append_operation(arith::trunci(dest_offset_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+    let size = copy_block
+        .append_operation(arith::trunci(size_u256, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    let address_ptr = store_in_slot(op_ctx, &copy_block, 0, address, location)?;
+    let code_offset_ptr = store_in_slot(op_ctx, &copy_block, 1, code_offset, location)?;
+
+    // copy code[code_offset..code_offset + size] to memory[dest_offset..dest_offset + size].
+    // The gas cost depends on whether the account was already accessed
+    let access_gas = op_ctx.copy_ext_code_syscall(
+        &copy_block,
+        address_ptr,
+        code_offset_ptr,
+        dest_offset,
+        size,
+        location,
+    )?;
+    // a negative cost means the destination was out of bounds, and nothing was copied
+    let zero = copy_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(IntegerType::new(context, 64).into(), 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let copied_flag = compare_values(context, &copy_block, CmpiPredicate::Sge, access_gas, zero)?;
+    let gas_flag = consume_gas_as_value(context, &copy_block, access_gas)?;
+    let access_gas_flag = copy_block
+        .append_operation(arith::andi(copied_flag, gas_flag, location))
+        .result(0)?
+        .into();
+
+    let end_block = region.append_block(Block::new(&[]));
+
+    copy_block.append_operation(cf::cond_br(
+        context,
+        access_gas_flag,
+        &end_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    Ok((start_block, end_block))
 }
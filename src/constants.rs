@@ -3,19 +3,38 @@ pub const GAS_COUNTER_GLOBAL: &str = "evm_mlir__gas_counter";
 pub const STACK_BASEPTR_GLOBAL: &str = "evm_mlir__stack_baseptr";
 pub const STACK_PTR_GLOBAL: &str = "evm_mlir__stack_ptr";
 pub const MEMORY_PTR_GLOBAL: &str = "evm_mlir__memory_ptr";
+/// The logical size of the memory, i.e. the highest accessed offset rounded up to a word.
+/// It's tracked apart from the memory's backing buffer, which may be larger.
 pub const MEMORY_SIZE_GLOBAL: &str = "evm_mlir__memory_size";
 pub const CALLDATA_PTR_GLOBAL: &str = "evm_mlir__calldata_ptr";
 pub const CALLDATA_SIZE_GLOBAL: &str = "evm_mlir__calldata_size";
+pub const CODE_GLOBAL: &str = "evm_mlir__code";
 pub const MAIN_ENTRYPOINT: &str = "main";
+/// Default maximum size of the memory, in bytes
+/// (see [`SyscallContext::with_memory_limit`](crate::syscall::SyscallContext::with_memory_limit)).
+pub const DEFAULT_MEMORY_LIMIT: usize = 64 * 1024 * 1024;
+/// Maximum size of the memory the generated code can address, in bytes.
+/// Memory sizes are 32-bit, and must still fit once rounded up to a whole word.
+pub const MAX_MEMORY_SIZE: u32 = u32::MAX - 31;
+/// Maximum depth of nested calls.
+pub const MAX_CALL_DEPTH: usize = 1024;
+/// Maximum size of a deployed contract's code ([EIP-170](https://eips.ethereum.org/EIPS/eip-170)).
+pub const MAX_CODE_SIZE: usize = 0x6000;
+/// Maximum size of the init code of CREATE and CREATE2, from Shanghai
+/// ([EIP-3860](https://eips.ethereum.org/EIPS/eip-3860)).
+pub const MAX_INITCODE_SIZE: usize = 2 * MAX_CODE_SIZE;
 
 /// Contains the gas costs of the EVM instructions
 pub mod gas_cost {
+    use crate::{env::Address, program::Opcode, spec::Spec, syscall::U256};
+
     pub const MSTORE: i64 = 3;
     pub const MSTORE8: i64 = 3;
     pub const MLOAD: i64 = 3;
     pub const ADD: i64 = 3;
     pub const AND: i64 = 3;
     pub const EXP: i64 = 10;
+    pub const EXP_PER_BYTE: i64 = 50;
     pub const LT: i64 = 3;
     pub const SGT: i64 = 3;
     pub const GT: i64 = 3;
@@ -32,10 +51,17 @@ pub mod gas_cost {
     pub const MULMOD: i64 = 8;
     pub const SIGNEXTEND: i64 = 5;
     pub const SHL: i64 = 3;
+    pub const SHR: i64 = 3;
     pub const SLT: i64 = 3;
     pub const XOR: i64 = 3;
+    pub const NOT: i64 = 3;
     pub const SAR: i64 = 3;
+    pub const KECCAK256: i64 = 30;
+    pub const KECCAK256_PER_WORD: i64 = 6;
     pub const CODESIZE: i64 = 2;
+    pub const CODECOPY: i64 = 3;
+    pub const RETURNDATASIZE: i64 = 2;
+    pub const RETURNDATACOPY: i64 = 3;
     pub const POP: i64 = 2;
     pub const PC: i64 = 2;
     pub const MSIZE: i64 = 2;
@@ -50,8 +76,159 @@ pub mod gas_cost {
     pub const BYTE: i64 = 3;
     pub const CALLDATALOAD: i64 = 3;
     pub const CALLDATASIZE: i64 = 2;
+    pub const CALLDATACOPY: i64 = 3;
+    pub const COPY_PER_WORD: i64 = 3;
+    pub const CALLVALUE: i64 = 2;
+    pub const ADDRESS: i64 = 2;
+    pub const CALLER: i64 = 2;
+    pub const ORIGIN: i64 = 2;
+    pub const GASPRICE: i64 = 2;
+    pub const BLOCKHASH: i64 = 20;
+    pub const COINBASE: i64 = 2;
+    pub const TIMESTAMP: i64 = 2;
+    pub const NUMBER: i64 = 2;
+    pub const GASLIMIT: i64 = 2;
+    pub const CHAINID: i64 = 2;
+    pub const BASEFEE: i64 = 2;
+    pub const PREVRANDAO: i64 = 2;
     pub const JUMPI: i64 = 10;
     pub const LOG: i64 = 375;
+    pub const LOG_TOPIC: i64 = 375;
+    pub const LOG_DATA: i64 = 8;
+    pub const CALL: i64 = 100;
+    pub const CALL_VALUE_TRANSFER: i64 = 9_000;
+    /// Charged by CALL when transferring value to an empty account.
+    pub const CALL_NEW_ACCOUNT: i64 = 25_000;
+    /// Gas given to the callee for free when transferring value.
+    pub const CALL_STIPEND: u64 = 2_300;
+    pub const COLD_SLOAD: i64 = 2_100;
+    pub const WARM_STORAGE_READ: i64 = 100;
+    pub const COLD_ACCOUNT_ACCESS: i64 = 2_600;
+    pub const SELFBALANCE: i64 = 5;
+    pub const SELFDESTRUCT: i64 = 5_000;
+    /// Charged by SELFDESTRUCT when sending a non-zero balance to an empty account.
+    pub const SELFDESTRUCT_NEW_ACCOUNT: i64 = 25_000;
+    /// Refunded for each destroyed account, before London ([EIP-3529]).
+    ///
+    /// [EIP-3529]: https://eips.ethereum.org/EIPS/eip-3529
+    pub const SELFDESTRUCT_REFUND: i64 = 24_000;
+    pub const CREATE: i64 = 32_000;
+    /// Charged per byte of the code deployed by CREATE and CREATE2.
+    pub const CODE_DEPOSIT: u64 = 200;
+    pub const TLOAD: i64 = 100;
+    pub const TSTORE: i64 = 100;
+    pub const SSTORE_SET: i64 = 20_000;
+    pub const SSTORE_RESET: i64 = 2_900;
+    /// SSTORE fails if the remaining gas is less or equal than this ([EIP-2200]).
+    ///
+    /// [EIP-2200]: https://eips.ethereum.org/EIPS/eip-2200
+    pub const SSTORE_MIN_REMAINING_GAS: i64 = 2_300;
+    /// Before Berlin, storage reads had a flat cost.
+    pub const SLOAD_ISTANBUL: i64 = 800;
+    /// Before Berlin, account accesses had a flat cost.
+    pub const BALANCE_ISTANBUL: i64 = 700;
+    /// Before Berlin, the cold access cost wasn't part of the reset cost.
+    pub const SSTORE_RESET_ISTANBUL: i64 = 5_000;
+
+    /// Returns the cost of reading a storage slot under `spec`.
+    /// From Berlin on, it depends on whether the slot `is_cold`.
+    pub fn sload(spec: Spec, is_cold: bool) -> i64 {
+        if !spec.enabled(Spec::Berlin) {
+            SLOAD_ISTANBUL
+        } else if is_cold {
+            COLD_SLOAD
+        } else {
+            WARM_STORAGE_READ
+        }
+    }
+
+    /// Returns the cost of accessing an account (e.g. with BALANCE) under `spec`.
+    /// From Berlin on, it depends on whether the account `is_cold`.
+    pub fn account_access(spec: Spec, is_cold: bool) -> i64 {
+        if !spec.enabled(Spec::Berlin) {
+            BALANCE_ISTANBUL
+        } else if is_cold {
+            COLD_ACCOUNT_ACCESS
+        } else {
+            WARM_STORAGE_READ
+        }
+    }
+
+    /// Refund for clearing a storage slot, lowered by [EIP-3529] in London.
+    ///
+    /// [EIP-3529]: https://eips.ethereum.org/EIPS/eip-3529
+    pub const SSTORE_CLEARS_SCHEDULE: i64 = 4_800;
+    pub const SSTORE_CLEARS_SCHEDULE_BERLIN: i64 = 15_000;
+
+    /// Returns the refund for clearing a storage slot under `spec`.
+    pub fn sstore_clears_schedule(spec: Spec) -> i64 {
+        if spec.enabled(Spec::London) {
+            SSTORE_CLEARS_SCHEDULE
+        } else {
+            SSTORE_CLEARS_SCHEDULE_BERLIN
+        }
+    }
+
+    /// Returns the change in the refund counter of writing `new` into a storage slot
+    /// holding `current`, whose value at the start of the transaction was `original`
+    /// ([EIP-2200], with the amounts of [EIP-3529] from London on).
+    ///
+    /// [EIP-2200]: https://eips.ethereum.org/EIPS/eip-2200
+    /// [EIP-3529]: https://eips.ethereum.org/EIPS/eip-3529
+    pub fn sstore_refund(spec: Spec, original: &U256, current: &U256, new: &U256) -> i64 {
+        let zero = U256::default();
+        let clears_schedule = sstore_clears_schedule(spec);
+
+        if current == new {
+            return 0;
+        }
+        if original == current {
+            return if *original != zero && *new == zero {
+                clears_schedule
+            } else {
+                0
+            };
+        }
+
+        let mut refund = 0;
+        if *original != zero {
+            if *current == zero {
+                // the slot was cleared before, so its refund is taken back
+                refund -= clears_schedule;
+            } else if *new == zero {
+                refund += clears_schedule;
+            }
+        }
+        if original == new {
+            // the slot is restored, so only a warm read is charged in the end
+            let charged = if *original == zero {
+                SSTORE_SET
+            } else {
+                sstore_reset(spec)
+            };
+            refund += charged - sload(spec, false);
+        }
+        refund
+    }
+
+    /// Returns the maximum fraction of the used gas that can be refunded
+    /// (`gas_used / max_refund_quotient`) under `spec`.
+    pub fn max_refund_quotient(spec: Spec) -> u64 {
+        if spec.enabled(Spec::London) {
+            5
+        } else {
+            2
+        }
+    }
+
+    /// Returns the cost of resetting a storage slot to a new value under `spec`.
+    pub fn sstore_reset(spec: Spec) -> i64 {
+        if spec.enabled(Spec::Berlin) {
+            SSTORE_RESET
+        } else {
+            SSTORE_RESET_ISTANBUL
+        }
+    }
 
     pub fn memory_expansion_cost(last_size: u32, new_size: u32) -> i64 {
         let new_memory_size_word = (new_size + 31) / 32;
@@ -64,6 +241,163 @@ pub mod gas_cost {
     }
 
     pub fn log_dynamic_gas_cost(size: u32, topic_count: u32) -> i64 {
-        (super::gas_cost::LOG * topic_count as i64) + (8 * size as i64)
+        (LOG_TOPIC * topic_count as i64) + (LOG_DATA * size as i64)
+    }
+
+    pub const TX_BASE_COST: u64 = 21_000;
+    pub const TX_CREATE_COST: u64 = 32_000;
+    pub const TX_DATA_ZERO_BYTE_COST: u64 = 4;
+    pub const TX_DATA_NON_ZERO_BYTE_COST: u64 = 16;
+    pub const TX_ACCESS_LIST_ADDRESS_COST: u64 = 2_400;
+    pub const TX_ACCESS_LIST_STORAGE_KEY_COST: u64 = 1_900;
+    /// Charged per word of init code from Shanghai ([EIP-3860]), both to creation
+    /// transactions and to CREATE and CREATE2.
+    ///
+    /// [EIP-3860]: https://eips.ethereum.org/EIPS/eip-3860
+    pub const INIT_CODE_WORD_COST: u64 = 2;
+
+    /// Returns the gas charged to a transaction before any of its code is executed.
+    ///
+    /// This includes the base cost (plus the creation cost for contract creations),
+    /// the calldata cost, the access list cost and, for creations from Shanghai, the
+    /// [EIP-3860] init code cost.
+    ///
+    /// [EIP-3860]: https://eips.ethereum.org/EIPS/eip-3860
+    pub fn intrinsic_gas(
+        calldata: &[u8],
+        is_creation: bool,
+        access_list: &[(Address, Vec<U256>)],
+        spec: Spec,
+    ) -> u64 {
+        let zero_bytes = calldata.iter().filter(|byte| **byte == 0).count() as u64;
+        let non_zero_bytes = calldata.len() as u64 - zero_bytes;
+        let calldata_cost =
+            zero_bytes * TX_DATA_ZERO_BYTE_COST + non_zero_bytes * TX_DATA_NON_ZERO_BYTE_COST;
+
+        let storage_keys: u64 = access_list.iter().map(|(_, keys)| keys.len() as u64).sum();
+        let access_list_cost = access_list.len() as u64 * TX_ACCESS_LIST_ADDRESS_COST
+            + storage_keys * TX_ACCESS_LIST_STORAGE_KEY_COST;
+
+        let creation_cost = if is_creation { TX_CREATE_COST } else { 0 };
+        let init_code_cost = if is_creation && spec.enabled(Spec::Shanghai) {
+            let init_code_words = (calldata.len() as u64 + 31) / 32;
+            init_code_words * INIT_CODE_WORD_COST
+        } else {
+            0
+        };
+
+        TX_BASE_COST + calldata_cost + access_list_cost + creation_cost + init_code_cost
+    }
+
+    /// The static cost of each opcode, charged by the generated code before running it.
+    ///
+    /// The dynamic costs (memory expansion, per-word copies, storage and account accesses,
+    /// etc.) aren't part of the schedule. The schedule is fixed at compile time, so
+    /// it applies to the compiled program, but not to the code it calls into.
+    #[derive(Clone, Debug, Eq, PartialEq)]
+    pub struct GasSchedule {
+        costs: [i64; 256],
+    }
+
+    impl Default for GasSchedule {
+        fn default() -> Self {
+            Self::mainnet()
+        }
+    }
+
+    impl GasSchedule {
+        /// Returns the Ethereum mainnet costs.
+        pub fn mainnet() -> Self {
+            let mut costs = [0; 256];
+            for (opcode, cost) in [
+                (Opcode::ADD, ADD),
+                (Opcode::MUL, MUL),
+                (Opcode::SUB, SUB),
+                (Opcode::DIV, DIV),
+                (Opcode::SDIV, SDIV),
+                (Opcode::MOD, MOD),
+                (Opcode::SMOD, SMOD),
+                (Opcode::ADDMOD, ADDMOD),
+                (Opcode::MULMOD, MULMOD),
+                (Opcode::EXP, EXP),
+                (Opcode::SIGNEXTEND, SIGNEXTEND),
+                (Opcode::LT, LT),
+                (Opcode::GT, GT),
+                (Opcode::SLT, SLT),
+                (Opcode::SGT, SGT),
+                (Opcode::EQ, EQ),
+                (Opcode::ISZERO, ISZERO),
+                (Opcode::AND, AND),
+                (Opcode::OR, OR),
+                (Opcode::XOR, XOR),
+                (Opcode::NOT, NOT),
+                (Opcode::BYTE, BYTE),
+                (Opcode::SHL, SHL),
+                (Opcode::SHR, SHR),
+                (Opcode::SAR, SAR),
+                (Opcode::KECCAK256, KECCAK256),
+                (Opcode::ADDRESS, ADDRESS),
+                (Opcode::ORIGIN, ORIGIN),
+                (Opcode::CALLER, CALLER),
+                (Opcode::CALLVALUE, CALLVALUE),
+                (Opcode::CALLDATALOAD, CALLDATALOAD),
+                (Opcode::CALLDATASIZE, CALLDATASIZE),
+                (Opcode::CALLDATACOPY, CALLDATACOPY),
+                (Opcode::CODESIZE, CODESIZE),
+                (Opcode::CODECOPY, CODECOPY),
+                (Opcode::GASPRICE, GASPRICE),
+                (Opcode::RETURNDATASIZE, RETURNDATASIZE),
+                (Opcode::RETURNDATACOPY, RETURNDATACOPY),
+                (Opcode::BLOCKHASH, BLOCKHASH),
+                (Opcode::COINBASE, COINBASE),
+                (Opcode::TIMESTAMP, TIMESTAMP),
+                (Opcode::NUMBER, NUMBER),
+                (Opcode::PREVRANDAO, PREVRANDAO),
+                (Opcode::GASLIMIT, GASLIMIT),
+                (Opcode::CHAINID, CHAINID),
+                (Opcode::SELFBALANCE, SELFBALANCE),
+                (Opcode::BASEFEE, BASEFEE),
+                (Opcode::POP, POP),
+                (Opcode::MLOAD, MLOAD),
+                (Opcode::MSTORE, MSTORE),
+                (Opcode::MSTORE8, MSTORE8),
+                (Opcode::JUMP, JUMP),
+                (Opcode::JUMPI, JUMPI),
+                (Opcode::PC, PC),
+                (Opcode::MSIZE, MSIZE),
+                (Opcode::GAS, GAS),
+                (Opcode::JUMPDEST, JUMPDEST),
+                (Opcode::TLOAD, TLOAD),
+                (Opcode::TSTORE, TSTORE),
+                (Opcode::MCOPY, MCOPY),
+                (Opcode::PUSH0, PUSH0),
+                (Opcode::CREATE, CREATE),
+                (Opcode::CALL, CALL),
+                (Opcode::CREATE2, CREATE),
+                (Opcode::SELFDESTRUCT, SELFDESTRUCT),
+            ] {
+                costs[opcode as usize] = cost;
+            }
+            let push1 = Opcode::PUSH1 as usize;
+            costs[push1..push1 + 32].fill(PUSHN);
+            let dup1 = Opcode::DUP1 as usize;
+            costs[dup1..dup1 + 16].fill(DUPN);
+            let swap1 = Opcode::SWAP1 as usize;
+            costs[swap1..swap1 + 16].fill(SWAPN);
+            let log0 = Opcode::LOG0 as usize;
+            costs[log0..log0 + 5].fill(LOG);
+            Self { costs }
+        }
+
+        /// Returns the static cost of `opcode`.
+        pub fn cost(&self, opcode: u8) -> i64 {
+            self.costs[opcode as usize]
+        }
+
+        /// Sets the static cost of `opcode`.
+        pub fn with_cost(mut self, opcode: u8, cost: i64) -> Self {
+            self.costs[opcode as usize] = cost;
+            self
+        }
     }
 }
@@ -24,17 +24,23 @@ use melior::{
 use std::{
     ffi::CStr,
     mem::MaybeUninit,
-    path::Path,
+    path::{Path, PathBuf},
     ptr::{addr_of_mut, null_mut},
     sync::OnceLock,
 };
+use tempfile::TempDir;
 
 use crate::{
-    codegen::{context::OperationCtx, operations::generate_code_for_op, run_pass_manager},
-    constants::MAIN_ENTRYPOINT,
+    codegen::{
+        context::OperationCtx,
+        operations::{generate_code_for_op, generate_trace_step},
+        run_pass_manager,
+    },
+    constants::{gas_cost::GasSchedule, MAIN_ENTRYPOINT, MAX_STACK_SIZE},
     errors::CodegenError,
     module::MLIRModule,
-    program::Program,
+    program::{Operation, Program},
+    spec::Spec,
     syscall::ExitStatusCode,
     utils::return_empty_result,
 };
@@ -59,11 +65,84 @@ impl Context {
         Self { melior_context }
     }
 
+    /// Compiles the program for the [default spec](Spec::default).
     pub fn compile(
         &self,
         program: &Program,
         output_file: impl AsRef<Path>,
     ) -> Result<MLIRModule, CodegenError> {
+        self.compile_with_spec(program, output_file, Spec::default())
+    }
+
+    /// Compiles the program for the given spec, which decides
+    /// the opcodes available to it.
+    pub fn compile_with_spec(
+        &self,
+        program: &Program,
+        output_file: impl AsRef<Path>,
+        spec: Spec,
+    ) -> Result<MLIRModule, CodegenError> {
+        self.compile_with_gas_schedule(program, output_file, spec, GasSchedule::mainnet())
+    }
+
+    /// Compiles the program for the given spec, charging the static
+    /// opcode costs of `gas_schedule` instead of the mainnet ones.
+    pub fn compile_with_gas_schedule(
+        &self,
+        program: &Program,
+        output_file: impl AsRef<Path>,
+        spec: Spec,
+        gas_schedule: GasSchedule,
+    ) -> Result<MLIRModule, CodegenError> {
+        self.compile_with_stack_size(
+            program,
+            output_file,
+            spec,
+            gas_schedule,
+            MAX_STACK_SIZE as u32,
+        )
+    }
+
+    /// Compiles the program like [`compile_with_gas_schedule`](Self::compile_with_gas_schedule),
+    /// with a stack that holds at most `stack_size` values instead of [`MAX_STACK_SIZE`].
+    /// Pushing past it is a stack overflow.
+    ///
+    /// Fails with [`CodegenError::InvalidStackSize`] if `stack_size` is above [`i32::MAX`],
+    /// since the generated code addresses the stack with 32-bit signed offsets.
+    pub fn compile_with_stack_size(
+        &self,
+        program: &Program,
+        output_file: impl AsRef<Path>,
+        spec: Spec,
+        gas_schedule: GasSchedule,
+        stack_size: u32,
+    ) -> Result<MLIRModule, CodegenError> {
+        self.compile_module(
+            program,
+            output_file,
+            spec,
+            gas_schedule,
+            stack_size,
+            false,
+            false,
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn compile_module(
+        &self,
+        program: &Program,
+        output_file: impl AsRef<Path>,
+        spec: Spec,
+        gas_schedule: GasSchedule,
+        stack_size: u32,
+        instrument_overflow: bool,
+        print_stack_top: bool,
+    ) -> Result<MLIRModule, CodegenError> {
+        if i32::try_from(stack_size).is_err() {
+            return Err(CodegenError::InvalidStackSize(stack_size));
+        }
+
         static INITIALIZED: OnceLock<()> = OnceLock::new();
         INITIALIZED.get_or_init(|| unsafe {
             LLVM_InitializeAllTargets();
@@ -102,7 +181,16 @@ impl Context {
 
         let mut melior_module = MeliorModule::from_operation(op).expect("module failed to create");
 
-        compile_program(context, &melior_module, program)?;
+        compile_program(
+            context,
+            &melior_module,
+            program,
+            spec,
+            gas_schedule,
+            stack_size,
+            instrument_overflow,
+            print_stack_top,
+        )?;
 
         assert!(melior_module.as_operation().verify());
 
@@ -130,6 +218,107 @@ impl Context {
     }
 }
 
+/// A reusable compiler, which keeps the same MLIR [`Context`] (dialects, passes and
+/// LLVM translations already registered) across compilations, instead of
+/// initializing a fresh one for each program.
+#[derive(Debug)]
+pub struct Compiler {
+    context: Context,
+    /// Base path for the intermediate files. Without it, they're written to a
+    /// temporary directory that's removed after each compilation.
+    output_file: Option<PathBuf>,
+    spec: Spec,
+    gas_schedule: GasSchedule,
+    stack_size: u32,
+    instrument_overflow: bool,
+    print_stack_top: bool,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self {
+            context: Context::new(),
+            output_file: None,
+            spec: Spec::default(),
+            gas_schedule: GasSchedule::mainnet(),
+            stack_size: MAX_STACK_SIZE as u32,
+            instrument_overflow: false,
+            print_stack_top: false,
+        }
+    }
+
+    /// Sets the base path for the intermediate files emitted during compilation,
+    /// which are kept afterwards.
+    pub fn with_output_file(mut self, output_file: impl Into<PathBuf>) -> Self {
+        self.output_file = Some(output_file.into());
+        self
+    }
+
+    /// Sets the spec the programs are compiled for.
+    pub fn with_spec(mut self, spec: Spec) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    /// Sets the static opcode costs charged by the programs, instead of the mainnet ones.
+    pub fn with_gas_schedule(mut self, gas_schedule: GasSchedule) -> Self {
+        self.gas_schedule = gas_schedule;
+        self
+    }
+
+    /// Sets the most values the programs' stack can hold, instead of [`MAX_STACK_SIZE`].
+    /// Compiling fails if it's above [`i32::MAX`].
+    pub fn with_stack_size(mut self, stack_size: u32) -> Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    /// Sets whether ADD, SUB and MUL report their overflows through the
+    /// [overflow handler](crate::syscall::SyscallContext::with_overflow_handler).
+    /// The results still wrap around as the EVM defines them. Disabled by default.
+    pub fn with_overflow_instrumentation(mut self, enabled: bool) -> Self {
+        self.instrument_overflow = enabled;
+        self
+    }
+
+    /// Sets whether the programs print the top of their stack before each operation,
+    /// through [`SyscallContext::debug_print_stack_top`](crate::syscall::SyscallContext::debug_print_stack_top).
+    /// Disabled by default.
+    #[cfg(feature = "debug-syscalls")]
+    pub fn with_stack_top_printing(mut self, enabled: bool) -> Self {
+        self.print_stack_top = enabled;
+        self
+    }
+
+    pub fn compile(&self, program: &Program) -> Result<MLIRModule, CodegenError> {
+        // Kept until the compilation ends, along with the intermediate files in it
+        let output_dir;
+        let output_file = match &self.output_file {
+            Some(output_file) => output_file.clone(),
+            None => {
+                output_dir = TempDir::new()?;
+                output_dir.path().join("program")
+            }
+        };
+
+        self.context.compile_module(
+            program,
+            &output_file,
+            self.spec,
+            self.gas_schedule.clone(),
+            self.stack_size,
+            self.instrument_overflow,
+            self.print_stack_top,
+        )
+    }
+}
+
 /// Initialize an MLIR context.
 pub fn initialize_mlir() -> MeliorContext {
     let context = MeliorContext::new();
@@ -193,10 +382,17 @@ pub fn get_data_layout_rep() -> Result<String, CodegenError> {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
+#[cfg_attr(not(feature = "debug-syscalls"), allow(unused_variables))]
 fn compile_program(
     context: &MeliorContext,
     module: &MeliorModule,
     program: &Program,
+    spec: Spec,
+    gas_schedule: GasSchedule,
+    stack_size: u32,
+    instrument_overflow: bool,
+    print_stack_top: bool,
 ) -> Result<(), CodegenError> {
     let location = Location::unknown(context);
     let ptr_type = pointer(context, 0);
@@ -228,16 +424,51 @@ fn compile_program(
     // PERF: avoid generating unneeded setup blocks
     let setup_block = main_region.append_block(Block::new(&[]));
 
-    let mut op_ctx = OperationCtx::new(context, module, &main_region, &setup_block, program)?;
+    let mut op_ctx = OperationCtx::new(
+        context,
+        module,
+        &main_region,
+        &setup_block,
+        program,
+        spec,
+        gas_schedule,
+        stack_size,
+        instrument_overflow,
+    )?;
 
     let mut last_block = setup_block;
+    let mut pc = 0;
 
-    // Generate code for the program
+    // Generate code for the program.
+    // This is sequential on purpose: every block is appended to the same region, owned by an
+    // MLIR context that isn't thread-safe for building IR (melior's types aren't `Send`), and
+    // each operation also registers its JUMPDESTs in `op_ctx`. Splitting the code in several
+    // regions wouldn't help either, since the jumps need a single function to branch within.
     for op in &op_ctx.program.operations {
-        let (block_start, block_end) = generate_code_for_op(&mut op_ctx, &main_region, op.clone())?;
+        let (block_start, block_end) = generate_code_for_op(&mut op_ctx, &main_region, op.clone())
+            .map_err(|error| CodegenError::OperationError {
+                pc,
+                source: Box::new(error),
+            })?;
+
+        #[cfg(feature = "debug-syscalls")]
+        let block_start = if print_stack_top {
+            crate::utils::debug_print_stack_top(&op_ctx, &main_region, block_start)?
+        } else {
+            block_start
+        };
+
+        // Report the step before running the operation, also when reaching a JUMPDEST
+        // through a jump
+        let opcode = op.to_bytecode()[0];
+        let block_start = generate_trace_step(&op_ctx, &main_region, block_start, pc, opcode)?;
+        if let Operation::Jumpdest { pc } = op {
+            op_ctx.register_jump_destination(*pc, block_start);
+        }
 
         last_block.append_operation(cf::br(&block_start, &[], location));
         last_block = block_end;
+        pc += op.to_bytecode().len();
     }
 
     op_ctx.populate_jumptable()?;
@@ -1,23 +1,255 @@
-#[derive(Clone, Debug, Default)]
+use std::{borrow::Cow, collections::HashMap};
+
+use crate::{constants::gas_cost, errors::InvalidTransaction, spec::Spec, syscall::U256};
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct Address(pub [u8; 20]);
 
+impl From<&U256> for Address {
+    /// Converts a 32-byte word to an address, keeping its lowest 20 bytes.
+    fn from(value: &U256) -> Self {
+        let mut address = [0; 20];
+        address[..4].copy_from_slice(&(value.hi as u32).to_be_bytes());
+        address[4..].copy_from_slice(&value.lo.to_be_bytes());
+        Address(address)
+    }
+}
+
+impl From<&Address> for U256 {
+    /// Converts the address to a 32-byte word, left-padding it with zeros.
+    fn from(address: &Address) -> Self {
+        let (hi, lo) = address.0.split_at(4);
+        U256 {
+            hi: u32::from_be_bytes(hi.try_into().unwrap()) as u128,
+            lo: u128::from_be_bytes(lo.try_into().unwrap()),
+        }
+    }
+}
+
+/// The execution environment. It borrows the calldata for `'a` when it's given as a slice,
+/// so running the same transaction repeatedly doesn't need to copy it.
 #[derive(Clone, Debug, Default)]
-pub struct Env {
+pub struct Env<'a> {
     /// Block-related info
     pub block: BlockEnv,
     /// Transaction-related info
-    pub tx: TxEnv,
+    pub tx: TxEnv<'a>,
+    /// Chain-related info
+    pub cfg: CfgEnv,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct CfgEnv {
+    /// The chain ID ([EIP-155](https://eips.ethereum.org/EIPS/eip-155))
+    pub chain_id: u64,
+    /// The hardfork whose rules apply, both to the opcodes available and to the gas costs
+    pub spec: Spec,
 }
 
+impl<'a> Env<'a> {
+    /// Returns a builder starting from [the default environment](Env::default).
+    ///
+    /// ```
+    /// use evm_mlir::{
+    ///     program::{Operation, Program},
+    ///     Env, Evm,
+    /// };
+    ///
+    /// let env = Env::builder()
+    ///     .calldata(vec![0xaa; 4])
+    ///     .gas_limit(100_000)
+    ///     .build();
+    /// // Return the calldata size as a 32-byte word
+    /// let program = Program::from(vec![
+    ///     Operation::CallDataSize,
+    ///     Operation::Push0,
+    ///     Operation::Mstore,
+    ///     Operation::Push((1, 32_u8.into())),
+    ///     Operation::Push0,
+    ///     Operation::Return,
+    /// ]);
+    ///
+    /// let result = Evm::new(env, program).transact().unwrap();
+    ///
+    /// assert_eq!(result.return_data().unwrap()[31], 4);
+    /// ```
+    pub fn builder() -> EnvBuilder<'a> {
+        EnvBuilder::default()
+    }
+
+    /// Returns the [intrinsic gas](gas_cost::intrinsic_gas) of [the configured transaction](Env::tx),
+    /// a message call without an access list.
+    pub fn intrinsic_gas(&self) -> u64 {
+        gas_cost::intrinsic_gas(&self.tx.calldata, false, &[], self.cfg.spec)
+    }
+
+    /// Checks that [the configured transaction](Env::tx) fits in the block's gas limit.
+    pub fn validate_gas_limit(&self) -> Result<(), InvalidTransaction> {
+        self.block
+            .validate_gas_limits(std::slice::from_ref(&self.tx))
+    }
+
+    /// Like [`Self::validate_gas_limit`], but also checks that the `gas` given to the
+    /// execution, which may differ from the transaction's gas limit, fits in the block.
+    pub fn validate_execution_gas(&self, gas: u64) -> Result<(), InvalidTransaction> {
+        self.validate_gas_limit()?;
+        if gas > self.block.gas_limit {
+            return Err(InvalidTransaction::GasLimitExceedsBlockLimit {
+                tx_gas_limit: gas,
+                block_gas_limit: self.block.gas_limit,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Builds an [`Env`] field by field. Fields left unset keep their default value,
+/// except for the origin, which defaults to the caller like in a top-level call.
 #[derive(Clone, Debug, Default)]
+pub struct EnvBuilder<'a> {
+    env: Env<'a>,
+    origin: Option<Address>,
+}
+
+impl<'a> EnvBuilder<'a> {
+    pub fn caller(mut self, caller: Address) -> Self {
+        self.env.tx.caller = caller;
+        self
+    }
+
+    pub fn origin(mut self, origin: Address) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+
+    pub fn to(mut self, to: Address) -> Self {
+        self.env.tx.to = to;
+        self
+    }
+
+    /// Sets the calldata, either owned (`Vec<u8>`) or borrowed (`&[u8]`).
+    pub fn calldata(mut self, calldata: impl Into<Cow<'a, [u8]>>) -> Self {
+        self.env.tx.calldata = calldata.into();
+        self
+    }
+
+    pub fn value(mut self, value: U256) -> Self {
+        self.env.tx.value = value;
+        self
+    }
+
+    /// Sets the transaction's gas limit, which is the gas available to [`Evm::transact`](crate::Evm::transact).
+    pub fn gas_limit(mut self, gas_limit: u64) -> Self {
+        self.env.tx.gas_limit = gas_limit;
+        self
+    }
+
+    pub fn gas_price(mut self, gas_price: U256) -> Self {
+        self.env.tx.gas_price = gas_price;
+        self
+    }
+
+    /// Replaces all the block-related info
+    pub fn block(mut self, block: BlockEnv) -> Self {
+        self.env.block = block;
+        self
+    }
+
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.env.cfg.chain_id = chain_id;
+        self
+    }
+
+    pub fn spec(mut self, spec: Spec) -> Self {
+        self.env.cfg.spec = spec;
+        self
+    }
+
+    pub fn build(self) -> Env<'a> {
+        let mut env = self.env;
+        env.tx.origin = self.origin.unwrap_or_else(|| env.tx.caller.clone());
+        env
+    }
+}
+
+#[derive(Clone, Debug)]
 pub struct BlockEnv {
     pub number: u64,
+    /// The address receiving the block's fees
+    pub coinbase: Address,
+    /// The block's timestamp, in seconds since the UNIX epoch
+    pub timestamp: u64,
+    /// The most gas the block's transactions can use. Unlimited by default.
+    pub gas_limit: u64,
+    /// The block's base fee per gas ([EIP-1559](https://eips.ethereum.org/EIPS/eip-1559))
+    pub basefee: U256,
+    /// The beacon chain randomness of the block ([EIP-4399](https://eips.ethereum.org/EIPS/eip-4399))
+    pub prevrandao: U256,
+    /// The hashes of the recent blocks, by number, read by BLOCKHASH.
+    /// Only the last 256 blocks before [`Self::number`] are reachable.
+    pub block_hashes: HashMap<u64, U256>,
+}
+
+impl Default for BlockEnv {
+    fn default() -> Self {
+        Self {
+            number: 0,
+            coinbase: Address::default(),
+            timestamp: 0,
+            gas_limit: u64::MAX,
+            basefee: U256::default(),
+            prevrandao: U256::default(),
+            block_hashes: HashMap::new(),
+        }
+    }
+}
+
+impl BlockEnv {
+    /// Checks that every transaction's gas limit fits in the block's gas limit,
+    /// and that their sum doesn't exceed it either.
+    pub fn validate_gas_limits(&self, txs: &[TxEnv]) -> Result<(), InvalidTransaction> {
+        let mut cumulative_gas_limit: u64 = 0;
+        for tx in txs {
+            if tx.gas_limit > self.gas_limit {
+                return Err(InvalidTransaction::GasLimitExceedsBlockLimit {
+                    tx_gas_limit: tx.gas_limit,
+                    block_gas_limit: self.gas_limit,
+                });
+            }
+            cumulative_gas_limit = cumulative_gas_limit
+                .checked_add(tx.gas_limit)
+                .filter(|total| *total <= self.gas_limit)
+                .ok_or(InvalidTransaction::BlockGasLimitReached {
+                    block_gas_limit: self.gas_limit,
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Returns the hash of the block `number`, or 0 if it's not one of the last 256 blocks
+    /// or its hash is unknown.
+    pub fn block_hash(&self, number: u64) -> U256 {
+        let is_recent = number < self.number && number >= self.number.saturating_sub(256);
+        if !is_recent {
+            return U256::default();
+        }
+        self.block_hashes.get(&number).copied().unwrap_or_default()
+    }
 }
 
 #[derive(Clone, Debug, Default)]
-pub struct TxEnv {
-    pub from: Address,
+pub struct TxEnv<'a> {
+    /// The account making the call (`msg.sender`).
+    pub caller: Address,
+    /// The account that originated the transaction (`tx.origin`).
+    /// For top-level calls this is the same as [`Self::caller`].
+    pub origin: Address,
+    /// The address of the contract being executed (`address(this)`).
     pub to: Address,
-    pub calldata: Vec<u8>,
+    pub calldata: Cow<'a, [u8]>,
+    /// The value transferred with the call (`msg.value`), in wei.
+    pub value: U256,
     pub gas_limit: u64,
+    /// The gas price paid by the transaction, in wei.
+    pub gas_price: U256,
 }
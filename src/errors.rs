@@ -1,5 +1,19 @@
 use thiserror::Error;
 
+use crate::program::ParseError;
+
+#[derive(Debug, Error)]
+pub enum EvmError {
+    #[error("error parsing bytecode: {0}")]
+    ParseError(#[from] ParseError),
+    #[error("error compiling program: {0}")]
+    CodegenError(#[from] CodegenError),
+    #[error("io error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("invalid transaction: {0}")]
+    InvalidTransaction(#[from] InvalidTransaction),
+}
+
 #[derive(Debug, Error)]
 pub enum CodegenError {
     #[error("error linking: {0}")]
@@ -10,4 +24,25 @@ pub enum CodegenError {
     MeliorError(#[from] melior::Error),
     #[error("not yet implemented: {0}")]
     NotImplemented(String),
+    #[error("invalid operation: {0}")]
+    InvalidOperation(String),
+    #[error("stack size {0} is too large")]
+    InvalidStackSize(u32),
+    #[error("error compiling the operation at pc {pc}: {source}")]
+    OperationError {
+        /// The program counter of the operation that failed to compile
+        pc: usize,
+        source: Box<CodegenError>,
+    },
+}
+
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum InvalidTransaction {
+    #[error("transaction gas limit ({tx_gas_limit}) exceeds block gas limit ({block_gas_limit})")]
+    GasLimitExceedsBlockLimit {
+        tx_gas_limit: u64,
+        block_gas_limit: u64,
+    },
+    #[error("transactions' cumulative gas limit exceeds block gas limit ({block_gas_limit})")]
+    BlockGasLimitReached { block_gas_limit: u64 },
 }
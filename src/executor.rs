@@ -1,31 +1,232 @@
+use std::{
+    ffi::{CStr, CString},
+    path::Path,
+    ptr::null_mut,
+    sync::OnceLock,
+};
+
+use llvm_sys::{
+    bit_reader::LLVMParseBitcodeInContext2,
+    core::{
+        LLVMContextCreate, LLVMContextDispose, LLVMCreateMemoryBufferWithContentsOfFile,
+        LLVMDisposeMemoryBuffer, LLVMDisposeMessage, LLVMDisposeModule, LLVMGetNamedFunction,
+    },
+    execution_engine::{
+        LLVMAddGlobalMapping, LLVMCreateMCJITCompilerForModule, LLVMDisposeExecutionEngine,
+        LLVMExecutionEngineRef, LLVMGetFunctionAddress, LLVMInitializeMCJITCompilerOptions,
+        LLVMLinkInMCJIT, LLVMMCJITCompilerOptions,
+    },
+    prelude::{LLVMContextRef, LLVMModuleRef},
+    target::{LLVM_InitializeNativeAsmPrinter, LLVM_InitializeNativeTarget},
+};
 use melior::ExecutionEngine;
+use tempfile::TempDir;
 
 use crate::{
     constants::MAIN_ENTRYPOINT,
+    context::Context,
+    errors::{CodegenError, EvmError},
     module::MLIRModule,
-    syscall::{self, MainFunc, SyscallContext},
+    program::Program,
+    spec::Spec,
+    syscall::{self, ExecutionResult, MainFunc, SyscallContext},
 };
 
 pub struct Executor {
-    engine: ExecutionEngine,
+    engine: Engine,
+}
+
+enum Engine {
+    /// Built by MLIR from a compiled module.
+    Mlir(ExecutionEngine),
+    /// Built by LLVM from a module reloaded from bitcode.
+    Bitcode(BitcodeEngine),
 }
 
 impl Executor {
     pub fn new(module: &MLIRModule) -> Self {
         let engine = ExecutionEngine::new(module.module(), 0, &[], false);
         syscall::register_syscalls(&engine);
-        Self { engine }
+        Self {
+            engine: Engine::Mlir(engine),
+        }
+    }
+
+    /// Builds an executor from the bitcode written by
+    /// [`compile_to_bitcode`](crate::codegen::compile_to_bitcode), skipping the MLIR lowering.
+    pub fn from_bitcode(path: impl AsRef<Path>) -> Result<Self, CodegenError> {
+        let engine = BitcodeEngine::load(path.as_ref())?;
+        syscall::register_syscalls(&engine);
+        Ok(Self {
+            engine: Engine::Bitcode(engine),
+        })
     }
 
     pub fn execute(&self, context: &mut SyscallContext, initial_gas: u64) -> u8 {
         let main_fn: MainFunc = self.get_main_entrypoint();
 
+        context.initial_gas = initial_gas;
         main_fn(context, initial_gas)
     }
 
     fn get_main_entrypoint(&self) -> MainFunc {
         let function_name = format!("_mlir_ciface_{MAIN_ENTRYPOINT}");
-        let fptr = self.engine.lookup(&function_name);
+        let fptr = match &self.engine {
+            Engine::Mlir(engine) => engine.lookup(&function_name),
+            Engine::Bitcode(engine) => engine.lookup(&function_name),
+        };
         unsafe { std::mem::transmute(fptr) }
     }
 }
+
+/// An execution engine the generated code can call symbols registered in.
+pub trait SymbolRegistry {
+    /// Makes `ptr` callable from the generated code as `name`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must point to a function with the signature the generated code declares for `name`.
+    unsafe fn register_symbol(&self, name: &str, ptr: *mut ());
+}
+
+impl SymbolRegistry for ExecutionEngine {
+    unsafe fn register_symbol(&self, name: &str, ptr: *mut ()) {
+        ExecutionEngine::register_symbol(self, name, ptr);
+    }
+}
+
+/// An MCJIT engine owning the LLVM module it runs, and the context the module lives in.
+struct BitcodeEngine {
+    engine: LLVMExecutionEngineRef,
+    module: LLVMModuleRef,
+    context: LLVMContextRef,
+}
+
+impl BitcodeEngine {
+    fn load(path: &Path) -> Result<Self, CodegenError> {
+        static INITIALIZED: OnceLock<()> = OnceLock::new();
+        INITIALIZED.get_or_init(|| unsafe {
+            LLVMLinkInMCJIT();
+            LLVM_InitializeNativeTarget();
+            LLVM_InitializeNativeAsmPrinter();
+        });
+
+        let filename = CString::new(path.as_os_str().to_string_lossy().as_bytes()).unwrap();
+
+        unsafe {
+            let mut buffer = null_mut();
+            let mut message = null_mut();
+            if LLVMCreateMemoryBufferWithContentsOfFile(
+                filename.as_ptr(),
+                &mut buffer,
+                &mut message,
+            ) != 0
+            {
+                return Err(CodegenError::LLVMCompileError(take_message(message)));
+            }
+
+            let context = LLVMContextCreate();
+            let mut module = null_mut();
+            let failed = LLVMParseBitcodeInContext2(context, buffer, &mut module) != 0;
+            LLVMDisposeMemoryBuffer(buffer);
+            if failed {
+                LLVMContextDispose(context);
+                return Err(CodegenError::LLVMCompileError(format!(
+                    "invalid bitcode in {}",
+                    path.display()
+                )));
+            }
+
+            let mut options: LLVMMCJITCompilerOptions = std::mem::zeroed();
+            LLVMInitializeMCJITCompilerOptions(&mut options, std::mem::size_of_val(&options));
+            let mut engine = null_mut();
+            if LLVMCreateMCJITCompilerForModule(
+                &mut engine,
+                module,
+                &mut options,
+                std::mem::size_of_val(&options),
+                &mut message,
+            ) != 0
+            {
+                LLVMDisposeModule(module);
+                LLVMContextDispose(context);
+                return Err(CodegenError::LLVMCompileError(take_message(message)));
+            }
+
+            Ok(Self {
+                engine,
+                module,
+                context,
+            })
+        }
+    }
+
+    fn lookup(&self, name: &str) -> *mut () {
+        let name = CString::new(name).unwrap();
+        unsafe { LLVMGetFunctionAddress(self.engine, name.as_ptr()) as *mut () }
+    }
+}
+
+impl SymbolRegistry for BitcodeEngine {
+    /// Maps the declaration of `name` to `ptr`. Syscalls the module doesn't declare are skipped.
+    unsafe fn register_symbol(&self, name: &str, ptr: *mut ()) {
+        let name = CString::new(name).unwrap();
+        let function = LLVMGetNamedFunction(self.module, name.as_ptr());
+        if !function.is_null() {
+            LLVMAddGlobalMapping(self.engine, function, ptr.cast());
+        }
+    }
+}
+
+impl Drop for BitcodeEngine {
+    fn drop(&mut self) {
+        // The engine owns the module, so disposing it disposes the module too
+        unsafe {
+            LLVMDisposeExecutionEngine(self.engine);
+            LLVMContextDispose(self.context);
+        }
+    }
+}
+
+/// Returns the contents of an error message written by LLVM, freeing it.
+unsafe fn take_message(message: *mut std::ffi::c_char) -> String {
+    if message.is_null() {
+        return "unknown LLVM error".to_string();
+    }
+    let text = CStr::from_ptr(message).to_string_lossy().into_owned();
+    LLVMDisposeMessage(message);
+    text
+}
+
+/// A program compiled once, which can then run any number of times.
+///
+/// The syscalls are registered when it's compiled, so running it only calls into
+/// the already generated code.
+pub struct CompiledProgram {
+    executor: Executor,
+}
+
+impl CompiledProgram {
+    /// Compiles `program` for the given spec.
+    pub fn compile(program: &Program, spec: Spec) -> Result<Self, EvmError> {
+        // This is for intermediate files, which are removed along with the directory
+        let output_dir = TempDir::new()?;
+        let output_file = output_dir.path().join("program");
+
+        let context = Context::new();
+        let module = context.compile_with_spec(program, &output_file, spec)?;
+
+        Ok(Self {
+            executor: Executor::new(&module),
+        })
+    }
+
+    /// Runs the program in `context` with `initial_gas` available.
+    ///
+    /// The context keeps the state changes (storage, logs, return data, etc.) of the run,
+    /// so each independent execution should get a fresh one.
+    pub fn run(&self, context: &mut SyscallContext, initial_gas: u64) -> ExecutionResult {
+        self.executor.execute(context, initial_gas);
+        context.get_result()
+    }
+}
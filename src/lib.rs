@@ -1,11 +1,17 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use executor::Executor;
+use errors::EvmError;
+use executor::{CompiledProgram, Executor};
 use program::Program;
 use syscall::{ExecutionResult, SyscallContext};
+use tempfile::TempDir;
 
 use crate::context::Context;
 
+// The generated code and the syscalls' U256 layout assume a little-endian host
+#[cfg(target_endian = "big")]
+compile_error!("evm_mlir only supports little-endian targets");
+
 pub mod codegen;
 pub mod constants;
 pub mod context;
@@ -14,37 +20,133 @@ pub mod errors;
 pub mod executor;
 pub mod module;
 pub mod program;
+pub mod spec;
 pub mod syscall;
 pub mod utils;
 
 pub use env::Env;
 
+/// Parses, compiles and executes the given bytecode in the given environment.
+///
+/// `initial_gas` is the amount of gas available to the bytecode's execution, in
+/// EVM gas units. No intrinsic gas is charged, so this would be the transaction's gas
+/// limit minus its intrinsic cost. See [`execute_with_intrinsic_gas`] for charging it.
+///
+/// Fails without running anything if either the transaction's gas limit or `initial_gas`
+/// doesn't fit in the block's gas limit.
+pub fn execute(bytecode: &[u8], env: Env, initial_gas: u64) -> Result<ExecutionResult, EvmError> {
+    env.validate_execution_gas(initial_gas)?;
+    let program = Program::from_bytecode(bytecode)?;
+    let compiled_program = CompiledProgram::compile(&program, env.cfg.spec)?;
+
+    let mut context = SyscallContext::with_env(env);
+    Ok(compiled_program.run(&mut context, initial_gas))
+}
+
+/// Like [`execute`], but first charges the [intrinsic gas](Env::intrinsic_gas) of the
+/// transaction out of `initial_gas`, which is then the transaction's gas limit.
+///
+/// If `initial_gas` doesn't cover the intrinsic gas, it halts without running any code.
+/// Otherwise, the gas used by the result includes the intrinsic gas, which also raises
+/// the cap of the refund.
+pub fn execute_with_intrinsic_gas(
+    bytecode: &[u8],
+    env: Env,
+    initial_gas: u64,
+) -> Result<ExecutionResult, EvmError> {
+    env.validate_execution_gas(initial_gas)?;
+    let intrinsic_gas = env.intrinsic_gas();
+    let Some(execution_gas) = initial_gas.checked_sub(intrinsic_gas) else {
+        return Ok(ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        });
+    };
+
+    let program = Program::from_bytecode(bytecode)?;
+    let compiled_program = CompiledProgram::compile(&program, env.cfg.spec)?;
+
+    let mut context = SyscallContext::with_env(env).with_intrinsic_gas(intrinsic_gas);
+    Ok(compiled_program.run(&mut context, execution_gas))
+}
+
+/// Parses and compiles the given bytecode ahead of time, writing the resulting
+/// object file to `path` instead of running it in the JIT.
+///
+/// The object exports the [`MAIN_ENTRYPOINT`](constants::MAIN_ENTRYPOINT) function
+/// and its C interface, and expects the syscalls to be provided when it's linked.
+pub fn compile_to_object(bytecode: &[u8], path: impl AsRef<Path>) -> Result<(), EvmError> {
+    let program = Program::from_bytecode(bytecode)?;
+
+    // This is for intermediate files, which are removed along with the directory
+    let output_dir = TempDir::new()?;
+    let output_file = output_dir.path().join("program");
+
+    let object_file = codegen::compile(&program, &output_file)?;
+    std::fs::copy(object_file, path)?;
+    Ok(())
+}
+
+/// Parses the given bytecode and returns the textual form of the MLIR module
+/// generated for it, before any lowering pass runs.
+///
+/// Meant for inspecting and debugging the generated code.
+pub fn compile_to_mlir_string(bytecode: &[u8]) -> Result<String, EvmError> {
+    let program = Program::from_bytecode(bytecode)?;
+
+    // This is for intermediate files, which are removed along with the directory
+    let output_dir = TempDir::new()?;
+    let output_file = output_dir.path().join("program");
+
+    let context = Context::new();
+    context.compile(&program, &output_file)?;
+
+    let mlir = std::fs::read_to_string(output_file.with_extension("mlir"))?;
+    Ok(mlir)
+}
+
+/// Parses the given bytecode and returns the textual form of the LLVM IR
+/// it's lowered to, after LLVM's optimization passes.
+///
+/// Like [`compile_to_mlir_string`], meant for inspecting and debugging the generated code.
+pub fn compile_to_llvm_ir_string(bytecode: &[u8]) -> Result<String, EvmError> {
+    let program = Program::from_bytecode(bytecode)?;
+
+    // This is for intermediate files, which are removed along with the directory
+    let output_dir = TempDir::new()?;
+    let output_file = output_dir.path().join("program");
+
+    let object_file = codegen::compile(&program, &output_file)?;
+    let llvm_ir = std::fs::read_to_string(object_file.with_extension("ll"))?;
+    Ok(llvm_ir)
+}
+
 #[derive(Debug)]
-pub struct Evm {
-    pub env: Env,
+pub struct Evm<'a> {
+    pub env: Env<'a>,
     pub program: Program,
 }
 
-impl Evm {
+impl<'a> Evm<'a> {
     /// Creates a new EVM instance with the given environment and program.
     // TODO: the program should be loaded from the bytecode of the configured transaction.
-    pub fn new(env: Env, program: Program) -> Self {
+    pub fn new(env: Env<'a>, program: Program) -> Self {
         Self { env, program }
     }
 
     /// Executes [the configured transaction](Env::tx).
-    pub fn transact(&self) -> ExecutionResult {
+    /// Fails without running anything if its gas limit doesn't fit in the block's gas limit.
+    pub fn transact(&self) -> Result<ExecutionResult, EvmError> {
+        self.env.validate_gas_limit()?;
         let output_file = PathBuf::from("output");
 
         let context = Context::new();
-        let module = context
-            .compile(&self.program, &output_file)
-            .expect("failed to compile program");
+        let module = context.compile_with_spec(&self.program, &output_file, self.env.cfg.spec)?;
 
         let executor = Executor::new(&module);
         let mut context = SyscallContext::with_env(self.env.clone());
 
         executor.execute(&mut context, self.env.tx.gas_limit);
-        context.get_result()
+        Ok(context.get_result())
     }
 }
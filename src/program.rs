@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use num_bigint::BigUint;
 use thiserror::Error;
 
@@ -25,39 +27,40 @@ pub enum Opcode {
     AND = 0x16,
     OR = 0x17,
     XOR = 0x18,
-    // NOT = 0x19,
+    NOT = 0x19,
     BYTE = 0x1A,
     SHL = 0x1B,
     SHR = 0x1C,
     SAR = 0x1D,
     // unused 0x1E-0x1F
-    // KECCAK256 = 0x20,
+    KECCAK256 = 0x20,
     // unused 0x21-0x2F
-    // ADDRESS = 0x30,
-    // BALANCE = 0x31,
-    // ORIGIN = 0x32,
-    // CALLER = 0x33,
-    // CALLVALUE = 0x34,
+    ADDRESS = 0x30,
+    BALANCE = 0x31,
+    ORIGIN = 0x32,
+    CALLER = 0x33,
+    CALLVALUE = 0x34,
     CALLDATALOAD = 0x35,
     CALLDATASIZE = 0x36,
-    // CALLDATACOPY = 0x37,
+    CALLDATACOPY = 0x37,
     CODESIZE = 0x38,
-    // CODECOPY = 0x39,
-    // GASPRICE = 0x3A,
-    // EXTCODESIZE = 0x3B,
-    // EXTCODECOPY = 0x3C,
-    // RETURNDATASIZE = 0x3D,
-    // RETURNDATACOPY = 0x3E,
-    // EXTCODEHASH = 0x3F,
-    // BLOCKHASH = 0x40,
-    // COINBASE = 0x41,
-    // TIMESTAMP = 0x42,
-    // NUMBER = 0x43,
-    // DIFFICULTY = 0x44,
-    // GASLIMIT = 0x45,
-    // CHAINID = 0x46,
-    // SELFBALANCE = 0x47,
-    // BASEFEE = 0x48,
+    CODECOPY = 0x39,
+    GASPRICE = 0x3A,
+    EXTCODESIZE = 0x3B,
+    EXTCODECOPY = 0x3C,
+    RETURNDATASIZE = 0x3D,
+    RETURNDATACOPY = 0x3E,
+    EXTCODEHASH = 0x3F,
+    BLOCKHASH = 0x40,
+    COINBASE = 0x41,
+    TIMESTAMP = 0x42,
+    NUMBER = 0x43,
+    // formerly DIFFICULTY, before the Merge
+    PREVRANDAO = 0x44,
+    GASLIMIT = 0x45,
+    CHAINID = 0x46,
+    SELFBALANCE = 0x47,
+    BASEFEE = 0x48,
     // BLOBHASH = 0x49,
     // BLOBBASEFEE = 0x4A,
     // unused 0x4B-0x4F
@@ -65,16 +68,16 @@ pub enum Opcode {
     MLOAD = 0x51,
     MSTORE = 0x52,
     MSTORE8 = 0x53,
-    // SLOAD = 0x54,
-    // SSTORE = 0x55,
+    SLOAD = 0x54,
+    SSTORE = 0x55,
     JUMP = 0x56,
     JUMPI = 0x57,
     PC = 0x58,
     MSIZE = 0x59,
     GAS = 0x5A,
     JUMPDEST = 0x5B,
-    // TLOAD = 0x5C,
-    // TSTORE = 0x5D,
+    TLOAD = 0x5C,
+    TSTORE = 0x5D,
     MCOPY = 0x5E,
     PUSH0 = 0x5F,
     PUSH1 = 0x60,
@@ -147,18 +150,18 @@ pub enum Opcode {
     LOG3 = 0xA3,
     LOG4 = 0xA4,
     // unused 0xA5-0xEF
-    // CREATE = 0xF0,
-    // CALL = 0xF1,
+    CREATE = 0xF0,
+    CALL = 0xF1,
     // CALLCODE = 0xF2,
     RETURN = 0xF3,
     // DELEGATECALL = 0xF4,
-    // CREATE2 = 0xF5,
+    CREATE2 = 0xF5,
     // unused 0xF6-0xF9
     // STATICCALL = 0xFA,
     // unused 0xFB-0xFC
     REVERT = 0xFD,
-    // INVALID = 0xFE,
-    // SELFDESTRUCT = 0xFF,
+    INVALID = 0xFE,
+    SELFDESTRUCT = 0xFF,
 }
 
 #[derive(Error, Debug)]
@@ -166,8 +169,24 @@ pub enum Opcode {
 pub struct OpcodeParseError(u8);
 
 #[derive(Error, Debug)]
-#[error("The following opcodes are not valid: `{:#?}`", self.0)]
-pub struct ParseError(Vec<OpcodeParseError>);
+pub enum ParseError {
+    #[error("The following opcodes are not valid: `{0:#?}`")]
+    InvalidOpcodes(Vec<OpcodeParseError>),
+    /// A PUSH whose value doesn't fit in its immediate. Bytecode can't encode one,
+    /// but a program built from operations can.
+    #[error("The value `{value:#x}` doesn't fit in the immediate of PUSH{width}")]
+    PushTooWide { width: u8, value: BigUint },
+}
+
+impl ParseError {
+    /// Returns the unsupported opcodes found in the bytecode, in the order they appear.
+    pub fn opcodes(&self) -> Vec<u8> {
+        match self {
+            ParseError::InvalidOpcodes(errors) => errors.iter().map(|error| error.0).collect(),
+            ParseError::PushTooWide { .. } => vec![],
+        }
+    }
+}
 
 impl TryFrom<u8> for Opcode {
     type Error = OpcodeParseError;
@@ -194,9 +213,11 @@ impl TryFrom<u8> for Opcode {
             x if x == Opcode::AND as u8 => Opcode::AND,
             x if x == Opcode::OR as u8 => Opcode::OR,
             x if x == Opcode::XOR as u8 => Opcode::XOR,
+            x if x == Opcode::NOT as u8 => Opcode::NOT,
             x if x == Opcode::SHR as u8 => Opcode::SHR,
             x if x == Opcode::SHL as u8 => Opcode::SHL,
             x if x == Opcode::SAR as u8 => Opcode::SAR,
+            x if x == Opcode::KECCAK256 as u8 => Opcode::KECCAK256,
             x if x == Opcode::CODESIZE as u8 => Opcode::CODESIZE,
             x if x == Opcode::POP as u8 => Opcode::POP,
             x if x == Opcode::MLOAD as u8 => Opcode::MLOAD,
@@ -276,12 +297,43 @@ impl TryFrom<u8> for Opcode {
             x if x == Opcode::RETURN as u8 => Opcode::RETURN,
             x if x == Opcode::MSTORE as u8 => Opcode::MSTORE,
             x if x == Opcode::MSTORE8 as u8 => Opcode::MSTORE8,
+            x if x == Opcode::SLOAD as u8 => Opcode::SLOAD,
+            x if x == Opcode::SSTORE as u8 => Opcode::SSTORE,
             x if x == Opcode::LOG0 as u8 => Opcode::LOG0,
             x if x == Opcode::LOG1 as u8 => Opcode::LOG1,
             x if x == Opcode::LOG2 as u8 => Opcode::LOG2,
             x if x == Opcode::LOG3 as u8 => Opcode::LOG3,
             x if x == Opcode::LOG4 as u8 => Opcode::LOG4,
             x if x == Opcode::CALLDATALOAD as u8 => Opcode::CALLDATALOAD,
+            x if x == Opcode::CALLVALUE as u8 => Opcode::CALLVALUE,
+            x if x == Opcode::CALLER as u8 => Opcode::CALLER,
+            x if x == Opcode::ORIGIN as u8 => Opcode::ORIGIN,
+            x if x == Opcode::ADDRESS as u8 => Opcode::ADDRESS,
+            x if x == Opcode::CALLDATACOPY as u8 => Opcode::CALLDATACOPY,
+            x if x == Opcode::CODECOPY as u8 => Opcode::CODECOPY,
+            x if x == Opcode::RETURNDATASIZE as u8 => Opcode::RETURNDATASIZE,
+            x if x == Opcode::RETURNDATACOPY as u8 => Opcode::RETURNDATACOPY,
+            x if x == Opcode::CALL as u8 => Opcode::CALL,
+            x if x == Opcode::GASPRICE as u8 => Opcode::GASPRICE,
+            x if x == Opcode::COINBASE as u8 => Opcode::COINBASE,
+            x if x == Opcode::TIMESTAMP as u8 => Opcode::TIMESTAMP,
+            x if x == Opcode::NUMBER as u8 => Opcode::NUMBER,
+            x if x == Opcode::GASLIMIT as u8 => Opcode::GASLIMIT,
+            x if x == Opcode::CHAINID as u8 => Opcode::CHAINID,
+            x if x == Opcode::PREVRANDAO as u8 => Opcode::PREVRANDAO,
+            x if x == Opcode::BASEFEE as u8 => Opcode::BASEFEE,
+            x if x == Opcode::BALANCE as u8 => Opcode::BALANCE,
+            x if x == Opcode::SELFBALANCE as u8 => Opcode::SELFBALANCE,
+            x if x == Opcode::INVALID as u8 => Opcode::INVALID,
+            x if x == Opcode::TLOAD as u8 => Opcode::TLOAD,
+            x if x == Opcode::TSTORE as u8 => Opcode::TSTORE,
+            x if x == Opcode::SELFDESTRUCT as u8 => Opcode::SELFDESTRUCT,
+            x if x == Opcode::CREATE as u8 => Opcode::CREATE,
+            x if x == Opcode::CREATE2 as u8 => Opcode::CREATE2,
+            x if x == Opcode::EXTCODESIZE as u8 => Opcode::EXTCODESIZE,
+            x if x == Opcode::EXTCODECOPY as u8 => Opcode::EXTCODECOPY,
+            x if x == Opcode::EXTCODEHASH as u8 => Opcode::EXTCODEHASH,
+            x if x == Opcode::BLOCKHASH as u8 => Opcode::BLOCKHASH,
             x => return Err(OpcodeParseError(x)),
         };
 
@@ -289,7 +341,7 @@ impl TryFrom<u8> for Opcode {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Operation {
     Stop,
     Add,
@@ -312,31 +364,72 @@ pub enum Operation {
     And,
     Or,
     Xor,
+    Not,
     Byte,
     Shr,
     Shl,
     Sar,
+    Keccak256,
     Codesize,
     Pop,
     Mload,
     Jump,
     Jumpi,
-    PC { pc: usize },
+    PC {
+        pc: usize,
+    },
     Msize,
     Gas,
-    Jumpdest { pc: usize },
+    Jumpdest {
+        pc: usize,
+    },
     Mcopy,
     Push0,
     Push((u8, BigUint)),
+    /// DUPn, with `n` in `1..=16`. Other values halt the execution, like [`Self::Invalid`].
     Dup(u8),
+    /// SWAPn, with `n` in `1..=16`. Other values halt the execution, like [`Self::Invalid`].
     Swap(u8),
     Return,
     Revert,
     Mstore,
     Mstore8,
+    Sload,
+    Sstore,
+    /// LOGn, with `n` in `0..=4`. Other values fail to compile, with the offending pc.
     Log(u8),
     CalldataLoad,
     CallDataSize,
+    CallValue,
+    Caller,
+    Origin,
+    Address,
+    CalldataCopy,
+    CodeCopy,
+    ReturnDataSize,
+    ReturnDataCopy,
+    Call,
+    GasPrice,
+    Coinbase,
+    Timestamp,
+    Number,
+    GasLimit,
+    ChainId,
+    PrevRandao,
+    BaseFee,
+    Balance,
+    SelfBalance,
+    /// The designated INVALID opcode, also used for bytes that aren't assigned to any opcode.
+    Invalid,
+    Tload,
+    Tstore,
+    SelfDestruct,
+    Create,
+    Create2,
+    ExtCodeSize,
+    ExtCodeCopy,
+    ExtCodeHash,
+    BlockHash,
 }
 
 impl Operation {
@@ -362,10 +455,12 @@ impl Operation {
             Operation::And => vec![Opcode::AND as u8],
             Operation::Or => vec![Opcode::OR as u8],
             Operation::Xor => vec![Opcode::XOR as u8],
+            Operation::Not => vec![Opcode::NOT as u8],
             Operation::Byte => vec![Opcode::BYTE as u8],
             Operation::Shr => vec![Opcode::SHR as u8],
             Operation::Shl => vec![Opcode::SHL as u8],
             Operation::Sar => vec![Opcode::SAR as u8],
+            Operation::Keccak256 => vec![Opcode::KECCAK256 as u8],
             Operation::Codesize => vec![Opcode::CODESIZE as u8],
             Operation::Pop => vec![Opcode::POP as u8],
             Operation::Mload => vec![Opcode::MLOAD as u8],
@@ -392,21 +487,165 @@ impl Operation {
             Operation::Revert => vec![Opcode::REVERT as u8],
             Operation::Mstore => vec![Opcode::MSTORE as u8],
             Operation::Mstore8 => vec![Opcode::MSTORE8 as u8],
-            Operation::Log(n) => vec![Opcode::LOG0 as u8 + n - 1],
+            Operation::Sload => vec![Opcode::SLOAD as u8],
+            Operation::Sstore => vec![Opcode::SSTORE as u8],
+            Operation::Log(n) => vec![Opcode::LOG0 as u8 + n],
             Operation::CalldataLoad => vec![Opcode::CALLDATALOAD as u8],
             Operation::CallDataSize => vec![Opcode::CALLDATASIZE as u8],
+            Operation::CallValue => vec![Opcode::CALLVALUE as u8],
+            Operation::Caller => vec![Opcode::CALLER as u8],
+            Operation::Origin => vec![Opcode::ORIGIN as u8],
+            Operation::Address => vec![Opcode::ADDRESS as u8],
+            Operation::CalldataCopy => vec![Opcode::CALLDATACOPY as u8],
+            Operation::CodeCopy => vec![Opcode::CODECOPY as u8],
+            Operation::ReturnDataSize => vec![Opcode::RETURNDATASIZE as u8],
+            Operation::ReturnDataCopy => vec![Opcode::RETURNDATACOPY as u8],
+            Operation::Call => vec![Opcode::CALL as u8],
+            Operation::GasPrice => vec![Opcode::GASPRICE as u8],
+            Operation::Coinbase => vec![Opcode::COINBASE as u8],
+            Operation::Timestamp => vec![Opcode::TIMESTAMP as u8],
+            Operation::Number => vec![Opcode::NUMBER as u8],
+            Operation::GasLimit => vec![Opcode::GASLIMIT as u8],
+            Operation::ChainId => vec![Opcode::CHAINID as u8],
+            Operation::PrevRandao => vec![Opcode::PREVRANDAO as u8],
+            Operation::BaseFee => vec![Opcode::BASEFEE as u8],
+            Operation::Balance => vec![Opcode::BALANCE as u8],
+            Operation::SelfBalance => vec![Opcode::SELFBALANCE as u8],
+            Operation::Invalid => vec![Opcode::INVALID as u8],
+            Operation::Tload => vec![Opcode::TLOAD as u8],
+            Operation::Tstore => vec![Opcode::TSTORE as u8],
+            Operation::SelfDestruct => vec![Opcode::SELFDESTRUCT as u8],
+            Operation::Create => vec![Opcode::CREATE as u8],
+            Operation::Create2 => vec![Opcode::CREATE2 as u8],
+            Operation::ExtCodeSize => vec![Opcode::EXTCODESIZE as u8],
+            Operation::ExtCodeCopy => vec![Opcode::EXTCODECOPY as u8],
+            Operation::ExtCodeHash => vec![Opcode::EXTCODEHASH as u8],
+            Operation::BlockHash => vec![Opcode::BLOCKHASH as u8],
         }
     }
 }
 
+/// Static information about a program, gathered without running it.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ProgramStats {
+    /// How many times each opcode appears, by opcode byte.
+    /// Unassigned bytes are counted as INVALID, since that's how they're decoded.
+    pub opcode_counts: BTreeMap<u8, usize>,
+    /// The unsupported opcodes found while decoding, in bytecode order.
+    pub unsupported_opcodes: Vec<u8>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Program {
     pub(crate) operations: Vec<Operation>,
     pub(crate) code_size: u32,
+    /// The offsets of the JUMPDEST opcodes in the bytecode
+    pub(crate) valid_jumpdests: BTreeSet<usize>,
 }
 
 impl Program {
+    /// Builds a program from its operations, checking that
+    /// each PUSH value fits in the width of its immediate.
+    ///
+    /// [`Program::from`] trusts the operations as given instead.
+    pub fn new(operations: Vec<Operation>) -> Result<Self, ParseError> {
+        for operation in &operations {
+            if let Operation::Push((width, value)) = operation {
+                if value.bits() > *width as u64 * 8 {
+                    return Err(ParseError::PushTooWide {
+                        width: *width,
+                        value: value.clone(),
+                    });
+                }
+            }
+        }
+        Ok(Self::from(operations))
+    }
+
+    /// Decodes `bytecode` into a program.
+    ///
+    /// Bytes that aren't assigned to any opcode are decoded as [`Operation::Invalid`],
+    /// while unsupported opcodes make the parsing fail.
+    pub fn parse(bytecode: &[u8]) -> Result<Self, ParseError> {
+        Self::from_bytecode(bytecode)
+    }
+
+    /// Returns the decoded operations, in program order.
+    pub fn operations(&self) -> &[Operation] {
+        &self.operations
+    }
+
+    /// Returns the size of the program's bytecode, in bytes.
+    pub fn code_size(&self) -> usize {
+        self.code_size as usize
+    }
+
+    /// Returns the offsets of the JUMPDEST opcodes, which are the only valid jump targets.
+    pub fn valid_jumpdests(&self) -> &BTreeSet<usize> {
+        &self.valid_jumpdests
+    }
+
+    /// Gathers statistics about the program's operations.
+    ///
+    /// Programs can't contain unsupported opcodes, since they make the parsing fail,
+    /// so `unsupported_opcodes` is always empty. Use [`Program::analyze_bytecode`]
+    /// to inspect bytecode that doesn't parse.
+    pub fn analyze(&self) -> ProgramStats {
+        ProgramStats {
+            opcode_counts: count_opcodes(&self.operations),
+            unsupported_opcodes: vec![],
+        }
+    }
+
+    /// Gathers statistics about `bytecode` without requiring it to parse.
+    ///
+    /// Unsupported opcodes are skipped and listed, instead of failing like [`Program::parse`].
+    pub fn analyze_bytecode(bytecode: &[u8]) -> ProgramStats {
+        let (operations, failed_opcodes) = Self::decode(bytecode);
+        ProgramStats {
+            opcode_counts: count_opcodes(&operations),
+            unsupported_opcodes: failed_opcodes.iter().map(|error| error.0).collect(),
+        }
+    }
+
+    /// Returns the bytecode of the program, as it's seen by CODESIZE and CODECOPY.
+    ///
+    /// Each PUSH is encoded with its own width, so decoding and re-encoding
+    /// a program gives back the original bytes.
+    pub fn to_bytecode(&self) -> Vec<u8> {
+        let mut bytecode: Vec<_> = self
+            .operations
+            .iter()
+            .flat_map(Operation::to_bytecode)
+            .collect();
+        // A truncated PUSH at the end is zero-padded when decoding,
+        // but the padding isn't part of the code
+        bytecode.truncate(self.code_size as usize);
+        bytecode
+    }
+
     pub fn from_bytecode(bytecode: &[u8]) -> Result<Self, ParseError> {
+        let (operations, failed_opcodes) = Self::decode(bytecode);
+
+        // NOTE: a truncated PUSH at the end makes the operations longer than the bytecode
+        let code_size = bytecode.len() as u32;
+
+        if failed_opcodes.is_empty() {
+            // PUSH immediates are skipped while decoding, so a 0x5B byte in them
+            // never becomes a JUMPDEST
+            let valid_jumpdests = Self::get_jumpdests(&operations);
+            Ok(Program {
+                operations,
+                code_size,
+                valid_jumpdests,
+            })
+        } else {
+            Err(ParseError::InvalidOpcodes(failed_opcodes))
+        }
+    }
+
+    /// Decodes `bytecode`, skipping the unsupported opcodes and returning them apart.
+    fn decode(bytecode: &[u8]) -> (Vec<Operation>, Vec<OpcodeParseError>) {
         let mut operations = vec![];
         let mut pc = 0;
         let mut failed_opcodes = vec![];
@@ -416,6 +655,13 @@ impl Program {
                 break;
             };
 
+            if !is_assigned_opcode(opcode) {
+                // Executing an unassigned byte is the same as executing INVALID
+                operations.push(Operation::Invalid);
+                pc += 1;
+                continue;
+            }
+
             let opcode = Opcode::try_from(opcode);
 
             if let Err(e) = opcode {
@@ -446,10 +692,12 @@ impl Program {
                 Opcode::AND => Operation::And,
                 Opcode::OR => Operation::Or,
                 Opcode::XOR => Operation::Xor,
+                Opcode::NOT => Operation::Not,
                 Opcode::BYTE => Operation::Byte,
                 Opcode::SHR => Operation::Shr,
                 Opcode::SHL => Operation::Shl,
                 Opcode::SAR => Operation::Sar,
+                Opcode::KECCAK256 => Operation::Keccak256,
                 Opcode::CODESIZE => Operation::Codesize,
                 Opcode::POP => Operation::Pop,
                 Opcode::MLOAD => Operation::Mload,
@@ -461,198 +709,38 @@ impl Program {
                 Opcode::JUMPDEST => Operation::Jumpdest { pc },
                 Opcode::MCOPY => Operation::Mcopy,
                 Opcode::PUSH0 => Operation::Push0,
-                Opcode::PUSH1 => {
-                    // TODO: return error if not enough bytes (same for PUSHN)
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 1)].try_into().unwrap();
-                    Operation::Push((1, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH2 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 2)].try_into().unwrap();
-                    pc += 1;
-                    Operation::Push((2, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH3 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 3)].try_into().unwrap();
-                    pc += 2;
-                    Operation::Push((3, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH4 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 4)].try_into().unwrap();
-                    pc += 3;
-                    Operation::Push((4, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH5 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 5)].try_into().unwrap();
-                    pc += 4;
-                    Operation::Push((5, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH6 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 6)].try_into().unwrap();
-                    pc += 5;
-                    Operation::Push((6, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH7 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 7)].try_into().unwrap();
-                    pc += 6;
-                    Operation::Push((7, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH8 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 8)].try_into().unwrap();
-                    pc += 7;
-                    Operation::Push((8, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH9 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 9)].try_into().unwrap();
-                    pc += 8;
-                    Operation::Push((9, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH10 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 10)].try_into().unwrap();
-                    pc += 9;
-                    Operation::Push((10, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH11 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 11)].try_into().unwrap();
-                    pc += 10;
-                    Operation::Push((11, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH12 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 12)].try_into().unwrap();
-                    pc += 11;
-                    Operation::Push((12, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH13 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 13)].try_into().unwrap();
-                    pc += 12;
-                    Operation::Push((13, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH14 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 14)].try_into().unwrap();
-                    pc += 13;
-                    Operation::Push((14, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH15 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 15)].try_into().unwrap();
-                    pc += 14;
-                    Operation::Push((15, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH16 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 16)].try_into().unwrap();
-                    pc += 15;
-                    Operation::Push((16, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH17 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 17)].try_into().unwrap();
-                    pc += 16;
-                    Operation::Push((17, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH18 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 18)].try_into().unwrap();
-                    pc += 17;
-                    Operation::Push((18, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH19 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 19)].try_into().unwrap();
-                    pc += 18;
-                    Operation::Push((19, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH20 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 20)].try_into().unwrap();
-                    pc += 19;
-                    Operation::Push((20, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH21 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 21)].try_into().unwrap();
-                    pc += 20;
-                    Operation::Push((21, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH22 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 32)].try_into().unwrap();
-                    pc += 21;
-                    Operation::Push((22, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH23 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 32)].try_into().unwrap();
-                    pc += 22;
-                    Operation::Push((23, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH24 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 32)].try_into().unwrap();
-                    pc += 23;
-                    Operation::Push((24, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH25 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 32)].try_into().unwrap();
-                    pc += 24;
-                    Operation::Push((25, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH26 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 26)].try_into().unwrap();
-                    pc += 25;
-                    Operation::Push((26, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH27 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 27)].try_into().unwrap();
-                    pc += 26;
-                    Operation::Push((27, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH28 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 28)].try_into().unwrap();
-                    pc += 27;
-                    Operation::Push((28, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH29 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 29)].try_into().unwrap();
-                    pc += 28;
-                    Operation::Push((29, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH30 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 30)].try_into().unwrap();
-                    pc += 29;
-                    Operation::Push((30, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH31 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 31)].try_into().unwrap();
-                    pc += 30;
-                    Operation::Push((31, (BigUint::from_bytes_be(x))))
-                }
-                Opcode::PUSH32 => {
-                    pc += 1;
-                    let x = bytecode[pc..(pc + 32)].try_into().unwrap();
-                    pc += 31;
-                    Operation::Push((32, (BigUint::from_bytes_be(x))))
-                }
+                Opcode::PUSH1 => read_push(bytecode, &mut pc, 1),
+                Opcode::PUSH2 => read_push(bytecode, &mut pc, 2),
+                Opcode::PUSH3 => read_push(bytecode, &mut pc, 3),
+                Opcode::PUSH4 => read_push(bytecode, &mut pc, 4),
+                Opcode::PUSH5 => read_push(bytecode, &mut pc, 5),
+                Opcode::PUSH6 => read_push(bytecode, &mut pc, 6),
+                Opcode::PUSH7 => read_push(bytecode, &mut pc, 7),
+                Opcode::PUSH8 => read_push(bytecode, &mut pc, 8),
+                Opcode::PUSH9 => read_push(bytecode, &mut pc, 9),
+                Opcode::PUSH10 => read_push(bytecode, &mut pc, 10),
+                Opcode::PUSH11 => read_push(bytecode, &mut pc, 11),
+                Opcode::PUSH12 => read_push(bytecode, &mut pc, 12),
+                Opcode::PUSH13 => read_push(bytecode, &mut pc, 13),
+                Opcode::PUSH14 => read_push(bytecode, &mut pc, 14),
+                Opcode::PUSH15 => read_push(bytecode, &mut pc, 15),
+                Opcode::PUSH16 => read_push(bytecode, &mut pc, 16),
+                Opcode::PUSH17 => read_push(bytecode, &mut pc, 17),
+                Opcode::PUSH18 => read_push(bytecode, &mut pc, 18),
+                Opcode::PUSH19 => read_push(bytecode, &mut pc, 19),
+                Opcode::PUSH20 => read_push(bytecode, &mut pc, 20),
+                Opcode::PUSH21 => read_push(bytecode, &mut pc, 21),
+                Opcode::PUSH22 => read_push(bytecode, &mut pc, 22),
+                Opcode::PUSH23 => read_push(bytecode, &mut pc, 23),
+                Opcode::PUSH24 => read_push(bytecode, &mut pc, 24),
+                Opcode::PUSH25 => read_push(bytecode, &mut pc, 25),
+                Opcode::PUSH26 => read_push(bytecode, &mut pc, 26),
+                Opcode::PUSH27 => read_push(bytecode, &mut pc, 27),
+                Opcode::PUSH28 => read_push(bytecode, &mut pc, 28),
+                Opcode::PUSH29 => read_push(bytecode, &mut pc, 29),
+                Opcode::PUSH30 => read_push(bytecode, &mut pc, 30),
+                Opcode::PUSH31 => read_push(bytecode, &mut pc, 31),
+                Opcode::PUSH32 => read_push(bytecode, &mut pc, 32),
                 Opcode::DUP1 => Operation::Dup(1),
                 Opcode::DUP2 => Operation::Dup(2),
                 Opcode::DUP3 => Operation::Dup(3),
@@ -689,6 +777,8 @@ impl Program {
                 Opcode::REVERT => Operation::Revert,
                 Opcode::MSTORE => Operation::Mstore,
                 Opcode::MSTORE8 => Operation::Mstore8,
+                Opcode::SLOAD => Operation::Sload,
+                Opcode::SSTORE => Operation::Sstore,
                 Opcode::LOG0 => Operation::Log(0),
                 Opcode::LOG1 => Operation::Log(1),
                 Opcode::LOG2 => Operation::Log(2),
@@ -696,21 +786,41 @@ impl Program {
                 Opcode::LOG4 => Operation::Log(4),
                 Opcode::CALLDATALOAD => Operation::CalldataLoad,
                 Opcode::CALLDATASIZE => Operation::CallDataSize,
+                Opcode::CALLVALUE => Operation::CallValue,
+                Opcode::CALLER => Operation::Caller,
+                Opcode::ORIGIN => Operation::Origin,
+                Opcode::ADDRESS => Operation::Address,
+                Opcode::CALLDATACOPY => Operation::CalldataCopy,
+                Opcode::CODECOPY => Operation::CodeCopy,
+                Opcode::RETURNDATASIZE => Operation::ReturnDataSize,
+                Opcode::RETURNDATACOPY => Operation::ReturnDataCopy,
+                Opcode::CALL => Operation::Call,
+                Opcode::GASPRICE => Operation::GasPrice,
+                Opcode::COINBASE => Operation::Coinbase,
+                Opcode::TIMESTAMP => Operation::Timestamp,
+                Opcode::NUMBER => Operation::Number,
+                Opcode::GASLIMIT => Operation::GasLimit,
+                Opcode::CHAINID => Operation::ChainId,
+                Opcode::PREVRANDAO => Operation::PrevRandao,
+                Opcode::BASEFEE => Operation::BaseFee,
+                Opcode::BALANCE => Operation::Balance,
+                Opcode::SELFBALANCE => Operation::SelfBalance,
+                Opcode::INVALID => Operation::Invalid,
+                Opcode::TLOAD => Operation::Tload,
+                Opcode::TSTORE => Operation::Tstore,
+                Opcode::SELFDESTRUCT => Operation::SelfDestruct,
+                Opcode::CREATE => Operation::Create,
+                Opcode::CREATE2 => Operation::Create2,
+                Opcode::EXTCODESIZE => Operation::ExtCodeSize,
+                Opcode::EXTCODECOPY => Operation::ExtCodeCopy,
+                Opcode::EXTCODEHASH => Operation::ExtCodeHash,
+                Opcode::BLOCKHASH => Operation::BlockHash,
             };
             operations.push(op);
             pc += 1;
         }
 
-        let code_size = Self::get_codesize(&operations);
-
-        if failed_opcodes.is_empty() {
-            Ok(Program {
-                operations,
-                code_size,
-            })
-        } else {
-            Err(ParseError(failed_opcodes))
-        }
+        (operations, failed_opcodes)
     }
 
     fn get_codesize(operations: &[Operation]) -> u32 {
@@ -723,15 +833,66 @@ impl Program {
             })
             .sum()
     }
+
+    fn get_jumpdests(operations: &[Operation]) -> BTreeSet<usize> {
+        operations
+            .iter()
+            .filter_map(|op| match op {
+                Operation::Jumpdest { pc } => Some(*pc),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Reads the `size`-byte immediate of the PUSH at `pc`, leaving `pc` at its last byte.
+///
+/// If the bytecode ends before the immediate does, the missing (low-order)
+/// bytes are read as zeros, just like the code past the end.
+fn read_push(bytecode: &[u8], pc: &mut usize, size: u8) -> Operation {
+    let start = *pc + 1;
+    let available = bytecode.len().saturating_sub(start).min(size as usize);
+
+    let mut immediate = vec![0_u8; size as usize];
+    immediate[..available].copy_from_slice(&bytecode[start..start + available]);
+
+    *pc += size as usize;
+    Operation::Push((size, BigUint::from_bytes_be(&immediate)))
+}
+
+/// Counts the operations by their opcode byte.
+fn count_opcodes(operations: &[Operation]) -> BTreeMap<u8, usize> {
+    let mut opcode_counts = BTreeMap::new();
+    for operation in operations {
+        *opcode_counts.entry(operation.to_bytecode()[0]).or_insert(0) += 1;
+    }
+    opcode_counts
+}
+
+/// Returns true if the byte is assigned to an opcode, even if it isn't supported yet.
+fn is_assigned_opcode(byte: u8) -> bool {
+    matches!(
+        byte,
+        0x00..=0x0B
+            | 0x10..=0x1D
+            | 0x20
+            | 0x30..=0x4A
+            | 0x50..=0xA4
+            | 0xF0..=0xF5
+            | 0xFA
+            | 0xFD..=0xFF
+    )
 }
 
 impl From<Vec<Operation>> for Program {
     fn from(operations: Vec<Operation>) -> Self {
         let code_size = Self::get_codesize(&operations);
+        let valid_jumpdests = Self::get_jumpdests(&operations);
 
         Program {
             operations,
             code_size,
+            valid_jumpdests,
         }
     }
 }
@@ -0,0 +1,39 @@
+use crate::program::Operation;
+
+/// The Ethereum hardforks, in activation order.
+///
+/// The active spec decides which opcodes are available and which gas schedule applies.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Spec {
+    Istanbul,
+    /// Introduces cold and warm accesses ([EIP-2929](https://eips.ethereum.org/EIPS/eip-2929))
+    Berlin,
+    /// Introduces BASEFEE ([EIP-3198](https://eips.ethereum.org/EIPS/eip-3198))
+    London,
+    /// Replaces DIFFICULTY with PREVRANDAO ([EIP-4399](https://eips.ethereum.org/EIPS/eip-4399))
+    Merge,
+    /// Introduces PUSH0 ([EIP-3855](https://eips.ethereum.org/EIPS/eip-3855)),
+    /// and limits and meters the init code of contract creations ([EIP-3860](https://eips.ethereum.org/EIPS/eip-3860))
+    Shanghai,
+    /// Introduces MCOPY ([EIP-5656](https://eips.ethereum.org/EIPS/eip-5656)),
+    /// and TLOAD and TSTORE ([EIP-1153](https://eips.ethereum.org/EIPS/eip-1153))
+    #[default]
+    Cancun,
+}
+
+impl Spec {
+    /// Returns true if the features introduced in `fork` are active in this spec.
+    pub fn enabled(self, fork: Spec) -> bool {
+        self >= fork
+    }
+
+    /// Returns true if `operation` is available in this spec.
+    pub fn is_operation_enabled(self, operation: &Operation) -> bool {
+        match operation {
+            Operation::BaseFee => self.enabled(Spec::London),
+            Operation::Push0 => self.enabled(Spec::Shanghai),
+            Operation::Mcopy | Operation::Tload | Operation::Tstore => self.enabled(Spec::Cancun),
+            _ => true,
+        }
+    }
+}
@@ -10,20 +10,39 @@
 //!
 //! New syscalls should be implemented by adding a new method to the [`SyscallContext`]
 //! struct (see [`SyscallContext::write_result`] for an example). After that, the syscall
-//! should be registered in the [`register_syscalls`] function, which will make it available
-//! to the generated code. Afterwards, the syscall should be declared in
+//! should be added to the [`syscall_table`], whose entries [`register_syscalls`] makes
+//! available to the generated code. Afterwards, the syscall should be declared in
 //! [`mlir::declare_syscalls`], which will make the syscall available inside the MLIR code.
 //! Finally, the function can be called from the MLIR code like a normal function (see
 //! [`mlir::write_result_syscall`] for an example).
-use std::ffi::c_void;
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::c_void,
+    fmt::Debug,
+    ops::Range,
+    rc::Rc,
+};
 
-use melior::ExecutionEngine;
+use sha3::{Digest, Keccak256};
+use thiserror::Error;
 
-use crate::env::Env;
+use crate::{
+    constants::{gas_cost, DEFAULT_MEMORY_LIMIT, MAX_CALL_DEPTH, MAX_CODE_SIZE, MAX_STACK_SIZE},
+    env::{Address, Env},
+    executor::{CompiledProgram, Executor, SymbolRegistry},
+    program::Program,
+    spec::Spec,
+};
 
 /// Function type for the main entrypoint of the generated code
 pub type MainFunc = extern "C" fn(&mut SyscallContext, initial_gas: u64) -> u8;
 
+/// A 256-bit word, as exchanged with the generated code.
+///
+/// Its layout matches the generated code's `i256` on little-endian hosts: the low half
+/// comes first. Use [`U256::from_be_bytes`] and [`U256::to_be_bytes`] to convert it
+/// from and to the EVM's big-endian byte order.
 #[derive(Clone, Copy, Debug, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[repr(C, align(16))]
 pub struct U256 {
@@ -31,7 +50,35 @@ pub struct U256 {
     pub hi: u128,
 }
 
-#[derive(Debug, Clone)]
+impl U256 {
+    /// Creates a word from its big-endian bytes.
+    pub fn from_be_bytes(bytes: &[u8; 32]) -> Self {
+        let (hi, lo) = bytes.split_at(16);
+        U256 {
+            hi: u128::from_be_bytes(hi.try_into().unwrap()),
+            lo: u128::from_be_bytes(lo.try_into().unwrap()),
+        }
+    }
+
+    /// Returns the word's big-endian bytes.
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0; 32];
+        bytes[..16].copy_from_slice(&self.hi.to_be_bytes());
+        bytes[16..].copy_from_slice(&self.lo.to_be_bytes());
+        bytes
+    }
+}
+
+impl From<u64> for U256 {
+    fn from(value: u64) -> Self {
+        U256 {
+            lo: value as u128,
+            hi: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
 pub enum ExitStatusCode {
     Return = 0,
     Stop,
@@ -44,13 +91,27 @@ impl ExitStatusCode {
     pub fn to_u8(self) -> u8 {
         self as u8
     }
+    /// Converts `value` to a status code, mapping unknown values to [`Self::Default`].
+    /// See the [`TryFrom`] implementation for a conversion that rejects them.
     pub fn from_u8(value: u8) -> Self {
+        Self::try_from(value).unwrap_or(Self::Default)
+    }
+}
+
+#[derive(Error, Debug, Eq, PartialEq)]
+#[error("The exit status code `{0}` is not valid")]
+pub struct InvalidExitStatusCode(pub u8);
+
+impl TryFrom<u8> for ExitStatusCode {
+    type Error = InvalidExitStatusCode;
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
-            x if x == Self::Return.to_u8() => Self::Return,
-            x if x == Self::Stop.to_u8() => Self::Stop,
-            x if x == Self::Revert.to_u8() => Self::Revert,
-            x if x == Self::Error.to_u8() => Self::Error,
-            _ => Self::Default,
+            x if x == Self::Return.to_u8() => Ok(Self::Return),
+            x if x == Self::Stop.to_u8() => Ok(Self::Stop),
+            x if x == Self::Revert.to_u8() => Ok(Self::Revert),
+            x if x == Self::Error.to_u8() => Ok(Self::Error),
+            x if x == Self::Default.to_u8() => Ok(Self::Default),
+            x => Err(InvalidExitStatusCode(x)),
         }
     }
 }
@@ -59,14 +120,48 @@ impl ExitStatusCode {
 pub enum ExecutionResult {
     Success {
         return_data: Vec<u8>,
+        /// The gas left after the execution, including the refund
         gas_remaining: u64,
+        /// The gas consumed by the execution, after the refund
+        gas_used: u64,
+        /// The gas refunded at the end of the execution (e.g. for clearing storage)
+        gas_refunded: u64,
         logs: Vec<Log>,
+        /// How many times each opcode ran, when [profiling](SyscallContext::with_profiling)
+        opcode_counts: Option<OpcodeCounts>,
     },
     Revert {
         return_data: Vec<u8>,
         gas_remaining: u64,
+        gas_used: u64,
+        /// The logs emitted before reverting. The EVM discards them, so they must
+        /// never be committed; they're only kept for tracing and debugging
+        logs: Vec<Log>,
+        /// Where the execution reverted, when [captured](SyscallContext::with_failure_capture)
+        failure: Option<FailureInfo>,
+        /// How many times each opcode ran, when [profiling](SyscallContext::with_profiling)
+        opcode_counts: Option<OpcodeCounts>,
+    },
+    /// Exceptional halt (out of gas, stack under/overflow, invalid jump, etc.).
+    /// By EVM rules these consume all the gas, so `gas_remaining` is always 0.
+    Halt {
+        gas_remaining: u64,
+        /// Where the execution halted, when [captured](SyscallContext::with_failure_capture)
+        failure: Option<FailureInfo>,
     },
-    Halt,
+}
+
+/// How many times each opcode ran during an execution, by opcode.
+/// Opcodes that never ran are left out.
+pub type OpcodeCounts = BTreeMap<u8, u64>;
+
+/// The operation a failed execution stopped at, and the stack it found.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FailureInfo {
+    /// The program counter of the reverting or halting operation.
+    pub pc: usize,
+    /// The stack values right before the operation ran, from bottom to top.
+    pub stack: Vec<U256>,
 }
 
 impl ExecutionResult {
@@ -82,38 +177,242 @@ impl ExecutionResult {
         matches!(self, Self::Halt { .. })
     }
 
+    /// Returns the data returned by RETURN or REVERT.
+    ///
+    /// An exceptional halt has no return data at all, which is why it's `None` rather than
+    /// empty. For a caller, both a revert and a halt are failed calls, pushing 0 to its stack,
+    /// but only a revert sets the data seen by RETURNDATASIZE and RETURNDATACOPY.
     pub fn return_data(&self) -> Option<&[u8]> {
         match self {
             Self::Success { return_data, .. } | Self::Revert { return_data, .. } => {
                 Some(return_data)
             }
-            Self::Halt => None,
+            Self::Halt { .. } => None,
+        }
+    }
+
+    /// Returns the 32-byte word at `index` of the return data, read as big-endian,
+    /// as in an ABI-encoded output. It's `None` past the last whole word, or after a halt.
+    pub fn return_word(&self, index: usize) -> Option<U256> {
+        self.return_data_words().nth(index)
+    }
+
+    /// Iterates the return data as big-endian 32-byte words.
+    /// Trailing bytes that don't make a whole word are skipped.
+    pub fn return_data_words(&self) -> impl Iterator<Item = U256> + '_ {
+        self.return_data()
+            .unwrap_or_default()
+            .chunks_exact(32)
+            .map(|word| U256::from_be_bytes(word.try_into().unwrap()))
+    }
+
+    pub fn gas_remaining(&self) -> u64 {
+        match self {
+            Self::Success { gas_remaining, .. }
+            | Self::Revert { gas_remaining, .. }
+            | Self::Halt { gas_remaining, .. } => *gas_remaining,
         }
     }
 
+    /// Returns the logs committed by the execution, which only happens on success.
     pub fn return_logs(&self) -> Option<&Vec<Log>> {
         match self {
             Self::Success { logs, .. } => Some(logs),
             _ => None,
         }
     }
+
+    /// Returns the logs emitted by the execution regardless of its outcome.
+    ///
+    /// Unlike [`Self::return_logs`], this includes the logs of a reverted
+    /// execution, which are not part of the resulting state.
+    pub fn emitted_logs(&self) -> &[Log] {
+        match self {
+            Self::Success { logs, .. } | Self::Revert { logs, .. } => logs,
+            Self::Halt { .. } => &[],
+        }
+    }
+
+    /// Returns where a reverted or halted execution stopped, if it was
+    /// [captured](SyscallContext::with_failure_capture).
+    pub fn failure(&self) -> Option<&FailureInfo> {
+        match self {
+            Self::Revert { failure, .. } | Self::Halt { failure, .. } => failure.as_ref(),
+            Self::Success { .. } => None,
+        }
+    }
+
+    /// Returns how many times each opcode ran, if the execution was
+    /// [profiled](SyscallContext::with_profiling). An exceptional halt reports no counts.
+    pub fn opcode_counts(&self) -> Option<&OpcodeCounts> {
+        match self {
+            Self::Success { opcode_counts, .. } | Self::Revert { opcode_counts, .. } => {
+                opcode_counts.as_ref()
+            }
+            Self::Halt { .. } => None,
+        }
+    }
+}
+
+/// Provides the bytecode of the accounts reachable through calls.
+pub trait CodeProvider: Debug {
+    /// Returns the code of the account at `address`, or `None` if it has no code.
+    fn get_code(&self, address: &Address) -> Option<Vec<u8>>;
+}
+
+impl CodeProvider for HashMap<Address, Vec<u8>> {
+    fn get_code(&self, address: &Address) -> Option<Vec<u8>> {
+        self.get(address).cloned()
+    }
+}
+
+/// The state of the execution right before an operation runs, as reported to a [`Tracer`].
+///
+/// It carries what's needed to build a `debug_traceTransaction` struct log:
+/// the gas cost of a step is the difference with the gas remaining at the next one.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct StepInfo {
+    /// The program counter of the operation.
+    pub pc: usize,
+    /// The opcode of the operation.
+    pub opcode: u8,
+    /// The gas remaining before the operation runs.
+    pub gas_remaining: u64,
+    /// The number of calls the execution is nested in, starting at 0.
+    pub depth: usize,
+    /// The stack values, from bottom to top.
+    pub stack: Vec<U256>,
+    /// The contents of the memory.
+    pub memory: Vec<u8>,
+}
+
+/// Callback called before each executed operation.
+pub type Tracer = Box<dyn FnMut(StepInfo)>;
+
+/// Wrapper around the installed [`Tracer`], since closures don't implement [`Debug`].
+struct TracerHook(Tracer);
+
+impl Debug for TracerHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Tracer")
+    }
+}
+
+/// Callback called with the opcode of each ADD, SUB or MUL whose result wrapped around,
+/// in programs compiled with overflow instrumentation.
+pub type OverflowHandler = Box<dyn FnMut(u8)>;
+
+/// Wrapper around the installed [`OverflowHandler`], since closures don't implement [`Debug`].
+struct OverflowHook(OverflowHandler);
+
+impl Debug for OverflowHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("OverflowHandler")
+    }
+}
+
+/// Wrapper around the writer the debug syscalls print to, since it doesn't implement [`Debug`].
+#[cfg(feature = "debug-syscalls")]
+struct DebugWriter(Box<dyn std::io::Write>);
+
+#[cfg(feature = "debug-syscalls")]
+impl Debug for DebugWriter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DebugWriter")
+    }
 }
 
 /// The context passed to syscalls
 #[derive(Debug, Default)]
-pub struct SyscallContext {
+pub struct SyscallContext<'a> {
     /// The memory segment of the EVM.
     /// For extending it, see [`Self::extend_memory`]
     memory: Vec<u8>,
+    /// The maximum size of the memory, in bytes, regardless of the gas available.
+    /// Defaults to [`DEFAULT_MEMORY_LIMIT`] when unset.
+    memory_limit: Option<usize>,
     /// The result of the execution
     return_data: Option<(usize, usize)>,
     gas_remaining: Option<u64>,
     exit_status: Option<ExitStatusCode>,
+    /// Whether to keep the PC and stack of each operation, to report where a failed
+    /// execution stopped.
+    capture_failures: bool,
+    /// The last operation reported while capturing failures.
+    last_step: Option<FailureInfo>,
+    /// How many times each opcode ran so far, including the ones of nested calls.
+    /// Only counted while profiling.
+    opcode_counts: Option<OpcodeCounts>,
     /// The execution environment. It contains chain, block, and tx data.
-    #[allow(unused)]
-    pub env: Env,
-    #[allow(unused)]
+    pub env: Env<'a>,
     logs: Vec<Log>,
+    /// The storage of every account, keyed by the account's address and the slot.
+    /// It can be pre-seeded before execution, and inspected afterwards.
+    pub storage: HashMap<(Address, U256), U256>,
+    /// The gas to refund at the end of the transaction. It can be negative
+    /// during the execution, when a refund is taken back.
+    refund: i64,
+    /// The gas given to the execution, set by the [`Executor`] before running.
+    pub(crate) initial_gas: u64,
+    /// The gas charged to the transaction before running its code. It isn't available to
+    /// the execution, but counts as used, also towards the refund cap.
+    intrinsic_gas: u64,
+    /// The transient storage of every account ([EIP-1153]), keyed like [`Self::storage`].
+    /// It starts empty, since it's discarded at the end of each transaction.
+    ///
+    /// [EIP-1153]: https://eips.ethereum.org/EIPS/eip-1153
+    pub transient_storage: HashMap<(Address, U256), U256>,
+    /// The values of the written storage slots as they were before the first write.
+    original_storage: HashMap<(Address, U256), U256>,
+    /// The storage slots accessed so far ([EIP-2929] warm slots).
+    ///
+    /// [EIP-2929]: https://eips.ethereum.org/EIPS/eip-2929
+    accessed_storage_keys: HashSet<(Address, U256)>,
+    /// The balance of each account, in wei. Accounts not in the map have no balance.
+    /// It can be pre-seeded before execution, and inspected afterwards.
+    pub balances: HashMap<Address, U256>,
+    /// The accounts accessed so far ([EIP-2929] warm addresses),
+    /// besides the caller and the executing contract, which are always warm.
+    ///
+    /// [EIP-2929]: https://eips.ethereum.org/EIPS/eip-2929
+    accessed_addresses: HashSet<Address>,
+    /// The accounts created in the current transaction.
+    created_accounts: HashSet<Address>,
+    /// The nonce of each account. Accounts not in the map have a nonce of 0.
+    /// It can be pre-seeded before execution, and inspected afterwards.
+    pub nonces: HashMap<Address, u64>,
+    /// The code of the contracts deployed by CREATE and CREATE2 in the current transaction.
+    pub deployed_code: HashMap<Address, Vec<u8>>,
+    /// The accounts destroyed by SELFDESTRUCT in the current transaction.
+    pub destroyed_accounts: HashSet<Address>,
+    /// The data returned by the last call made from this context,
+    /// read by RETURNDATASIZE and RETURNDATACOPY.
+    call_return_data: Vec<u8>,
+    /// Provides the code of the called contracts.
+    /// Calls to accounts without code always succeed without executing anything.
+    code_provider: Option<Rc<dyn CodeProvider>>,
+    /// The number of calls this context is nested in.
+    depth: usize,
+    /// Called before each executed operation, including the ones of nested calls.
+    tracer: Option<TracerHook>,
+    /// The most operations to execute, including the ones of nested calls,
+    /// before halting. Unlimited when unset.
+    step_limit: Option<u64>,
+    /// The operations executed so far, counted only while a step limit is set.
+    steps: u64,
+    /// Called on each overflow reported by the instrumented code.
+    /// Nested calls aren't instrumented, so they never call it.
+    overflow_handler: Option<OverflowHook>,
+    /// The values the stack holds when the execution starts, from bottom to top.
+    initial_stack: Vec<U256>,
+    /// The memory of the stack, allocated by the generated code when it starts.
+    stack: Vec<U256>,
+    /// Whether the execution is read-only, as inside a STATICCALL.
+    /// State-modifying operations (SSTORE, LOG, and CALL with value) fail when set.
+    pub is_static: bool,
+    /// Where the debug syscalls print to. Defaults to stderr when unset.
+    #[cfg(feature = "debug-syscalls")]
+    debug_writer: Option<DebugWriter>,
 }
 
 #[derive(Clone, Debug, Default, Eq, PartialEq)]
@@ -122,34 +421,179 @@ pub struct Log {
     pub data: Vec<u8>,
 }
 
+impl Log {
+    /// Returns the topic at `index` as a big-endian word, as the EVM sees it.
+    pub fn topic(&self, index: usize) -> Option<[u8; 32]> {
+        self.topics.get(index).map(U256::to_be_bytes)
+    }
+
+    /// Returns all the topics as big-endian words, in emission order.
+    pub fn topics_be_bytes(&self) -> Vec<[u8; 32]> {
+        self.topics.iter().map(U256::to_be_bytes).collect()
+    }
+}
+
 /// Accessors for disponibilizing the execution results
-impl SyscallContext {
-    pub fn with_env(env: Env) -> Self {
+impl<'a> SyscallContext<'a> {
+    pub fn with_env(env: Env<'a>) -> Self {
+        Self {
+            env,
+            ..Self::default()
+        }
+    }
+
+    /// Creates a context whose memory can grow up to `memory_capacity` bytes
+    /// without reallocating.
+    pub fn with_capacity(env: Env<'a>, memory_capacity: usize) -> Self {
         Self {
             env,
+            memory: Vec::with_capacity(memory_capacity),
             ..Self::default()
         }
     }
+
+    /// Limits the memory to `memory_limit` bytes. Extending it further halts the execution,
+    /// even if there's gas for it, so huge amounts of gas can't exhaust the host's memory.
+    pub fn with_memory_limit(mut self, memory_limit: usize) -> Self {
+        self.memory_limit = Some(memory_limit);
+        self
+    }
+
+    /// Records the PC and the stack of the operation where the execution reverts or halts,
+    /// to be returned as its [failure](ExecutionResult::failure).
+    ///
+    /// This is meant for debugging: the stack is copied before every operation.
+    pub fn with_failure_capture(mut self) -> Self {
+        self.capture_failures = true;
+        self
+    }
+
+    /// Counts how many times each opcode runs, including the ones of nested calls,
+    /// to be returned as the [opcode counts](ExecutionResult::opcode_counts) of the result.
+    pub fn with_profiling(mut self) -> Self {
+        self.opcode_counts = Some(OpcodeCounts::new());
+        self
+    }
+
+    /// Sets the provider of the code executed by nested calls.
+    pub fn with_code_provider(mut self, code_provider: impl CodeProvider + 'static) -> Self {
+        self.code_provider = Some(Rc::new(code_provider));
+        self
+    }
+
+    /// Seeds the stack with `stack`, given from bottom to top, before the first operation runs.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `stack` has more than [`MAX_STACK_SIZE`] values.
+    pub fn with_initial_stack(mut self, stack: Vec<U256>) -> Self {
+        assert!(
+            stack.len() <= MAX_STACK_SIZE,
+            "the initial stack can't have more than {MAX_STACK_SIZE} values"
+        );
+        self.initial_stack = stack;
+        self
+    }
+
+    /// Installs a tracer, called with the [`StepInfo`] of each executed operation.
+    pub fn with_tracer(mut self, tracer: impl FnMut(StepInfo) + 'static) -> Self {
+        self.tracer = Some(TracerHook(Box::new(tracer)));
+        self
+    }
+
+    /// Counts `intrinsic_gas` as already charged to the transaction, on top of the gas
+    /// available to the execution.
+    pub fn with_intrinsic_gas(mut self, intrinsic_gas: u64) -> Self {
+        self.intrinsic_gas = intrinsic_gas;
+        self
+    }
+
+    /// Halts the execution once it has run `step_limit` operations, regardless of the gas left.
+    /// Operations of nested calls count towards it too.
+    pub fn with_step_limit(mut self, step_limit: u64) -> Self {
+        self.step_limit = Some(step_limit);
+        self
+    }
+
+    /// Installs a handler, called with the opcode of each overflowing ADD, SUB or MUL
+    /// when the program is compiled with
+    /// [overflow instrumentation](crate::context::Compiler::with_overflow_instrumentation).
+    pub fn with_overflow_handler(mut self, handler: impl FnMut(u8) + 'static) -> Self {
+        self.overflow_handler = Some(OverflowHook(Box::new(handler)));
+        self
+    }
+
+    /// Sets where the debug syscalls print to, instead of stderr.
+    #[cfg(feature = "debug-syscalls")]
+    pub fn with_debug_writer(mut self, writer: impl std::io::Write + 'static) -> Self {
+        self.debug_writer = Some(DebugWriter(Box::new(writer)));
+        self
+    }
+
+    /// Returns the gas refunded to a transaction that succeeded with `gas_remaining` gas left,
+    /// capped to a fraction of the gas used.
+    /// Refunds only apply to whole transactions, so nested calls never refund anything.
+    fn gas_refunded(&self, gas_remaining: u64) -> u64 {
+        if self.depth > 0 {
+            return 0;
+        }
+        let max_refund =
+            self.gas_used(gas_remaining) / gas_cost::max_refund_quotient(self.env.cfg.spec);
+        (self.refund.max(0) as u64).min(max_refund)
+    }
+
+    /// Returns the gas used by an execution that ended with `gas_remaining` gas left,
+    /// including the intrinsic gas.
+    fn gas_used(&self, gas_remaining: u64) -> u64 {
+        (self.initial_gas + self.intrinsic_gas).saturating_sub(gas_remaining)
+    }
+
+    /// Returns the logs emitted so far, including those of nested calls that succeeded.
+    ///
+    /// Like [`ExecutionResult::emitted_logs`], this doesn't tell whether they'll be
+    /// committed, which depends on how the execution ends.
+    pub fn logs(&self) -> &[Log] {
+        &self.logs
+    }
+
+    /// Returns the memory range given to RETURN or REVERT.
+    ///
+    /// The generated code extends the memory before returning, but a range that doesn't
+    /// fit in the memory yields no data instead of panicking.
     pub fn return_values(&self) -> &[u8] {
         // TODO: maybe initialize as (0, 0) instead of None
         let (offset, size) = self.return_data.unwrap_or((0, 0));
-        &self.memory[offset..offset + size]
+        self.memory.get(offset..offset + size).unwrap_or_default()
     }
 
     pub fn get_result(&self) -> ExecutionResult {
         let gas_remaining = self.gas_remaining.unwrap_or(0);
-        let exit_status = self.exit_status.clone().unwrap_or(ExitStatusCode::Default);
+        let exit_status = self.exit_status.unwrap_or(ExitStatusCode::Default);
         match exit_status {
-            ExitStatusCode::Return | ExitStatusCode::Stop => ExecutionResult::Success {
+            ExitStatusCode::Return | ExitStatusCode::Stop => {
+                let gas_refunded = self.gas_refunded(gas_remaining);
+                let gas_remaining = gas_remaining + gas_refunded;
+                ExecutionResult::Success {
+                    return_data: self.return_values().to_vec(),
+                    gas_remaining,
+                    gas_used: self.gas_used(gas_remaining),
+                    gas_refunded,
+                    logs: self.logs.to_owned(),
+                    opcode_counts: self.opcode_counts.clone(),
+                }
+            }
+            ExitStatusCode::Revert => ExecutionResult::Revert {
                 return_data: self.return_values().to_vec(),
                 gas_remaining,
+                gas_used: self.gas_used(gas_remaining),
                 logs: self.logs.to_owned(),
+                failure: self.last_step.clone(),
+                opcode_counts: self.opcode_counts.clone(),
             },
-            ExitStatusCode::Revert => ExecutionResult::Revert {
-                return_data: self.return_values().to_vec(),
-                gas_remaining,
+            ExitStatusCode::Error | ExitStatusCode::Default => ExecutionResult::Halt {
+                gas_remaining: 0,
+                failure: self.last_step.clone(),
             },
-            ExitStatusCode::Error | ExitStatusCode::Default => ExecutionResult::Halt,
         }
     }
 }
@@ -158,7 +602,7 @@ impl SyscallContext {
 ///
 /// Note that each function is marked as `extern "C"`, which is necessary for the
 /// function to be callable from the generated code.
-impl SyscallContext {
+impl<'a> SyscallContext<'a> {
     pub extern "C" fn write_result(
         &mut self,
         offset: u32,
@@ -166,41 +610,122 @@ impl SyscallContext {
         remaining_gas: u64,
         execution_result: u8,
     ) {
+        // An unknown status means the generated code is wrong, which shouldn't go unnoticed
+        debug_assert!(
+            ExitStatusCode::try_from(execution_result).is_ok(),
+            "the generated code wrote an invalid exit status: {execution_result}"
+        );
         self.return_data = Some((offset as usize, bytes_len as usize));
         self.gas_remaining = Some(remaining_gas);
         self.exit_status = Some(ExitStatusCode::from_u8(execution_result));
     }
 
+    /// Returns whether [`Self::trace_step`] must be called before each operation,
+    /// which is needed for tracing, for capturing failures, for profiling and for
+    /// enforcing a step limit.
+    pub extern "C" fn is_tracing(&self) -> u8 {
+        (self.tracer.is_some()
+            || self.capture_failures
+            || self.opcode_counts.is_some()
+            || self.step_limit.is_some()) as u8
+    }
+
+    /// Reports the operation at `pc` to the tracer, if there's one, records it in case the
+    /// execution fails there, and counts its `opcode` while profiling.
+    /// The stack holds the values in `stack_baseptr..stack_ptr`.
+    /// Returns 1 if the operation would exceed the step limit, so the execution must halt,
+    /// or 0 otherwise.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn trace_step(
+        &mut self,
+        pc: u64,
+        opcode: u8,
+        gas_remaining: u64,
+        stack_baseptr: *const U256,
+        stack_ptr: *const U256,
+    ) -> u8 {
+        // SAFETY: the generated code passes the bounds of its stack allocation.
+        // The values are read unaligned, since the stack elements may be less aligned than U256.
+        let read_stack = || -> Vec<U256> {
+            unsafe {
+                let stack_size = stack_ptr.offset_from(stack_baseptr) as usize;
+                (0..stack_size)
+                    .map(|i| stack_baseptr.add(i).read_unaligned())
+                    .collect()
+            }
+        };
+        if self.capture_failures {
+            self.last_step = Some(FailureInfo {
+                pc: pc as usize,
+                stack: read_stack(),
+            });
+        }
+        if let Some(step_limit) = self.step_limit {
+            if self.steps >= step_limit {
+                return 1;
+            }
+            self.steps += 1;
+        }
+        if let Some(opcode_counts) = &mut self.opcode_counts {
+            *opcode_counts.entry(opcode).or_default() += 1;
+        }
+        let Some(TracerHook(tracer)) = self.tracer.as_mut() else {
+            return 0;
+        };
+        tracer(StepInfo {
+            pc: pc as usize,
+            opcode,
+            gas_remaining,
+            depth: self.depth,
+            stack: read_stack(),
+            memory: self.memory.clone(),
+        });
+        0
+    }
+
     pub extern "C" fn get_calldata_size(&self) -> u32 {
-        let size = self.env.tx.calldata.len();
-        print!("Calldata size: {}", size as u32);
         self.env.tx.calldata.len() as u32
     }
 
+    /// Extends the memory to `new_size` bytes, returning a pointer to it,
+    /// or a null pointer if it can't be allocated or exceeds the memory limit.
+    ///
+    /// The memory's length is always the logical EVM memory size, while its capacity
+    /// grows geometrically, so growing the memory in small steps doesn't reallocate each time.
     pub extern "C" fn extend_memory(&mut self, new_size: u32) -> *mut u8 {
         let new_size = new_size as usize;
         if new_size <= self.memory.len() {
             return self.memory.as_mut_ptr();
         }
-        match self.memory.try_reserve(new_size - self.memory.len()) {
+        let memory_limit = self.memory_limit.unwrap_or(DEFAULT_MEMORY_LIMIT);
+        if new_size > memory_limit {
+            return std::ptr::null_mut();
+        }
+        let new_capacity = new_size.max(self.memory.capacity() * 2).min(memory_limit);
+        match self
+            .memory
+            .try_reserve_exact(new_capacity - self.memory.len())
+        {
             Ok(()) => {
                 self.memory.resize(new_size, 0);
                 self.memory.as_mut_ptr()
             }
-            // TODO: use tracing here
-            Err(err) => {
-                eprintln!("Failed to reserve memory: {err}");
-                std::ptr::null_mut()
-            }
+            // Like exceeding the limit, this makes the generated code halt
+            Err(_) => std::ptr::null_mut(),
         }
     }
 
-    pub extern "C" fn append_log(&mut self, offset: u32, size: u32) {
-        self.create_log(offset, size, vec![]);
+    pub extern "C" fn append_log(&mut self, offset: u32, size: u32) -> u8 {
+        self.create_log(offset, size, vec![])
     }
     #[allow(improper_ctypes)]
-    pub extern "C" fn append_log_with_one_topic(&mut self, offset: u32, size: u32, topic: &U256) {
-        self.create_log(offset, size, vec![*topic]);
+    pub extern "C" fn append_log_with_one_topic(
+        &mut self,
+        offset: u32,
+        size: u32,
+        topic: &U256,
+    ) -> u8 {
+        self.create_log(offset, size, vec![*topic])
     }
 
     #[allow(improper_ctypes)]
@@ -210,8 +735,8 @@ impl SyscallContext {
         size: u32,
         topic1: &U256,
         topic2: &U256,
-    ) {
-        self.create_log(offset, size, vec![*topic1, *topic2]);
+    ) -> u8 {
+        self.create_log(offset, size, vec![*topic1, *topic2])
     }
 
     #[allow(improper_ctypes)]
@@ -222,8 +747,8 @@ impl SyscallContext {
         topic1: &U256,
         topic2: &U256,
         topic3: &U256,
-    ) {
-        self.create_log(offset, size, vec![*topic1, *topic2, *topic3]);
+    ) -> u8 {
+        self.create_log(offset, size, vec![*topic1, *topic2, *topic3])
     }
 
     #[allow(improper_ctypes)]
@@ -235,419 +760,2712 @@ impl SyscallContext {
         topic2: &U256,
         topic3: &U256,
         topic4: &U256,
-    ) {
-        self.create_log(offset, size, vec![*topic1, *topic2, *topic3, *topic4]);
+    ) -> u8 {
+        self.create_log(offset, size, vec![*topic1, *topic2, *topic3, *topic4])
     }
 
     /// Receives a memory offset and size, and a vector of topics.
     /// Creates a Log with topics and data equal to memory[offset..offset + size]
     /// and pushes it to the logs vector.
-    fn create_log(&mut self, offset: u32, size: u32, topics: Vec<U256>) {
-        let offset = offset as usize;
-        let size = size as usize;
-        let data: Vec<u8> = self.memory[offset..offset + size].into();
+    /// Returns 1 if the log was created, or 0 if the data is out of bounds.
+    fn create_log(&mut self, offset: u32, size: u32, topics: Vec<U256>) -> u8 {
+        let Some(data) = self.memory.get(byte_range(offset, size)) else {
+            return 0;
+        };
 
-        let log = Log { data, topics };
+        let log = Log {
+            data: data.to_vec(),
+            topics,
+        };
         self.logs.push(log);
+        1
     }
+    /// Returns a pointer to the calldata. It's dangling if the calldata is empty,
+    /// but the generated code never reads past the calldata size.
     pub extern "C" fn get_calldata_ptr(&mut self) -> *const u8 {
         self.env.tx.calldata.as_ptr()
     }
-}
 
-pub mod symbols {
-    pub const WRITE_RESULT: &str = "evm_mlir__write_result";
-    pub const EXTEND_MEMORY: &str = "evm_mlir__extend_memory";
-    pub const APPEND_LOG: &str = "evm_mlir__append_log";
-    pub const APPEND_LOG_ONE_TOPIC: &str = "evm_mlir__append_log_with_one_topic";
-    pub const APPEND_LOG_TWO_TOPICS: &str = "evm_mlir__append_log_with_two_topics";
-    pub const APPEND_LOG_THREE_TOPICS: &str = "evm_mlir__append_log_with_three_topics";
-    pub const APPEND_LOG_FOUR_TOPICS: &str = "evm_mlir__append_log_with_four_topics";
-    pub const GET_CALLDATA_PTR: &str = "evm_mlir__get_calldata_ptr";
-    pub const GET_CALLDATA_SIZE: &str = "evm_mlir__get_calldata_size";
-}
+    /// Computes the Keccak-256 hash of memory[offset..offset + size],
+    /// and writes it to `hash_ptr`.
+    /// Returns 1 if it was computed, or 0 if the range is out of bounds.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn keccak256_hasher(
+        &mut self,
+        offset: u32,
+        size: u32,
+        hash_ptr: &mut U256,
+    ) -> u8 {
+        let Some(data) = self.memory.get(byte_range(offset, size)) else {
+            return 0;
+        };
 
-/// Registers all the syscalls as symbols in the execution engine
-///
-/// This allows the generated code to call the syscalls by name.
-pub fn register_syscalls(engine: &ExecutionEngine) {
-    unsafe {
-        engine.register_symbol(
-            symbols::WRITE_RESULT,
-            SyscallContext::write_result as *const fn(*mut c_void, u32, u32, u64, u8) as *mut (),
-        );
-        engine.register_symbol(
-            symbols::EXTEND_MEMORY,
-            SyscallContext::extend_memory as *const fn(*mut c_void, u32) as *mut (),
-        );
-        engine.register_symbol(
-            symbols::APPEND_LOG,
-            SyscallContext::append_log as *const fn(*mut c_void, u32, u32) as *mut (),
-        );
-        engine.register_symbol(
-            symbols::APPEND_LOG_ONE_TOPIC,
-            SyscallContext::append_log_with_one_topic
-                as *const fn(*mut c_void, u32, u32, *const U256) as *mut (),
-        );
-        engine.register_symbol(
-            symbols::APPEND_LOG_TWO_TOPICS,
-            SyscallContext::append_log_with_two_topics
-                as *const fn(*mut c_void, u32, u32, *const U256, *const U256)
-                as *mut (),
-        );
-        engine.register_symbol(
-            symbols::APPEND_LOG_THREE_TOPICS,
-            SyscallContext::append_log_with_three_topics
-                as *const fn(*mut c_void, u32, u32, *const U256, *const U256, *const U256)
-                as *mut (),
-        );
-        engine.register_symbol(
-            symbols::APPEND_LOG_FOUR_TOPICS,
-            SyscallContext::append_log_with_four_topics
-                as *const fn(
-                    *mut c_void,
-                    u32,
-                    u32,
-                    *const U256,
-                    *const U256,
-                    *const U256,
-                    *const U256,
-                ) as *mut (),
-        );
-        engine.register_symbol(
-            symbols::GET_CALLDATA_PTR,
-            SyscallContext::get_calldata_ptr as *const fn(*mut c_void) as *mut (),
-        );
-        engine.register_symbol(
-            symbols::GET_CALLDATA_SIZE,
-            SyscallContext::get_calldata_size as *const fn(*mut c_void) as *mut (),
-        );
-    };
-}
+        *hash_ptr = keccak256(data);
+        1
+    }
 
-/// MLIR util for declaring syscalls
-pub(crate) mod mlir {
-    use melior::{
-        dialect::{func, llvm::r#type::pointer},
-        ir::{
-            attribute::{FlatSymbolRefAttribute, StringAttribute, TypeAttribute},
-            r#type::{FunctionType, IntegerType},
-            Block, Identifier, Location, Module as MeliorModule, Region, Value,
-        },
-        Context as MeliorContext,
-    };
+    /// Reads the storage slot `key` into `value`.
+    /// Returns the gas cost of the access, according to [EIP-2929](https://eips.ethereum.org/EIPS/eip-2929)
+    /// from Berlin on. If it's more than `gas_remaining`, the slot isn't read nor marked
+    /// as accessed, since the execution halts.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn storage_read(
+        &mut self,
+        key: &U256,
+        value: &mut U256,
+        gas_remaining: u64,
+    ) -> i64 {
+        let key = self.storage_key(key);
+        let is_cold = !self.accessed_storage_keys.contains(&key);
+        let gas = gas_cost::sload(self.env.cfg.spec, is_cold);
+        if gas as u64 > gas_remaining {
+            return gas;
+        }
 
-    use crate::errors::CodegenError;
+        *value = self.storage.get(&key).copied().unwrap_or_default();
+        self.accessed_storage_keys.insert(key);
+        gas
+    }
 
-    use super::symbols;
+    /// Writes `value` into the storage slot `key`.
+    /// Returns the gas cost of the write, according to
+    /// [EIP-2200](https://eips.ethereum.org/EIPS/eip-2200) and
+    /// [EIP-2929](https://eips.ethereum.org/EIPS/eip-2929), and updates the refund counter.
+    /// If the cost is more than `gas_remaining`, nothing is changed, since the execution halts.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn storage_write(
+        &mut self,
+        key: &U256,
+        value: &U256,
+        gas_remaining: u64,
+    ) -> i64 {
+        let key = self.storage_key(key);
+        let is_cold = !self.accessed_storage_keys.contains(&key);
+        let current = self.storage.get(&key).copied().unwrap_or_default();
+        let original = self.original_storage.get(&key).copied().unwrap_or(current);
 
-    pub(crate) fn declare_syscalls(context: &MeliorContext, module: &MeliorModule) {
-        let location = Location::unknown(context);
+        let spec = self.env.cfg.spec;
 
-        // Type declarations
-        let ptr_type = pointer(context, 0);
-        let uint32 = IntegerType::new(context, 32).into();
-        //let uint256 = IntegerType::new(context, 256).into();
-        let uint64 = IntegerType::new(context, 64).into();
-        let uint8 = IntegerType::new(context, 8).into();
+        let mut gas = if current == *value || original != current {
+            // no-op, or the slot was already written in this transaction
+            gas_cost::sload(spec, false)
+        } else if original == U256::default() {
+            gas_cost::SSTORE_SET
+        } else {
+            gas_cost::sstore_reset(spec)
+        };
+        if is_cold && spec.enabled(Spec::Berlin) {
+            gas += gas_cost::COLD_SLOAD;
+        }
+        if gas as u64 > gas_remaining {
+            return gas;
+        }
 
-        let attributes = &[(
-            Identifier::new(context, "sym_visibility"),
-            StringAttribute::new(context, "private").into(),
-        )];
+        self.accessed_storage_keys.insert(key.clone());
+        self.original_storage.entry(key.clone()).or_insert(current);
+        self.refund += gas_cost::sstore_refund(spec, &original, &current, value);
+        self.storage.insert(key, *value);
+        gas
+    }
 
-        // Syscall declarations
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::WRITE_RESULT),
-            TypeAttribute::new(
-                FunctionType::new(context, &[ptr_type, uint32, uint32, uint64, uint8], &[]).into(),
-            ),
-            Region::new(),
-            attributes,
-            location,
-        ));
+    /// Returns the key of the slot `key` of the executing contract in [`Self::storage`].
+    fn storage_key(&self, key: &U256) -> (Address, U256) {
+        (self.env.tx.to.clone(), *key)
+    }
 
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::GET_CALLDATA_SIZE),
-            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[uint32]).into()),
-            Region::new(),
-            attributes,
-            location,
-        ));
+    /// Reads a transient storage slot into `value`. Unset slots are zero.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn transient_read(&self, key: &U256, value: &mut U256) {
+        *value = self
+            .transient_storage
+            .get(&self.storage_key(key))
+            .copied()
+            .unwrap_or_default();
+    }
 
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::EXTEND_MEMORY),
-            TypeAttribute::new(FunctionType::new(context, &[ptr_type, uint32], &[ptr_type]).into()),
-            Region::new(),
+    /// Writes a transient storage slot.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn transient_write(&mut self, key: &U256, value: &U256) {
+        self.transient_storage.insert(self.storage_key(key), *value);
+    }
+
+    /// Writes the value sent with the transaction (`msg.value`) to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_callvalue(&self, value: &mut U256) {
+        *value = self.env.tx.value;
+    }
+
+    /// Writes the address of the caller (`msg.sender`) to `value`,
+    /// left-padded to 32 bytes.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_caller(&self, value: &mut U256) {
+        *value = U256::from(&self.env.tx.caller);
+    }
+
+    /// Writes the address of the transaction originator (`tx.origin`) to `value`,
+    /// left-padded to 32 bytes.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_origin(&self, value: &mut U256) {
+        *value = U256::from(&self.env.tx.origin);
+    }
+
+    /// Writes the address of the executing contract to `value`,
+    /// left-padded to 32 bytes.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_address(&self, value: &mut U256) {
+        *value = U256::from(&self.env.tx.to);
+    }
+
+    pub extern "C" fn get_returndata_size(&self) -> u32 {
+        self.call_return_data.len() as u32
+    }
+
+    /// Copies `call_return_data[offset..offset + size]` into `memory[dest_offset..dest_offset + size]`.
+    /// The bounds are checked by the generated code beforehand, but if either range is
+    /// out of bounds anyway nothing is copied. Returns 1 if the data was copied, or 0 otherwise.
+    pub extern "C" fn copy_returndata(&mut self, dest_offset: u32, offset: u32, size: u32) -> u8 {
+        let source = self.call_return_data.get(byte_range(offset, size));
+        let destination = self.memory.get_mut(byte_range(dest_offset, size));
+        let (Some(source), Some(destination)) = (source, destination) else {
+            return 0;
+        };
+        destination.copy_from_slice(source);
+
+        1
+    }
+
+    /// Returns the gas cost of a CALL to `address` transferring `value`, besides its static
+    /// cost and the value transfer cost, and marks `address` as accessed.
+    ///
+    /// The static cost covers a warm access, so this charges the rest of the access cost
+    /// ([EIP-2929](https://eips.ethereum.org/EIPS/eip-2929)), plus the cost of creating
+    /// `address` if it's an empty account receiving value.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_call_gas(&mut self, address: &U256, value: &U256) -> i64 {
+        let address = Address::from(address);
+        let is_new_account = *value != U256::default() && self.is_empty_account(&address);
+
+        let mut gas = self.account_access_gas(address) - gas_cost::CALL;
+        if is_new_account {
+            gas += gas_cost::CALL_NEW_ACCOUNT;
+        }
+        gas
+    }
+
+    /// Executes a message call to the contract at `address`, with `gas` available to it
+    /// (plus the stipend, when transferring value).
+    /// The calldata is `memory[args_offset..args_offset + args_size]`, and the returned
+    /// data is copied to `memory[ret_offset..ret_offset + ret_size]`.
+    ///
+    /// Writes the callee's unused gas to `gas_remaining`, and returns 1 if the call succeeded
+    /// or 0 otherwise. The storage, logs and value transfer of a call are only kept if it succeeds.
+    /// The memory is extended by the generated code beforehand; if either range is out
+    /// of bounds anyway, the call fails consuming all its gas.
+    #[allow(improper_ctypes)]
+    #[allow(clippy::too_many_arguments)]
+    pub extern "C" fn call(
+        &mut self,
+        gas: u64,
+        address: &U256,
+        value: &U256,
+        args_offset: u32,
+        args_size: u32,
+        ret_offset: u32,
+        ret_size: u32,
+        gas_remaining: &mut u64,
+    ) -> u8 {
+        let args_range = byte_range(args_offset, args_size);
+        let ret_range = byte_range(ret_offset, ret_size);
+        if self.memory.get(args_range.clone()).is_none()
+            || self.memory.get(ret_range.clone()).is_none()
+        {
+            *gas_remaining = 0;
+            return 0;
+        }
+
+        let stipend = if *value == U256::default() {
+            0
+        } else {
+            gas_cost::CALL_STIPEND
+        };
+        let gas = gas + stipend;
+        self.call_return_data.clear();
+
+        let caller = self.env.tx.to.clone();
+        if self.depth >= MAX_CALL_DEPTH || !self.has_balance(&caller, value) {
+            // The call fails without consuming its gas
+            *gas_remaining = gas;
+            return 0;
+        }
+
+        let address = Address::from(address);
+        let code = self.get_code(&address);
+
+        let mut env = self.env.clone();
+        env.tx.caller = caller.clone();
+        env.tx.to = address.clone();
+        env.tx.value = *value;
+        env.tx.calldata = Cow::Owned(self.memory[args_range].to_vec());
+        env.tx.gas_limit = gas;
+
+        let mut callee = self.nested_context(env);
+        callee.sub_balance(&caller, value);
+        callee.add_balance(address, *value);
+
+        if code.is_empty() {
+            self.tracer = callee.tracer.take();
+            self.opcode_counts = callee.opcode_counts.take();
+            self.commit_nested_context(callee);
+            *gas_remaining = gas;
+            return 1;
+        }
+
+        let result = callee.run(&code, gas);
+        self.tracer = callee.tracer.take();
+        self.opcode_counts = callee.opcode_counts.take();
+        self.steps = callee.steps;
+
+        let Some(result) = result else {
+            // The callee's bytecode is invalid, which consumes all its gas
+            *gas_remaining = 0;
+            return 0;
+        };
+
+        let success = match result {
+            ExecutionResult::Success {
+                return_data,
+                gas_remaining: callee_gas_remaining,
+                ..
+            } => {
+                self.commit_nested_context(callee);
+                self.call_return_data = return_data;
+                *gas_remaining = callee_gas_remaining;
+                1
+            }
+            // A revert gives back the unused gas, and its return data is still available
+            ExecutionResult::Revert {
+                return_data,
+                gas_remaining: callee_gas_remaining,
+                ..
+            } => {
+                self.call_return_data = return_data;
+                *gas_remaining = callee_gas_remaining;
+                0
+            }
+            // An exceptional halt consumes all the gas given to the callee,
+            // and leaves the return data empty
+            ExecutionResult::Halt { .. } => {
+                *gas_remaining = 0;
+                0
+            }
+        };
+
+        let copy_size = ret_range.len().min(self.call_return_data.len());
+        self.memory[ret_range][..copy_size].copy_from_slice(&self.call_return_data[..copy_size]);
+
+        success
+    }
+
+    /// Creates a contract with the init code `memory[offset..offset + size]`, sending it `value`,
+    /// at the address derived from the executing contract's address and nonce.
+    ///
+    /// Writes the unused gas to `gas_remaining`, and the new contract's address to `address`,
+    /// or 0 if the creation failed. If the init code is out of bounds, it fails consuming
+    /// all its gas.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn create(
+        &mut self,
+        offset: u32,
+        size: u32,
+        value: &U256,
+        gas: u64,
+        gas_remaining: &mut u64,
+        address: &mut U256,
+    ) {
+        let Some(init_code) = self.memory.get(byte_range(offset, size)) else {
+            *gas_remaining = 0;
+            *address = U256::default();
+            return;
+        };
+        let init_code = init_code.to_vec();
+        let created = self.create_contract(init_code, value, None, gas, gas_remaining);
+        *address = created.as_ref().map(U256::from).unwrap_or_default();
+    }
+
+    /// Like [`Self::create`], but the address is derived from the executing contract's address,
+    /// `salt` and the hash of the init code ([EIP-1014](https://eips.ethereum.org/EIPS/eip-1014)).
+    #[allow(improper_ctypes)]
+    #[allow(clippy::too_many_arguments)]
+    pub extern "C" fn create2(
+        &mut self,
+        offset: u32,
+        size: u32,
+        value: &U256,
+        salt: &U256,
+        gas: u64,
+        gas_remaining: &mut u64,
+        address: &mut U256,
+    ) {
+        let Some(init_code) = self.memory.get(byte_range(offset, size)) else {
+            *gas_remaining = 0;
+            *address = U256::default();
+            return;
+        };
+        let init_code = init_code.to_vec();
+        let created = self.create_contract(init_code, value, Some(salt), gas, gas_remaining);
+        *address = created.as_ref().map(U256::from).unwrap_or_default();
+    }
+
+    /// Runs `init_code` with `gas` in a nested context, and deploys the code it returns.
+    /// Returns the address of the new contract, or `None` if the creation failed.
+    fn create_contract(
+        &mut self,
+        init_code: Vec<u8>,
+        value: &U256,
+        salt: Option<&U256>,
+        gas: u64,
+        gas_remaining: &mut u64,
+    ) -> Option<Address> {
+        self.call_return_data.clear();
+        let sender = self.env.tx.to.clone();
+
+        if self.depth >= MAX_CALL_DEPTH || !self.has_balance(&sender, value) {
+            // The creation fails without consuming its gas
+            *gas_remaining = gas;
+            return None;
+        }
+
+        let nonce = self.nonces.entry(sender.clone()).or_default();
+        let address = match salt {
+            None => create_address(&sender, *nonce),
+            Some(salt) => create2_address(&sender, salt, &init_code),
+        };
+        *nonce += 1;
+        self.mark_address_as_accessed(address.clone());
+
+        let has_nonce = self.nonces.get(&address).is_some_and(|nonce| *nonce != 0);
+        if has_nonce || !self.get_code(&address).is_empty() {
+            // Creating a contract on an existing one consumes all the gas
+            *gas_remaining = 0;
+            return None;
+        }
+
+        let mut env = self.env.clone();
+        env.tx.caller = sender.clone();
+        env.tx.to = address.clone();
+        env.tx.value = *value;
+        env.tx.calldata = Cow::Borrowed(&[]);
+        env.tx.gas_limit = gas;
+
+        let mut callee = self.nested_context(env);
+        callee.created_accounts.insert(address.clone());
+        callee.nonces.insert(address.clone(), 1);
+        callee.sub_balance(&sender, value);
+        callee.add_balance(address.clone(), *value);
+
+        let result = if init_code.is_empty() {
+            Some(ExecutionResult::Success {
+                return_data: vec![],
+                gas_remaining: gas,
+                gas_used: 0,
+                gas_refunded: 0,
+                logs: vec![],
+                opcode_counts: None,
+            })
+        } else {
+            callee.run(&init_code, gas)
+        };
+        self.tracer = callee.tracer.take();
+        self.opcode_counts = callee.opcode_counts.take();
+        self.steps = callee.steps;
+
+        let (code, callee_gas_remaining) = match result {
+            Some(ExecutionResult::Success {
+                return_data,
+                gas_remaining,
+                ..
+            }) => (return_data, gas_remaining),
+            // A revert gives back the unused gas, and its return data is still available
+            Some(ExecutionResult::Revert {
+                return_data,
+                gas_remaining: callee_gas_remaining,
+                ..
+            }) => {
+                self.call_return_data = return_data;
+                *gas_remaining = callee_gas_remaining;
+                return None;
+            }
+            // Exceptional halts and invalid init code consume all the gas
+            Some(ExecutionResult::Halt { .. }) | None => {
+                *gas_remaining = 0;
+                return None;
+            }
+        };
+
+        // The deployed code is paid per byte, and some code can't be deployed at all
+        let deposit_cost = gas_cost::CODE_DEPOSIT * code.len() as u64;
+        let is_code_rejected = code.len() > MAX_CODE_SIZE
+            || (self.env.cfg.spec.enabled(Spec::London) && code.first() == Some(&0xEF));
+        if is_code_rejected || deposit_cost > callee_gas_remaining {
+            *gas_remaining = 0;
+            return None;
+        }
+
+        self.commit_nested_context(callee);
+        if !code.is_empty() {
+            self.deployed_code.insert(address.clone(), code);
+        }
+        *gas_remaining = callee_gas_remaining - deposit_cost;
+        Some(address)
+    }
+
+    /// Returns a context for a nested call or creation running with `env`.
+    /// It gets a copy of the state, to be committed back with [`Self::commit_nested_context`]
+    /// if it succeeds. The tracer is lent to it, and must be taken back once it finishes,
+    /// along with the count of executed steps.
+    fn nested_context(&mut self, env: Env<'a>) -> SyscallContext<'a> {
+        SyscallContext {
+            env,
+            storage: self.storage.clone(),
+            transient_storage: self.transient_storage.clone(),
+            original_storage: self.original_storage.clone(),
+            accessed_storage_keys: self.accessed_storage_keys.clone(),
+            balances: self.balances.clone(),
+            accessed_addresses: self.accessed_addresses.clone(),
+            created_accounts: self.created_accounts.clone(),
+            destroyed_accounts: self.destroyed_accounts.clone(),
+            nonces: self.nonces.clone(),
+            deployed_code: self.deployed_code.clone(),
+            code_provider: self.code_provider.clone(),
+            memory_limit: self.memory_limit,
+            depth: self.depth + 1,
+            is_static: self.is_static,
+            tracer: self.tracer.take(),
+            // The callee keeps counting where the caller left off
+            opcode_counts: self.opcode_counts.take(),
+            step_limit: self.step_limit,
+            steps: self.steps,
+            ..Self::default()
+        }
+    }
+
+    /// Keeps the state changes, refund and logs of a nested context that succeeded.
+    fn commit_nested_context(&mut self, callee: SyscallContext<'a>) {
+        self.storage = callee.storage;
+        self.transient_storage = callee.transient_storage;
+        self.original_storage = callee.original_storage;
+        self.accessed_storage_keys = callee.accessed_storage_keys;
+        self.balances = callee.balances;
+        self.accessed_addresses = callee.accessed_addresses;
+        self.created_accounts = callee.created_accounts;
+        self.destroyed_accounts = callee.destroyed_accounts;
+        self.nonces = callee.nonces;
+        self.deployed_code = callee.deployed_code;
+        self.refund += callee.refund;
+        self.logs.extend(callee.logs);
+    }
+
+    /// Compiles and runs `bytecode` in this context.
+    /// Returns `None` if the bytecode couldn't be compiled.
+    fn run(&mut self, bytecode: &[u8], initial_gas: u64) -> Option<ExecutionResult> {
+        let program = Program::from_bytecode(bytecode).ok()?;
+        let compiled_program = CompiledProgram::compile(&program, self.env.cfg.spec).ok()?;
+        Some(compiled_program.run(self, initial_gas))
+    }
+
+    pub extern "C" fn is_static(&self) -> u8 {
+        self.is_static as u8
+    }
+
+    /// Allocates the memory of a stack that fits `size` values, returning a pointer to it.
+    /// It's kept in the context until the next execution allocates its own.
+    pub extern "C" fn allocate_stack(&mut self, size: u32) -> *mut U256 {
+        self.stack = vec![U256::default(); size as usize];
+        self.stack.as_mut_ptr()
+    }
+
+    /// Writes the initial stack values to `stack`, returning how many there are.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn copy_initial_stack(&self, stack: *mut U256) -> u32 {
+        for (i, value) in self.initial_stack.iter().enumerate() {
+            // SAFETY: the generated code passes its stack allocation, which fits
+            // MAX_STACK_SIZE values. The stack elements may be less aligned than U256.
+            unsafe { stack.add(i).write_unaligned(*value) };
+        }
+        self.initial_stack.len() as u32
+    }
+
+    /// Reports that the operation with `opcode` overflowed. The result still wraps around.
+    pub extern "C" fn on_overflow(&mut self, opcode: u8) {
+        if let Some(OverflowHook(handler)) = &mut self.overflow_handler {
+            handler(opcode);
+        }
+    }
+
+    /// Prints the value at the top of the stack as a 32-byte hex word, for debugging.
+    #[cfg(feature = "debug-syscalls")]
+    #[allow(improper_ctypes)]
+    pub extern "C" fn debug_print_stack_top(&mut self, value: &U256) {
+        let hex: String = value
+            .to_be_bytes()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let line = format!("stack top: 0x{hex}\n");
+        // Failing to print shouldn't abort the execution being debugged
+        let _ = match &mut self.debug_writer {
+            Some(DebugWriter(writer)) => writer.write_all(line.as_bytes()),
+            None => std::io::Write::write_all(&mut std::io::stderr(), line.as_bytes()),
+        };
+    }
+
+    /// Writes the transaction's gas price to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_gasprice(&self, value: &mut U256) {
+        *value = self.env.tx.gas_price;
+    }
+
+    /// Replaces the block number at `number` with the block's hash,
+    /// or 0 if the block is not one of the last 256 ones.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_block_hash(&self, number: &mut U256) {
+        *number = if number.hi == 0 && number.lo <= u64::MAX as u128 {
+            self.env.block.block_hash(number.lo as u64)
+        } else {
+            U256::default()
+        };
+    }
+
+    /// Writes the block's beneficiary address to `value`,
+    /// left-padded to 32 bytes.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_coinbase(&self, value: &mut U256) {
+        *value = U256::from(&self.env.block.coinbase);
+    }
+
+    /// Writes the block's timestamp to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_timestamp(&self, value: &mut U256) {
+        *value = U256::from(self.env.block.timestamp);
+    }
+
+    /// Writes the block's number to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_block_number(&self, value: &mut U256) {
+        *value = U256::from(self.env.block.number);
+    }
+
+    /// Writes the block's gas limit to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_gaslimit(&self, value: &mut U256) {
+        *value = U256::from(self.env.block.gas_limit);
+    }
+
+    /// Writes the chain ID to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_chainid(&self, value: &mut U256) {
+        *value = U256::from(self.env.cfg.chain_id);
+    }
+
+    /// Writes the block's randomness (`block.prevrandao`) to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_prevrandao(&self, value: &mut U256) {
+        *value = self.env.block.prevrandao;
+    }
+
+    /// Writes the block's base fee to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_basefee(&self, value: &mut U256) {
+        *value = self.env.block.basefee;
+    }
+
+    /// Writes the balance of the account at `address` to `balance`.
+    /// Returns the gas cost of the access, according to [EIP-2929](https://eips.ethereum.org/EIPS/eip-2929).
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_balance(&mut self, address: &U256, balance: &mut U256) -> i64 {
+        let address = Address::from(address);
+        *balance = self.balances.get(&address).copied().unwrap_or_default();
+
+        self.account_access_gas(address)
+    }
+
+    /// Writes the size of the code of the account at `address` to `size`.
+    /// Returns the gas cost of the access, according to [EIP-2929](https://eips.ethereum.org/EIPS/eip-2929).
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_ext_code_size(&mut self, address: &U256, size: &mut U256) -> i64 {
+        let address = Address::from(address);
+        *size = U256::from(self.get_code(&address).len() as u64);
+
+        self.account_access_gas(address)
+    }
+
+    /// Writes the Keccak-256 hash of the code of the account at `address` to `hash`,
+    /// or 0 if the account is empty ([EIP-1052](https://eips.ethereum.org/EIPS/eip-1052)).
+    /// Returns the gas cost of the access, according to [EIP-2929](https://eips.ethereum.org/EIPS/eip-2929).
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_ext_code_hash(&mut self, address: &U256, hash: &mut U256) -> i64 {
+        let address = Address::from(address);
+        *hash = if self.is_empty_account(&address) {
+            U256::default()
+        } else {
+            keccak256(&self.get_code(&address))
+        };
+
+        self.account_access_gas(address)
+    }
+
+    /// Copies `code[code_offset..code_offset + size]` of the account at `address` into
+    /// `memory[dest_offset..dest_offset + size]`. The bytes past the end of the code are
+    /// filled with zeros. The memory is extended by the generated code beforehand.
+    /// Returns the gas cost of the access, according to [EIP-2929](https://eips.ethereum.org/EIPS/eip-2929),
+    /// or -1 if the destination is out of bounds anyway, in which case nothing is copied.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn copy_ext_code(
+        &mut self,
+        address: &U256,
+        code_offset: &U256,
+        dest_offset: u32,
+        size: u32,
+    ) -> i64 {
+        let address = Address::from(address);
+        let code = self.get_code(&address);
+
+        let Some(destination) = self.memory.get_mut(byte_range(dest_offset, size)) else {
+            return -1;
+        };
+        // an offset that doesn't fit in 64 bits is past the end of any code
+        let code_offset = if code_offset.hi == 0 && code_offset.lo <= u64::MAX as u128 {
+            (code_offset.lo as usize).min(code.len())
+        } else {
+            code.len()
+        };
+        let copy_size = destination.len().min(code.len() - code_offset);
+        destination[..copy_size].copy_from_slice(&code[code_offset..code_offset + copy_size]);
+        destination[copy_size..].fill(0);
+
+        self.account_access_gas(address)
+    }
+
+    /// Marks `address` as accessed, returning the gas cost of the access.
+    fn account_access_gas(&mut self, address: Address) -> i64 {
+        let is_cold = self.mark_address_as_accessed(address);
+        gas_cost::account_access(self.env.cfg.spec, is_cold)
+    }
+
+    /// Marks `address` as accessed, returning true if it was cold.
+    fn mark_address_as_accessed(&mut self, address: Address) -> bool {
+        let is_always_warm = address == self.env.tx.caller || address == self.env.tx.to;
+        !is_always_warm && self.accessed_addresses.insert(address)
+    }
+
+    /// Returns the gas cost of a SELFDESTRUCT sending the balance to `beneficiary`,
+    /// besides the base cost, and marks `beneficiary` as accessed.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_selfdestruct_gas(&mut self, beneficiary: &U256) -> i64 {
+        let beneficiary = Address::from(beneficiary);
+        let is_cold = self.mark_address_as_accessed(beneficiary);
+
+        let mut gas = 0;
+        if is_cold && self.env.cfg.spec.enabled(Spec::Berlin) {
+            gas += gas_cost::COLD_ACCOUNT_ACCESS;
+        }
+        let has_balance = self
+            .balances
+            .get(&self.env.tx.to)
+            .is_some_and(|balance| *balance != U256::default());
+        if has_balance && self.is_empty_account(&beneficiary) {
+            gas += gas_cost::SELFDESTRUCT_NEW_ACCOUNT;
+        }
+        gas
+    }
+
+    /// Sends the executing contract's balance to `beneficiary`, and destroys the contract.
+    ///
+    /// From Cancun, contracts are only destroyed if they were created in the same transaction
+    /// ([EIP-6780](https://eips.ethereum.org/EIPS/eip-6780)). Otherwise, only the balance is sent.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn selfdestruct(&mut self, beneficiary: &U256) {
+        let beneficiary = Address::from(beneficiary);
+        let address = self.env.tx.to.clone();
+        let spec = self.env.cfg.spec;
+        let is_destroyed = !spec.enabled(Spec::Cancun) || self.created_accounts.contains(&address);
+
+        let balance = self.balances.remove(&address).unwrap_or_default();
+        // The balance sent to the contract itself is burnt if the contract is destroyed
+        if beneficiary != address || !is_destroyed {
+            self.add_balance(beneficiary, balance);
+        }
+
+        if is_destroyed && self.destroyed_accounts.insert(address) && !spec.enabled(Spec::London) {
+            self.refund += gas_cost::SELFDESTRUCT_REFUND;
+        }
+    }
+
+    /// Returns true if the account has no balance, no nonce and no code
+    /// ([EIP-161](https://eips.ethereum.org/EIPS/eip-161)).
+    fn is_empty_account(&self, address: &Address) -> bool {
+        let has_balance = self
+            .balances
+            .get(address)
+            .is_some_and(|balance| *balance != U256::default());
+        let has_nonce = self.nonces.get(address).is_some_and(|nonce| *nonce != 0);
+        !has_balance && !has_nonce && self.get_code(address).is_empty()
+    }
+
+    /// Returns the code of the account at `address`, which is either deployed
+    /// in the current transaction or given by the code provider.
+    fn get_code(&self, address: &Address) -> Vec<u8> {
+        if let Some(code) = self.deployed_code.get(address) {
+            return code.clone();
+        }
+        self.code_provider
+            .as_ref()
+            .and_then(|code_provider| code_provider.get_code(address))
+            .unwrap_or_default()
+    }
+
+    /// Returns true if the account at `address` has at least `amount` wei.
+    fn has_balance(&self, address: &Address, amount: &U256) -> bool {
+        let balance = self.balances.get(address).copied().unwrap_or_default();
+        (balance.hi, balance.lo) >= (amount.hi, amount.lo)
+    }
+
+    /// Takes `amount` from the balance of `address`, which must have at least that much.
+    fn sub_balance(&mut self, address: &Address, amount: &U256) {
+        debug_assert!(self.has_balance(address, amount));
+        if *amount == U256::default() {
+            return;
+        }
+        let balance = self.balances.entry(address.clone()).or_default();
+        let (lo, borrow) = balance.lo.overflowing_sub(amount.lo);
+        balance.lo = lo;
+        balance.hi = balance.hi - amount.hi - borrow as u128;
+    }
+
+    fn add_balance(&mut self, address: Address, amount: U256) {
+        if amount == U256::default() {
+            return;
+        }
+        let balance = self.balances.entry(address).or_default();
+        let (lo, carry) = balance.lo.overflowing_add(amount.lo);
+        balance.lo = lo;
+        balance.hi = balance
+            .hi
+            .wrapping_add(amount.hi)
+            .wrapping_add(carry as u128);
+    }
+
+    /// Writes the balance of the executing contract to `value`.
+    #[allow(improper_ctypes)]
+    pub extern "C" fn get_self_balance(&self, value: &mut U256) {
+        *value = self
+            .balances
+            .get(&self.env.tx.to)
+            .copied()
+            .unwrap_or_default();
+    }
+}
+
+/// Returns the address of the contract created by `sender` with the given `nonce`,
+/// which is the last 20 bytes of `keccak256(rlp([sender, nonce]))`.
+fn create_address(sender: &Address, nonce: u64) -> Address {
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+
+    let mut encoded_nonce = vec![];
+    match nonce_bytes {
+        // single bytes below 0x80 are their own encoding
+        [byte] if *byte < 0x80 => encoded_nonce.push(*byte),
+        _ => {
+            encoded_nonce.push(0x80 + nonce_bytes.len() as u8);
+            encoded_nonce.extend_from_slice(nonce_bytes);
+        }
+    }
+
+    let mut hasher = Keccak256::new();
+    hasher.update([0xc0 + 21 + encoded_nonce.len() as u8, 0x80 + 20]);
+    hasher.update(sender.0);
+    hasher.update(encoded_nonce);
+    address_from_hash(&hasher.finalize())
+}
+
+/// Returns the address of the contract created by `sender` with CREATE2, which is the
+/// last 20 bytes of `keccak256(0xff ++ sender ++ salt ++ keccak256(init_code))`.
+fn create2_address(sender: &Address, salt: &U256, init_code: &[u8]) -> Address {
+    let mut hasher = Keccak256::new();
+    hasher.update([0xff]);
+    hasher.update(sender.0);
+    hasher.update(salt.to_be_bytes());
+    hasher.update(Keccak256::digest(init_code));
+    address_from_hash(&hasher.finalize())
+}
+
+fn address_from_hash(hash: &[u8]) -> Address {
+    Address(hash[12..].try_into().unwrap())
+}
+
+/// Returns the range of the `size` bytes starting at `offset`.
+/// It can't overflow, since both bounds fit in 32 bits.
+fn byte_range(offset: u32, size: u32) -> Range<usize> {
+    let offset = offset as usize;
+    offset..offset + size as usize
+}
+
+/// Returns the Keccak-256 hash of `data`, as a big-endian number.
+fn keccak256(data: &[u8]) -> U256 {
+    U256::from_be_bytes(Keccak256::digest(data).as_slice().try_into().unwrap())
+}
+
+pub mod symbols {
+    pub const WRITE_RESULT: &str = "evm_mlir__write_result";
+    pub const IS_TRACING: &str = "evm_mlir__is_tracing";
+    pub const TRACE_STEP: &str = "evm_mlir__trace_step";
+    pub const EXTEND_MEMORY: &str = "evm_mlir__extend_memory";
+    pub const APPEND_LOG: &str = "evm_mlir__append_log";
+    pub const APPEND_LOG_ONE_TOPIC: &str = "evm_mlir__append_log_with_one_topic";
+    pub const APPEND_LOG_TWO_TOPICS: &str = "evm_mlir__append_log_with_two_topics";
+    pub const APPEND_LOG_THREE_TOPICS: &str = "evm_mlir__append_log_with_three_topics";
+    pub const APPEND_LOG_FOUR_TOPICS: &str = "evm_mlir__append_log_with_four_topics";
+    pub const GET_CALLDATA_PTR: &str = "evm_mlir__get_calldata_ptr";
+    pub const GET_CALLDATA_SIZE: &str = "evm_mlir__get_calldata_size";
+    pub const KECCAK256_HASHER: &str = "evm_mlir__keccak256_hasher";
+    pub const STORAGE_READ: &str = "evm_mlir__storage_read";
+    pub const STORAGE_WRITE: &str = "evm_mlir__storage_write";
+    pub const TRANSIENT_READ: &str = "evm_mlir__transient_read";
+    pub const TRANSIENT_WRITE: &str = "evm_mlir__transient_write";
+    pub const GET_RETURNDATA_SIZE: &str = "evm_mlir__get_returndata_size";
+    pub const COPY_RETURNDATA: &str = "evm_mlir__copy_returndata";
+    pub const GET_CALL_GAS: &str = "evm_mlir__get_call_gas";
+    pub const CALL: &str = "evm_mlir__call";
+    pub const GET_BALANCE: &str = "evm_mlir__get_balance";
+    pub const IS_STATIC: &str = "evm_mlir__is_static";
+    pub const ALLOCATE_STACK: &str = "evm_mlir__allocate_stack";
+    pub const COPY_INITIAL_STACK: &str = "evm_mlir__copy_initial_stack";
+    pub const ON_OVERFLOW: &str = "evm_mlir__on_overflow";
+    pub const GET_CALLVALUE: &str = "evm_mlir__get_callvalue";
+    pub const GET_CALLER: &str = "evm_mlir__get_caller";
+    pub const GET_ORIGIN: &str = "evm_mlir__get_origin";
+    pub const GET_ADDRESS: &str = "evm_mlir__get_address";
+    pub const GET_GASPRICE: &str = "evm_mlir__get_gasprice";
+    pub const GET_BLOCK_HASH: &str = "evm_mlir__get_block_hash";
+    pub const GET_COINBASE: &str = "evm_mlir__get_coinbase";
+    pub const GET_TIMESTAMP: &str = "evm_mlir__get_timestamp";
+    pub const GET_BLOCK_NUMBER: &str = "evm_mlir__get_block_number";
+    pub const GET_GASLIMIT: &str = "evm_mlir__get_gaslimit";
+    pub const GET_CHAINID: &str = "evm_mlir__get_chainid";
+    pub const GET_PREVRANDAO: &str = "evm_mlir__get_prevrandao";
+    pub const GET_BASEFEE: &str = "evm_mlir__get_basefee";
+    pub const GET_SELF_BALANCE: &str = "evm_mlir__get_self_balance";
+    pub const GET_SELFDESTRUCT_GAS: &str = "evm_mlir__get_selfdestruct_gas";
+    pub const SELFDESTRUCT: &str = "evm_mlir__selfdestruct";
+    pub const GET_EXT_CODE_SIZE: &str = "evm_mlir__get_ext_code_size";
+    pub const GET_EXT_CODE_HASH: &str = "evm_mlir__get_ext_code_hash";
+    pub const COPY_EXT_CODE: &str = "evm_mlir__copy_ext_code";
+    pub const CREATE: &str = "evm_mlir__create";
+    pub const CREATE2: &str = "evm_mlir__create2";
+    #[cfg(feature = "debug-syscalls")]
+    pub const DEBUG_PRINT_STACK_TOP: &str = "evm_mlir__debug_print_stack_top";
+}
+
+/// Returns the name and address of each syscall.
+///
+/// This allows embedders to register the syscalls in their own execution engine,
+/// or to check which ones are available.
+pub fn syscall_table() -> Vec<(&'static str, *mut ())> {
+    #[allow(unused_mut)]
+    let mut table = vec![
+        (
+            symbols::WRITE_RESULT,
+            SyscallContext::write_result as *const fn(*mut c_void, u32, u32, u64, u8) as *mut (),
+        ),
+        (
+            symbols::IS_TRACING,
+            SyscallContext::is_tracing as *const fn(*mut c_void) -> u8 as *mut (),
+        ),
+        (
+            symbols::TRACE_STEP,
+            SyscallContext::trace_step
+                as *const fn(*mut c_void, u64, u8, u64, *const U256, *const U256) -> u8
+                as *mut (),
+        ),
+        (
+            symbols::EXTEND_MEMORY,
+            SyscallContext::extend_memory as *const fn(*mut c_void, u32) as *mut (),
+        ),
+        (
+            symbols::APPEND_LOG,
+            SyscallContext::append_log as *const fn(*mut c_void, u32, u32) -> u8 as *mut (),
+        ),
+        (
+            symbols::APPEND_LOG_ONE_TOPIC,
+            SyscallContext::append_log_with_one_topic
+                as *const fn(*mut c_void, u32, u32, *const U256) -> u8 as *mut (),
+        ),
+        (
+            symbols::APPEND_LOG_TWO_TOPICS,
+            SyscallContext::append_log_with_two_topics
+                as *const fn(*mut c_void, u32, u32, *const U256, *const U256) -> u8
+                as *mut (),
+        ),
+        (
+            symbols::APPEND_LOG_THREE_TOPICS,
+            SyscallContext::append_log_with_three_topics
+                as *const fn(*mut c_void, u32, u32, *const U256, *const U256, *const U256) -> u8
+                as *mut (),
+        ),
+        (
+            symbols::APPEND_LOG_FOUR_TOPICS,
+            SyscallContext::append_log_with_four_topics
+                as *const fn(
+                    *mut c_void,
+                    u32,
+                    u32,
+                    *const U256,
+                    *const U256,
+                    *const U256,
+                    *const U256,
+                ) -> u8 as *mut (),
+        ),
+        (
+            symbols::GET_CALLDATA_PTR,
+            SyscallContext::get_calldata_ptr as *const fn(*mut c_void) as *mut (),
+        ),
+        (
+            symbols::GET_CALLDATA_SIZE,
+            SyscallContext::get_calldata_size as *const fn(*mut c_void) as *mut (),
+        ),
+        (
+            symbols::KECCAK256_HASHER,
+            SyscallContext::keccak256_hasher as *const fn(*mut c_void, u32, u32, *mut U256) -> u8
+                as *mut (),
+        ),
+        (
+            symbols::STORAGE_READ,
+            SyscallContext::storage_read
+                as *const fn(*mut c_void, *const U256, *mut U256, u64) -> i64
+                as *mut (),
+        ),
+        (
+            symbols::STORAGE_WRITE,
+            SyscallContext::storage_write
+                as *const fn(*mut c_void, *const U256, *const U256, u64) -> i64
+                as *mut (),
+        ),
+        (
+            symbols::TRANSIENT_READ,
+            SyscallContext::transient_read as *const fn(*mut c_void, *const U256, *mut U256)
+                as *mut (),
+        ),
+        (
+            symbols::TRANSIENT_WRITE,
+            SyscallContext::transient_write as *const fn(*mut c_void, *const U256, *const U256)
+                as *mut (),
+        ),
+        (
+            symbols::GET_CALLVALUE,
+            SyscallContext::get_callvalue as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_CALLER,
+            SyscallContext::get_caller as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_ORIGIN,
+            SyscallContext::get_origin as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_ADDRESS,
+            SyscallContext::get_address as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_RETURNDATA_SIZE,
+            SyscallContext::get_returndata_size as *const fn(*mut c_void) -> u32 as *mut (),
+        ),
+        (
+            symbols::COPY_RETURNDATA,
+            SyscallContext::copy_returndata as *const fn(*mut c_void, u32, u32, u32) -> u8
+                as *mut (),
+        ),
+        (
+            symbols::GET_CALL_GAS,
+            SyscallContext::get_call_gas as *const fn(*mut c_void, *const U256, *const U256) -> i64
+                as *mut (),
+        ),
+        (
+            symbols::CALL,
+            SyscallContext::call
+                as *const fn(
+                    *mut c_void,
+                    u64,
+                    *const U256,
+                    *const U256,
+                    u32,
+                    u32,
+                    u32,
+                    u32,
+                    *mut u64,
+                ) -> u8 as *mut (),
+        ),
+        (
+            symbols::IS_STATIC,
+            SyscallContext::is_static as *const fn(*mut c_void) -> u8 as *mut (),
+        ),
+        (
+            symbols::ALLOCATE_STACK,
+            SyscallContext::allocate_stack as *const fn(*mut c_void, u32) -> *mut U256 as *mut (),
+        ),
+        (
+            symbols::COPY_INITIAL_STACK,
+            SyscallContext::copy_initial_stack as *const fn(*mut c_void, *mut U256) -> u32
+                as *mut (),
+        ),
+        (
+            symbols::ON_OVERFLOW,
+            SyscallContext::on_overflow as *const fn(*mut c_void, u8) as *mut (),
+        ),
+        (
+            symbols::GET_GASPRICE,
+            SyscallContext::get_gasprice as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_BLOCK_HASH,
+            SyscallContext::get_block_hash as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_COINBASE,
+            SyscallContext::get_coinbase as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_TIMESTAMP,
+            SyscallContext::get_timestamp as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_BLOCK_NUMBER,
+            SyscallContext::get_block_number as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_GASLIMIT,
+            SyscallContext::get_gaslimit as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_CHAINID,
+            SyscallContext::get_chainid as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_PREVRANDAO,
+            SyscallContext::get_prevrandao as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_BASEFEE,
+            SyscallContext::get_basefee as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_BALANCE,
+            SyscallContext::get_balance as *const fn(*mut c_void, *const U256, *mut U256) -> i64
+                as *mut (),
+        ),
+        (
+            symbols::GET_SELF_BALANCE,
+            SyscallContext::get_self_balance as *const fn(*mut c_void, *mut U256) as *mut (),
+        ),
+        (
+            symbols::GET_SELFDESTRUCT_GAS,
+            SyscallContext::get_selfdestruct_gas as *const fn(*mut c_void, *const U256) -> i64
+                as *mut (),
+        ),
+        (
+            symbols::SELFDESTRUCT,
+            SyscallContext::selfdestruct as *const fn(*mut c_void, *const U256) as *mut (),
+        ),
+        (
+            symbols::GET_EXT_CODE_SIZE,
+            SyscallContext::get_ext_code_size
+                as *const fn(*mut c_void, *const U256, *mut U256) -> i64 as *mut (),
+        ),
+        (
+            symbols::GET_EXT_CODE_HASH,
+            SyscallContext::get_ext_code_hash
+                as *const fn(*mut c_void, *const U256, *mut U256) -> i64 as *mut (),
+        ),
+        (
+            symbols::COPY_EXT_CODE,
+            SyscallContext::copy_ext_code
+                as *const fn(*mut c_void, *const U256, *const U256, u32, u32) -> i64
+                as *mut (),
+        ),
+        (
+            symbols::CREATE,
+            SyscallContext::create
+                as *const fn(*mut c_void, u32, u32, *const U256, u64, *mut u64, *mut U256)
+                as *mut (),
+        ),
+        (
+            symbols::CREATE2,
+            SyscallContext::create2
+                as *const fn(
+                    *mut c_void,
+                    u32,
+                    u32,
+                    *const U256,
+                    *const U256,
+                    u64,
+                    *mut u64,
+                    *mut U256,
+                ) as *mut (),
+        ),
+    ];
+
+    #[cfg(feature = "debug-syscalls")]
+    table.push((
+        symbols::DEBUG_PRINT_STACK_TOP,
+        SyscallContext::debug_print_stack_top as *const fn(*mut c_void, *const U256) as *mut (),
+    ));
+
+    table
+}
+
+/// Registers all the syscalls as symbols in the execution engine
+///
+/// This allows the generated code to call the syscalls by name.
+pub fn register_syscalls(engine: &impl SymbolRegistry) {
+    for (symbol, function) in syscall_table() {
+        unsafe { engine.register_symbol(symbol, function) };
+    }
+}
+
+/// MLIR util for declaring syscalls
+pub(crate) mod mlir {
+    use melior::{
+        dialect::{func, llvm::r#type::pointer},
+        ir::{
+            attribute::{FlatSymbolRefAttribute, StringAttribute, TypeAttribute},
+            r#type::{FunctionType, IntegerType},
+            Block, Identifier, Location, Module as MeliorModule, Region, Value,
+        },
+        Context as MeliorContext,
+    };
+
+    use crate::errors::CodegenError;
+
+    use super::symbols;
+
+    pub(crate) fn declare_syscalls(context: &MeliorContext, module: &MeliorModule) {
+        let location = Location::unknown(context);
+
+        // Type declarations
+        let ptr_type = pointer(context, 0);
+        let uint32 = IntegerType::new(context, 32).into();
+        //let uint256 = IntegerType::new(context, 256).into();
+        let uint64 = IntegerType::new(context, 64).into();
+        let uint8 = IntegerType::new(context, 8).into();
+
+        let attributes = &[(
+            Identifier::new(context, "sym_visibility"),
+            StringAttribute::new(context, "private").into(),
+        )];
+
+        // Syscall declarations
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::WRITE_RESULT),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, uint32, uint32, uint64, uint8], &[]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::IS_TRACING),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[uint8]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::TRACE_STEP),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[ptr_type, uint64, uint8, uint64, ptr_type, ptr_type],
+                    &[uint8],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_CALLDATA_SIZE),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[uint32]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::EXTEND_MEMORY),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, uint32], &[ptr_type]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::APPEND_LOG),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, uint32, uint32], &[uint8]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::APPEND_LOG_ONE_TOPIC),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, uint32, uint32, ptr_type], &[uint8]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::APPEND_LOG_TWO_TOPICS),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[ptr_type, uint32, uint32, ptr_type, ptr_type],
+                    &[uint8],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::APPEND_LOG_THREE_TOPICS),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[ptr_type, uint32, uint32, ptr_type, ptr_type, ptr_type],
+                    &[uint8],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::APPEND_LOG_FOUR_TOPICS),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[
+                        ptr_type, uint32, uint32, ptr_type, ptr_type, ptr_type, ptr_type,
+                    ],
+                    &[uint8],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_CALLDATA_PTR),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[ptr_type]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::KECCAK256_HASHER),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, uint32, uint32, ptr_type], &[uint8]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::STORAGE_READ),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type, uint64], &[uint64])
+                    .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::STORAGE_WRITE),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type, uint64], &[uint64])
+                    .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::TRANSIENT_READ),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::TRANSIENT_WRITE),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_CALLVALUE),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_CALLER),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_ORIGIN),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_ADDRESS),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_RETURNDATA_SIZE),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[uint32]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::COPY_RETURNDATA),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, uint32, uint32, uint32], &[uint8]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_CALL_GAS),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[uint64]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::CALL),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[
+                        ptr_type, uint64, ptr_type, ptr_type, uint32, uint32, uint32, uint32,
+                        ptr_type,
+                    ],
+                    &[uint8],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::IS_STATIC),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[uint8]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::ALLOCATE_STACK),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, uint32], &[ptr_type]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::COPY_INITIAL_STACK),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[uint32]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::ON_OVERFLOW),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, uint8], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_GASPRICE),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_BLOCK_HASH),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_COINBASE),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_TIMESTAMP),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_BLOCK_NUMBER),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_GASLIMIT),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_CHAINID),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_PREVRANDAO),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_BASEFEE),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_BALANCE),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[uint64]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_SELF_BALANCE),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_SELFDESTRUCT_GAS),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[uint64]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::SELFDESTRUCT),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_EXT_CODE_SIZE),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[uint64]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::GET_EXT_CODE_HASH),
+            TypeAttribute::new(
+                FunctionType::new(context, &[ptr_type, ptr_type, ptr_type], &[uint64]).into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::COPY_EXT_CODE),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[ptr_type, ptr_type, ptr_type, uint32, uint32],
+                    &[uint64],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::CREATE),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[
+                        ptr_type, uint32, uint32, ptr_type, uint64, ptr_type, ptr_type,
+                    ],
+                    &[],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::CREATE2),
+            TypeAttribute::new(
+                FunctionType::new(
+                    context,
+                    &[
+                        ptr_type, uint32, uint32, ptr_type, ptr_type, uint64, ptr_type, ptr_type,
+                    ],
+                    &[],
+                )
+                .into(),
+            ),
+            Region::new(),
+            attributes,
+            location,
+        ));
+
+        #[cfg(feature = "debug-syscalls")]
+        module.body().append_operation(func::func(
+            context,
+            StringAttribute::new(context, symbols::DEBUG_PRINT_STACK_TOP),
+            TypeAttribute::new(FunctionType::new(context, &[ptr_type, ptr_type], &[]).into()),
+            Region::new(),
             attributes,
             location,
         ));
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::APPEND_LOG),
-            TypeAttribute::new(FunctionType::new(context, &[ptr_type, uint32, uint32], &[]).into()),
-            Region::new(),
-            attributes,
+    }
+
+    /// Stores the return values in the syscall context
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn write_result_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &Block,
+        offset: Value,
+        size: Value,
+        gas: Value,
+        reason: Value,
+        location: Location,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::WRITE_RESULT),
+            &[syscall_ctx, offset, size, gas, reason],
+            &[],
+            location,
+        ));
+    }
+
+    /// Returns 1 if [`trace_step_syscall`] must be called before each operation, or 0 otherwise.
+    pub(crate) fn is_tracing_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::IS_TRACING),
+                &[syscall_ctx],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Reports the operation at `pc`, before running it, with the gas remaining at that point.
+    /// Returns 1 if the step limit was reached, or 0 otherwise.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn trace_step_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        pc: Value<'c, 'c>,
+        opcode: Value<'c, 'c>,
+        gas: Value<'c, 'c>,
+        stack_baseptr: Value<'c, 'c>,
+        stack_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::TRACE_STEP),
+                &[syscall_ctx, pc, opcode, gas, stack_baseptr, stack_ptr],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    pub(crate) fn get_calldata_size_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint32 = IntegerType::new(mlir_ctx, 32).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_CALLDATA_SIZE),
+                &[syscall_ctx],
+                &[uint32],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Extends the memory segment of the syscall context.
+    /// Returns a pointer to the start of the memory segment.
+    pub(crate) fn extend_memory_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        new_size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let ptr_type = pointer(mlir_ctx, 0);
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::EXTEND_MEMORY),
+                &[syscall_ctx, new_size],
+                &[ptr_type],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Receives log data and appends a log to the logs vector.
+    /// Returns 1 if it was appended, or 0 if the data was out of bounds.
+    pub(crate) fn append_log_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        data: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG),
+                &[syscall_ctx, data, size],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Receives log data and a topic and appends a log to the logs vector
+    pub(crate) fn append_log_with_one_topic_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        data: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        topic: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG_ONE_TOPIC),
+                &[syscall_ctx, data, size, topic],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Receives log data, two topics and appends a log to the logs vector
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn append_log_with_two_topics_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        data: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        topic1_ptr: Value<'c, 'c>,
+        topic2_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG_TWO_TOPICS),
+                &[syscall_ctx, data, size, topic1_ptr, topic2_ptr],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Receives log data, three topics and appends a log to the logs vector
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn append_log_with_three_topics_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        data: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        topic1_ptr: Value<'c, 'c>,
+        topic2_ptr: Value<'c, 'c>,
+        topic3_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG_THREE_TOPICS),
+                &[syscall_ctx, data, size, topic1_ptr, topic2_ptr, topic3_ptr],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Receives log data, three topics and appends a log to the logs vector
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn append_log_with_four_topics_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        data: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        topic1_ptr: Value<'c, 'c>,
+        topic2_ptr: Value<'c, 'c>,
+        topic3_ptr: Value<'c, 'c>,
+        topic4_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG_FOUR_TOPICS),
+                &[
+                    syscall_ctx,
+                    data,
+                    size,
+                    topic1_ptr,
+                    topic2_ptr,
+                    topic3_ptr,
+                    topic4_ptr,
+                ],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+    /// Returns a pointer to the calldata.
+    #[allow(unused)]
+    pub(crate) fn get_calldata_ptr_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let ptr_type = pointer(mlir_ctx, 0);
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_CALLDATA_PTR),
+                &[syscall_ctx],
+                &[ptr_type],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Computes the Keccak-256 hash of a memory segment, writing it to `hash_ptr`.
+    /// Returns 1 if it was computed, or 0 if the segment was out of bounds.
+    pub(crate) fn keccak256_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        hash_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::KECCAK256_HASHER),
+                &[syscall_ctx, offset, size, hash_ptr],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Reads a storage slot into `value_ptr`, returning the gas cost of the access.
+    pub(crate) fn storage_read_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        key_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        gas_remaining: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint64 = IntegerType::new(mlir_ctx, 64).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::STORAGE_READ),
+                &[syscall_ctx, key_ptr, value_ptr, gas_remaining],
+                &[uint64],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Writes a storage slot, returning the gas cost of the write.
+    pub(crate) fn storage_write_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        key_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        gas_remaining: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint64 = IntegerType::new(mlir_ctx, 64).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::STORAGE_WRITE),
+                &[syscall_ctx, key_ptr, value_ptr, gas_remaining],
+                &[uint64],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Reads a transient storage slot into `value_ptr`.
+    pub(crate) fn transient_read_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        key_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::TRANSIENT_READ),
+            &[syscall_ctx, key_ptr, value_ptr],
+            &[],
+            location,
+        ));
+    }
+
+    /// Writes a transient storage slot.
+    pub(crate) fn transient_write_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        key_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::TRANSIENT_WRITE),
+            &[syscall_ctx, key_ptr, value_ptr],
+            &[],
+            location,
+        ));
+    }
+
+    /// Writes the transaction's call value into `value_ptr`.
+    pub(crate) fn get_callvalue_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_CALLVALUE),
+            &[syscall_ctx, value_ptr],
+            &[],
+            location,
+        ));
+    }
+
+    /// Writes the caller's address into `value_ptr`.
+    pub(crate) fn get_caller_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_CALLER),
+            &[syscall_ctx, value_ptr],
+            &[],
+            location,
+        ));
+    }
+
+    /// Writes the transaction originator's address into `value_ptr`.
+    pub(crate) fn get_origin_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_ORIGIN),
+            &[syscall_ctx, value_ptr],
+            &[],
+            location,
+        ));
+    }
+
+    /// Writes the executing contract's address into `value_ptr`.
+    pub(crate) fn get_address_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_ADDRESS),
+            &[syscall_ctx, value_ptr],
+            &[],
+            location,
+        ));
+    }
+
+    /// Returns the size of the last call's return data.
+    pub(crate) fn get_returndata_size_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint32 = IntegerType::new(mlir_ctx, 32).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_RETURNDATA_SIZE),
+                &[syscall_ctx],
+                &[uint32],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Copies a segment of the last call's return data into memory,
+    /// returning 1 if it was copied or 0 if it was out of bounds.
+    pub(crate) fn copy_returndata_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        dest_offset: Value<'c, 'c>,
+        offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::COPY_RETURNDATA),
+                &[syscall_ctx, dest_offset, offset, size],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Returns the dynamic gas cost of a CALL to the address at `address_ptr`,
+    /// transferring the value at `value_ptr`.
+    pub(crate) fn get_call_gas_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint64 = IntegerType::new(mlir_ctx, 64).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_CALL_GAS),
+                &[syscall_ctx, address_ptr, value_ptr],
+                &[uint64],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Executes a message call, returning its success flag.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn call_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        gas: Value<'c, 'c>,
+        address_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        args_offset: Value<'c, 'c>,
+        args_size: Value<'c, 'c>,
+        ret_offset: Value<'c, 'c>,
+        ret_size: Value<'c, 'c>,
+        gas_remaining_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::CALL),
+                &[
+                    syscall_ctx,
+                    gas,
+                    address_ptr,
+                    value_ptr,
+                    args_offset,
+                    args_size,
+                    ret_offset,
+                    ret_size,
+                    gas_remaining_ptr,
+                ],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Returns 1 if the execution is read-only, or 0 otherwise.
+    pub(crate) fn is_static_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint8 = IntegerType::new(mlir_ctx, 8).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::IS_STATIC),
+                &[syscall_ctx],
+                &[uint8],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Allocates the memory of a stack that fits `size` values, returning a pointer to it.
+    pub(crate) fn allocate_stack_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let ptr_type = pointer(mlir_ctx, 0);
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::ALLOCATE_STACK),
+                &[syscall_ctx, size],
+                &[ptr_type],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Writes the initial stack values to `stack_ptr`, returning how many there are.
+    pub(crate) fn copy_initial_stack_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        stack_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint32 = IntegerType::new(mlir_ctx, 32).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::COPY_INITIAL_STACK),
+                &[syscall_ctx, stack_ptr],
+                &[uint32],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Prints the value at `value_ptr`, taken from the top of the stack.
+    #[cfg(feature = "debug-syscalls")]
+    pub(crate) fn debug_print_stack_top_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::DEBUG_PRINT_STACK_TOP),
+            &[syscall_ctx, value_ptr],
+            &[],
             location,
         ));
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::APPEND_LOG_ONE_TOPIC),
-            TypeAttribute::new(
-                FunctionType::new(context, &[ptr_type, uint32, uint32, ptr_type], &[]).into(),
-            ),
-            Region::new(),
-            attributes,
+    }
+
+    /// Reports that the operation with `opcode` overflowed.
+    pub(crate) fn on_overflow_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        opcode: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::ON_OVERFLOW),
+            &[syscall_ctx, opcode],
+            &[],
             location,
         ));
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::APPEND_LOG_TWO_TOPICS),
-            TypeAttribute::new(
-                FunctionType::new(
-                    context,
-                    &[ptr_type, uint32, uint32, ptr_type, ptr_type],
-                    &[],
-                )
-                .into(),
-            ),
-            Region::new(),
-            attributes,
+    }
+
+    /// Writes the transaction's gas price into `value_ptr`.
+    pub(crate) fn get_gasprice_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_GASPRICE),
+            &[syscall_ctx, value_ptr],
+            &[],
             location,
         ));
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::APPEND_LOG_THREE_TOPICS),
-            TypeAttribute::new(
-                FunctionType::new(
-                    context,
-                    &[ptr_type, uint32, uint32, ptr_type, ptr_type, ptr_type],
-                    &[],
-                )
-                .into(),
-            ),
-            Region::new(),
-            attributes,
+    }
+
+    /// Replaces the block number at `number_ptr` with the block's hash.
+    pub(crate) fn get_block_hash_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        number_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_BLOCK_HASH),
+            &[syscall_ctx, number_ptr],
+            &[],
             location,
         ));
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::APPEND_LOG_FOUR_TOPICS),
-            TypeAttribute::new(
-                FunctionType::new(
-                    context,
-                    &[
-                        ptr_type, uint32, uint32, ptr_type, ptr_type, ptr_type, ptr_type,
-                    ],
-                    &[],
-                )
-                .into(),
-            ),
-            Region::new(),
-            attributes,
+    }
+
+    /// Writes the block's beneficiary address into `value_ptr`.
+    pub(crate) fn get_coinbase_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_COINBASE),
+            &[syscall_ctx, value_ptr],
+            &[],
             location,
         ));
+    }
 
-        module.body().append_operation(func::func(
-            context,
-            StringAttribute::new(context, symbols::GET_CALLDATA_PTR),
-            TypeAttribute::new(FunctionType::new(context, &[ptr_type], &[ptr_type]).into()),
-            Region::new(),
-            attributes,
+    /// Writes the block's timestamp into `value_ptr`.
+    pub(crate) fn get_timestamp_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_TIMESTAMP),
+            &[syscall_ctx, value_ptr],
+            &[],
             location,
         ));
     }
 
-    /// Stores the return values in the syscall context
-    #[allow(clippy::too_many_arguments)]
-    pub(crate) fn write_result_syscall<'c>(
+    /// Writes the block's number into `value_ptr`.
+    pub(crate) fn get_block_number_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
-        block: &Block,
-        offset: Value,
-        size: Value,
-        gas: Value,
-        reason: Value,
-        location: Location,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
+        location: Location<'c>,
     ) {
         block.append_operation(func::call(
             mlir_ctx,
-            FlatSymbolRefAttribute::new(mlir_ctx, symbols::WRITE_RESULT),
-            &[syscall_ctx, offset, size, gas, reason],
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_BLOCK_NUMBER),
+            &[syscall_ctx, value_ptr],
             &[],
             location,
         ));
     }
 
-    pub(crate) fn get_calldata_size_syscall<'c>(
+    /// Writes the block's gas limit into `value_ptr`.
+    pub(crate) fn get_gaslimit_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
         block: &'c Block,
+        value_ptr: Value<'c, 'c>,
         location: Location<'c>,
-    ) -> Result<Value<'c, 'c>, CodegenError> {
-        let uint32 = IntegerType::new(mlir_ctx, 32).into();
-        let value = block
-            .append_operation(func::call(
-                mlir_ctx,
-                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_CALLDATA_SIZE),
-                &[syscall_ctx],
-                &[uint32],
-                location,
-            ))
-            .result(0)?;
-        Ok(value.into())
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_GASLIMIT),
+            &[syscall_ctx, value_ptr],
+            &[],
+            location,
+        ));
     }
 
-    /// Extends the memory segment of the syscall context.
-    /// Returns a pointer to the start of the memory segment.
-    pub(crate) fn extend_memory_syscall<'c>(
+    /// Writes the chain ID into `value_ptr`.
+    pub(crate) fn get_chainid_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
         block: &'c Block,
-        new_size: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
         location: Location<'c>,
-    ) -> Result<Value<'c, 'c>, CodegenError> {
-        let ptr_type = pointer(mlir_ctx, 0);
-        let value = block
-            .append_operation(func::call(
-                mlir_ctx,
-                FlatSymbolRefAttribute::new(mlir_ctx, symbols::EXTEND_MEMORY),
-                &[syscall_ctx, new_size],
-                &[ptr_type],
-                location,
-            ))
-            .result(0)?;
-        Ok(value.into())
+    ) {
+        block.append_operation(func::call(
+            mlir_ctx,
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_CHAINID),
+            &[syscall_ctx, value_ptr],
+            &[],
+            location,
+        ));
     }
 
-    /// Receives log data and appends a log to the logs vector
-    pub(crate) fn append_log_syscall<'c>(
+    /// Writes the block's randomness into `value_ptr`.
+    pub(crate) fn get_prevrandao_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
         block: &'c Block,
-        data: Value<'c, 'c>,
-        size: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
         location: Location<'c>,
     ) {
         block.append_operation(func::call(
             mlir_ctx,
-            FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG),
-            &[syscall_ctx, data, size],
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_PREVRANDAO),
+            &[syscall_ctx, value_ptr],
             &[],
             location,
         ));
     }
 
-    /// Receives log data and a topic and appends a log to the logs vector
-    pub(crate) fn append_log_with_one_topic_syscall<'c>(
+    /// Writes the block's base fee into `value_ptr`.
+    pub(crate) fn get_basefee_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
         block: &'c Block,
-        data: Value<'c, 'c>,
-        size: Value<'c, 'c>,
-        topic: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
         location: Location<'c>,
     ) {
         block.append_operation(func::call(
             mlir_ctx,
-            FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG_ONE_TOPIC),
-            &[syscall_ctx, data, size, topic],
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_BASEFEE),
+            &[syscall_ctx, value_ptr],
             &[],
             location,
         ));
     }
 
-    /// Receives log data, two topics and appends a log to the logs vector
-    #[allow(clippy::too_many_arguments)]
-    pub(crate) fn append_log_with_two_topics_syscall<'c>(
+    /// Reads an account's balance into `balance_ptr`, returning the gas cost of the access.
+    pub(crate) fn get_balance_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
         block: &'c Block,
-        data: Value<'c, 'c>,
-        size: Value<'c, 'c>,
-        topic1_ptr: Value<'c, 'c>,
-        topic2_ptr: Value<'c, 'c>,
+        address_ptr: Value<'c, 'c>,
+        balance_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint64 = IntegerType::new(mlir_ctx, 64).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_BALANCE),
+                &[syscall_ctx, address_ptr, balance_ptr],
+                &[uint64],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Writes the executing contract's balance into `value_ptr`.
+    pub(crate) fn get_self_balance_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        value_ptr: Value<'c, 'c>,
         location: Location<'c>,
     ) {
         block.append_operation(func::call(
             mlir_ctx,
-            FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG_TWO_TOPICS),
-            &[syscall_ctx, data, size, topic1_ptr, topic2_ptr],
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_SELF_BALANCE),
+            &[syscall_ctx, value_ptr],
             &[],
             location,
         ));
     }
 
-    /// Receives log data, three topics and appends a log to the logs vector
-    #[allow(clippy::too_many_arguments)]
-    pub(crate) fn append_log_with_three_topics_syscall<'c>(
+    /// Returns the gas cost of a SELFDESTRUCT to the beneficiary at `beneficiary_ptr`,
+    /// besides the base cost.
+    pub(crate) fn get_selfdestruct_gas_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
         block: &'c Block,
-        data: Value<'c, 'c>,
-        size: Value<'c, 'c>,
-        topic1_ptr: Value<'c, 'c>,
-        topic2_ptr: Value<'c, 'c>,
-        topic3_ptr: Value<'c, 'c>,
+        beneficiary_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint64 = IntegerType::new(mlir_ctx, 64).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_SELFDESTRUCT_GAS),
+                &[syscall_ctx, beneficiary_ptr],
+                &[uint64],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Sends the executing contract's balance to the beneficiary at `beneficiary_ptr`,
+    /// and destroys the contract.
+    pub(crate) fn selfdestruct_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        beneficiary_ptr: Value<'c, 'c>,
         location: Location<'c>,
     ) {
         block.append_operation(func::call(
             mlir_ctx,
-            FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG_THREE_TOPICS),
-            &[syscall_ctx, data, size, topic1_ptr, topic2_ptr, topic3_ptr],
+            FlatSymbolRefAttribute::new(mlir_ctx, symbols::SELFDESTRUCT),
+            &[syscall_ctx, beneficiary_ptr],
             &[],
             location,
         ));
     }
 
-    /// Receives log data, three topics and appends a log to the logs vector
+    /// Creates a contract, writing its address (or 0 on failure) to `address_ptr`.
+    /// `salt_ptr` is only given for CREATE2.
     #[allow(clippy::too_many_arguments)]
-    pub(crate) fn append_log_with_four_topics_syscall<'c>(
+    pub(crate) fn create_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
         block: &'c Block,
-        data: Value<'c, 'c>,
+        offset: Value<'c, 'c>,
         size: Value<'c, 'c>,
-        topic1_ptr: Value<'c, 'c>,
-        topic2_ptr: Value<'c, 'c>,
-        topic3_ptr: Value<'c, 'c>,
-        topic4_ptr: Value<'c, 'c>,
+        value_ptr: Value<'c, 'c>,
+        salt_ptr: Option<Value<'c, 'c>>,
+        gas: Value<'c, 'c>,
+        gas_remaining_ptr: Value<'c, 'c>,
+        address_ptr: Value<'c, 'c>,
         location: Location<'c>,
     ) {
+        let (symbol, args) = match salt_ptr {
+            None => (
+                symbols::CREATE,
+                vec![
+                    syscall_ctx,
+                    offset,
+                    size,
+                    value_ptr,
+                    gas,
+                    gas_remaining_ptr,
+                    address_ptr,
+                ],
+            ),
+            Some(salt_ptr) => (
+                symbols::CREATE2,
+                vec![
+                    syscall_ctx,
+                    offset,
+                    size,
+                    value_ptr,
+                    salt_ptr,
+                    gas,
+                    gas_remaining_ptr,
+                    address_ptr,
+                ],
+            ),
+        };
         block.append_operation(func::call(
             mlir_ctx,
-            FlatSymbolRefAttribute::new(mlir_ctx, symbols::APPEND_LOG_FOUR_TOPICS),
-            &[
-                syscall_ctx,
-                data,
-                size,
-                topic1_ptr,
-                topic2_ptr,
-                topic3_ptr,
-                topic4_ptr,
-            ],
+            FlatSymbolRefAttribute::new(mlir_ctx, symbol),
+            &args,
             &[],
             location,
         ));
     }
-    /// Returns a pointer to the calldata.
-    #[allow(unused)]
-    pub(crate) fn get_calldata_ptr_syscall<'c>(
+
+    /// Writes the code size of the account at `address_ptr` into `size_ptr`,
+    /// returning the gas cost of the access.
+    pub(crate) fn get_ext_code_size_syscall<'c>(
         mlir_ctx: &'c MeliorContext,
         syscall_ctx: Value<'c, 'c>,
         block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        size_ptr: Value<'c, 'c>,
         location: Location<'c>,
     ) -> Result<Value<'c, 'c>, CodegenError> {
-        let ptr_type = pointer(mlir_ctx, 0);
+        let uint64 = IntegerType::new(mlir_ctx, 64).into();
         let value = block
             .append_operation(func::call(
                 mlir_ctx,
-                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_CALLDATA_PTR),
-                &[syscall_ctx],
-                &[ptr_type],
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_EXT_CODE_SIZE),
+                &[syscall_ctx, address_ptr, size_ptr],
+                &[uint64],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Writes the code hash of the account at `address_ptr` into `hash_ptr`,
+    /// returning the gas cost of the access.
+    pub(crate) fn get_ext_code_hash_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        hash_ptr: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint64 = IntegerType::new(mlir_ctx, 64).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::GET_EXT_CODE_HASH),
+                &[syscall_ctx, address_ptr, hash_ptr],
+                &[uint64],
+                location,
+            ))
+            .result(0)?;
+        Ok(value.into())
+    }
+
+    /// Copies the code of the account at `address_ptr` into memory,
+    /// returning the gas cost of the access.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn copy_ext_code_syscall<'c>(
+        mlir_ctx: &'c MeliorContext,
+        syscall_ctx: Value<'c, 'c>,
+        block: &'c Block,
+        address_ptr: Value<'c, 'c>,
+        code_offset_ptr: Value<'c, 'c>,
+        dest_offset: Value<'c, 'c>,
+        size: Value<'c, 'c>,
+        location: Location<'c>,
+    ) -> Result<Value<'c, 'c>, CodegenError> {
+        let uint64 = IntegerType::new(mlir_ctx, 64).into();
+        let value = block
+            .append_operation(func::call(
+                mlir_ctx,
+                FlatSymbolRefAttribute::new(mlir_ctx, symbols::COPY_EXT_CODE),
+                &[syscall_ctx, address_ptr, code_offset_ptr, dest_offset, size],
+                &[uint64],
                 location,
             ))
             .result(0)?;
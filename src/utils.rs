@@ -3,14 +3,14 @@ use melior::{
         arith,
         arith::CmpiPredicate,
         cf, func,
-        llvm::{self, r#type::pointer, AllocaOptions, LoadStoreOptions},
+        llvm::{self, r#type::pointer, LoadStoreOptions},
         ods,
     },
     ir::{
-        attribute::{DenseI32ArrayAttribute, IntegerAttribute, TypeAttribute},
+        attribute::{DenseI32ArrayAttribute, IntegerAttribute},
         operation::OperationResult,
         r#type::IntegerType,
-        Block, Location, Region, Value,
+        Attribute, Block, Location, Region, Value,
     },
     Context as MeliorContext,
 };
@@ -18,8 +18,8 @@ use melior::{
 use crate::{
     codegen::context::OperationCtx,
     constants::{
-        GAS_COUNTER_GLOBAL, MAX_STACK_SIZE, MEMORY_PTR_GLOBAL, MEMORY_SIZE_GLOBAL,
-        STACK_BASEPTR_GLOBAL, STACK_PTR_GLOBAL,
+        gas_cost, GAS_COUNTER_GLOBAL, MAX_MEMORY_SIZE, MAX_STACK_SIZE, MEMORY_PTR_GLOBAL,
+        MEMORY_SIZE_GLOBAL, STACK_BASEPTR_GLOBAL, STACK_PTR_GLOBAL,
     },
     errors::CodegenError,
     syscall::ExitStatusCode,
@@ -65,31 +65,8 @@ pub fn consume_gas<'ctx>(
     amount: i64,
 ) -> Result<Value<'ctx, 'ctx>, CodegenError> {
     let location = Location::unknown(context);
-    let ptr_type = pointer(context, 0);
     let uint64 = IntegerType::new(context, 64).into();
 
-    // Get address of gas counter global
-    let gas_counter_ptr = block
-        .append_operation(llvm_mlir::addressof(
-            context,
-            GAS_COUNTER_GLOBAL,
-            ptr_type,
-            location,
-        ))
-        .result(0)?;
-
-    // Load gas counter
-    let gas_counter = block
-        .append_operation(llvm::load(
-            context,
-            gas_counter_ptr.into(),
-            uint64,
-            location,
-            LoadStoreOptions::default(),
-        ))
-        .result(0)?
-        .into();
-
     let gas_value = block
         .append_operation(arith::constant(
             context,
@@ -99,32 +76,7 @@ pub fn consume_gas<'ctx>(
         .result(0)?
         .into();
 
-    // Check that gas_counter >= gas_value
-    let flag = block
-        .append_operation(arith::cmpi(
-            context,
-            arith::CmpiPredicate::Sge,
-            gas_counter,
-            gas_value,
-            location,
-        ))
-        .result(0)?;
-
-    // Subtract gas from gas counter
-    let new_gas_counter = block
-        .append_operation(arith::subi(gas_counter, gas_value, location))
-        .result(0)?;
-
-    // Store new gas counter
-    let _res = block.append_operation(llvm::store(
-        context,
-        new_gas_counter.into(),
-        gas_counter_ptr.into(),
-        location,
-        LoadStoreOptions::default(),
-    ));
-
-    Ok(flag.into())
+    consume_gas_as_value(context, block, gas_value)
 }
 
 pub fn get_stack_pointer<'ctx>(
@@ -245,20 +197,32 @@ pub fn consume_gas_as_value<'ctx>(
         .result(0)?
         .into();
 
-    // Check that gas_counter >= gas_value
+    // Check that gas_counter >= gas_value. Both are unsigned, so costs that don't fit
+    // in 63 bits (e.g. truncated dynamic costs) aren't taken as negative
     let flag = block
         .append_operation(arith::cmpi(
             context,
-            arith::CmpiPredicate::Sge,
+            arith::CmpiPredicate::Uge,
             gas_counter,
             gas_value,
             location,
         ))
-        .result(0)?;
+        .result(0)?
+        .into();
 
-    // Subtract gas from gas counter
-    let new_gas_counter = block
+    // Subtract gas from gas counter, leaving it untouched if there's not enough,
+    // so it never wraps around
+    let subtracted_gas_counter = block
         .append_operation(arith::subi(gas_counter, gas_value, location))
+        .result(0)?
+        .into();
+    let new_gas_counter = block
+        .append_operation(arith::select(
+            flag,
+            subtracted_gas_counter,
+            gas_counter,
+            location,
+        ))
         .result(0)?;
 
     // Store new gas counter
@@ -270,7 +234,44 @@ pub fn consume_gas_as_value<'ctx>(
         LoadStoreOptions::default(),
     ));
 
-    Ok(flag.into())
+    Ok(flag)
+}
+
+/// Adds `gas_value` back to the gas counter.
+/// Used for the gas a call didn't consume.
+pub(crate) fn return_unused_gas<'ctx>(
+    context: &'ctx MeliorContext,
+    block: &'ctx Block,
+    gas_value: Value<'ctx, 'ctx>,
+) -> Result<(), CodegenError> {
+    let location = Location::unknown(context);
+    let ptr_type = pointer(context, 0);
+
+    // Get address of gas counter global
+    let gas_counter_ptr = block
+        .append_operation(llvm_mlir::addressof(
+            context,
+            GAS_COUNTER_GLOBAL,
+            ptr_type,
+            location,
+        ))
+        .result(0)?;
+
+    let gas_counter = get_remaining_gas(context, block)?;
+
+    let new_gas_counter = block
+        .append_operation(arith::addi(gas_counter, gas_value, location))
+        .result(0)?;
+
+    block.append_operation(llvm::store(
+        context,
+        new_gas_counter.into(),
+        gas_counter_ptr.into(),
+        location,
+        LoadStoreOptions::default(),
+    ));
+
+    Ok(())
 }
 
 // computes dynamic_gas = 375 * topic_count + 8 * size
@@ -284,19 +285,20 @@ pub(crate) fn compute_log_dynamic_gas<'a>(
     let context = op_ctx.mlir_context;
     let uint64 = IntegerType::new(context, 64);
 
-    let constant_375 = block
+    // dynamic_gas = 375 * topic_count + 8 * size
+    let gas_per_topic = block
         .append_operation(arith::constant(
             context,
-            integer_constant_from_i64(context, 375).into(),
+            integer_constant_from_i64(context, gas_cost::LOG_TOPIC).into(),
             location,
         ))
         .result(0)?
         .into();
 
-    let constant_8 = block
+    let gas_per_byte = block
         .append_operation(arith::constant(
             context,
-            integer_constant_from_i64(context, 8).into(),
+            integer_constant_from_i64(context, gas_cost::LOG_DATA).into(),
             location,
         ))
         .result(0)?
@@ -311,16 +313,16 @@ pub(crate) fn compute_log_dynamic_gas<'a>(
         .result(0)?
         .into();
 
-    let topic_count_x_375 = block
-        .append_operation(arith::muli(topic_count, constant_375, location))
+    let topics_gas = block
+        .append_operation(arith::muli(topic_count, gas_per_topic, location))
         .result(0)?
         .into();
-    let size_x_8 = block
-        .append_operation(arith::muli(size, constant_8, location))
+    let data_gas = block
+        .append_operation(arith::muli(size, gas_per_byte, location))
         .result(0)?
         .into();
     let dynamic_gas = block
-        .append_operation(arith::addi(topic_count_x_375, size_x_8, location))
+        .append_operation(arith::addi(topics_gas, data_gas, location))
         .result(0)?
         .into();
     let dynamic_gas = block
@@ -546,6 +548,10 @@ pub fn swap_stack_elements<'ctx>(
 ) -> Result<(), CodegenError> {
     debug_assert!((position_1 as u32) < MAX_STACK_SIZE as u32);
     debug_assert!((position_2 as u32) < MAX_STACK_SIZE as u32);
+    // Swapping a slot with itself leaves the stack as it is
+    if position_1 == position_2 {
+        return Ok(());
+    }
     let location = Location::unknown(context);
 
     let (first_element, first_elem_address) = get_nth_from_stack(context, block, position_1)?;
@@ -574,11 +580,13 @@ pub fn swap_stack_elements<'ctx>(
     Ok(())
 }
 
-/// Generates code for checking if the stack has enough space for `element_count` more elements.
+/// Generates code for checking if the stack has enough space for `element_count` more elements,
+/// when it can hold at most `stack_size` elements.
 pub fn check_stack_has_space_for<'ctx>(
     context: &'ctx MeliorContext,
     block: &'ctx Block,
     element_count: u32,
+    stack_size: u32,
 ) -> Result<Value<'ctx, 'ctx>, CodegenError> {
     debug_assert!(element_count < MAX_STACK_SIZE as u32);
     let location = Location::unknown(context);
@@ -627,19 +635,19 @@ pub fn check_stack_has_space_for<'ctx>(
         ))
         .result(0)?;
 
-    // Compare `subtracted_stack_ptr = stack_ptr + element_count - MAX_STACK_SIZE`
+    // Compare `subtracted_stack_ptr = stack_ptr + element_count - stack_size`
     let subtracted_stack_ptr = block
         .append_operation(llvm::get_element_ptr(
             context,
             stack_ptr.into(),
-            DenseI32ArrayAttribute::new(context, &[element_count as i32 - MAX_STACK_SIZE as i32]),
+            DenseI32ArrayAttribute::new(context, &[element_count as i32 - stack_size as i32]),
             uint256.into(),
             ptr_type,
             location,
         ))
         .result(0)?;
 
-    // Compare `stack_ptr + element_count - MAX_STACK_SIZE <= stack_baseptr`
+    // Compare `stack_ptr + element_count - stack_size <= stack_baseptr`
     let flag = block
         .append_operation(
             ods::llvm::icmp(
@@ -766,6 +774,42 @@ pub fn compare_values<'ctx>(
     Ok(flag.into())
 }
 
+/// Returns a flag that is set when `num` is the lowest signed 256-bit value and `den` is -1,
+/// the only signed division whose result overflows. LLVM's `sdiv` and `srem` are undefined
+/// for it, while the EVM defines the quotient as `num` and the remainder as 0.
+pub fn check_is_signed_division_overflow<'ctx>(
+    context: &'ctx MeliorContext,
+    block: &'ctx Block,
+    num: Value<'ctx, 'ctx>,
+    den: Value<'ctx, 'ctx>,
+) -> Result<Value<'ctx, 'ctx>, CodegenError> {
+    let location = Location::unknown(context);
+
+    let zero = constant_value_from_i64(context, block, 0)?;
+    let one = constant_value_from_i64(context, block, 1)?;
+    let max_bits = constant_value_from_i64(context, block, 255)?;
+
+    // int_min = 1 << 255
+    let int_min = block
+        .append_operation(arith::shli(one, max_bits, location))
+        .result(0)?
+        .into();
+    // minus_one = 0 - 1
+    let minus_one = block
+        .append_operation(arith::subi(zero, one, location))
+        .result(0)?
+        .into();
+
+    let num_is_int_min = compare_values(context, block, CmpiPredicate::Eq, num, int_min)?;
+    let den_is_minus_one = compare_values(context, block, CmpiPredicate::Eq, den, minus_one)?;
+
+    let flag = block
+        .append_operation(arith::andi(num_is_int_min, den_is_minus_one, location))
+        .result(0)?
+        .into();
+    Ok(flag)
+}
+
 pub fn check_if_zero<'ctx>(
     context: &'ctx MeliorContext,
     block: &'ctx Block,
@@ -950,8 +994,208 @@ pub(crate) fn compute_memory_cost<'c>(
     Ok(memory_cost)
 }
 
+/// Computes the gas cost of expanding the memory from `last_size` to `new_size` bytes,
+/// i.e. the difference between their [memory costs](compute_memory_cost).
+/// Mirrors [`memory_expansion_cost`](crate::constants::gas_cost::memory_expansion_cost) for values only known at runtime.
+pub(crate) fn memory_expansion_cost<'c>(
+    op_ctx: &'c OperationCtx,
+    block: &'c Block,
+    last_size: Value<'c, 'c>,
+    new_size: Value<'c, 'c>,
+) -> Result<Value<'c, 'c>, CodegenError> {
+    let location = Location::unknown(op_ctx.mlir_context);
+
+    let memory_cost_before = compute_memory_cost(op_ctx, block, last_size)?;
+    let memory_cost_after = compute_memory_cost(op_ctx, block, new_size)?;
+
+    let expansion_cost = block
+        .append_operation(arith::subi(memory_cost_after, memory_cost_before, location))
+        .result(0)?
+        .into();
+    Ok(expansion_cost)
+}
+
+/// Branches from `block` to `finish_block` if the memory range of `size` bytes at `offset`
+/// can be addressed, and to the error block otherwise.
+///
+/// Both are still the 256-bit values popped from the stack: memory offsets and sizes are
+/// 32-bit, so this must be checked before truncating them, which would drop the higher bits.
+/// Empty ranges never access the memory, so their offset can be anything.
+pub(crate) fn check_memory_range<'c>(
+    op_ctx: &'c OperationCtx,
+    block: &'c Block,
+    finish_block: &'c Block,
+    offset: Value<'c, 'c>,
+    size: Value<'c, 'c>,
+) -> Result<(), CodegenError> {
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    let zero = constant_value_from_i64(context, block, 0)?;
+    let max_size = constant_value_from_i64(context, block, MAX_MEMORY_SIZE as i64)?;
+
+    let offset_fits = compare_values(context, block, CmpiPredicate::Ule, offset, max_size)?;
+    let size_fits = compare_values(context, block, CmpiPredicate::Ule, size, max_size)?;
+    // The sum may only wrap around if one of them doesn't fit already
+    let end = block
+        .append_operation(arith::addi(offset, size, location))
+        .result(0)?
+        .into();
+    let end_fits = compare_values(context, block, CmpiPredicate::Ule, end, max_size)?;
+    let bounds_fit = block
+        .append_operation(arith::andi(offset_fits, size_fits, location))
+        .result(0)?
+        .into();
+    let range_fits = block
+        .append_operation(arith::andi(bounds_fit, end_fits, location))
+        .result(0)?
+        .into();
+
+    let is_empty = compare_values(context, block, CmpiPredicate::Eq, size, zero)?;
+    let flag = block
+        .append_operation(arith::ori(range_fits, is_empty, location))
+        .result(0)?
+        .into();
+
+    block.append_operation(cf::cond_br(
+        context,
+        flag,
+        finish_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    Ok(())
+}
+
+/// Returns `offset` for a memory range of `size` bytes, or 0 if the range is empty.
+///
+/// Empty ranges never access nor extend the memory, wherever they are, so this keeps
+/// their offset from counting towards the required memory size. Both are still the
+/// 256-bit values popped from the stack.
+pub(crate) fn zero_offset_if_empty<'c>(
+    context: &'c MeliorContext,
+    block: &'c Block,
+    offset: Value<'c, 'c>,
+    size: Value<'c, 'c>,
+) -> Result<Value<'c, 'c>, CodegenError> {
+    let location = Location::unknown(context);
+
+    let zero = constant_value_from_i64(context, block, 0)?;
+    let is_empty = compare_values(context, block, CmpiPredicate::Eq, size, zero)?;
+    let offset = block
+        .append_operation(arith::select(is_empty, zero, offset, location))
+        .result(0)?
+        .into();
+    Ok(offset)
+}
+
+/// Charges the per-word cost of copying `size` bytes into the memory at `dest_offset`,
+/// and extends the memory to fit them, before branching to `finish_block`.
+/// Shared by CALLDATACOPY, CODECOPY, RETURNDATACOPY and EXTCODECOPY.
+///
+/// Both are still the 256-bit values popped from the stack. They're checked to be
+/// [addressable](check_memory_range) before anything else, so `finish_block` can
+/// truncate them to 32 bits.
+pub(crate) fn extend_memory_for_copy<'c>(
+    op_ctx: &'c OperationCtx,
+    block: &'c Block,
+    finish_block: &'c Block,
+    region: &Region<'c>,
+    dest_offset: Value<'c, 'c>,
+    size: Value<'c, 'c>,
+) -> Result<(), CodegenError> {
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+    let uint32 = IntegerType::new(context, 32);
+    let uint64 = IntegerType::new(context, 64);
+    let uint256 = IntegerType::new(context, 256);
+
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, block, &in_bounds_block, dest_offset, size)?;
+
+    // dynamic_gas = 3 * (size / 32 + (size % 32 != 0)), since size + 31 could wrap around
+    let zero = constant_value_from_i64(context, &in_bounds_block, 0)?;
+    let constant_32 = constant_value_from_i64(context, &in_bounds_block, 32)?;
+    let full_words = in_bounds_block
+        .append_operation(arith::divui(size, constant_32, location))
+        .result(0)?
+        .into();
+    let remainder = in_bounds_block
+        .append_operation(arith::remui(size, constant_32, location))
+        .result(0)?
+        .into();
+    let has_partial_word = compare_values(
+        context,
+        &in_bounds_block,
+        CmpiPredicate::Ne,
+        remainder,
+        zero,
+    )?;
+    let partial_word = in_bounds_block
+        .append_operation(arith::extui(has_partial_word, uint256.into(), location))
+        .result(0)?
+        .into();
+    let size_in_words = in_bounds_block
+        .append_operation(arith::addi(full_words, partial_word, location))
+        .result(0)?
+        .into();
+    let gas_per_word = constant_value_from_i64(context, &in_bounds_block, gas_cost::COPY_PER_WORD)?;
+    let dynamic_gas = in_bounds_block
+        .append_operation(arith::muli(size_in_words, gas_per_word, location))
+        .result(0)?
+        .into();
+    let dynamic_gas = in_bounds_block
+        .append_operation(arith::trunci(dynamic_gas, uint64.into(), location))
+        .result(0)?
+        .into();
+    let dynamic_gas_flag = consume_gas_as_value(context, &in_bounds_block, dynamic_gas)?;
+
+    let memory_extension_block = region.append_block(Block::new(&[]));
+
+    in_bounds_block.append_operation(cf::cond_br(
+        context,
+        dynamic_gas_flag,
+        &memory_extension_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let dest_offset = memory_extension_block
+        .append_operation(arith::trunci(dest_offset, uint32.into(), location))
+        .result(0)?
+        .into();
+    let size = memory_extension_block
+        .append_operation(arith::trunci(size, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    // required_size = dest_offset + size
+    let required_size = memory_extension_block
+        .append_operation(arith::addi(dest_offset, size, location))
+        .result(0)?
+        .into();
+
+    extend_memory(
+        op_ctx,
+        &memory_extension_block,
+        finish_block,
+        region,
+        required_size,
+        0,
+    )
+}
+
 /// Wrapper for calling the [`extend_memory`](crate::syscall::SyscallContext::extend_memory) syscall.
 /// Extends memory only if the current memory size is less than the required size, consuming the corresponding gas.
+///
+/// NOTE: extending the memory may reallocate it, which invalidates any pointer to it
+/// loaded beforehand. Callers must load the [`MEMORY_PTR_GLOBAL`] *after* the extension
+/// (i.e. in `finish_block`), never before.
 pub(crate) fn extend_memory<'c>(
     op_ctx: &'c OperationCtx,
     block: &'c Block,
@@ -1011,13 +1255,8 @@ pub(crate) fn extend_memory<'c>(
     ));
 
     // Consume gas for memory extension case
-    let memory_cost_before = compute_memory_cost(op_ctx, &extension_block, memory_size)?;
-    let memory_cost_after = compute_memory_cost(op_ctx, &extension_block, rounded_required_size)?;
-
-    let dynamic_gas_value = extension_block
-        .append_operation(arith::subi(memory_cost_after, memory_cost_before, location))
-        .result(0)?
-        .into();
+    let dynamic_gas_value =
+        memory_expansion_cost(op_ctx, &extension_block, memory_size, rounded_required_size)?;
     let fixed_gas_value = extension_block
         .append_operation(arith::constant(
             context,
@@ -1035,11 +1274,24 @@ pub(crate) fn extend_memory<'c>(
     // Consume gas for no memory extension case
     let no_extension_gas_flag = consume_gas(context, &no_extension_block, fixed_gas)?;
 
+    // The memory is only allocated once its gas is paid
+    let allocation_block = region.append_block(Block::new(&[]));
+
+    extension_block.append_operation(cf::cond_br(
+        context,
+        extension_gas_flag,
+        &allocation_block,
+        &op_ctx.error_block,
+        &[],
+        &[],
+        location,
+    ));
+
     let memory_ptr =
-        op_ctx.extend_memory_syscall(&extension_block, rounded_required_size, location)?;
+        op_ctx.extend_memory_syscall(&allocation_block, rounded_required_size, location)?;
 
     // Store new memory size and pointer
-    let res = extension_block.append_operation(llvm::store(
+    let res = allocation_block.append_operation(llvm::store(
         context,
         rounded_required_size,
         memory_size_ptr,
@@ -1047,7 +1299,7 @@ pub(crate) fn extend_memory<'c>(
         LoadStoreOptions::default(),
     ));
     assert!(res.verify());
-    let memory_ptr_ptr = extension_block
+    let memory_ptr_ptr = allocation_block
         .append_operation(llvm_mlir::addressof(
             context,
             MEMORY_PTR_GLOBAL,
@@ -1055,7 +1307,7 @@ pub(crate) fn extend_memory<'c>(
             location,
         ))
         .result(0)?;
-    let res = extension_block.append_operation(llvm::store(
+    let res = allocation_block.append_operation(llvm::store(
         context,
         memory_ptr,
         memory_ptr_ptr.into(),
@@ -1064,12 +1316,33 @@ pub(crate) fn extend_memory<'c>(
     ));
     assert!(res.verify());
 
+    // A null pointer means the memory couldn't be allocated, or would exceed the memory limit
+    let memory_address = allocation_block
+        .append_operation(ods::llvm::ptrtoint(context, uint64.into(), memory_ptr, location).into())
+        .result(0)?
+        .into();
+    let zero = allocation_block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint64.into(), 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let is_allocated_flag = compare_values(
+        context,
+        &allocation_block,
+        CmpiPredicate::Ne,
+        memory_address,
+        zero,
+    )?;
+
     // Jump to finish block
-    extension_block.append_operation(cf::cond_br(
+    allocation_block.append_operation(cf::cond_br(
         context,
-        extension_gas_flag,
+        is_allocated_flag,
         finish_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1079,7 +1352,7 @@ pub(crate) fn extend_memory<'c>(
         context,
         no_extension_gas_flag,
         finish_block,
-        &op_ctx.revert_block,
+        &op_ctx.error_block,
         &[],
         &[],
         location,
@@ -1130,27 +1403,38 @@ pub(crate) fn return_result_from_stack(
 
     let offset_u256 = stack_pop(context, block)?;
     let size_u256 = stack_pop(context, block)?;
+    let offset_u256 = zero_offset_if_empty(context, block, offset_u256, size_u256)?;
 
-    let offset = block
+    let in_bounds_block = region.append_block(Block::new(&[]));
+    check_memory_range(op_ctx, block, &in_bounds_block, offset_u256, size_u256)?;
+
+    let offset = in_bounds_block
         .append_operation(arith::trunci(offset_u256, uint32.into(), location))
         .result(0)
         .unwrap()
         .into();
 
-    let size = block
+    let size = in_bounds_block
         .append_operation(arith::trunci(size_u256, uint32.into(), location))
         .result(0)
         .unwrap()
         .into();
 
-    let required_size = block
+    let required_size = in_bounds_block
         .append_operation(arith::addi(offset, size, location))
         .result(0)?
         .into();
 
     let return_block = region.append_block(Block::new(&[]));
 
-    extend_memory(op_ctx, block, &return_block, region, required_size, 0)?;
+    extend_memory(
+        op_ctx,
+        &in_bounds_block,
+        &return_block,
+        region,
+        required_size,
+        0,
+    )?;
 
     return_result_with_offset_and_size(op_ctx, &return_block, offset, size, reason_code, location)?;
 
@@ -1188,42 +1472,91 @@ pub fn integer_constant_from_i64(context: &MeliorContext, value: i64) -> Integer
     IntegerAttribute::new(uint256.into(), value)
 }
 
+/// Returns a 256-bit constant with the value of `value`, which may not fit in an `i64`.
+pub fn integer_constant_from_usize(context: &MeliorContext, value: usize) -> Attribute {
+    Attribute::parse(context, &format!("{value} : i256")).expect("a usize always fits in an i256")
+}
+
 pub fn integer_constant_from_u8(context: &MeliorContext, value: u8) -> IntegerAttribute {
     let uint8 = IntegerType::new(context, 8);
     IntegerAttribute::new(uint8.into(), value.into())
 }
-/// Allocates memory for a 32-byte value, stores the value in the memory
-/// and returns a pointer to the value
-pub(crate) fn allocate_and_store_value<'a>(
+/// Returns true if the execution isn't read-only (i.e. it's not inside a STATICCALL),
+/// so state-modifying operations are allowed.
+pub(crate) fn check_is_not_static<'a>(
     op_ctx: &'a OperationCtx<'a>,
     block: &'a Block<'a>,
-    value: Value<'a, 'a>,
-    location: Location<'a>,
 ) -> Result<Value<'a, 'a>, CodegenError> {
     let context = op_ctx.mlir_context;
-    let ptr_type = pointer(context, 0);
-    let uint32 = IntegerType::new(context, 32);
-    let uint256 = IntegerType::new(context, 256);
+    let location = Location::unknown(context);
+    let uint8 = IntegerType::new(context, 8);
 
-    let number_of_elements = block
+    let is_static = op_ctx.is_static_syscall(block, location)?;
+    let zero = block
         .append_operation(arith::constant(
             context,
-            IntegerAttribute::new(uint32.into(), 1).into(),
+            IntegerAttribute::new(uint8.into(), 0).into(),
             location,
         ))
         .result(0)?
         .into();
 
-    let value_ptr = block
-        .append_operation(llvm::alloca(
-            context,
-            number_of_elements,
-            ptr_type,
-            location,
-            AllocaOptions::new().elem_type(TypeAttribute::new(uint256.into()).into()),
-        ))
-        .result(0)?
-        .into();
+    compare_values(context, block, CmpiPredicate::Eq, is_static, zero)
+}
+
+/// Generates a block printing the value at the top of the stack, without popping it,
+/// and then jumping to `op_block`. Nothing is printed while the stack is empty.
+/// It's a debugging aid for the generated code, emitted before each operation when
+/// [enabled](crate::context::Compiler::with_stack_top_printing).
+#[cfg(feature = "debug-syscalls")]
+pub(crate) fn debug_print_stack_top<'c>(
+    op_ctx: &OperationCtx<'c>,
+    region: &'c Region<'c>,
+    op_block: melior::ir::BlockRef<'c, 'c>,
+) -> Result<melior::ir::BlockRef<'c, 'c>, CodegenError> {
+    let start_block = region.append_block(Block::new(&[]));
+    let context = op_ctx.mlir_context;
+    let location = Location::unknown(context);
+
+    let print_block = region.append_block(Block::new(&[]));
+
+    let flag = check_stack_has_at_least(context, &start_block, 1)?;
+    start_block.append_operation(cf::cond_br(
+        context,
+        flag,
+        &print_block,
+        &op_block,
+        &[],
+        &[],
+        location,
+    ));
+
+    let (value, _) = get_nth_from_stack(context, &print_block, 1)?;
+    // The stack slots may be less aligned than the syscall's U256
+    let value_ptr = store_in_slot(op_ctx, &print_block, 0, value, location)?;
+    crate::syscall::mlir::debug_print_stack_top_syscall(
+        context,
+        op_ctx.syscall_ctx,
+        &print_block,
+        value_ptr,
+        location,
+    );
+    print_block.append_operation(cf::br(&op_block, &[], location));
+
+    Ok(start_block)
+}
+
+/// Stores a 32-byte value in the `nth` of the [U256 slots](OperationCtx::u256_slots)
+/// and returns a pointer to the value, to be passed to a syscall.
+pub(crate) fn store_in_slot<'a>(
+    op_ctx: &'a OperationCtx<'a>,
+    block: &'a Block<'a>,
+    nth: usize,
+    value: Value<'a, 'a>,
+    location: Location<'a>,
+) -> Result<Value<'a, 'a>, CodegenError> {
+    let context = op_ctx.mlir_context;
+    let value_ptr = op_ctx.u256_slots[nth];
 
     block.append_operation(llvm::store(
         context,
@@ -1237,13 +1570,143 @@ pub(crate) fn allocate_and_store_value<'a>(
     Ok(value_ptr)
 }
 
+/// Copies `source[offset..offset + size]` into `destination`, where `source` is a
+/// buffer of `source_size` bytes. The bytes past the end of `source` are filled with zeros,
+/// as done by CALLDATACOPY and CODECOPY.
+///
+/// `source_size`, `offset` and `size` are 256-bit values, while `destination` must have
+/// room for `size` bytes (i.e. memory must have already been extended, as done by
+/// [`extend_memory_for_copy`]), so `size` fits in 32 bits.
+pub(crate) fn copy_zero_padded<'c>(
+    context: &'c MeliorContext,
+    block: &'c Block,
+    destination: Value<'c, 'c>,
+    source: Value<'c, 'c>,
+    source_size: Value<'c, 'c>,
+    offset: Value<'c, 'c>,
+    size: Value<'c, 'c>,
+) -> Result<(), CodegenError> {
+    let location = Location::unknown(context);
+    let uint1 = IntegerType::new(context, 1);
+    let uint8 = IntegerType::new(context, 8);
+    let uint32 = IntegerType::new(context, 32);
+    let ptr_type = pointer(context, 0);
+
+    // zero the whole destination region, so the bytes past the end of source are 0
+    let zero_byte = block
+        .append_operation(arith::constant(
+            context,
+            IntegerAttribute::new(uint8.into(), 0).into(),
+            location,
+        ))
+        .result(0)?
+        .into();
+    let size_u32 = block
+        .append_operation(arith::trunci(size, uint32.into(), location))
+        .result(0)?
+        .into();
+    block.append_operation(
+        ods::llvm::intr_memset(
+            context,
+            destination,
+            zero_byte,
+            size_u32,
+            IntegerAttribute::new(uint1.into(), 0),
+            location,
+        )
+        .into(),
+    );
+
+    // offset < source_size => some bytes of source have to be copied
+    let offset_in_bounds = compare_values(context, block, CmpiPredicate::Ult, offset, source_size)?;
+    let zero = constant_value_from_i64(context, block, 0)?;
+    let available_bytes = block
+        .append_operation(arith::subi(source_size, offset, location))
+        .result(0)?
+        .into();
+    let available_bytes = block
+        .append_operation(arith::select(
+            offset_in_bounds,
+            available_bytes,
+            zero,
+            location,
+        ))
+        .result(0)?
+        .into();
+    // len = min(source_size - offset, size), or 0 if offset is out of bounds
+    let len = block
+        .append_operation(arith::minui(available_bytes, size, location))
+        .result(0)?
+        .into();
+    let len = block
+        .append_operation(arith::trunci(len, uint32.into(), location))
+        .result(0)?
+        .into();
+    // an out of bounds offset may not fit in 32 bits, but nothing is copied in that case
+    let offset = block
+        .append_operation(arith::select(offset_in_bounds, offset, zero, location))
+        .result(0)?
+        .into();
+    let offset = block
+        .append_operation(arith::trunci(offset, uint32.into(), location))
+        .result(0)?
+        .into();
+
+    // source_at_offset = source + offset
+    let source_at_offset = block
+        .append_operation(llvm::get_element_ptr_dynamic(
+            context,
+            source,
+            &[offset],
+            uint8.into(),
+            ptr_type,
+            location,
+        ))
+        .result(0)?
+        .into();
+
+    // copy source[offset..offset + len] to destination[..len]
+    block.append_operation(
+        ods::llvm::intr_memcpy(
+            context,
+            destination,
+            source_at_offset,
+            len,
+            IntegerAttribute::new(uint1.into(), 0),
+            location,
+        )
+        .into(),
+    );
+
+    Ok(())
+}
+
+/// Converts a word between the native byte order and the big-endian one
+/// used by the EVM's memory and calldata.
+///
+/// The generated code runs on the host it's compiled on, which is always little endian
+/// (see the check at the crate root), so this is always a byte swap.
+pub(crate) fn swap_endianness<'c>(
+    context: &'c MeliorContext,
+    block: &'c Block,
+    value: Value<'c, 'c>,
+) -> Result<Value<'c, 'c>, CodegenError> {
+    let uint256 = IntegerType::new(context, 256);
+    let location = Location::unknown(context);
+    let swapped = block
+        .append_operation(llvm::intr_bswap(value, uint256.into(), location))
+        .result(0)?
+        .into();
+    Ok(swapped)
+}
+
 pub mod llvm_mlir {
     use melior::{
         dialect::llvm::{self, attributes::Linkage},
         ir::{
             attribute::{FlatSymbolRefAttribute, StringAttribute, TypeAttribute},
             operation::OperationBuilder,
-            Identifier, Location, Region,
+            Attribute, Identifier, Location, Region,
         },
         Context as MeliorContext,
     };
@@ -1275,6 +1738,40 @@ pub mod llvm_mlir {
             .expect("valid operation")
     }
 
+    /// Declares a read-only global initialized with `value`.
+    pub fn global_constant<'c>(
+        context: &'c MeliorContext,
+        name: &str,
+        global_type: melior::ir::Type<'c>,
+        value: Attribute<'c>,
+        location: Location<'c>,
+    ) -> melior::ir::Operation<'c> {
+        // TODO: use ODS
+        OperationBuilder::new("llvm.mlir.global", location)
+            .add_regions([Region::new()])
+            .add_attributes(&[
+                (
+                    Identifier::new(context, "sym_name"),
+                    StringAttribute::new(context, name).into(),
+                ),
+                (
+                    Identifier::new(context, "global_type"),
+                    TypeAttribute::new(global_type).into(),
+                ),
+                (
+                    Identifier::new(context, "linkage"),
+                    llvm::attributes::linkage(context, Linkage::Internal),
+                ),
+                (
+                    Identifier::new(context, "constant"),
+                    Attribute::unit(context),
+                ),
+                (Identifier::new(context, "value"), value),
+            ])
+            .build()
+            .expect("valid operation")
+    }
+
     pub fn addressof<'c>(
         context: &'c MeliorContext,
         name: &str,
@@ -0,0 +1,45 @@
+//! Helpers shared by the integration tests.
+// Each test crate only uses some of them
+#![allow(dead_code)]
+
+use evm_mlir::{
+    context::Context,
+    executor::Executor,
+    module::MLIRModule,
+    program::{Operation, Program},
+    syscall::SyscallContext,
+};
+use tempfile::NamedTempFile;
+
+/// Compiles `operations` for the spec of the context's environment and runs them
+/// in `context` with `initial_gas`.
+///
+/// Returns the context, to inspect the result and the state left behind by the run.
+pub fn run_program_with(
+    operations: Vec<Operation>,
+    context: SyscallContext<'_>,
+    initial_gas: u64,
+) -> SyscallContext<'_> {
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    let spec = context.env.cfg.spec;
+    let mlir_context = Context::new();
+    let module = mlir_context
+        .compile_with_spec(&Program::from(operations), &output_file, spec)
+        .expect("failed to compile program");
+
+    run_module_with(&module, context, initial_gas)
+}
+
+/// Runs an already compiled `module` in `context` with `initial_gas`.
+pub fn run_module_with<'a>(
+    module: &MLIRModule,
+    mut context: SyscallContext<'a>,
+    initial_gas: u64,
+) -> SyscallContext<'a> {
+    let executor = Executor::new(module);
+    executor.execute(&mut context, initial_gas);
+
+    context
+}
@@ -0,0 +1,473 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeSet,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use evm_mlir::{
+    compile_to_llvm_ir_string, compile_to_mlir_string, compile_to_object,
+    constants::{
+        gas_cost::{self, GasSchedule},
+        MAIN_ENTRYPOINT,
+    },
+    context::{Compiler, Context},
+    errors::CodegenError,
+    program::{Opcode, Operation, Program},
+    syscall::{syscall_table, SyscallContext},
+};
+use num_bigint::BigUint;
+use tempfile::{NamedTempFile, TempDir};
+
+mod common;
+use common::run_module_with;
+
+fn return_value_program(value: u8) -> Program {
+    Program::from(vec![
+        Operation::Push((1_u8, BigUint::from(value))),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1_u8, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ])
+}
+
+#[test]
+fn compiler_is_reusable_across_programs() {
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    let compiler = Compiler::new().with_output_file(output_file.to_path_buf());
+
+    for value in [1_u8, 2, 3] {
+        let module = compiler
+            .compile(&return_value_program(value))
+            .expect("failed to compile program");
+        let context = run_module_with(&module, SyscallContext::default(), 1e7 as _);
+
+        let result = context.get_result();
+        assert!(result.is_success());
+        assert_eq!(result.return_data().unwrap()[31], value);
+    }
+}
+
+#[test]
+fn compiler_without_output_file_compiles_in_a_temporary_directory() {
+    let compiler = Compiler::new();
+
+    let module = compiler
+        .compile(&return_value_program(7))
+        .expect("failed to compile program");
+    let context = run_module_with(&module, SyscallContext::default(), 1e7 as _);
+
+    let result = context.get_result();
+    assert!(result.is_success());
+    assert_eq!(result.return_data().unwrap()[31], 7);
+}
+
+#[test]
+fn compiler_keeps_the_intermediate_files_at_its_output_file() {
+    let output_dir = TempDir::new().expect("failed to create temp dir");
+    let output_file = output_dir.path().join("program");
+    let compiler = Compiler::new().with_output_file(&output_file);
+
+    compiler
+        .compile(&return_value_program(7))
+        .expect("failed to compile program");
+
+    assert!(output_file.with_extension("mlir").exists());
+    assert!(output_file.with_extension("after-pass.mlir").exists());
+}
+
+#[test]
+fn compiler_charges_the_costs_of_its_gas_schedule() {
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    let program = Program::from(vec![
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Add,
+        Operation::Stop,
+    ]);
+
+    let gas_used = |gas_schedule: GasSchedule| {
+        let compiler = Compiler::new()
+            .with_output_file(output_file.to_path_buf())
+            .with_gas_schedule(gas_schedule);
+        let module = compiler
+            .compile(&program)
+            .expect("failed to compile program");
+        let context = run_module_with(&module, SyscallContext::default(), 1e7 as _);
+
+        let result = context.get_result();
+        assert!(result.is_success());
+        1e7 as u64 - result.gas_remaining()
+    };
+
+    let mainnet_gas = gas_used(GasSchedule::mainnet());
+    let custom_gas = gas_used(GasSchedule::mainnet().with_cost(Opcode::ADD as u8, 100));
+
+    assert_eq!(
+        mainnet_gas,
+        2 * gas_cost::PUSH0 as u64 + gas_cost::ADD as u64
+    );
+    assert_eq!(custom_gas - mainnet_gas, 100 - gas_cost::ADD as u64);
+}
+
+#[test]
+fn mainnet_gas_schedule_matches_reference_costs() {
+    // Static costs from the yellow paper, as amended up to Cancun.
+    // Opcodes with only dynamic costs charge 0 here and aren't listed.
+    let mut reference = vec![
+        (Opcode::ADD, 3),
+        (Opcode::MUL, 5),
+        (Opcode::SUB, 3),
+        (Opcode::DIV, 5),
+        (Opcode::SDIV, 5),
+        (Opcode::MOD, 5),
+        (Opcode::SMOD, 5),
+        (Opcode::ADDMOD, 8),
+        (Opcode::MULMOD, 8),
+        (Opcode::EXP, 10),
+        (Opcode::SIGNEXTEND, 5),
+        (Opcode::LT, 3),
+        (Opcode::GT, 3),
+        (Opcode::SLT, 3),
+        (Opcode::SGT, 3),
+        (Opcode::EQ, 3),
+        (Opcode::ISZERO, 3),
+        (Opcode::AND, 3),
+        (Opcode::OR, 3),
+        (Opcode::XOR, 3),
+        (Opcode::NOT, 3),
+        (Opcode::BYTE, 3),
+        (Opcode::SHL, 3),
+        (Opcode::SHR, 3),
+        (Opcode::SAR, 3),
+        (Opcode::KECCAK256, 30),
+        (Opcode::ADDRESS, 2),
+        (Opcode::ORIGIN, 2),
+        (Opcode::CALLER, 2),
+        (Opcode::CALLVALUE, 2),
+        (Opcode::CALLDATALOAD, 3),
+        (Opcode::CALLDATASIZE, 2),
+        (Opcode::CALLDATACOPY, 3),
+        (Opcode::CODESIZE, 2),
+        (Opcode::CODECOPY, 3),
+        (Opcode::GASPRICE, 2),
+        (Opcode::RETURNDATASIZE, 2),
+        (Opcode::RETURNDATACOPY, 3),
+        (Opcode::BLOCKHASH, 20),
+        (Opcode::COINBASE, 2),
+        (Opcode::TIMESTAMP, 2),
+        (Opcode::NUMBER, 2),
+        (Opcode::PREVRANDAO, 2),
+        (Opcode::GASLIMIT, 2),
+        (Opcode::CHAINID, 2),
+        (Opcode::SELFBALANCE, 5),
+        (Opcode::BASEFEE, 2),
+        (Opcode::POP, 2),
+        (Opcode::MLOAD, 3),
+        (Opcode::MSTORE, 3),
+        (Opcode::MSTORE8, 3),
+        (Opcode::JUMP, 8),
+        (Opcode::JUMPI, 10),
+        (Opcode::PC, 2),
+        (Opcode::MSIZE, 2),
+        (Opcode::GAS, 2),
+        (Opcode::JUMPDEST, 1),
+        (Opcode::TLOAD, 100),
+        (Opcode::TSTORE, 100),
+        (Opcode::MCOPY, 3),
+        (Opcode::PUSH0, 2),
+        (Opcode::CREATE, 32_000),
+        (Opcode::CALL, 100),
+        (Opcode::CREATE2, 32_000),
+        (Opcode::SELFDESTRUCT, 5_000),
+    ]
+    .into_iter()
+    .map(|(opcode, cost)| (opcode as u8, cost))
+    .collect::<Vec<_>>();
+    // PUSH1-PUSH32, DUP1-DUP16, SWAP1-SWAP16 and LOG0-LOG4
+    reference.extend((0..32).map(|n| (Opcode::PUSH1 as u8 + n, 3)));
+    reference.extend((0..16).map(|n| (Opcode::DUP1 as u8 + n, 3)));
+    reference.extend((0..16).map(|n| (Opcode::SWAP1 as u8 + n, 3)));
+    reference.extend((0..5).map(|n| (Opcode::LOG0 as u8 + n, 375)));
+
+    let schedule = GasSchedule::mainnet();
+    for (opcode, cost) in reference {
+        assert_eq!(
+            schedule.cost(opcode),
+            cost,
+            "wrong static cost for opcode {opcode:#04x}"
+        );
+    }
+}
+
+#[test]
+fn compiler_with_small_stack_overflows_earlier() {
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    let compiler = Compiler::new()
+        .with_output_file(output_file.to_path_buf())
+        .with_stack_size(4);
+
+    let run_pushes = |push_count: usize| {
+        let mut operations = vec![Operation::Push0; push_count];
+        operations.push(Operation::Stop);
+        let module = compiler
+            .compile(&Program::from(operations))
+            .expect("failed to compile program");
+        let context = run_module_with(&module, SyscallContext::default(), 1e7 as _);
+        context.get_result()
+    };
+
+    assert!(run_pushes(4).is_success());
+    assert!(run_pushes(5).is_halt());
+}
+
+#[test]
+fn compiler_with_big_stack_holds_more_values_than_the_native_stack() {
+    // 2^20 values take 32 MiB, more than a test thread's native stack
+    let compiler = Compiler::new().with_stack_size(1 << 20);
+    let mut operations = vec![Operation::Push0; 2_000];
+    operations.push(Operation::Stop);
+
+    let module = compiler
+        .compile(&Program::from(operations))
+        .expect("failed to compile program");
+    let context = run_module_with(&module, SyscallContext::default(), 1e7 as _);
+
+    assert!(context.get_result().is_success());
+}
+
+#[test]
+fn compiler_rejects_stack_sizes_out_of_its_offsets_range() {
+    let compiler = Compiler::new().with_stack_size(u32::MAX);
+
+    let result = compiler.compile(&Program::from(vec![Operation::Stop]));
+
+    assert!(matches!(
+        result,
+        Err(CodegenError::InvalidStackSize(u32::MAX))
+    ));
+}
+
+#[test]
+fn overflow_instrumentation_reports_wrapping_operations() {
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    let compiler = Compiler::new()
+        .with_output_file(output_file.to_path_buf())
+        .with_overflow_instrumentation(true);
+
+    let reported_opcodes = |a: BigUint, b: BigUint, operation: Operation| {
+        let program = Program::from(vec![
+            Operation::Push((32, b)),
+            Operation::Push((32, a)),
+            operation,
+            Operation::Stop,
+        ]);
+        let module = compiler
+            .compile(&program)
+            .expect("failed to compile program");
+        let reported = Rc::new(RefCell::new(vec![]));
+        let handler_reported = reported.clone();
+        let context = SyscallContext::default()
+            .with_overflow_handler(move |opcode| handler_reported.borrow_mut().push(opcode));
+        let context = run_module_with(&module, context, 1e7 as _);
+
+        assert!(context.get_result().is_success());
+        let opcodes = reported.borrow().clone();
+        opcodes
+    };
+    let max = BigUint::from_bytes_be(&[0xff; 32]);
+
+    assert_eq!(
+        reported_opcodes(max.clone(), 1_u8.into(), Operation::Add),
+        vec![Opcode::ADD as u8]
+    );
+    assert_eq!(
+        reported_opcodes(0_u8.into(), 1_u8.into(), Operation::Sub),
+        vec![Opcode::SUB as u8]
+    );
+    assert_eq!(
+        reported_opcodes(max.clone(), 2_u8.into(), Operation::Mul),
+        vec![Opcode::MUL as u8]
+    );
+    assert!(reported_opcodes(max.clone() - 1_u8, 1_u8.into(), Operation::Add).is_empty());
+    assert!(reported_opcodes(1_u8.into(), 1_u8.into(), Operation::Sub).is_empty());
+    assert!(reported_opcodes(0_u8.into(), max, Operation::Mul).is_empty());
+}
+
+#[test]
+fn compile_to_object_writes_the_entrypoint() {
+    let object_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    // PUSH1 42, PUSH0, MSTORE, PUSH1 32, PUSH0, RETURN
+    let bytecode = [0x60, 0x2a, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3];
+
+    compile_to_object(&bytecode, &object_file).expect("failed to compile bytecode");
+
+    let object = std::fs::read(&object_file).expect("failed to read the object file");
+    assert!(!object.is_empty());
+    let symbol = format!("_mlir_ciface_{MAIN_ENTRYPOINT}");
+    assert!(object
+        .windows(symbol.len())
+        .any(|window| window == symbol.as_bytes()));
+}
+
+#[test]
+fn mlir_string_contains_the_generated_operations() {
+    // PUSH1 1, PUSH1 2, ADD
+    let bytecode = [0x60, 0x01, 0x60, 0x02, 0x01];
+
+    let mlir = compile_to_mlir_string(&bytecode).expect("failed to compile bytecode");
+
+    assert!(mlir.contains("arith.addi"));
+    assert!(mlir.contains(MAIN_ENTRYPOINT));
+}
+
+#[test]
+fn llvm_ir_string_defines_the_entrypoint() {
+    // PUSH1 1, PUSH1 2, ADD
+    let bytecode = [0x60, 0x01, 0x60, 0x02, 0x01];
+
+    let llvm_ir = compile_to_llvm_ir_string(&bytecode).expect("failed to compile bytecode");
+
+    let entrypoint = format!("@{MAIN_ENTRYPOINT}(");
+    assert!(llvm_ir
+        .lines()
+        .any(|line| line.starts_with("define") && line.contains(&entrypoint)));
+}
+
+#[test]
+fn halting_code_is_shared_by_all_operations() {
+    // Every operation can halt (e.g. by running out of gas), but they all branch to
+    // the same error block, so the results written don't grow with the program
+    let count_results_written = |operations: Vec<Operation>| {
+        let mlir = compiled_mlir(&Program::from(operations));
+        mlir.matches("call @evm_mlir__write_result").count()
+    };
+
+    let small_program = vec![Operation::Push0, Operation::Pop];
+    let large_program = std::iter::repeat([Operation::Push0, Operation::Pop])
+        .take(250)
+        .flatten()
+        .collect();
+
+    // One in the error block, and another one to stop at the end of the code
+    assert_eq!(count_results_written(small_program), 2);
+    assert_eq!(count_results_written(large_program), 2);
+}
+
+/// Compares the per-program compilation time of a reused [`Compiler`]
+/// against creating a fresh [`Context`] for each program.
+/// Run with `cargo test --test compiler -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn compiler_reuse_benchmark() {
+    const ITERATIONS: u32 = 50;
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    let program = return_value_program(42);
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        let context = Context::new();
+        context
+            .compile(&program, &output_file)
+            .expect("failed to compile program");
+    }
+    let fresh: Duration = start.elapsed() / ITERATIONS;
+
+    let compiler = Compiler::new().with_output_file(output_file.to_path_buf());
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        compiler
+            .compile(&program)
+            .expect("failed to compile program");
+    }
+    let reused: Duration = start.elapsed() / ITERATIONS;
+
+    println!("fresh context: {fresh:?}/compile, reused compiler: {reused:?}/compile");
+}
+
+/// Compiles `program` and returns its MLIR code, as dumped before being lowered to LLVM
+fn compiled_mlir(program: &Program) -> String {
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    Context::new()
+        .compile(program, &output_file)
+        .expect("failed to compile program");
+
+    std::fs::read_to_string(output_file.with_extension("mlir"))
+        .expect("failed to read the MLIR code")
+}
+
+/// Measures the compilation time of a 10k-operation program, which is mostly spent
+/// generating the code of each operation.
+/// Run with `cargo test --test compiler -- --ignored --nocapture`.
+#[test]
+#[ignore]
+fn large_program_compilation_benchmark() {
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+    let operations: Vec<_> = std::iter::repeat([Operation::Push0, Operation::Pop])
+        .take(5_000)
+        .flatten()
+        .collect();
+    let program = Program::from(operations);
+    let compiler = Compiler::new().with_output_file(output_file.to_path_buf());
+
+    let start = Instant::now();
+    compiler
+        .compile(&program)
+        .expect("failed to compile program");
+    let elapsed = start.elapsed();
+
+    println!("compiled 10000 operations in {elapsed:?}");
+}
+
+/// Returns the names of the syscalls declared in the MLIR code of a compiled program
+fn declared_syscalls() -> Vec<String> {
+    // The MLIR code is dumped before being lowered, so the declarations are still `func.func`s
+    let mlir = compiled_mlir(&return_value_program(0));
+    mlir.lines()
+        .filter_map(|line| line.trim().strip_prefix("func.func private @"))
+        .map(|declaration| declaration.split('(').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn syscall_table_has_an_entry_per_declared_syscall() {
+    assert_eq!(syscall_table().len(), declared_syscalls().len());
+}
+
+#[test]
+fn every_declared_syscall_is_registered() {
+    let declared: BTreeSet<String> = declared_syscalls().into_iter().collect();
+    let registered: BTreeSet<String> = syscall_table()
+        .into_iter()
+        .map(|(symbol, _)| symbol.to_string())
+        .collect();
+
+    let not_registered: Vec<_> = declared.difference(&registered).collect();
+    let not_declared: Vec<_> = registered.difference(&declared).collect();
+    assert!(
+        not_registered.is_empty(),
+        "syscalls declared but not registered: {not_registered:?}"
+    );
+    assert!(
+        not_declared.is_empty(),
+        "syscalls registered but not declared: {not_declared:?}"
+    );
+}
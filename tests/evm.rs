@@ -1,10 +1,30 @@
+use std::{
+    borrow::Cow,
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    process::Command,
+    rc::Rc,
+};
+
 use evm_mlir::{
-    program::{Operation, Program},
-    syscall::{Log, U256},
+    constants::gas_cost::{self, intrinsic_gas},
+    env::{Address, BlockEnv, TxEnv},
+    errors::{EvmError, InvalidTransaction},
+    execute, execute_with_intrinsic_gas,
+    executor::CompiledProgram,
+    program::{Opcode, Operation, ParseError, Program},
+    spec::Spec,
+    syscall::{
+        ExecutionResult, ExitStatusCode, InvalidExitStatusCode, Log, OpcodeCounts, StepInfo,
+        SyscallContext, U256,
+    },
     Env, Evm,
 };
 use num_bigint::BigUint;
 
+mod common;
+use common::run_program_with;
+
 fn get_fibonacci_program(n: u64) -> Vec<Operation> {
     assert!(n > 0, "n must be greater than 0");
 
@@ -59,13 +79,94 @@ fn fibonacci_example() {
 
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let number = BigUint::from_bytes_be(result.return_data().unwrap());
     assert_eq!(number, 55_u32.into());
 }
 
+/// Runs a loop that adds the calldata size 10 times, returning the sum
+fn run_calldatasize_loop() -> ExecutionResult {
+    let loop_pc = 4;
+    let end_pc = 21;
+    let program = Program::from(vec![
+        Operation::Push((1, BigUint::from(0_u8))),  // accumulator
+        Operation::Push((1, BigUint::from(10_u8))), // counter
+        Operation::Jumpdest { pc: loop_pc },
+        Operation::Dup(1),
+        Operation::IsZero,
+        Operation::Push((1, BigUint::from(end_pc))),
+        Operation::Jumpi,
+        // accumulator += calldata size
+        Operation::Swap(1),
+        Operation::CallDataSize,
+        Operation::Add,
+        Operation::Swap(1),
+        // decrement counter
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Swap(1),
+        Operation::Sub,
+        Operation::Push((1, BigUint::from(loop_pc))),
+        Operation::Jump,
+        Operation::Jumpdest { pc: end_pc },
+        Operation::Pop,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.tx.calldata = vec![0xff; 4].into();
+    let evm = Evm::new(env, program);
+
+    evm.transact().unwrap()
+}
+
+#[test]
+fn calldatasize_in_a_loop() {
+    let result = run_calldatasize_loop();
+
+    assert!(result.is_success());
+    let sum = BigUint::from_bytes_be(result.return_data().unwrap());
+    assert_eq!(sum, 40_u8.into());
+}
+
+/// Set in the child process of [`calldatasize_in_a_loop_prints_nothing`]
+const STDOUT_CHILD_ENV: &str = "EVM_MLIR_TEST_STDOUT_CHILD";
+
+#[test]
+fn calldatasize_in_a_loop_prints_nothing() {
+    let (start, end) = ("<<execution start>>", "<<execution end>>");
+    if std::env::var_os(STDOUT_CHILD_ENV).is_some() {
+        println!("{start}");
+        run_calldatasize_loop();
+        println!("{end}");
+        return;
+    }
+
+    // The test harness captures the output, so it runs again in a child process without it
+    let output = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "calldatasize_in_a_loop_prints_nothing",
+            "--exact",
+            "--nocapture",
+        ])
+        .env(STDOUT_CHILD_ENV, "1")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let (_, after_start) = stdout
+        .split_once(start)
+        .expect("the child should run the loop");
+    let (printed, _) = after_start.split_once(end).unwrap();
+    assert_eq!(printed, "\n");
+}
+
 #[test]
 fn calldataload_with_all_bytes_before_end_of_calldata() {
     // in this case offset + 32 < calldata_size
@@ -90,11 +191,11 @@ fn calldataload_with_all_bytes_before_end_of_calldata() {
 
     let mut env = Env::default();
     env.tx.gas_limit = 999_999;
-    env.tx.calldata = [0x00; 64].into();
-    env.tx.calldata[31] = 1;
+    env.tx.calldata = vec![0x00; 64].into();
+    env.tx.calldata.to_mut()[31] = 1;
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let calldata_slice = result.return_data().unwrap();
@@ -127,11 +228,11 @@ fn calldataload_with_some_bytes_after_end_of_calldata() {
 
     let mut env = Env::default();
     env.tx.gas_limit = 999_999;
-    env.tx.calldata = [0x00; 32].into();
-    env.tx.calldata[31] = 1;
+    env.tx.calldata = vec![0x00; 32].into();
+    env.tx.calldata.to_mut()[31] = 1;
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let calldata_slice = result.return_data().unwrap();
@@ -140,6 +241,52 @@ fn calldataload_with_some_bytes_after_end_of_calldata() {
     assert_eq!(calldata_slice, expected_result);
 }
 
+/// Loads the calldata word at `offset`, returning it as big-endian bytes
+fn run_calldataload(calldata: Vec<u8>, offset: BigUint) -> Vec<u8> {
+    let program = Program::from(vec![
+        Operation::Push((32_u8, offset)),
+        Operation::CalldataLoad,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1_u8, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.tx.calldata = calldata.into();
+    let evm = Evm::new(env, program);
+
+    let result = evm.transact().unwrap();
+
+    assert!(result.is_success());
+    result.return_data().unwrap().to_vec()
+}
+
+#[test]
+fn calldataload_in_range_and_straddling_the_end_agree() {
+    let calldata: Vec<u8> = (1..=40).collect();
+
+    // the last whole word: offset + 32 == calldata_size
+    let in_range = run_calldataload(calldata.clone(), 8_u8.into());
+    assert_eq!(in_range, calldata[8..]);
+
+    // one byte past it, so the last byte is zero-padded
+    let straddling = run_calldataload(calldata.clone(), 9_u8.into());
+    assert_eq!(straddling[..31], in_range[1..]);
+    assert_eq!(straddling[31], 0);
+}
+
+#[test]
+fn calldataload_with_offset_overflowing_the_word_end() {
+    // offset + 32 wraps around, but the offset is still past the calldata
+    let offset = BigUint::from_bytes_be(&[0xff; 32]);
+
+    let word = run_calldataload(vec![0xff; 64], offset);
+
+    assert_eq!(word, [0_u8; 32]);
+}
+
 #[test]
 fn calldataload_with_offset_greater_than_calldata_size() {
     // in this case offset > calldata_size
@@ -164,10 +311,10 @@ fn calldataload_with_offset_greater_than_calldata_size() {
 
     let mut env = Env::default();
     env.tx.gas_limit = 999_999;
-    env.tx.calldata = [0xff; 32].into();
+    env.tx.calldata = vec![0xff; 32].into();
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let calldata_slice = result.return_data().unwrap();
@@ -196,7 +343,7 @@ fn log0() {
 
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let logs = result.return_logs().unwrap();
@@ -232,7 +379,7 @@ fn log1() {
 
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let logs = result.return_logs().unwrap();
@@ -271,7 +418,7 @@ fn log2() {
 
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let logs = result.return_logs().unwrap();
@@ -313,7 +460,7 @@ fn log3() {
 
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let logs = result.return_logs().unwrap();
@@ -362,7 +509,7 @@ fn log4() {
 
     let evm = Evm::new(env, program);
 
-    let result = evm.transact();
+    let result = evm.transact().unwrap();
 
     assert!(&result.is_success());
     let logs = result.return_logs().unwrap();
@@ -377,3 +524,2464 @@ fn log4() {
     }];
     assert_eq!(logs.to_owned(), expected_logs);
 }
+
+#[test]
+fn compiled_program_runs_many_times() {
+    let program = Program::from(return_stack_top(vec![
+        Operation::Push0,
+        Operation::CalldataLoad,
+    ]));
+    let compiled_program = CompiledProgram::compile(&program, Spec::default()).unwrap();
+
+    let mut results = vec![];
+    for calldata in [[0x11_u8; 32], [0x22_u8; 32]] {
+        let env = Env::builder().calldata(calldata.to_vec()).build();
+        let mut context = SyscallContext::with_env(env);
+        results.push(compiled_program.run(&mut context, 999_999));
+    }
+
+    assert_eq!(results[0].return_data(), Some([0x11_u8; 32].as_slice()));
+    assert_eq!(results[1].return_data(), Some([0x22_u8; 32].as_slice()));
+    assert_eq!(results[0].gas_remaining(), results[1].gas_remaining());
+}
+
+#[test]
+fn calldataload_reads_borrowed_calldata() {
+    let program = Program::from(return_stack_top(vec![
+        Operation::Push((1, 4_u8.into())),
+        Operation::CalldataLoad,
+    ]));
+    let compiled_program = CompiledProgram::compile(&program, Spec::default()).unwrap();
+    let calldata: Vec<u8> = (0..64).collect();
+
+    for _ in 0..2 {
+        let env = Env::builder().calldata(calldata.as_slice()).build();
+        assert!(matches!(env.tx.calldata, Cow::Borrowed(_)));
+        let mut context = SyscallContext::with_env(env);
+        let result = compiled_program.run(&mut context, 999_999);
+
+        assert_eq!(result.return_data(), Some(&calldata[4..36]));
+    }
+}
+
+#[test]
+fn log4_topics_follow_stack_order() {
+    // Every byte is distinct, so swapped topics or halves don't go unnoticed
+    let topics: Vec<[u8; 32]> = (0..4_u8)
+        .map(|n| std::array::from_fn(|i| n * 0x40 + i as u8))
+        .collect();
+
+    let mut operations: Vec<_> = topics
+        .iter()
+        .rev()
+        .map(|topic| Operation::Push((32_u8, BigUint::from_bytes_be(topic))))
+        .collect();
+    // LOG4 with empty data: topic1 ends up right below offset and size
+    operations.extend([Operation::Push0, Operation::Push0, Operation::Log(4)]);
+
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+
+    let evm = Evm::new(env, Program::from(operations));
+
+    let result = evm.transact().unwrap();
+
+    assert!(result.is_success());
+    let logs = result.return_logs().unwrap();
+    let expected_topics: Vec<U256> = topics.iter().map(U256::from_be_bytes).collect();
+    assert_eq!(logs.len(), 1);
+    assert_eq!(logs[0].topics, expected_topics);
+}
+
+#[test]
+fn context_logs_match_the_result_logs() {
+    let topic = [0xab_u8; 32];
+    let program = Program::from(vec![
+        Operation::Push((32_u8, BigUint::from_bytes_be(&topic))),
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Log(1),
+    ]);
+    let compiled_program = CompiledProgram::compile(&program, Spec::default()).unwrap();
+    let mut context = SyscallContext::default();
+
+    let result = compiled_program.run(&mut context, 999_999);
+
+    assert!(result.is_success());
+    assert_eq!(context.logs(), result.return_logs().unwrap().as_slice());
+    let log = &context.logs()[0];
+    assert_eq!(log.topic(0), Some(topic));
+    assert_eq!(log.topic(1), None);
+    assert_eq!(log.topics_be_bytes(), vec![topic]);
+}
+
+#[test]
+fn exit_status_codes_round_trip() {
+    let codes = [
+        ExitStatusCode::Return,
+        ExitStatusCode::Stop,
+        ExitStatusCode::Revert,
+        ExitStatusCode::Error,
+        ExitStatusCode::Default,
+    ];
+
+    for code in codes {
+        assert_eq!(ExitStatusCode::try_from(code.to_u8()), Ok(code));
+        assert_eq!(ExitStatusCode::from_u8(code.to_u8()), code);
+    }
+    assert_eq!(ExitStatusCode::try_from(5), Err(InvalidExitStatusCode(5)));
+    assert_eq!(ExitStatusCode::from_u8(5), ExitStatusCode::Default);
+}
+
+#[test]
+fn execution_result_accessors_cover_every_variant() {
+    let success = ExecutionResult::Success {
+        return_data: vec![1],
+        gas_remaining: 10,
+        gas_used: 5,
+        gas_refunded: 0,
+        logs: vec![],
+        opcode_counts: None,
+    };
+    let revert = ExecutionResult::Revert {
+        return_data: vec![2],
+        gas_remaining: 20,
+        gas_used: 5,
+        logs: vec![],
+        failure: None,
+        opcode_counts: None,
+    };
+    let halt = ExecutionResult::Halt {
+        gas_remaining: 0,
+        failure: None,
+    };
+
+    assert!(success.is_success() && !success.is_revert() && !success.is_halt());
+    assert!(!revert.is_success() && revert.is_revert() && !revert.is_halt());
+    assert!(!halt.is_success() && !halt.is_revert() && halt.is_halt());
+
+    assert_eq!(success.return_data(), Some(&[1][..]));
+    assert_eq!(revert.return_data(), Some(&[2][..]));
+    assert_eq!(halt.return_data(), None);
+
+    assert_eq!(success.gas_remaining(), 10);
+    assert_eq!(revert.gas_remaining(), 20);
+    assert_eq!(halt.gas_remaining(), 0);
+
+    assert!(success.return_logs().is_some());
+    assert!(revert.return_logs().is_none());
+    assert!(halt.return_logs().is_none());
+}
+
+#[test]
+fn return_data_reads_as_abi_words() {
+    let second_word = (BigUint::from(1_u8) << 255) + 1_u8;
+    let program = Program::from(vec![
+        Operation::Push((1, 42_u8.into())),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((32, second_word)),
+        Operation::Push((1, 32_u8.into())),
+        Operation::Mstore,
+        Operation::Push((1, 64_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+
+    let result = execute(&program.to_bytecode(), Env::default(), 999_999).unwrap();
+
+    let second_word = U256 {
+        hi: 1 << 127,
+        lo: 1,
+    };
+    assert_eq!(result.return_word(0), Some(U256::from(42)));
+    assert_eq!(result.return_word(1), Some(second_word));
+    assert_eq!(result.return_word(2), None);
+    assert_eq!(
+        result.return_data_words().collect::<Vec<_>>(),
+        [U256::from(42), second_word]
+    );
+    assert_eq!(
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+        .return_word(0),
+        None
+    );
+}
+
+#[test]
+fn logs_before_revert_are_emitted_but_not_returned() {
+    let program = Program::from(vec![
+        Operation::Push((1_u8, 0xff_u8.into())),
+        Operation::Push0,
+        Operation::Mstore8,
+        Operation::Push((1_u8, 1_u8.into())),
+        Operation::Push0,
+        Operation::Log(0),
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Revert,
+    ]);
+
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+
+    let evm = Evm::new(env, program);
+
+    let result = evm.transact().unwrap();
+
+    assert!(result.is_revert());
+    assert!(result.return_logs().is_none());
+    let expected_logs: Vec<Log> = vec![Log {
+        data: vec![0xff],
+        topics: vec![],
+    }];
+    assert_eq!(result.emitted_logs(), expected_logs.as_slice());
+}
+
+/// Runs `operation` and returns the 32-byte word it left on top of the stack.
+fn run_and_return_stack_top(operation: Operation, mut env: Env) -> Vec<u8> {
+    let program = Program::from(vec![
+        operation,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+    env.tx.gas_limit = 999_999;
+    let evm = Evm::new(env, program);
+
+    let result = evm.transact().unwrap();
+
+    assert!(result.is_success());
+    result.return_data().unwrap().to_vec()
+}
+
+#[test]
+fn u256_from_and_to_be_bytes() {
+    let mut two_to_128 = [0; 32];
+    two_to_128[15] = 1;
+    let cases = [
+        ([0; 32], U256 { hi: 0, lo: 0 }),
+        (
+            {
+                let mut one = [0; 32];
+                one[31] = 1;
+                one
+            },
+            U256::from(1),
+        ),
+        (two_to_128, U256 { hi: 1, lo: 0 }),
+        (
+            {
+                let mut below_two_to_128 = [0xff; 32];
+                below_two_to_128[..16].fill(0);
+                below_two_to_128
+            },
+            U256 {
+                hi: 0,
+                lo: u128::MAX,
+            },
+        ),
+        (
+            [0xff; 32],
+            U256 {
+                hi: u128::MAX,
+                lo: u128::MAX,
+            },
+        ),
+    ];
+
+    for (bytes, value) in cases {
+        assert_eq!(U256::from_be_bytes(&bytes), value);
+        assert_eq!(value.to_be_bytes(), bytes);
+    }
+}
+
+#[test]
+fn u256_layout_matches_i256() {
+    assert_eq!(std::mem::size_of::<U256>(), 32);
+    assert_eq!(std::mem::align_of::<U256>(), 16);
+}
+
+#[test]
+fn callvalue() {
+    let mut env = Env::default();
+    env.tx.value = U256 {
+        hi: 0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10,
+        lo: 0x1112_1314_1516_1718_191a_1b1c_1d1e_1f20,
+    };
+
+    let top = run_and_return_stack_top(Operation::CallValue, env);
+
+    let expected: Vec<u8> = (1..=32).collect();
+    assert_eq!(top, expected);
+}
+
+#[test]
+fn callvalue_defaults_to_zero() {
+    let top = run_and_return_stack_top(Operation::CallValue, Env::default());
+
+    assert_eq!(top, [0_u8; 32]);
+}
+
+#[test]
+fn caller() {
+    let mut env = Env::default();
+    env.tx.caller = Address([0xab; 20]);
+
+    let top = run_and_return_stack_top(Operation::Caller, env);
+
+    let mut expected = [0xab_u8; 32];
+    expected[..12].fill(0);
+    assert_eq!(top, expected);
+}
+
+#[test]
+fn caller_in_a_long_loop_does_not_grow_the_native_stack() {
+    let iterations = 1_000_000_u32;
+    let loop_pc = 4;
+    let operations = vec![
+        Operation::Push((3, iterations.into())), // counter
+        Operation::Jumpdest { pc: loop_pc },
+        Operation::Caller,
+        Operation::Pop,
+        Operation::Push((1, 1_u8.into())),
+        Operation::Swap(1),
+        Operation::Sub,
+        Operation::Dup(1),
+        Operation::Push((1, loop_pc.into())),
+        Operation::Jumpi,
+        Operation::Stop,
+    ];
+
+    let initial_gas = 50_000_000;
+    let context = run_program_with(operations, SyscallContext::default(), initial_gas);
+
+    let result = context.get_result();
+    assert!(result.is_success());
+    // each iteration costs 30 gas
+    assert_eq!(
+        result.gas_remaining(),
+        initial_gas - 3 - 30 * iterations as u64
+    );
+}
+
+#[test]
+fn origin() {
+    let mut env = Env::default();
+    env.tx.caller = Address([0xab; 20]);
+    env.tx.origin = Address(std::array::from_fn(|i| i as u8 + 1));
+
+    let top = run_and_return_stack_top(Operation::Origin, env);
+
+    let mut expected = [0_u8; 32];
+    for (i, byte) in expected[12..].iter_mut().enumerate() {
+        *byte = i as u8 + 1;
+    }
+    assert_eq!(top, expected);
+}
+
+#[test]
+fn address() {
+    let mut env = Env::default();
+    env.tx.caller = Address([0xab; 20]);
+    env.tx.to = Address([0xcd; 20]);
+
+    let top = run_and_return_stack_top(Operation::Address, env);
+
+    let mut expected = [0xcd_u8; 32];
+    expected[..12].fill(0);
+    assert_eq!(top, expected);
+}
+
+/// Copies `memory[0..size] = calldata[offset..offset + size]` and returns it.
+fn run_calldatacopy(calldata: Vec<u8>, offset: BigUint, size: u8) -> Vec<u8> {
+    let program = Program::from(vec![
+        Operation::Push((1, size.into())),
+        Operation::Push((32, offset)),
+        Operation::Push0,
+        Operation::CalldataCopy,
+        Operation::Push((1, size.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.tx.calldata = calldata.into();
+    let evm = Evm::new(env, program);
+
+    let result = evm.transact().unwrap();
+
+    assert!(result.is_success());
+    result.return_data().unwrap().to_vec()
+}
+
+#[test]
+fn calldatacopy_within_calldata() {
+    let calldata: Vec<u8> = (1..=64).collect();
+
+    let copied = run_calldatacopy(calldata.clone(), 16_u8.into(), 32);
+
+    assert_eq!(copied, calldata[16..48]);
+}
+
+#[test]
+fn calldatacopy_past_end_of_calldata_is_zero_padded() {
+    let calldata: Vec<u8> = (1..=40).collect();
+
+    let copied = run_calldatacopy(calldata.clone(), 20_u8.into(), 32);
+
+    let mut expected = [0_u8; 32];
+    expected[..20].copy_from_slice(&calldata[20..]);
+    assert_eq!(copied, expected);
+}
+
+#[test]
+fn calldatacopy_with_offset_at_calldata_size() {
+    let calldata: Vec<u8> = (1..=32).collect();
+
+    let copied = run_calldatacopy(calldata, 32_u8.into(), 32);
+
+    assert_eq!(copied, [0_u8; 32]);
+}
+
+#[test]
+fn calldatacopy_with_offset_beyond_32_bits() {
+    // The offset doesn't fit in 32 bits, so it must not be truncated
+    let calldata: Vec<u8> = (1..=32).collect();
+    let offset = BigUint::from(1_u8) << 32;
+
+    let copied = run_calldatacopy(calldata, offset, 32);
+
+    assert_eq!(copied, [0_u8; 32]);
+}
+
+const CALLEE_ADDRESS: Address = Address([0xca; 20]);
+
+/// Returns a program that calls [`CALLEE_ADDRESS`] and returns the first 32 bytes
+/// returned by the callee, followed by the call's success flag.
+fn call_callee_program() -> Vec<Operation> {
+    vec![
+        Operation::Push((1, 32_u8.into())), // ret size
+        Operation::Push0,                   // ret offset
+        Operation::Push0,                   // args size
+        Operation::Push0,                   // args offset
+        Operation::Push0,                   // value
+        Operation::Push((20, BigUint::from_bytes_be(&CALLEE_ADDRESS.0))),
+        Operation::Push((3, 100_000_u32.into())), // gas
+        Operation::Call,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Mstore,
+        Operation::Push((1, 64_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]
+}
+
+/// Runs `caller`, which can call `callee` at [`CALLEE_ADDRESS`].
+/// Returns the result and the callee's storage.
+fn run_with_callee(
+    caller: Vec<Operation>,
+    callee: Vec<Operation>,
+) -> (ExecutionResult, HashMap<U256, U256>) {
+    run_with_callee_in_frame(caller, callee, false)
+}
+
+/// Like [`run_with_callee`], but runs `caller` in a static frame if `is_static`.
+fn run_with_callee_in_frame(
+    caller: Vec<Operation>,
+    callee: Vec<Operation>,
+    is_static: bool,
+) -> (ExecutionResult, HashMap<U256, U256>) {
+    let mut context = SyscallContext::with_env(Env::default());
+    context.is_static = is_static;
+    run_with_callee_in(caller, callee, context)
+}
+
+/// Like [`run_with_callee`], but runs `caller` in the given `context`.
+fn run_with_callee_in(
+    caller: Vec<Operation>,
+    callee: Vec<Operation>,
+    context: SyscallContext,
+) -> (ExecutionResult, HashMap<U256, U256>) {
+    let callee_code = Program::from(callee).to_bytecode();
+    let context = context.with_code_provider(HashMap::from([(CALLEE_ADDRESS, callee_code)]));
+    let context = run_program_with(caller, context, 999_999);
+
+    let callee_storage = context
+        .storage
+        .iter()
+        .filter(|((address, _), _)| *address == CALLEE_ADDRESS)
+        .map(|((_, key), value)| (*key, *value))
+        .collect();
+    (context.get_result(), callee_storage)
+}
+
+#[test]
+fn call_returning_data() {
+    let callee = vec![
+        Operation::Push((1, 42_u8.into())),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+
+    let (result, _) = run_with_callee(call_callee_program(), callee);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    assert_eq!(BigUint::from_bytes_be(&return_data[..32]), 42_u8.into());
+    assert_eq!(BigUint::from_bytes_be(&return_data[32..]), 1_u8.into());
+}
+
+#[test]
+fn call_to_account_without_code_succeeds() {
+    let (result, _) = run_with_callee(call_callee_program(), vec![]);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    assert_eq!(return_data[..32], [0; 32]);
+    assert_eq!(BigUint::from_bytes_be(&return_data[32..]), 1_u8.into());
+}
+
+#[test]
+fn call_keeps_callee_storage_on_success() {
+    let callee = vec![
+        Operation::Push((1, 7_u8.into())),
+        Operation::Push0,
+        Operation::Sstore,
+        Operation::Stop,
+    ];
+
+    let (result, storage) = run_with_callee(call_callee_program(), callee);
+
+    assert!(result.is_success());
+    assert_eq!(storage[&U256::default()], U256 { lo: 7, hi: 0 });
+}
+
+#[test]
+fn call_rolls_back_callee_storage_on_revert() {
+    let callee = vec![
+        Operation::Push((1, 7_u8.into())),
+        Operation::Push0,
+        Operation::Sstore,
+        Operation::Push((1, 5_u8.into())),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Revert,
+    ];
+
+    let (result, storage) = run_with_callee(call_callee_program(), callee);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    // the revert data is still returned to the caller
+    assert_eq!(BigUint::from_bytes_be(&return_data[..32]), 5_u8.into());
+    assert_eq!(BigUint::from_bytes_be(&return_data[32..]), BigUint::ZERO);
+    assert!(storage.is_empty());
+}
+
+#[test]
+fn callee_storage_is_separate_from_caller_storage() {
+    let caller = [
+        vec![
+            Operation::Push((1, 7_u8.into())),
+            Operation::Push0,
+            Operation::Sstore,
+        ],
+        call_callee_program(),
+    ]
+    .concat();
+    let callee = vec![
+        Operation::Push0,
+        Operation::Sload,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+
+    let (result, storage) = run_with_callee(caller, callee);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    // the callee doesn't see the slot written by the caller
+    assert_eq!(return_data[..32], [0; 32]);
+    assert!(storage.is_empty());
+}
+
+#[test]
+fn call_to_halting_callee_consumes_its_gas() {
+    let callee = vec![Operation::Pop];
+
+    let (result, _) = run_with_callee(call_callee_program(), callee.clone());
+    let (succeeding_result, _) = run_with_callee(call_callee_program(), vec![Operation::Stop]);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    assert_eq!(BigUint::from_bytes_be(&return_data[32..]), BigUint::ZERO);
+    // all the 100_000 gas given to the callee is consumed
+    assert_eq!(
+        succeeding_result.gas_remaining() - result.gas_remaining(),
+        100_000
+    );
+}
+
+#[test]
+fn call_to_reverting_callee_returns_its_unused_gas() {
+    let callee = vec![Operation::Push0, Operation::Push0, Operation::Revert];
+
+    let (result, _) = run_with_callee(call_callee_program(), callee);
+    let (succeeding_result, _) = run_with_callee(call_callee_program(), vec![Operation::Stop]);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    assert_eq!(BigUint::from_bytes_be(&return_data[32..]), BigUint::ZERO);
+    // only the two PUSH0 of the callee are consumed
+    assert_eq!(
+        succeeding_result.gas_remaining() - result.gas_remaining(),
+        (gas_cost::PUSH0 * 2) as u64
+    );
+}
+
+#[test]
+fn call_to_invalid_callee_consumes_its_gas() {
+    let (result, _) = run_with_callee(call_callee_program(), vec![Operation::Invalid]);
+    let (succeeding_result, _) = run_with_callee(call_callee_program(), vec![Operation::Stop]);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    assert_eq!(BigUint::from_bytes_be(&return_data[32..]), BigUint::ZERO);
+    // none of the 100_000 gas given to the callee is returned
+    assert_eq!(
+        succeeding_result.gas_remaining() - result.gas_remaining(),
+        100_000
+    );
+}
+
+#[test]
+fn call_to_halting_callee_leaves_no_return_data() {
+    let callee = vec![Operation::Pop];
+    let mut caller = call_callee_program();
+    caller.truncate(8); // up to the CALL
+    caller.extend([
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::ReturnDataSize,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Mstore,
+        Operation::Push((1, 64_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+
+    let (result, _) = run_with_callee(caller, callee);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    // the call failed, and RETURNDATASIZE is 0
+    assert_eq!(BigUint::from_bytes_be(&return_data[..32]), BigUint::ZERO);
+    assert_eq!(BigUint::from_bytes_be(&return_data[32..]), BigUint::ZERO);
+}
+
+#[test]
+fn returndatasize_after_call() {
+    let callee = vec![
+        Operation::Push((1, 20_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    let mut caller = call_callee_program();
+    caller.truncate(8); // up to the CALL
+    caller.extend([
+        Operation::Pop,
+        Operation::ReturnDataSize,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+
+    let (result, _) = run_with_callee(caller, callee);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    assert_eq!(BigUint::from_bytes_be(return_data), 20_u8.into());
+}
+
+/// Returns a program that calls [`CALLEE_ADDRESS`] sending it `value` wei, and returns the
+/// first 64 bytes returned by the callee, followed by its own balance and the call's success flag.
+fn call_with_value_program(value: u8) -> Vec<Operation> {
+    vec![
+        Operation::Push((1, 64_u8.into())), // ret size
+        Operation::Push0,                   // ret offset
+        Operation::Push0,                   // args size
+        Operation::Push0,                   // args offset
+        Operation::Push((1, value.into())),
+        Operation::Push((20, BigUint::from_bytes_be(&CALLEE_ADDRESS.0))),
+        Operation::Push((3, 100_000_u32.into())), // gas
+        Operation::Call,
+        Operation::Push((1, 96_u8.into())),
+        Operation::Mstore,
+        Operation::SelfBalance,
+        Operation::Push((1, 64_u8.into())),
+        Operation::Mstore,
+        Operation::Push((1, 128_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]
+}
+
+/// Returns a callee that returns its own balance followed by its caller's,
+/// ending with `last_operation`.
+fn balances_callee(last_operation: Operation) -> Vec<Operation> {
+    vec![
+        Operation::SelfBalance,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Caller,
+        Operation::Balance,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Mstore,
+        Operation::Push((1, 64_u8.into())),
+        Operation::Push0,
+        last_operation,
+    ]
+}
+
+/// Runs `caller` with a balance of 10 wei, which can call `callee` at [`CALLEE_ADDRESS`].
+/// Returns the result and the balances left behind.
+fn run_with_callee_and_balance(
+    caller: Vec<Operation>,
+    callee: Vec<Operation>,
+) -> (ExecutionResult, HashMap<Address, U256>) {
+    let callee_code = Program::from(callee).to_bytecode();
+    let env = Env::default();
+    let mut context = SyscallContext::with_env(env.clone())
+        .with_code_provider(HashMap::from([(CALLEE_ADDRESS, callee_code)]));
+    context.balances = HashMap::from([(env.tx.to, U256::from(10))]);
+    let context = run_program_with(caller, context, 999_999);
+
+    (context.get_result(), context.balances)
+}
+
+#[test]
+fn call_with_value_transfers_it() {
+    let (result, balances) = run_with_callee_and_balance(
+        call_with_value_program(3),
+        balances_callee(Operation::Return),
+    );
+
+    assert!(result.is_success());
+    let return_words: Vec<_> = result.return_data_words().collect();
+    // the callee's SELFBALANCE and its caller's BALANCE
+    assert_eq!(return_words[0], U256::from(3));
+    assert_eq!(return_words[1], U256::from(7));
+    // the caller's SELFBALANCE after the call, and the success flag
+    assert_eq!(return_words[2], U256::from(7));
+    assert_eq!(return_words[3], U256::from(1));
+    assert_eq!(balances[&Env::default().tx.to], U256::from(7));
+    assert_eq!(balances[&CALLEE_ADDRESS], U256::from(3));
+}
+
+#[test]
+fn call_with_value_rolls_back_the_transfer_on_revert() {
+    let (result, balances) = run_with_callee_and_balance(
+        call_with_value_program(3),
+        balances_callee(Operation::Revert),
+    );
+
+    assert!(result.is_success());
+    let return_words: Vec<_> = result.return_data_words().collect();
+    // the callee saw the transfer before reverting
+    assert_eq!(return_words[0], U256::from(3));
+    assert_eq!(return_words[1], U256::from(7));
+    assert_eq!(return_words[2], U256::from(10));
+    assert_eq!(return_words[3], U256::default());
+    assert_eq!(balances[&Env::default().tx.to], U256::from(10));
+    assert!(!balances.contains_key(&CALLEE_ADDRESS));
+}
+
+#[test]
+fn call_with_value_to_account_without_code_transfers_it() {
+    let (result, balances) = run_with_callee_and_balance(call_with_value_program(3), vec![]);
+
+    assert!(result.is_success());
+    let return_words: Vec<_> = result.return_data_words().collect();
+    assert_eq!(return_words[2], U256::from(7));
+    assert_eq!(return_words[3], U256::from(1));
+    assert_eq!(balances[&CALLEE_ADDRESS], U256::from(3));
+}
+
+#[test]
+fn call_with_insufficient_balance_fails_without_consuming_its_gas() {
+    let (result, balances) = run_with_callee_and_balance(
+        call_with_value_program(11),
+        balances_callee(Operation::Return),
+    );
+
+    assert!(result.is_success());
+    let return_words: Vec<_> = result.return_data_words().collect();
+    // the callee never ran, so nothing was returned
+    assert_eq!(return_words[0], U256::default());
+    assert_eq!(return_words[2], U256::from(10));
+    assert_eq!(return_words[3], U256::default());
+    assert_eq!(balances[&Env::default().tx.to], U256::from(10));
+    // the gas given to the callee is returned
+    assert!(result.gas_remaining() > 999_999 - 100_000);
+}
+
+#[test]
+fn opcode_counts_include_nested_calls() {
+    let callee = vec![
+        Operation::Push((1, 42_u8.into())),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    let context = SyscallContext::with_env(Env::default()).with_profiling();
+
+    let (result, _) = run_with_callee_in(call_callee_program(), callee, context);
+
+    assert!(result.is_success());
+    // the caller's operations, and then the callee's
+    let expected_counts = OpcodeCounts::from([
+        (Opcode::PUSH1 as u8, 3 + 2),
+        (Opcode::PUSH0 as u8, 5 + 2),
+        (Opcode::PUSH20 as u8, 1),
+        (Opcode::PUSH3 as u8, 1),
+        (Opcode::CALL as u8, 1),
+        (Opcode::MSTORE as u8, 1 + 1),
+        (Opcode::RETURN as u8, 1 + 1),
+    ]);
+    assert_eq!(result.opcode_counts(), Some(&expected_counts));
+}
+
+#[test]
+fn log_in_static_frame_halts() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::Log(0)];
+
+    let (result, _) = run_with_callee_in_frame(program, vec![], true);
+
+    assert!(result.is_halt());
+}
+
+#[test]
+fn sstore_in_static_frame_halts() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::Sstore];
+
+    let (result, storage) = run_with_callee_in_frame(program, vec![], true);
+
+    assert!(result.is_halt());
+    assert!(storage.is_empty());
+}
+
+#[test]
+fn call_with_value_in_static_frame_halts() {
+    let mut program = call_callee_program();
+    // transfer 1 wei
+    program[4] = Operation::Push((1, 1_u8.into()));
+
+    let (result, _) = run_with_callee_in_frame(program.clone(), vec![], true);
+    assert!(result.is_halt());
+
+    let (result, _) = run_with_callee_in_frame(program, vec![], false);
+    assert!(result.is_success());
+}
+
+#[test]
+fn callee_of_static_frame_is_static() {
+    let callee = vec![Operation::Push0, Operation::Push0, Operation::Log(0)];
+
+    let (result, _) = run_with_callee_in_frame(call_callee_program(), callee, true);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    assert_eq!(BigUint::from_bytes_be(&return_data[32..]), BigUint::ZERO);
+    assert!(result.return_logs().unwrap().is_empty());
+}
+
+#[test]
+fn block_and_tx_context_opcodes() {
+    let mut env = Env::default();
+    env.tx.gas_price = U256 { lo: 7, hi: 1 };
+    env.block.coinbase = Address([0xcb; 20]);
+    env.block.timestamp = 1_700_000_000;
+    env.block.number = 19_000_000;
+    env.block.gas_limit = 30_000_000;
+    env.cfg.chain_id = 1;
+
+    let mut coinbase = [0xcb_u8; 32];
+    coinbase[..12].fill(0);
+    let mut gas_price = [0_u8; 32];
+    gas_price[15] = 1;
+    gas_price[31] = 7;
+    let expected_values = [
+        (Operation::GasPrice, BigUint::from_bytes_be(&gas_price)),
+        (Operation::Coinbase, BigUint::from_bytes_be(&coinbase)),
+        (Operation::Timestamp, 1_700_000_000_u64.into()),
+        (Operation::Number, 19_000_000_u64.into()),
+        (Operation::GasLimit, 30_000_000_u64.into()),
+        (Operation::ChainId, 1_u8.into()),
+    ];
+
+    for (operation, expected) in expected_values {
+        let top = run_and_return_stack_top(operation.clone(), env.clone());
+        assert_eq!(BigUint::from_bytes_be(&top), expected, "{operation:?}");
+    }
+}
+
+#[test]
+fn basefee() {
+    let mut env = Env::default();
+    env.block.basefee = U256 {
+        lo: 30_000_000_000,
+        hi: 0,
+    };
+
+    let top = run_and_return_stack_top(Operation::BaseFee, env);
+
+    assert_eq!(BigUint::from_bytes_be(&top), 30_000_000_000_u64.into());
+}
+
+#[test]
+fn prevrandao() {
+    let mut env = Env::default();
+    env.block.prevrandao = U256 {
+        hi: u128::MAX,
+        lo: 0xdead_beef,
+    };
+
+    let top = run_and_return_stack_top(Operation::PrevRandao, env);
+
+    let mut expected = [0xff_u8; 32];
+    expected[16..].fill(0);
+    expected[28..].copy_from_slice(&0xdead_beef_u32.to_be_bytes());
+    assert_eq!(top, expected);
+}
+
+#[test]
+fn tx_gas_limit_above_block_gas_limit_is_rejected() {
+    let mut env = Env::default();
+    env.block.gas_limit = 30_000_000;
+    env.tx.gas_limit = 30_000_001;
+
+    let result = env.validate_gas_limit();
+
+    assert_eq!(
+        result,
+        Err(InvalidTransaction::GasLimitExceedsBlockLimit {
+            tx_gas_limit: 30_000_001,
+            block_gas_limit: 30_000_000,
+        })
+    );
+}
+
+#[test]
+fn execution_gas_above_block_gas_limit_is_not_executed() {
+    let mut env = Env::default();
+    env.block.gas_limit = 30_000_000;
+    env.tx.gas_limit = 999_999;
+    let is_rejected = |result: Result<ExecutionResult, EvmError>| {
+        matches!(
+            result,
+            Err(EvmError::InvalidTransaction(
+                InvalidTransaction::GasLimitExceedsBlockLimit {
+                    tx_gas_limit: 30_000_001,
+                    block_gas_limit: 30_000_000,
+                }
+            ))
+        )
+    };
+
+    let bytecode = [0x00];
+    assert!(is_rejected(execute(&bytecode, env.clone(), 30_000_001)));
+    assert!(is_rejected(execute_with_intrinsic_gas(
+        &bytecode, env, 30_000_001
+    )));
+}
+
+#[test]
+fn transactions_above_block_gas_limit_are_not_executed() {
+    let mut env = Env::default();
+    env.block.gas_limit = 30_000_000;
+    env.tx.gas_limit = 30_000_001;
+    let is_rejected = |result: Result<ExecutionResult, EvmError>| {
+        matches!(
+            result,
+            Err(EvmError::InvalidTransaction(
+                InvalidTransaction::GasLimitExceedsBlockLimit { .. }
+            ))
+        )
+    };
+
+    let bytecode = [0x00];
+    assert!(is_rejected(execute(&bytecode, env.clone(), 999_999)));
+    assert!(is_rejected(execute_with_intrinsic_gas(
+        &bytecode,
+        env.clone(),
+        999_999
+    )));
+    assert!(is_rejected(
+        Evm::new(env, Program::from(vec![Operation::Stop])).transact()
+    ));
+}
+
+#[test]
+fn default_block_gas_limit_is_unlimited() {
+    let mut env = Env::default();
+    env.tx.gas_limit = u64::MAX;
+
+    assert_eq!(env.validate_gas_limit(), Ok(()));
+}
+
+#[test]
+fn cumulative_tx_gas_limit_above_block_gas_limit_is_rejected() {
+    let block = BlockEnv {
+        gas_limit: 100_000,
+        ..Default::default()
+    };
+    let tx = TxEnv {
+        gas_limit: 60_000,
+        ..Default::default()
+    };
+
+    assert!(block.validate_gas_limits(&[tx.clone()]).is_ok());
+    assert_eq!(
+        block.validate_gas_limits(&[tx.clone(), tx]),
+        Err(InvalidTransaction::BlockGasLimitReached {
+            block_gas_limit: 100_000
+        })
+    );
+}
+
+#[test]
+fn env_builder_sets_the_given_fields() {
+    let caller = Address([0xca; 20]);
+    let origin = Address([0x0e; 20]);
+
+    let env = Env::builder()
+        .caller(caller.clone())
+        .calldata(vec![1, 2, 3])
+        .value(U256 { hi: 0, lo: 7 })
+        .chain_id(1)
+        .spec(Spec::Shanghai)
+        .build();
+
+    assert_eq!(env.tx.caller, caller);
+    // The origin defaults to the caller
+    assert_eq!(env.tx.origin, caller);
+    assert_eq!(env.tx.calldata, vec![1, 2, 3]);
+    assert_eq!(env.tx.value, U256 { hi: 0, lo: 7 });
+    assert_eq!(env.cfg.chain_id, 1);
+    assert_eq!(env.cfg.spec, Spec::Shanghai);
+    assert_eq!(env.tx.gas_limit, 0);
+
+    let env = Env::builder().caller(caller).origin(origin.clone()).build();
+    assert_eq!(env.tx.origin, origin);
+}
+
+#[test]
+fn intrinsic_gas_of_empty_transaction() {
+    assert_eq!(intrinsic_gas(&[], false, &[], Spec::default()), 21_000);
+}
+
+#[test]
+fn intrinsic_gas_with_mixed_calldata() {
+    let calldata = [0x00, 0x01, 0x00, 0x02, 0xff];
+    // 2 zero bytes (4 gas each) and 3 non-zero bytes (16 gas each)
+    assert_eq!(
+        intrinsic_gas(&calldata, false, &[], Spec::default()),
+        21_000 + 2 * 4 + 3 * 16
+    );
+}
+
+#[test]
+fn intrinsic_gas_with_access_list() {
+    let access_list = vec![
+        (
+            Address::default(),
+            vec![U256::default(), U256 { lo: 1, hi: 0 }],
+        ),
+        (Address([1; 20]), vec![]),
+    ];
+    assert_eq!(
+        intrinsic_gas(&[], false, &access_list, Spec::default()),
+        21_000 + 2 * 2_400 + 2 * 1_900
+    );
+}
+
+#[test]
+fn intrinsic_gas_of_creation_with_init_code() {
+    // 33 non-zero bytes of init code span 2 words
+    let init_code = [0x60; 33];
+    assert_eq!(
+        intrinsic_gas(&init_code, true, &[], Spec::default()),
+        53_000 + 33 * 16 + 2 * 2
+    );
+}
+
+#[test]
+fn intrinsic_gas_of_creation_before_shanghai_has_no_init_code_cost() {
+    let init_code = [0x60; 33];
+    assert_eq!(
+        intrinsic_gas(&init_code, true, &[], Spec::Merge),
+        53_000 + 33 * 16
+    );
+}
+
+#[test]
+fn env_intrinsic_gas_counts_zero_and_non_zero_calldata_bytes() {
+    assert_eq!(Env::default().intrinsic_gas(), 21_000);
+
+    let env = Env::builder().calldata(vec![0x00; 3]).build();
+    assert_eq!(env.intrinsic_gas(), 21_000 + 3 * 4);
+
+    let env = Env::builder().calldata(vec![0xff; 3]).build();
+    assert_eq!(env.intrinsic_gas(), 21_000 + 3 * 16);
+
+    let env = Env::builder().calldata(vec![0x00, 0xff, 0x00]).build();
+    assert_eq!(env.intrinsic_gas(), 21_000 + 2 * 4 + 16);
+}
+
+#[test]
+fn execute_with_intrinsic_gas_charges_it_upfront() {
+    // PUSH0, POP, STOP
+    let bytecode = [0x5f, 0x50, 0x00];
+    let env = Env::builder().calldata(vec![0x00, 0x01]).build();
+    let intrinsic_gas = 21_000 + 4 + 16;
+    let execution_gas = (gas_cost::PUSH0 + gas_cost::POP) as u64;
+
+    let result =
+        execute_with_intrinsic_gas(&bytecode, env.clone(), intrinsic_gas + execution_gas).unwrap();
+    assert!(result.is_success());
+    assert_eq!(result.gas_remaining(), 0);
+    let ExecutionResult::Success { gas_used, .. } = result else {
+        unreachable!()
+    };
+    assert_eq!(gas_used, intrinsic_gas + execution_gas);
+
+    // Not enough for the execution after charging the intrinsic gas
+    let result =
+        execute_with_intrinsic_gas(&bytecode, env.clone(), intrinsic_gas + execution_gas - 1)
+            .unwrap();
+    assert!(result.is_halt());
+
+    // Not even enough for the intrinsic gas
+    let result = execute_with_intrinsic_gas(&bytecode, env.clone(), intrinsic_gas - 1).unwrap();
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+
+    // The raw path doesn't charge it
+    let result = execute(&bytecode, env, execution_gas).unwrap();
+    assert!(result.is_success());
+}
+
+#[test]
+fn execute_bytecode() {
+    // PUSH1 5, PUSH1 3, ADD, PUSH0, MSTORE, PUSH1 32, PUSH0, RETURN
+    let bytecode = [
+        0x60, 0x05, 0x60, 0x03, 0x01, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3,
+    ];
+
+    let result = execute(&bytecode, Env::default(), 999_999).unwrap();
+
+    assert!(result.is_success());
+    let mut expected = [0_u8; 32];
+    expected[31] = 8;
+    assert_eq!(result.return_data().unwrap(), expected);
+}
+
+#[test]
+fn execute_empty_bytecode_stops() {
+    let initial_gas = 999_999;
+
+    let result = execute(&[], Env::default(), initial_gas).unwrap();
+
+    assert_eq!(
+        result,
+        ExecutionResult::Success {
+            return_data: vec![],
+            gas_remaining: initial_gas,
+            gas_used: 0,
+            gas_refunded: 0,
+            logs: vec![],
+            opcode_counts: None,
+        }
+    );
+}
+
+#[test]
+fn codesize_is_the_length_of_the_bytecode() {
+    // CODESIZE, PUSH0, MSTORE, PUSH1 32, PUSH0, RETURN, STOP, followed by a truncated PUSH2
+    let bytecode = [0x38, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3, 0x00, 0x61, 0xff];
+
+    let result = execute(&bytecode, Env::default(), 999_999).unwrap();
+
+    assert!(result.is_success());
+    let mut expected = [0_u8; 32];
+    expected[31] = bytecode.len() as u8;
+    assert_eq!(result.return_data().unwrap(), expected);
+}
+
+#[test]
+fn execute_invalid_opcode_halts_consuming_all_gas() {
+    // PUSH1 1, INVALID
+    let bytecode = [0x60, 0x01, 0xfe];
+
+    let result = execute(&bytecode, Env::default(), 999_999).unwrap();
+
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+}
+
+#[test]
+fn execute_unassigned_opcode_halts_consuming_all_gas() {
+    // 0x0c isn't assigned to any opcode, so it behaves like INVALID
+    let bytecode = [0x60, 0x01, 0x0c];
+
+    let result = execute(&bytecode, Env::default(), 999_999).unwrap();
+
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+}
+
+#[test]
+fn jump_to_non_jumpdest_halts() {
+    // [00] PUSH1 3
+    // [02] JUMP
+    // [03] STOP
+    // [04] JUMPDEST
+    let bytecode = [0x60, 0x03, 0x56, 0x00, 0x5b];
+
+    let result = execute(&bytecode, Env::default(), 999_999).unwrap();
+
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+}
+
+#[test]
+fn jump_into_push_data_halts() {
+    // [00] PUSH1 1   // jumps to the PUSH1 immediate
+    // [02] JUMP
+    // [03] JUMPDEST
+    let bytecode = [0x60, 0x01, 0x56, 0x5b];
+
+    let result = execute(&bytecode, Env::default(), 999_999).unwrap();
+
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+}
+
+#[test]
+fn jump_to_jumpdest_byte_in_push_data_halts() {
+    // [00] PUSH1 0x5b
+    // [02] PUSH1 1    // jumps to the 0x5b byte in the first PUSH1
+    // [04] JUMP
+    // [05] JUMPDEST
+    let bytecode = [0x60, 0x5b, 0x60, 0x01, 0x56, 0x5b];
+
+    let program = Program::parse(&bytecode).unwrap();
+    let jumpdests: Vec<_> = program.valid_jumpdests().iter().copied().collect();
+    assert_eq!(jumpdests, [5]);
+
+    let result = execute(&bytecode, Env::default(), 999_999).unwrap();
+
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+}
+
+#[test]
+fn truncated_push_is_zero_padded() {
+    // PUSH32 with only 3 bytes left in the code
+    let bytecode = [0x7f, 0xaa, 0xbb, 0xcc];
+
+    let program = Program::from_bytecode(&bytecode).unwrap();
+
+    // The available bytes go in the high position, and the rest are zeros
+    let mut immediate = vec![0xaa, 0xbb, 0xcc];
+    immediate.resize(32, 0);
+    assert_eq!(
+        program.operations(),
+        [Operation::Push((32, BigUint::from_bytes_be(&immediate)))]
+    );
+    // The padding isn't part of the code
+    assert_eq!(program.to_bytecode(), bytecode);
+
+    let result = execute(&bytecode, Env::default(), 999_999).unwrap();
+    assert!(result.is_success());
+}
+
+#[test]
+fn parse_exposes_operations_and_jumpdests() {
+    // [00] PUSH1 4
+    // [02] JUMP
+    // [03] STOP
+    // [04] JUMPDEST
+    // [05] PUSH2 0x5b5b
+    // [08] JUMPDEST
+    // [09] STOP
+    let bytecode = [0x60, 0x04, 0x56, 0x00, 0x5b, 0x61, 0x5b, 0x5b, 0x5b, 0x00];
+
+    let program = Program::parse(&bytecode).unwrap();
+
+    assert_eq!(program.operations().len(), 7);
+    assert_eq!(program.code_size(), bytecode.len());
+    let jumpdests: Vec<_> = program.valid_jumpdests().iter().copied().collect();
+    assert_eq!(jumpdests, [4, 8]);
+}
+
+#[test]
+fn parse_unsupported_bytecode_fails() {
+    assert!(Program::parse(&[0x49]).is_err());
+}
+
+#[test]
+fn decoding_and_encoding_gives_back_the_bytecode() {
+    let push32_zero = [vec![0x7f], vec![0x00; 32]].concat();
+    let corpus: [&[u8]; 6] = [
+        // PUSH2 0x0001, keeping the leading zero
+        &[0x61, 0x00, 0x01],
+        // PUSH32 0
+        &push32_zero,
+        // PUSH1 0x5b, JUMPDEST, PUSH0, STOP
+        &[0x60, 0x5b, 0x5b, 0x5f, 0x00],
+        // PUSH20 with leading zeros, BALANCE
+        &[
+            0x73, 0x00, 0x00, 0x00, 0xaa, 0xbb, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x01, 0x31,
+        ],
+        // INVALID, CALLDATASIZE, LOG4
+        &[0xfe, 0x36, 0xa4],
+        // truncated PUSH4
+        &[0x63, 0x00, 0x01],
+    ];
+
+    for bytecode in corpus {
+        let program = Program::parse(bytecode).unwrap();
+        assert_eq!(program.to_bytecode(), bytecode);
+    }
+}
+
+#[test]
+fn analyze_counts_each_opcode() {
+    // PUSH1 1, PUSH1 2, ADD, PUSH1 3, ADD, 0x0c (unassigned), STOP
+    let bytecode = [0x60, 0x01, 0x60, 0x02, 0x01, 0x60, 0x03, 0x01, 0x0c, 0x00];
+
+    let stats = Program::parse(&bytecode).unwrap().analyze();
+
+    let expected = BTreeMap::from([(0x00, 1), (0x01, 2), (0x60, 3), (0xfe, 1)]);
+    assert_eq!(stats.opcode_counts, expected);
+}
+
+#[test]
+fn analyze_bytecode_lists_unsupported_opcodes() {
+    // BLOBHASH, PUSH0, BLOBBASEFEE, STOP
+    let bytecode = [0x49, 0x5f, 0x4a, 0x00];
+
+    let stats = Program::analyze_bytecode(&bytecode);
+
+    assert_eq!(stats.opcode_counts, BTreeMap::from([(0x00, 1), (0x5f, 1)]));
+    assert_eq!(stats.unsupported_opcodes, vec![0x49, 0x4a]);
+}
+
+#[test]
+fn parse_error_lists_unsupported_opcodes() {
+    // BLOBHASH, PUSH0, BLOBBASEFEE
+    let bytecode = [0x49, 0x5f, 0x4a];
+
+    let error = Program::parse(&bytecode).unwrap_err();
+
+    assert_eq!(error.opcodes(), vec![0x49, 0x4a]);
+}
+
+#[test]
+fn program_rejects_push_values_wider_than_the_immediate() {
+    let value = BigUint::from(0x1234_u16);
+
+    let error = Program::new(vec![Operation::Push((1, value.clone()))]).unwrap_err();
+
+    assert!(matches!(
+        error,
+        ParseError::PushTooWide { width: 1, value: ref wide } if *wide == value
+    ));
+    assert!(Program::new(vec![Operation::Push((2, value))]).is_ok());
+}
+
+#[test]
+fn execute_unsupported_bytecode_returns_error() {
+    // BLOBHASH is a valid opcode, but it isn't supported yet
+    let bytecode = [0x49];
+
+    let result = execute(&bytecode, Env::default(), 999_999);
+
+    assert!(matches!(result, Err(EvmError::ParseError(_))));
+}
+
+fn run_with_balances(
+    operations: Vec<Operation>,
+    env: Env,
+    balances: HashMap<Address, U256>,
+) -> ExecutionResult {
+    let mut context = SyscallContext::with_env(env);
+    context.balances = balances;
+
+    run_program_with(operations, context, 999_999).get_result()
+}
+
+/// Returns a program that pushes the balance of `address` and returns it
+fn balance_program(address: &Address) -> Vec<Operation> {
+    vec![
+        Operation::Push((20, BigUint::from_bytes_be(&address.0))),
+        Operation::Balance,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]
+}
+
+#[test]
+fn balance_of_seeded_account() {
+    let account = Address([0x11; 20]);
+    let balances = HashMap::from([(account.clone(), U256 { hi: 0, lo: 1234 })]);
+
+    let result = run_with_balances(balance_program(&account), Env::default(), balances);
+
+    assert!(result.is_success());
+    let return_data = result.return_data().unwrap();
+    assert_eq!(BigUint::from_bytes_be(return_data), 1234_u32.into());
+}
+
+#[test]
+fn balance_of_unknown_account_is_zero() {
+    let result = run_with_balances(
+        balance_program(&Address([0x22; 20])),
+        Env::default(),
+        HashMap::new(),
+    );
+
+    assert!(result.is_success());
+    assert_eq!(result.return_data().unwrap(), [0_u8; 32]);
+}
+
+#[test]
+fn balance_cold_and_warm_access_gas() {
+    let account = Address([0x11; 20]);
+    let push_and_balance = [
+        Operation::Push((20, BigUint::from_bytes_be(&account.0))),
+        Operation::Balance,
+    ];
+    let once = push_and_balance.to_vec();
+    let twice = [push_and_balance.clone(), push_and_balance].concat();
+
+    let once_result = run_with_balances(once, Env::default(), HashMap::new());
+    let twice_result = run_with_balances(twice, Env::default(), HashMap::new());
+
+    assert!(once_result.is_success());
+    assert!(twice_result.is_success());
+    // The first access is cold, the second one is warm
+    let cold_cost = 999_999 - once_result.gas_remaining();
+    let warm_cost = once_result.gas_remaining() - twice_result.gas_remaining();
+    assert_eq!(
+        cold_cost,
+        (gas_cost::PUSHN + gas_cost::COLD_ACCOUNT_ACCESS) as u64
+    );
+    assert_eq!(
+        warm_cost,
+        (gas_cost::PUSHN + gas_cost::WARM_STORAGE_READ) as u64
+    );
+}
+
+#[test]
+fn balance_of_executing_contract_is_warm() {
+    let mut env = Env::default();
+    env.tx.to = Address([0x33; 20]);
+    let operations = vec![
+        Operation::Push((20, BigUint::from_bytes_be(&env.tx.to.0))),
+        Operation::Balance,
+    ];
+
+    let result = run_with_balances(operations, env, HashMap::new());
+
+    assert!(result.is_success());
+    assert_eq!(
+        999_999 - result.gas_remaining(),
+        (gas_cost::PUSHN + gas_cost::WARM_STORAGE_READ) as u64
+    );
+}
+
+#[test]
+fn selfbalance() {
+    let mut env = Env::default();
+    env.tx.to = Address([0x33; 20]);
+    let balances = HashMap::from([
+        (env.tx.to.clone(), U256 { hi: 1, lo: 2 }),
+        (Address([0x11; 20]), U256 { hi: 0, lo: 99 }),
+    ]);
+    let operations = vec![
+        Operation::SelfBalance,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+
+    let result = run_with_balances(operations, env, balances);
+
+    assert!(result.is_success());
+    let mut expected = [0_u8; 32];
+    expected[15] = 1;
+    expected[31] = 2;
+    assert_eq!(result.return_data().unwrap(), expected);
+}
+
+fn transact_with_spec(operations: Vec<Operation>, spec: Spec) -> ExecutionResult {
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.cfg.spec = spec;
+    let evm = Evm::new(env, Program::from(operations));
+
+    evm.transact().unwrap()
+}
+
+#[test]
+fn push0_is_only_available_from_shanghai() {
+    let program = vec![Operation::Push0];
+
+    assert!(!transact_with_spec(program.clone(), Spec::Merge).is_success());
+    assert!(transact_with_spec(program, Spec::Shanghai).is_success());
+}
+
+#[test]
+fn basefee_is_only_available_from_london() {
+    let program = vec![Operation::BaseFee];
+
+    assert!(!transact_with_spec(program.clone(), Spec::Berlin).is_success());
+    assert!(transact_with_spec(program, Spec::London).is_success());
+}
+
+#[test]
+fn transient_storage_is_only_available_from_cancun() {
+    let program = vec![Operation::Push0, Operation::Tload];
+
+    assert!(!transact_with_spec(program.clone(), Spec::Shanghai).is_success());
+    assert!(transact_with_spec(program, Spec::Cancun).is_success());
+}
+
+#[test]
+fn sload_gas_depends_on_spec() {
+    let program = vec![Operation::Push((1, BigUint::ZERO)), Operation::Sload];
+
+    let istanbul_result = transact_with_spec(program.clone(), Spec::Istanbul);
+    let berlin_result = transact_with_spec(program, Spec::Berlin);
+
+    assert!(istanbul_result.is_success());
+    assert!(berlin_result.is_success());
+    assert_eq!(
+        999_999 - istanbul_result.gas_remaining(),
+        (gas_cost::PUSHN + gas_cost::SLOAD_ISTANBUL) as u64
+    );
+    assert_eq!(
+        999_999 - berlin_result.gas_remaining(),
+        (gas_cost::PUSHN + gas_cost::COLD_SLOAD) as u64
+    );
+}
+
+/// Returns a program that calls [`CALLEE_ADDRESS`] with no gas, sending it `value` wei
+fn call_without_gas_program(value: u8) -> Vec<Operation> {
+    vec![
+        Operation::Push((1, BigUint::ZERO)), // ret size
+        Operation::Push((1, BigUint::ZERO)), // ret offset
+        Operation::Push((1, BigUint::ZERO)), // args size
+        Operation::Push((1, BigUint::ZERO)), // args offset
+        Operation::Push((1, value.into())),
+        Operation::Push((20, BigUint::from_bytes_be(&CALLEE_ADDRESS.0))),
+        Operation::Push((1, BigUint::ZERO)), // gas
+        Operation::Call,
+    ]
+}
+
+#[test]
+fn call_access_gas_depends_on_spec() {
+    let program = call_without_gas_program(0);
+
+    let istanbul_result = transact_with_spec(program.clone(), Spec::Istanbul);
+    let berlin_result = transact_with_spec(program, Spec::Berlin);
+
+    assert!(istanbul_result.is_success());
+    assert!(berlin_result.is_success());
+    assert_eq!(
+        999_999 - istanbul_result.gas_remaining(),
+        (gas_cost::PUSHN * 7 + gas_cost::BALANCE_ISTANBUL) as u64
+    );
+    assert_eq!(
+        999_999 - berlin_result.gas_remaining(),
+        (gas_cost::PUSHN * 7 + gas_cost::COLD_ACCOUNT_ACCESS) as u64
+    );
+}
+
+#[test]
+fn call_warms_the_callee_address() {
+    let once = call_without_gas_program(0);
+    let twice = [once.clone(), vec![Operation::Pop], once.clone()].concat();
+
+    let once_result = transact_with_spec(once, Spec::Berlin);
+    let twice_result = transact_with_spec(twice, Spec::Berlin);
+
+    assert!(once_result.is_success());
+    assert!(twice_result.is_success());
+    // The second call is a warm access
+    assert_eq!(
+        once_result.gas_remaining() - twice_result.gas_remaining(),
+        (gas_cost::POP + gas_cost::PUSHN * 7 + gas_cost::WARM_STORAGE_READ) as u64
+    );
+}
+
+#[test]
+fn call_with_value_to_empty_account_gas() {
+    let result = transact_with_spec(call_without_gas_program(1), Spec::Berlin);
+
+    assert!(result.is_success());
+    // The stipend is given back, since the callee doesn't use it
+    let expected_gas = gas_cost::PUSHN * 7
+        + gas_cost::COLD_ACCOUNT_ACCESS
+        + gas_cost::CALL_VALUE_TRANSFER
+        + gas_cost::CALL_NEW_ACCOUNT
+        - gas_cost::CALL_STIPEND as i64;
+    assert_eq!(999_999 - result.gas_remaining(), expected_gas as u64);
+}
+
+/// Grows the memory one word at a time, returning how many times it was moved
+fn count_memory_moves(context: &mut SyscallContext, words: u32) -> usize {
+    let mut moves = 0;
+    let mut memory_ptr = context.extend_memory(32);
+    for word in 2..=words {
+        let new_memory_ptr = context.extend_memory(word * 32);
+        if new_memory_ptr != memory_ptr {
+            moves += 1;
+            memory_ptr = new_memory_ptr;
+        }
+    }
+    moves
+}
+
+#[test]
+fn memory_grows_geometrically() {
+    let mut context = SyscallContext::default();
+
+    let moves = count_memory_moves(&mut context, 1_000);
+
+    // Doubling the capacity from 32 bytes takes 10 steps to fit 1000 words
+    assert!(moves <= 10, "memory was moved {moves} times");
+}
+
+#[test]
+fn preallocated_memory_is_never_moved() {
+    let mut context = SyscallContext::with_capacity(Env::default(), 1_000 * 32);
+
+    let moves = count_memory_moves(&mut context, 1_000);
+
+    assert_eq!(moves, 0);
+}
+
+#[test]
+fn return_data_past_memory_end_is_empty() {
+    let mut context = SyscallContext::default();
+    context.extend_memory(32);
+    context.write_result(16, 64, 1_000, ExitStatusCode::Return.to_u8());
+
+    assert!(context.return_values().is_empty());
+    let result = context.get_result();
+    assert!(result.is_success());
+    assert_eq!(result.return_data(), Some([].as_slice()));
+}
+
+#[test]
+fn copy_returndata_out_of_bounds_copies_nothing() {
+    let mut context = SyscallContext::default();
+    context.extend_memory(32);
+
+    // There's no return data to copy from
+    assert_eq!(context.copy_returndata(0, 0, 32), 0);
+    // but empty ranges are always in bounds
+    assert_eq!(context.copy_returndata(16, 0, 0), 1);
+}
+
+#[test]
+fn copy_ext_code_past_memory_end_copies_nothing() {
+    let mut context = SyscallContext::default();
+    context.extend_memory(32);
+
+    let access_gas = context.copy_ext_code(&U256::default(), &U256::default(), 16, 32);
+
+    assert_eq!(access_gas, -1);
+}
+
+#[test]
+fn call_with_ranges_past_memory_end_fails_consuming_its_gas() {
+    let mut context = SyscallContext::default();
+    context.extend_memory(32);
+    let address = U256::from(0x1234);
+    let mut gas_remaining = 1;
+
+    let args_past_end = context.call(
+        1_000,
+        &address,
+        &U256::default(),
+        16,
+        32,
+        0,
+        0,
+        &mut gas_remaining,
+    );
+    assert_eq!((args_past_end, gas_remaining), (0, 0));
+
+    gas_remaining = 1;
+
+    let ret_past_end = context.call(
+        1_000,
+        &address,
+        &U256::default(),
+        0,
+        0,
+        32,
+        1,
+        &mut gas_remaining,
+    );
+    assert_eq!((ret_past_end, gas_remaining), (0, 0));
+}
+
+#[test]
+fn create_with_init_code_past_memory_end_fails_consuming_its_gas() {
+    let mut context = SyscallContext::default();
+    context.extend_memory(32);
+    let mut gas_remaining = 1;
+    let mut address = U256::from(1);
+
+    context.create(
+        16,
+        32,
+        &U256::default(),
+        1_000,
+        &mut gas_remaining,
+        &mut address,
+    );
+    assert_eq!((gas_remaining, address), (0, U256::default()));
+
+    gas_remaining = 1;
+    address = U256::from(1);
+
+    context.create2(
+        16,
+        32,
+        &U256::default(),
+        &U256::default(),
+        1_000,
+        &mut gas_remaining,
+        &mut address,
+    );
+    assert_eq!((gas_remaining, address), (0, U256::default()));
+}
+
+#[test]
+fn keccak256_and_logs_past_memory_end_fail() {
+    let mut context = SyscallContext::default();
+    context.extend_memory(32);
+    let mut hash = U256::default();
+
+    assert_eq!(context.keccak256_hasher(16, 32, &mut hash), 0);
+    assert_eq!(context.append_log(16, 32), 0);
+    assert_eq!(
+        context.append_log_with_one_topic(16, 32, &U256::default()),
+        0
+    );
+    // but empty ranges up to the memory end are in bounds
+    assert_eq!(context.keccak256_hasher(32, 0, &mut hash), 1);
+    assert_eq!(context.append_log(32, 0), 1);
+}
+
+#[test]
+fn gas_remaining_above_the_initial_gas_counts_as_no_gas_used() {
+    // The result is written directly, so the initial gas is never set
+    let mut context = SyscallContext::default();
+    context.write_result(0, 0, 1_000, ExitStatusCode::Revert.to_u8());
+
+    let ExecutionResult::Revert { gas_used, .. } = context.get_result() else {
+        panic!("expected a revert");
+    };
+    assert_eq!(gas_used, 0);
+}
+
+fn run_with_tracer(operations: Vec<Operation>) -> (ExecutionResult, Vec<StepInfo>) {
+    let steps = Rc::new(RefCell::new(Vec::new()));
+    let tracer_steps = steps.clone();
+    let context = SyscallContext::with_env(Env::default())
+        .with_tracer(move |step| tracer_steps.borrow_mut().push(step));
+    let context = run_program_with(operations, context, 999_999);
+
+    let steps = steps.take();
+    (context.get_result(), steps)
+}
+
+#[test]
+fn tracer_fires_once_per_executed_operation() {
+    let operations = vec![
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Push((1, BigUint::from(2_u8))),
+        Operation::Add,
+        Operation::Stop,
+    ];
+
+    let (result, steps) = run_with_tracer(operations);
+
+    assert!(result.is_success());
+    let pcs: Vec<_> = steps.iter().map(|step| step.pc).collect();
+    assert_eq!(pcs, [0, 2, 4, 5]);
+    let opcodes: Vec<_> = steps.iter().map(|step| step.opcode).collect();
+    assert_eq!(opcodes, [0x60, 0x60, 0x01, 0x00]);
+    assert_eq!(steps[2].stack, [U256::from(1), U256::from(2)]);
+    assert_eq!(steps[3].stack, [U256::from(3)]);
+    assert_eq!(steps[0].gas_remaining, 999_999);
+    assert_eq!(steps[2].gas_remaining, 999_999 - 2 * gas_cost::PUSHN as u64);
+    assert!(steps.iter().all(|step| step.depth == 0));
+}
+
+#[test]
+fn tracer_follows_jumps() {
+    let operations = vec![
+        Operation::Push((1, BigUint::from(4_u8))),
+        Operation::Jump,
+        Operation::Invalid,
+        Operation::Jumpdest { pc: 4 },
+        Operation::Stop,
+    ];
+
+    let (result, steps) = run_with_tracer(operations);
+
+    assert!(result.is_success());
+    let pcs: Vec<_> = steps.iter().map(|step| step.pc).collect();
+    assert_eq!(pcs, [0, 2, 4, 5]);
+}
+
+/// Runs `operations` with a step limit, returning the result and how many steps ran.
+fn run_with_step_limit(operations: Vec<Operation>, step_limit: u64) -> (ExecutionResult, usize) {
+    let steps = Rc::new(RefCell::new(0));
+    let tracer_steps = steps.clone();
+    let context = SyscallContext::with_env(Env::default())
+        .with_step_limit(step_limit)
+        .with_tracer(move |_| *tracer_steps.borrow_mut() += 1);
+    let context = run_program_with(operations, context, 999_999);
+
+    let steps = steps.take();
+    (context.get_result(), steps)
+}
+
+#[test]
+fn step_limit_halts_a_tight_loop() {
+    let operations = vec![
+        Operation::Jumpdest { pc: 0 },
+        Operation::Push0,
+        Operation::Jump,
+    ];
+
+    let (result, steps) = run_with_step_limit(operations, 30);
+
+    // The gas would last for thousands of iterations
+    assert!(result.is_halt());
+    assert_eq!(steps, 30);
+}
+
+#[test]
+fn step_limit_allows_exactly_that_many_operations() {
+    let operations = vec![
+        Operation::Push((1, BigUint::from(1_u8))),
+        Operation::Push((1, BigUint::from(2_u8))),
+        Operation::Add,
+        Operation::Stop,
+    ];
+
+    let (result, _) = run_with_step_limit(operations.clone(), 4);
+    assert!(result.is_success());
+
+    let (result, steps) = run_with_step_limit(operations, 3);
+    assert!(result.is_halt());
+    assert_eq!(steps, 3);
+}
+
+fn run_with_initial_stack(operations: Vec<Operation>, stack: Vec<U256>) -> ExecutionResult {
+    let context = SyscallContext::with_env(Env::default()).with_initial_stack(stack);
+
+    run_program_with(operations, context, 999_999).get_result()
+}
+
+#[test]
+fn add_with_initial_stack() {
+    let operations = vec![
+        Operation::Add,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+
+    let result = run_with_initial_stack(operations, vec![U256::from(40), U256::from(2)]);
+
+    assert!(result.is_success());
+    let sum = BigUint::from_bytes_be(result.return_data().unwrap());
+    assert_eq!(sum, 42_u8.into());
+}
+
+#[test]
+fn initial_stack_counts_towards_stack_underflow() {
+    let result = run_with_initial_stack(vec![Operation::Add], vec![U256::from(1)]);
+
+    assert!(result.is_halt());
+}
+
+fn selfdestruct_contract_address() -> Address {
+    Address([0xcc; 20])
+}
+
+/// Runs a SELFDESTRUCT to `beneficiary` from a contract holding 100 wei
+fn run_selfdestruct(beneficiary: &Address, spec: Spec) -> SyscallContext {
+    let program = vec![
+        Operation::Push((20, BigUint::from_bytes_be(&beneficiary.0))),
+        Operation::SelfDestruct,
+        // never reached, since SELFDESTRUCT stops the execution
+        Operation::Invalid,
+    ];
+    let mut env = Env::default();
+    env.tx.to = selfdestruct_contract_address();
+    env.cfg.spec = spec;
+    let mut context = SyscallContext::with_env(env);
+    context.balances = HashMap::from([(selfdestruct_contract_address(), U256::from(100))]);
+
+    run_program_with(program, context, 999_999)
+}
+
+#[test]
+fn selfdestruct_sends_balance_and_stops() {
+    let beneficiary = Address([0xbb; 20]);
+
+    let context = run_selfdestruct(&beneficiary, Spec::Shanghai);
+
+    assert!(context.get_result().is_success());
+    assert_eq!(context.balances[&beneficiary], U256::from(100));
+    assert!(!context
+        .balances
+        .contains_key(&selfdestruct_contract_address()));
+    assert!(context
+        .destroyed_accounts
+        .contains(&selfdestruct_contract_address()));
+}
+
+#[test]
+fn selfdestruct_only_sends_balance_from_cancun() {
+    // The contract wasn't created in the same transaction (EIP-6780)
+    let beneficiary = Address([0xbb; 20]);
+
+    let context = run_selfdestruct(&beneficiary, Spec::Cancun);
+
+    assert!(context.get_result().is_success());
+    assert_eq!(context.balances[&beneficiary], U256::from(100));
+    assert!(context.destroyed_accounts.is_empty());
+}
+
+#[test]
+fn selfdestruct_to_itself_keeps_balance_from_cancun() {
+    let context = run_selfdestruct(&selfdestruct_contract_address(), Spec::Cancun);
+
+    assert!(context.get_result().is_success());
+    assert_eq!(
+        context.balances[&selfdestruct_contract_address()],
+        U256::from(100)
+    );
+}
+
+#[test]
+fn selfdestruct_to_cold_empty_account_gas() {
+    let context = run_selfdestruct(&Address([0xbb; 20]), Spec::Cancun);
+
+    let ExecutionResult::Success { gas_used, .. } = context.get_result() else {
+        panic!("SELFDESTRUCT should succeed");
+    };
+    let expected_gas = gas_cost::PUSHN
+        + gas_cost::SELFDESTRUCT
+        + gas_cost::COLD_ACCOUNT_ACCESS
+        + gas_cost::SELFDESTRUCT_NEW_ACCOUNT;
+    assert_eq!(gas_used, expected_gas as u64);
+}
+
+/// Runs `program` from `creator`, returning the stack top through memory
+fn run_create(program: Vec<Operation>, creator: &Address) -> SyscallContext {
+    let program = return_stack_top(program);
+    let mut env = Env::default();
+    env.tx.to = creator.clone();
+
+    run_program_with(program, SyscallContext::with_env(env), 999_999)
+}
+
+/// Appends to `program` the operations that return its stack top
+fn return_stack_top(mut program: Vec<Operation>) -> Vec<Operation> {
+    program.extend([
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+    program
+}
+
+/// Returns `address` left-padded to 32 bytes
+fn address_word(address: &Address) -> Vec<u8> {
+    let mut word = vec![0; 12];
+    word.extend_from_slice(&address.0);
+    word
+}
+
+#[test]
+fn create_deploys_returned_runtime_code() {
+    let creator = Address([
+        0x6a, 0xc7, 0xea, 0x33, 0xf8, 0x83, 0x1e, 0xa9, 0xdc, 0xc5, 0x33, 0x93, 0xaa, 0xa8, 0x8b,
+        0x25, 0xa7, 0x85, 0xdb, 0xf0,
+    ]);
+    // The address of the first contract created by `creator`
+    let expected_address = Address([
+        0xcd, 0x23, 0x4a, 0x47, 0x1b, 0x72, 0xba, 0x2f, 0x1c, 0xcf, 0x0a, 0x70, 0xfc, 0xab, 0xa6,
+        0x48, 0xa5, 0xee, 0xcd, 0x8d,
+    ]);
+    // Returns the 1-byte runtime code 0x2A
+    let init_code = [0x60, 0x2a, 0x60, 0x00, 0x53, 0x60, 0x01, 0x60, 0x00, 0xf3];
+    let program = vec![
+        Operation::Push((10, BigUint::from_bytes_be(&init_code))),
+        Operation::Push0,
+        Operation::Mstore,
+        // size, offset, value
+        Operation::Push((1, 10_u8.into())),
+        Operation::Push((1, 22_u8.into())),
+        Operation::Push0,
+        Operation::Create,
+    ];
+
+    let context = run_create(program, &creator);
+
+    let result = context.get_result();
+    assert!(result.is_success());
+    assert_eq!(
+        result.return_data(),
+        Some(address_word(&expected_address).as_slice())
+    );
+    assert_eq!(context.deployed_code[&expected_address], vec![0x2a]);
+    assert_eq!(context.nonces[&creator], 1);
+    assert_eq!(context.nonces[&expected_address], 1);
+}
+
+#[test]
+fn create2_address_follows_eip_1014() {
+    // Example from EIP-1014: init code 0x00 and salt 0
+    let creator = Address([
+        0xde, 0xad, 0xbe, 0xef, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x00, 0x00, 0x00, 0x00,
+    ]);
+    let expected_address = Address([
+        0xb9, 0x28, 0xf6, 0x9b, 0xb1, 0xd9, 0x1c, 0xd6, 0x52, 0x74, 0xe3, 0xc7, 0x9d, 0x89, 0x86,
+        0x36, 0x29, 0x84, 0xfd, 0xa3,
+    ]);
+    let program = vec![
+        // salt, size, offset, value
+        Operation::Push0,
+        Operation::Push((1, 1_u8.into())),
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Create2,
+    ];
+
+    let context = run_create(program, &creator);
+
+    let result = context.get_result();
+    assert!(result.is_success());
+    assert_eq!(
+        result.return_data(),
+        Some(address_word(&expected_address).as_slice())
+    );
+    // STOP deploys no code
+    assert!(!context.deployed_code.contains_key(&expected_address));
+}
+
+#[test]
+fn create_with_reverting_init_code_pushes_zero() {
+    let creator = Address([0xcc; 20]);
+    // PUSH0 PUSH0 REVERT
+    let init_code = [0x5f, 0x5f, 0xfd];
+    let program = vec![
+        Operation::Push((3, BigUint::from_bytes_be(&init_code))),
+        Operation::Push0,
+        Operation::Mstore,
+        // size, offset, value
+        Operation::Push((1, 3_u8.into())),
+        Operation::Push((1, 29_u8.into())),
+        Operation::Push0,
+        Operation::Create,
+    ];
+
+    let context = run_create(program, &creator);
+
+    let result = context.get_result();
+    assert!(result.is_success());
+    assert_eq!(result.return_data(), Some([0; 32].as_slice()));
+    assert!(context.deployed_code.is_empty());
+    // The nonce is used even if the creation fails
+    assert_eq!(context.nonces[&creator], 1);
+}
+
+#[test]
+fn codesize_in_init_code_is_the_init_code_length() {
+    let creator = Address([0xcc; 20]);
+    // CODESIZE PUSH0 MSTORE PUSH1 32 PUSH0 RETURN: deploys the init code size as a word
+    let init_code = [0x38, 0x5f, 0x52, 0x60, 0x20, 0x5f, 0xf3];
+    let program = vec![
+        Operation::Push((7, BigUint::from_bytes_be(&init_code))),
+        Operation::Push0,
+        Operation::Mstore,
+        // size, offset, value
+        Operation::Push((1, 7_u8.into())),
+        Operation::Push((1, 25_u8.into())),
+        Operation::Push0,
+        Operation::Create,
+    ];
+
+    let context = run_create(program, &creator);
+
+    let result = context.get_result();
+    assert!(result.is_success());
+    let address = Address::from(&U256::from_be_bytes(
+        result.return_data().unwrap().try_into().unwrap(),
+    ));
+    let mut expected = vec![0_u8; 32];
+    expected[31] = init_code.len() as u8;
+    assert_eq!(context.deployed_code[&address], expected);
+}
+
+const EXT_CODE_ADDRESS: Address = Address([0xc0; 20]);
+/// An account with balance, but no code
+const NO_CODE_ADDRESS: Address = Address([0xc1; 20]);
+
+/// PUSH1 42 PUSH1 0 MSTORE
+fn ext_code() -> Vec<u8> {
+    vec![0x60, 0x2a, 0x60, 0x00, 0x52]
+}
+
+/// Runs `operations` with the code of [`EXT_CODE_ADDRESS`] available
+fn run_with_ext_code(operations: Vec<Operation>) -> ExecutionResult {
+    let mut context = SyscallContext::with_env(Env::default())
+        .with_code_provider(HashMap::from([(EXT_CODE_ADDRESS, ext_code())]));
+    context.balances = HashMap::from([(NO_CODE_ADDRESS, U256::from(1))]);
+
+    run_program_with(operations, context, 999_999).get_result()
+}
+
+fn push_address(address: &Address) -> Operation {
+    Operation::Push((20, BigUint::from_bytes_be(&address.0)))
+}
+
+#[test]
+fn extcodesize_of_account_with_code() {
+    let program = return_stack_top(vec![
+        push_address(&EXT_CODE_ADDRESS),
+        Operation::ExtCodeSize,
+    ]);
+
+    let result = run_with_ext_code(program);
+
+    assert!(result.is_success());
+    let size = BigUint::from_bytes_be(result.return_data().unwrap());
+    assert_eq!(size, ext_code().len().into());
+}
+
+#[test]
+fn extcodesize_of_account_without_code() {
+    let program = return_stack_top(vec![push_address(&NO_CODE_ADDRESS), Operation::ExtCodeSize]);
+
+    let result = run_with_ext_code(program);
+
+    assert!(result.is_success());
+    assert_eq!(result.return_data(), Some([0; 32].as_slice()));
+}
+
+#[test]
+fn extcodesize_gas_is_cold_then_warm() {
+    let program = vec![
+        push_address(&EXT_CODE_ADDRESS),
+        Operation::ExtCodeSize,
+        push_address(&EXT_CODE_ADDRESS),
+        Operation::ExtCodeSize,
+    ];
+
+    let ExecutionResult::Success { gas_used, .. } = run_with_ext_code(program) else {
+        panic!("EXTCODESIZE should succeed");
+    };
+    let expected_gas =
+        2 * gas_cost::PUSHN + gas_cost::COLD_ACCOUNT_ACCESS + gas_cost::WARM_STORAGE_READ;
+    assert_eq!(gas_used, expected_gas as u64);
+}
+
+#[test]
+fn extcodecopy_pads_with_zeros() {
+    let program = vec![
+        // size, code offset, destination offset, address
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push((1, 1_u8.into())),
+        Operation::Push0,
+        push_address(&EXT_CODE_ADDRESS),
+        Operation::ExtCodeCopy,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+
+    let result = run_with_ext_code(program);
+
+    assert!(result.is_success());
+    let mut expected = ext_code()[1..].to_vec();
+    expected.resize(32, 0);
+    assert_eq!(result.return_data(), Some(expected.as_slice()));
+}
+
+#[test]
+fn extcodecopy_gas() {
+    let program = vec![
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Push0,
+        push_address(&EXT_CODE_ADDRESS),
+        Operation::ExtCodeCopy,
+    ];
+
+    let ExecutionResult::Success { gas_used, .. } = run_with_ext_code(program) else {
+        panic!("EXTCODECOPY should succeed");
+    };
+    // one word is copied, and the memory is extended by one word
+    let memory_cost = 3;
+    let expected_gas = 2 * gas_cost::PUSHN
+        + 2 * gas_cost::PUSH0
+        + gas_cost::COPY_PER_WORD
+        + memory_cost
+        + gas_cost::COLD_ACCOUNT_ACCESS;
+    assert_eq!(gas_used, expected_gas as u64);
+}
+
+#[test]
+fn extcodehash_of_account_with_code() {
+    let program = return_stack_top(vec![
+        push_address(&EXT_CODE_ADDRESS),
+        Operation::ExtCodeHash,
+    ]);
+
+    let result = run_with_ext_code(program);
+
+    assert!(result.is_success());
+    let expected = BigUint::parse_bytes(
+        b"32a428b89bfc619d507705d1de3f7364d4f52696d77e80e622545352d284b02c",
+        16,
+    )
+    .unwrap();
+    assert_eq!(
+        BigUint::from_bytes_be(result.return_data().unwrap()),
+        expected
+    );
+}
+
+#[test]
+fn extcodehash_of_account_without_code_is_hash_of_empty_code() {
+    let program = return_stack_top(vec![push_address(&NO_CODE_ADDRESS), Operation::ExtCodeHash]);
+
+    let result = run_with_ext_code(program);
+
+    assert!(result.is_success());
+    let expected = BigUint::parse_bytes(
+        b"c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+        16,
+    )
+    .unwrap();
+    assert_eq!(
+        BigUint::from_bytes_be(result.return_data().unwrap()),
+        expected
+    );
+}
+
+#[test]
+fn extcodehash_of_empty_account_is_zero() {
+    let program = return_stack_top(vec![
+        push_address(&Address([0xee; 20])),
+        Operation::ExtCodeHash,
+    ]);
+
+    let result = run_with_ext_code(program);
+
+    assert!(result.is_success());
+    assert_eq!(result.return_data(), Some([0; 32].as_slice()));
+}
+
+/// Runs BLOCKHASH for `number` at block 1000, knowing the hashes of blocks 700 to 1000
+fn run_blockhash(number: u64) -> Vec<u8> {
+    let program = Program::from(return_stack_top(vec![
+        Operation::Push((8, number.into())),
+        Operation::BlockHash,
+    ]));
+    let mut env = Env::default();
+    env.tx.gas_limit = 999_999;
+    env.block.number = 1000;
+    env.block.block_hashes = (700..=1000)
+        .map(|number| (number, U256::from(number + 1)))
+        .collect();
+    let evm = Evm::new(env, program);
+
+    let result = evm.transact().unwrap();
+
+    assert!(result.is_success());
+    result.return_data().unwrap().to_vec()
+}
+
+#[test]
+fn blockhash_of_recent_block() {
+    assert_eq!(BigUint::from_bytes_be(&run_blockhash(999)), 1000_u64.into());
+    // the oldest reachable block
+    assert_eq!(BigUint::from_bytes_be(&run_blockhash(744)), 745_u64.into());
+}
+
+#[test]
+fn blockhash_of_old_block_is_zero() {
+    assert_eq!(run_blockhash(743), [0; 32]);
+}
+
+#[test]
+fn blockhash_of_current_block_is_zero() {
+    assert_eq!(run_blockhash(1000), [0; 32]);
+    assert_eq!(run_blockhash(1001), [0; 32]);
+}
+
+/// Runs an MSTORE at `offset` with memory limited to 1 KiB
+fn run_mstore_with_memory_limit(offset: u16) -> ExecutionResult {
+    let operations = vec![
+        Operation::Push0,
+        Operation::Push((2, offset.into())),
+        Operation::Mstore,
+    ];
+    let context = SyscallContext::with_env(Env::default()).with_memory_limit(1024);
+
+    run_program_with(operations, context, u64::MAX / 2).get_result()
+}
+
+#[test]
+fn memory_within_limit() {
+    assert!(run_mstore_with_memory_limit(1024 - 32).is_success());
+}
+
+#[test]
+fn memory_past_limit_halts_despite_enough_gas() {
+    assert!(run_mstore_with_memory_limit(1024).is_halt());
+}
+
+/// A writer keeping what's written to it, shared with the test that installs it.
+#[cfg(feature = "debug-syscalls")]
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+#[cfg(feature = "debug-syscalls")]
+impl std::io::Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "debug-syscalls")]
+#[test]
+fn debug_print_stack_top_writes_the_value() {
+    let output = SharedBuffer::default();
+    let mut context = SyscallContext::default().with_debug_writer(output.clone());
+
+    context.debug_print_stack_top(&U256 { hi: 1, lo: 0x2a });
+
+    let printed = String::from_utf8(output.0.borrow().clone()).unwrap();
+    assert_eq!(printed, format!("stack top: 0x{:032x}{:032x}\n", 1, 0x2a));
+}
+
+#[cfg(feature = "debug-syscalls")]
+#[test]
+fn compiled_program_prints_the_stack_top_before_each_operation() {
+    let output = SharedBuffer::default();
+    let context = SyscallContext::default().with_debug_writer(output.clone());
+    let compiler = evm_mlir::context::Compiler::new().with_stack_top_printing(true);
+    let program = Program::from(vec![
+        Operation::Push((1, 1_u8.into())),
+        Operation::Push((1, 2_u8.into())),
+        Operation::Add,
+        Operation::Stop,
+    ]);
+
+    let module = compiler
+        .compile(&program)
+        .expect("failed to compile program");
+    let context = common::run_module_with(&module, context, 999_999);
+
+    assert!(context.get_result().is_success());
+    // Nothing is printed before the first PUSH1, since the stack is empty
+    let printed = String::from_utf8(output.0.borrow().clone()).unwrap();
+    let expected: String = [1, 2, 3]
+        .iter()
+        .map(|value| format!("stack top: 0x{value:064x}\n"))
+        .collect();
+    assert_eq!(printed, expected);
+}
@@ -1,36 +1,57 @@
 use evm_mlir::{
+    codegen::compile_to_bitcode,
     constants::gas_cost::{self, log_dynamic_gas_cost},
     context::Context,
+    errors::CodegenError,
     executor::Executor,
-    program::{Operation, Program},
-    syscall::{ExecutionResult, SyscallContext},
+    program::{Opcode, Operation, Program},
+    syscall::{ExecutionResult, FailureInfo, OpcodeCounts, SyscallContext, U256},
 };
 use num_bigint::{BigInt, BigUint};
 use rstest::rstest;
+use sha3::{Digest, Keccak256};
+use std::collections::HashMap;
 use tempfile::NamedTempFile;
 
+mod common;
+use common::run_program_with;
+
 fn run_program_get_result_with_gas(
     operations: Vec<Operation>,
     initial_gas: u64,
 ) -> ExecutionResult {
-    // Insert a return operation at the end of the program to verify top of stack.
-    let program = Program::from(operations);
-    let output_file = NamedTempFile::new()
-        .expect("failed to generate tempfile")
-        .into_temp_path();
-
-    let context = Context::new();
-    let module = context
-        .compile(&program, &output_file)
-        .expect("failed to compile program");
+    run_program_get_result_with_context(operations, SyscallContext::default(), initial_gas)
+}
 
-    let executor = Executor::new(&module);
+fn run_program_get_result_with_context(
+    operations: Vec<Operation>,
+    context: SyscallContext,
+    initial_gas: u64,
+) -> ExecutionResult {
+    run_program_with(operations, context, initial_gas).get_result()
+}
 
+/// Runs `operations` with `storage` as the executing contract's storage, returning it afterwards
+fn run_program_with_storage(
+    operations: Vec<Operation>,
+    storage: HashMap<U256, U256>,
+    initial_gas: u64,
+) -> (ExecutionResult, HashMap<U256, U256>) {
     let mut context = SyscallContext::default();
-
-    let _result = executor.execute(&mut context, initial_gas);
-
-    context.get_result()
+    let contract = context.env.tx.to.clone();
+    context.storage = storage
+        .into_iter()
+        .map(|(key, value)| ((contract.clone(), key), value))
+        .collect();
+
+    let context = run_program_with(operations, context, initial_gas);
+    let storage = context
+        .storage
+        .iter()
+        .filter(|((address, _), _)| *address == contract)
+        .map(|((_, key), value)| (*key, *value))
+        .collect();
+    (context.get_result(), storage)
 }
 
 fn run_program_assert_result(operations: Vec<Operation>, expected_result: &[u8]) {
@@ -68,7 +89,13 @@ fn run_program_assert_stack_top_with_gas(
 
 fn run_program_assert_halt(program: Vec<Operation>) {
     let result = run_program_get_result_with_gas(program, 1e7 as _);
-    assert_eq!(result, ExecutionResult::Halt);
+    assert!(matches!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            ..
+        }
+    ));
 }
 
 fn run_program_assert_revert(program: Vec<Operation>, expected_result: &[u8]) {
@@ -85,6 +112,20 @@ fn run_program_assert_gas_exact(program: Vec<Operation>, expected_gas: u64) {
     assert!(result.is_halt());
 }
 
+fn assert_compilation_fails(operations: Vec<Operation>) {
+    let program = Program::from(operations);
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+
+    let context = Context::new();
+    let result = context.compile(&program, &output_file);
+    let Err(CodegenError::OperationError { source, .. }) = result else {
+        panic!("expected the compilation to fail");
+    };
+    assert!(matches!(*source, CodegenError::InvalidOperation(_)));
+}
+
 pub fn biguint_256_from_bigint(value: BigInt) -> BigUint {
     if value >= BigInt::ZERO {
         value.magnitude().clone()
@@ -104,6 +145,31 @@ fn test_calldatasize_with_gas() {
     run_program_assert_stack_top(program, BigUint::ZERO);
 }
 
+#[test]
+fn calldataload_with_empty_calldata_is_zero() {
+    let program = vec![Operation::Push0, Operation::CalldataLoad];
+    run_program_assert_stack_top(program, BigUint::ZERO);
+}
+
+#[test]
+fn calldatacopy_with_empty_calldata_is_zero() {
+    let program = vec![
+        // pollute the memory, so the copied zeros overwrite something
+        Operation::Push((1_u8, 0xff_u8.into())),
+        Operation::Push0,
+        Operation::Mstore,
+        // copy 32 bytes from the calldata into memory[0..32]
+        Operation::Push((1_u8, 32_u8.into())),
+        Operation::Push0,
+        Operation::Push0,
+        Operation::CalldataCopy,
+        Operation::Push((1_u8, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    run_program_assert_result(program, &[0; 32]);
+}
+
 #[test]
 fn test_return_with_gas() {
     let program = vec![
@@ -134,6 +200,304 @@ fn test_revert_with_gas() {
     run_program_assert_revert(program, &[0]);
 }
 
+#[rstest]
+#[case(Operation::Return)]
+#[case(Operation::Revert)]
+fn return_beyond_msize_expands_memory(#[case] operation: Operation) {
+    let program = vec![
+        // touch the first word, so memory is expanded from 32 bytes
+        Operation::Push((1, 0xff_u8.into())),
+        Operation::Push0,
+        Operation::Mstore8,
+        // return memory[64..104]
+        Operation::Push((1, 40_u8.into())),
+        Operation::Push((1, 64_u8.into())),
+        operation,
+    ];
+    let needed_gas = gas_cost::PUSHN * 3
+        + gas_cost::PUSH0
+        + gas_cost::MSTORE8
+        + gas_cost::memory_expansion_cost(0, 32)
+        + gas_cost::memory_expansion_cost(32, 104);
+    let initial_gas = 1e7 as u64;
+
+    let result = run_program_get_result_with_gas(program.clone(), (needed_gas - 1) as _);
+    assert!(result.is_halt());
+
+    let result = run_program_get_result_with_gas(program, initial_gas);
+    assert!(!result.is_halt());
+    assert_eq!(result.return_data().unwrap(), [0_u8; 40]);
+    assert_eq!(result.gas_remaining(), initial_gas - needed_gas as u64);
+}
+
+#[rstest]
+#[case(Operation::Return, BigUint::from(64_u8))]
+#[case(Operation::Return, BigUint::from(1_u8) << 255)]
+#[case(Operation::Revert, BigUint::from(64_u8))]
+#[case(Operation::Revert, BigUint::from(1_u8) << 255)]
+fn empty_return_data_does_not_expand_memory(#[case] operation: Operation, #[case] offset: BigUint) {
+    let program = vec![
+        Operation::Push0,              // size
+        Operation::Push((32, offset)), // offset
+        operation,
+    ];
+    // No memory expansion is paid for
+    let needed_gas = (gas_cost::PUSH0 + gas_cost::PUSHN) as u64;
+
+    let result = run_program_get_result_with_gas(program, needed_gas);
+
+    assert!(!result.is_halt());
+    assert!(result.return_data().unwrap().is_empty());
+    assert_eq!(result.gas_remaining(), 0);
+}
+
+#[test]
+fn halt_captures_the_pc_and_stack_of_the_failing_operation() {
+    let program = vec![
+        Operation::Push0,
+        Operation::Push((1, 5_u8.into())),
+        Operation::Add,
+        // underflows, with only the sum of the previous ADD left
+        Operation::Add,
+        Operation::Stop,
+    ];
+    let context = SyscallContext::default().with_failure_capture();
+
+    let result = run_program_get_result_with_context(program, context, 1e7 as _);
+
+    assert!(result.is_halt());
+    let expected_failure = FailureInfo {
+        pc: 4,
+        stack: vec![U256 { lo: 5, hi: 0 }],
+    };
+    assert_eq!(result.failure(), Some(&expected_failure));
+}
+
+#[test]
+fn revert_captures_its_pc_and_stack() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::Revert];
+    let context = SyscallContext::default().with_failure_capture();
+
+    let result = run_program_get_result_with_context(program, context, 1e7 as _);
+
+    assert!(result.is_revert());
+    let expected_failure = FailureInfo {
+        pc: 2,
+        stack: vec![U256::default(), U256::default()],
+    };
+    assert_eq!(result.failure(), Some(&expected_failure));
+}
+
+#[test]
+fn failures_are_only_captured_when_requested() {
+    let program = vec![Operation::Add];
+
+    let result = run_program_get_result_with_gas(program.clone(), 1e7 as _);
+    assert!(result.is_halt());
+    assert_eq!(result.failure(), None);
+
+    let context = SyscallContext::default().with_failure_capture();
+    let result = run_program_get_result_with_context(program, context, 1e7 as _);
+    assert_eq!(result.failure().map(|failure| failure.pc), Some(0));
+}
+
+#[test]
+fn reloaded_bitcode_runs_like_the_compiled_module() {
+    let program = Program::from(vec![
+        Operation::Push((1, 7_u8.into())),
+        Operation::Push((1, 5_u8.into())),
+        Operation::Mul,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Log(0),
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+
+    let context = Context::new();
+    let module = context
+        .compile(&program, &output_file)
+        .expect("failed to compile program");
+    let bitcode_file = compile_to_bitcode(&module, &output_file).expect("failed to emit bitcode");
+
+    let run = |executor: Executor| {
+        let mut context = SyscallContext::default();
+        executor.execute(&mut context, 1e7 as _);
+        context.get_result()
+    };
+    let compiled_result = run(Executor::new(&module));
+    let reloaded_result =
+        run(Executor::from_bitcode(&bitcode_file).expect("failed to reload bitcode"));
+
+    assert!(compiled_result.is_success());
+    assert_eq!(reloaded_result, compiled_result);
+    std::fs::remove_file(bitcode_file).unwrap();
+}
+
+#[test]
+fn opcode_counts_of_a_loop_reflect_its_iterations() {
+    let loop_pc = 2;
+    let program = vec![
+        Operation::Push((1, 3_u8.into())), // counter
+        Operation::Jumpdest { pc: loop_pc },
+        Operation::Push((1, 1_u8.into())),
+        Operation::Swap(1),
+        Operation::Sub,
+        Operation::Dup(1),
+        Operation::Push((1, loop_pc.into())),
+        Operation::Jumpi,
+        Operation::Stop,
+    ];
+    let context = SyscallContext::default().with_profiling();
+
+    let result = run_program_get_result_with_context(program, context, 1e7 as _);
+
+    assert!(result.is_success());
+    let expected_counts = OpcodeCounts::from([
+        // the counter, and two per iteration
+        (Opcode::PUSH1 as u8, 7),
+        (Opcode::JUMPDEST as u8, 3),
+        (Opcode::SWAP1 as u8, 3),
+        (Opcode::SUB as u8, 3),
+        (Opcode::DUP1 as u8, 3),
+        (Opcode::JUMPI as u8, 3),
+        (Opcode::STOP as u8, 1),
+    ]);
+    assert_eq!(result.opcode_counts(), Some(&expected_counts));
+}
+
+#[test]
+fn opcode_counts_are_only_kept_when_profiling() {
+    let program = vec![Operation::Push0, Operation::Stop];
+
+    let result = run_program_get_result_with_gas(program, 1e7 as _);
+
+    assert!(result.is_success());
+    assert_eq!(result.opcode_counts(), None);
+}
+
+#[test]
+fn out_of_gas_halts_while_revert_keeps_gas() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::Revert];
+    let needed_gas = gas_cost::PUSH0 * 2;
+    let initial_gas = 1_000;
+
+    // Running out of gas is an exceptional halt, which consumes all the gas
+    let result = run_program_get_result_with_gas(program.clone(), (needed_gas - 1) as _);
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+
+    // REVERT returns the unused gas
+    let result = run_program_get_result_with_gas(program, initial_gas);
+    assert_eq!(
+        result,
+        ExecutionResult::Revert {
+            return_data: vec![],
+            gas_remaining: initial_gas - needed_gas as u64,
+            gas_used: needed_gas as u64,
+            logs: vec![],
+            failure: None,
+            opcode_counts: None,
+        }
+    );
+}
+
+#[rstest]
+#[case(Operation::Return)]
+#[case(Operation::Revert)]
+fn gas_used_and_remaining_add_up_to_initial_gas(#[case] operation: Operation) {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(32_u8))),
+        Operation::Push0,
+        operation,
+    ];
+    let initial_gas = 1_000;
+
+    let result = run_program_get_result_with_gas(program, initial_gas);
+
+    let (ExecutionResult::Success {
+        gas_used,
+        gas_remaining,
+        ..
+    }
+    | ExecutionResult::Revert {
+        gas_used,
+        gas_remaining,
+        ..
+    }) = result
+    else {
+        panic!("expected the execution to finish normally");
+    };
+    assert!(gas_used > 0);
+    assert_eq!(gas_used + gas_remaining, initial_gas);
+}
+
+#[test]
+fn initial_gas_below_first_operation_cost_halts_immediately() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::Add];
+
+    let result = run_program_get_result_with_gas(program, (gas_cost::PUSH0 - 1) as _);
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+}
+
+#[test]
+fn gas_counter_above_i64_max_is_not_negative() {
+    let program = vec![Operation::Push0];
+    let initial_gas = u64::MAX;
+
+    let result = run_program_get_result_with_gas(program, initial_gas);
+    assert!(result.is_success());
+    assert_eq!(result.gas_remaining(), initial_gas - gas_cost::PUSH0 as u64);
+}
+
+#[test]
+fn running_out_of_gas_in_a_loop_leaves_no_gas() {
+    // An infinite loop, which runs out of gas after some iterations
+    let program = vec![
+        Operation::Jumpdest { pc: 0 },
+        Operation::Push0,
+        Operation::Jump,
+    ];
+
+    let result = run_program_get_result_with_gas(program, 1_000);
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+}
+
+#[test]
+fn stack_underflow_halts_consuming_all_gas() {
+    let result = run_program_get_result_with_gas(vec![Operation::Add], 1_000);
+    assert_eq!(
+        result,
+        ExecutionResult::Halt {
+            gas_remaining: 0,
+            failure: None,
+        }
+    );
+}
+
 #[test]
 fn push_once() {
     let value = BigUint::from(5_u8);
@@ -363,6 +727,21 @@ fn swap_stack_underflow() {
     run_program_assert_halt(program);
 }
 
+#[test]
+fn swap_equal_values_keeps_stack_and_charges_gas() {
+    let value = BigUint::from(7_u8);
+    let program = vec![
+        Operation::Push((1_u8, value.clone())),
+        Operation::Push((1_u8, value.clone())),
+        Operation::Swap(1),
+        Operation::Pop,
+    ];
+    let gas_needed = gas_cost::PUSHN * 2 + gas_cost::SWAPN + gas_cost::POP;
+
+    run_program_assert_gas_exact(program.clone(), gas_needed as _);
+    run_program_assert_stack_top(program, value);
+}
+
 #[test]
 fn swap_out_of_gas() {
     let (a, b) = (BigUint::from(1_u8), BigUint::from(2_u8));
@@ -647,6 +1026,20 @@ fn sdiv_with_zero_numerator() {
     run_program_assert_stack_top(program, expected_result);
 }
 
+#[test]
+fn sdiv_overflow() {
+    // -2^255 / -1 overflows, and the EVM defines the result as -2^255
+    let int_min = BigUint::from(1_u8) << 255;
+    let minus_one = biguint_256_from_bigint(BigInt::from(-1_i8));
+
+    let program = vec![
+        Operation::Push((32_u8, minus_one)),       // <No collapse>
+        Operation::Push((32_u8, int_min.clone())), // <No collapse>
+        Operation::Sdiv,
+    ];
+    run_program_assert_stack_top(program, int_min);
+}
+
 #[test]
 fn sdiv_gas_should_revert() {
     let (a, b) = (2_u8, 10_u8);
@@ -724,11 +1117,43 @@ fn shift_bigger_than_256() {
     run_program_assert_stack_top(program, 0_u8.into());
 }
 
+#[rstest]
+#[case(Operation::Shr, 255, BigUint::from(1_u8))]
+#[case(Operation::Shr, 256, BigUint::ZERO)]
+#[case(Operation::Shl, 255, BigUint::from(1_u8) << 255)]
+#[case(Operation::Shl, 256, BigUint::ZERO)]
+fn shift_boundary_with_top_bit_set(
+    #[case] operation: Operation,
+    #[case] shift: u16,
+    #[case] expected_result: BigUint,
+) {
+    // Only the highest and the lowest bits are set
+    let value = (BigUint::from(1_u8) << 255) + 1_u8;
+    let program = vec![
+        Operation::Push((32_u8, value)),
+        Operation::Push((2_u8, BigUint::from(shift))),
+        operation,
+    ];
+    run_program_assert_stack_top(program, expected_result);
+}
+
 #[test]
 fn shr_with_stack_underflow() {
     run_program_assert_halt(vec![Operation::Shr]);
 }
 
+#[test]
+fn shr_out_of_gas() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Push((1_u8, BigUint::from(4_u8))),
+        Operation::Shr,
+    ];
+    let gas_needed = gas_cost::PUSHN * 2 + gas_cost::SHR;
+
+    run_program_assert_gas_exact(program, gas_needed as _);
+}
+
 #[test]
 fn push_push_xor() {
     let program = vec![
@@ -759,6 +1184,28 @@ fn xor_out_of_gas() {
     run_program_assert_gas_exact(program, initial_gas as _);
 }
 
+#[rstest]
+#[case(BigUint::ZERO, BigUint::from_bytes_be(&[0xff; 32]))]
+#[case(BigUint::from_bytes_be(&[0xff; 32]), BigUint::ZERO)]
+#[case(BigUint::from(0xf0_u8), BigUint::from_bytes_be(&[0xff; 32]) - 0xf0_u8)]
+fn not(#[case] value: BigUint, #[case] expected: BigUint) {
+    let program = vec![Operation::Push((32_u8, value)), Operation::Not];
+
+    run_program_assert_stack_top(program, expected);
+}
+
+#[test]
+fn not_with_stack_underflow() {
+    run_program_assert_halt(vec![Operation::Not]);
+}
+
+#[test]
+fn not_out_of_gas() {
+    let program = vec![Operation::Push0, Operation::Not];
+    let initial_gas = gas_cost::PUSH0 + gas_cost::NOT;
+    run_program_assert_gas_exact(program, initial_gas as _);
+}
+
 #[test]
 fn push_push_pop() {
     // Operation::Push two values to the stack and then pop once
@@ -905,6 +1352,40 @@ fn sar_with_shift_out_of_bounds() {
     run_program_assert_stack_top(program, value);
 }
 
+#[test]
+fn sar_minus_one_by_300() {
+    let minus_one = BigUint::from_bytes_be(&[0xff; 32]);
+    let program = vec![
+        Operation::Push((32_u8, minus_one.clone())),
+        Operation::Push((2_u8, BigUint::from(300_u16))),
+        Operation::Sar,
+    ];
+    run_program_assert_stack_top(program, minus_one);
+}
+
+#[test]
+fn sar_min_negative_value_by_256() {
+    // every bit is shifted out, leaving only the sign
+    let value = BigUint::from(1_u8) << 255;
+    let program = vec![
+        Operation::Push((32_u8, value)),
+        Operation::Push((2_u8, BigUint::from(256_u16))),
+        Operation::Sar,
+    ];
+    run_program_assert_stack_top(program, BigUint::from_bytes_be(&[0xff; 32]));
+}
+
+#[test]
+fn sar_positive_value_by_256() {
+    let value = (BigUint::from(1_u8) << 255) - 1_u8;
+    let program = vec![
+        Operation::Push((32_u8, value)),
+        Operation::Push((2_u8, BigUint::from(256_u16))),
+        Operation::Sar,
+    ];
+    run_program_assert_stack_top(program, BigUint::ZERO);
+}
+
 #[test]
 fn byte_with_offset_out_of_bounds() {
     // must consider this case yet
@@ -919,6 +1400,24 @@ fn byte_with_offset_out_of_bounds() {
     run_program_assert_stack_top(program, 0_u8.into());
 }
 
+#[rstest]
+#[case(BigUint::ZERO, 0x01)]
+#[case(BigUint::from(31_u8), 0x20)]
+#[case(BigUint::from(32_u8), 0)]
+#[case(BigUint::from(255_u8), 0)]
+// 8 times this offset overflows 256 bits, wrapping around to 0
+#[case(BigUint::from(1_u8) << 253, 0)]
+fn byte_at_index_boundaries(#[case] index: BigUint, #[case] expected: u8) {
+    // byte i of the value is i + 1, counting from the most significant one
+    let value = BigUint::from_bytes_be(&std::array::from_fn::<u8, 32, _>(|i| i as u8 + 1));
+    let program = vec![
+        Operation::Push((32_u8, value)),
+        Operation::Push((32_u8, index)),
+        Operation::Byte,
+    ];
+    run_program_assert_stack_top(program, expected.into());
+}
+
 #[test]
 fn jumpdest() {
     let expected = 5_u8;
@@ -1129,13 +1628,61 @@ fn jumpi_does_not_revert_if_pc_is_wrong_but_branch_is_not_taken() {
 }
 
 #[test]
-fn pc_with_previous_push() {
-    let pc = 33;
+fn jump_to_jumpdest_at_pc_zero() {
+    // [00] JUMPDEST
+    // [01] MSIZE
+    // [02] PUSH1 11
+    // [04] JUMPI     // exit the loop once memory was written
+    // [05] PUSH1 1
+    // [07] PUSH0
+    // [08] MSTORE
+    // [09] PUSH0
+    // [10] JUMP      // jump back to pc 0
+    // [11] JUMPDEST
+    // [12] MSIZE
+    let end_pc = 11;
     let program = vec![
-        Operation::Push((1_u8, BigUint::from(8_u8))), // <No collapse>
-        Operation::PC { pc },                         // <No collapse>
+        Operation::Jumpdest { pc: 0 },
+        Operation::Msize,
+        Operation::Push((1_u8, BigUint::from(end_pc as u8))),
+        Operation::Jumpi,
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push0,
+        Operation::Jump,
+        Operation::Jumpdest { pc: end_pc },
+        Operation::Msize,
     ];
-    run_program_assert_stack_top(program, pc.into())
+    run_program_assert_stack_top(program, 32_u8.into());
+}
+
+#[rstest]
+#[case(3, gas_cost::JUMPDEST * 2)]
+#[case(4, gas_cost::JUMPDEST)]
+fn jump_to_adjacent_jumpdests(#[case] pc: usize, #[case] jumpdests_gas: i64) {
+    // [00] PUSH1 pc
+    // [02] JUMP
+    // [03] JUMPDEST
+    // [04] JUMPDEST
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(pc as u8))),
+        Operation::Jump,
+        Operation::Jumpdest { pc: 3 },
+        Operation::Jumpdest { pc: 4 },
+    ];
+    let needed_gas = gas_cost::PUSHN + gas_cost::JUMP + jumpdests_gas;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[test]
+fn pc_with_previous_push() {
+    let pc = 33;
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(8_u8))), // <No collapse>
+        Operation::PC { pc },                         // <No collapse>
+    ];
+    run_program_assert_stack_top(program, pc.into())
 }
 
 #[test]
@@ -1147,6 +1694,16 @@ fn pc_with_no_previous_operation() {
     run_program_assert_stack_top(program, pc.into())
 }
 
+#[test]
+fn pc_beyond_i64_range() {
+    // Unreachable in real bytecode, but the constant must not go through an i64
+    let pc = u64::MAX as usize;
+    let program = vec![
+        Operation::PC { pc }, // <No collapse>
+    ];
+    run_program_assert_stack_top(program, BigUint::from(u64::MAX))
+}
+
 #[test]
 fn pc_gas_should_revert() {
     let program = vec![Operation::Push0, Operation::PC { pc: 0 }];
@@ -1178,6 +1735,19 @@ fn check_memory_size_after_store() {
     run_program_assert_stack_top(program, 64_u8.into());
 }
 
+#[rstest]
+#[case(0, 32)]
+#[case(33, 96)]
+fn msize_after_mstore_is_word_aligned(#[case] offset: u8, #[case] expected_size: u8) {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Push((1_u8, BigUint::from(offset))),
+        Operation::Mstore,
+        Operation::Msize,
+    ];
+    run_program_assert_stack_top(program, expected_size.into());
+}
+
 #[test]
 fn msize_out_of_gas() {
     let program = vec![Operation::Msize];
@@ -1299,6 +1869,20 @@ fn smod_with_negative_operands() {
     run_program_assert_stack_top(program, expected_result);
 }
 
+#[test]
+fn smod_overflow() {
+    // -2^255 mod -1 = 0
+    let int_min = BigUint::from(1_u8) << 255;
+    let minus_one = biguint_256_from_bigint(BigInt::from(-1_i8));
+
+    let program = vec![
+        Operation::Push((32_u8, minus_one)),
+        Operation::Push((32_u8, int_min)),
+        Operation::SMod,
+    ];
+    run_program_assert_stack_top(program, BigUint::ZERO);
+}
+
 #[test]
 fn smod_with_negative_denominator() {
     // 8 mod -3 = 2
@@ -1512,11 +2096,37 @@ fn mulmod_with_zero_denominator() {
         Operation::Push((1_u8, BigUint::from(0_u8))),
         Operation::Push((1_u8, BigUint::from(31_u8))),
         Operation::Push((1_u8, BigUint::from(11_u8))),
-        Operation::Addmod,
+        Operation::Mulmod,
     ];
     run_program_assert_stack_top(program, 0_u8.into());
 }
 
+#[rstest]
+#[case(Operation::Div)]
+#[case(Operation::Sdiv)]
+#[case(Operation::Mod)]
+#[case(Operation::SMod)]
+#[case(Operation::Addmod)]
+#[case(Operation::Mulmod)]
+fn zero_denominator_returns_zero(#[case] operation: Operation) {
+    // The denominator is the last operand: the second one for DIV, SDIV, MOD and SMOD,
+    // and the third one for ADDMOD and MULMOD.
+    let operands = match operation {
+        Operation::Addmod | Operation::Mulmod => 2,
+        _ => 1,
+    };
+    let mut program = vec![Operation::Push((1_u8, BigUint::from(0_u8)))];
+    for _ in 0..operands {
+        program.push(Operation::Push((
+            32_u8,
+            BigUint::from_bytes_be(&[0xff; 32]),
+        )));
+    }
+    program.push(operation);
+
+    run_program_assert_stack_top(program, 0_u8.into());
+}
+
 #[test]
 fn mulmod_with_overflow() {
     let (a, b, den) = (
@@ -1699,12 +2309,11 @@ fn push_push_exp() {
         Operation::Exp,
     ];
 
-    run_program_assert_stack_top(program, a.pow(b.try_into().unwrap()));
+    // EXP takes the base from the top of the stack
+    run_program_assert_stack_top(program, b.pow(a.try_into().unwrap()));
 }
 
-// TODO: fix this test
 #[test]
-#[ignore]
 fn exp_with_overflow_should_wrap() {
     let a = 3_u8;
     let b = 256_u32;
@@ -1717,6 +2326,49 @@ fn exp_with_overflow_should_wrap() {
     run_program_assert_stack_top(program, expected_result);
 }
 
+#[rstest]
+#[case(0_u8.into(), 0_u8.into(), 1_u8.into())]
+#[case(2_u8.into(), 256_u32.into(), 0_u8.into())]
+#[case(2_u8.into(), 255_u8.into(), BigUint::from(1_u8) << 255)]
+#[case(0_u8.into(), 5_u8.into(), 0_u8.into())]
+#[case(7_u8.into(), 0_u8.into(), 1_u8.into())]
+#[case(BigUint::from_bytes_be(&[0xff; 32]), 2_u8.into(), 1_u8.into())]
+#[case(BigUint::from_bytes_be(&[0xff; 32]), 3_u8.into(), BigUint::from_bytes_be(&[0xff; 32]))]
+#[case(
+    3_u8.into(),
+    BigUint::from_bytes_be(&[0xff; 32]),
+    BigUint::from(3_u8).modpow(&BigUint::from_bytes_be(&[0xff; 32]), &(BigUint::from(1_u8) << 256))
+)]
+fn exp_reference_values(
+    #[case] base: BigUint,
+    #[case] exponent: BigUint,
+    #[case] expected: BigUint,
+) {
+    let program = vec![
+        Operation::Push((32_u8, exponent)),
+        Operation::Push((32_u8, base)),
+        Operation::Exp,
+    ];
+    run_program_assert_stack_top(program, expected);
+}
+
+#[rstest]
+#[case(0_u32, 0)]
+#[case(1_u32, 1)]
+#[case(255_u32, 1)]
+#[case(256_u32, 2)]
+#[case(u32::MAX, 4)]
+fn exp_dynamic_gas(#[case] exponent: u32, #[case] exponent_byte_size: i64) {
+    let program = vec![
+        Operation::Push((4_u8, exponent.into())),
+        Operation::Push((1_u8, 2_u8.into())),
+        Operation::Exp,
+    ];
+    let needed_gas =
+        gas_cost::PUSHN * 2 + gas_cost::EXP + gas_cost::EXP_PER_BYTE * exponent_byte_size;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
 #[test]
 fn exp_with_stack_underflow() {
     let program = vec![Operation::Exp];
@@ -1795,6 +2447,27 @@ fn signextend_one_byte_positive_value() {
     run_program_assert_stack_top(program, expected_result.into());
 }
 
+#[rstest]
+#[case(31)]
+#[case(32)]
+#[case(100)]
+fn signextend_with_index_out_of_range(#[case] value_bytes_size: u8) {
+    // With an index of 31 the sign bit is already the top bit, and from 32 onwards
+    // the value must be left unchanged
+    let mut value_bytes = [0_u8; 32];
+    value_bytes[0] = 0x80;
+    value_bytes[31] = 0x80;
+    let value = BigUint::from_bytes_be(&value_bytes);
+
+    let program = vec![
+        Operation::Push((32_u8, value.clone())),
+        Operation::Push((1_u8, BigUint::from(value_bytes_size))),
+        Operation::SignExtend,
+    ];
+
+    run_program_assert_stack_top(program, value);
+}
+
 #[test]
 fn signextend_with_stack_underflow() {
     let program = vec![Operation::SignExtend];
@@ -1920,7 +2593,8 @@ fn exp_reverts_when_program_runs_out_of_gas() {
         Operation::Exp,
     ];
 
-    let initial_gas = gas_cost::PUSHN * 2 + gas_cost::EXP;
+    // the exponent (3) takes a single byte
+    let initial_gas = gas_cost::PUSHN * 2 + gas_cost::EXP + gas_cost::EXP_PER_BYTE;
     run_program_assert_gas_exact(program, initial_gas as _);
 }
 
@@ -2174,6 +2848,55 @@ fn mstore_mload_with_memory_extension() {
     run_program_assert_stack_top(program, stored_value);
 }
 
+#[rstest]
+#[case(0)]
+#[case(64)]
+#[case(1)]
+#[case(33)]
+#[case(95)]
+fn mstore_mload_at_aligned_and_unaligned_offsets(#[case] offset: u8) {
+    // Every byte is distinct, so a misplaced byte changes the loaded value
+    let stored_value = BigUint::from_bytes_be(&std::array::from_fn::<u8, 32, _>(|i| i as u8 + 1));
+    let program = vec![
+        Operation::Push((32_u8, stored_value.clone())), // value
+        Operation::Push((1_u8, offset.into())),         // offset
+        Operation::Mstore,
+        Operation::Push((1_u8, offset.into())), // offset
+        Operation::Mload,
+    ];
+    run_program_assert_stack_top(program, stored_value);
+}
+
+#[test]
+fn mstore_writes_big_endian_bytes() {
+    // The most significant byte of the word goes at the lowest address
+    let program = vec![
+        Operation::Push((2_u8, BigUint::from(0x0102_u16))), // value
+        Operation::Push0,                                   // offset
+        Operation::Mstore,
+        Operation::Push((1_u8, BigUint::from(32_u8))), // size
+        Operation::Push0,                              // offset
+        Operation::Return,
+    ];
+    let mut expected = [0_u8; 32];
+    expected[30] = 0x01;
+    expected[31] = 0x02;
+    run_program_assert_result(program, &expected);
+}
+
+#[test]
+fn mload_reads_big_endian_bytes() {
+    // Storing 0x01 at 31 and loading from 1 places it one byte above the least significant one
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(0x01_u8))), // value
+        Operation::Push((1_u8, BigUint::from(31_u8))),   // offset
+        Operation::Mstore8,
+        Operation::Push((1_u8, BigUint::from(1_u8))), // offset
+        Operation::Mload,
+    ];
+    run_program_assert_stack_top(program, BigUint::from(0x0100_u16));
+}
+
 #[test]
 fn mload_not_allocated_address() {
     // When offset for MLOAD is bigger than the current memory size, memory is extended with zeros
@@ -2184,6 +2907,46 @@ fn mload_not_allocated_address() {
     run_program_assert_stack_top(program, 0_u8.into());
 }
 
+#[rstest]
+// offset + 32 doesn't fit in 32 bits
+#[case(BigUint::from(u32::MAX - 15))]
+// the offset itself doesn't fit in 32 bits
+#[case(BigUint::from(1_u8) << 128)]
+fn mstore_past_the_addressable_memory_halts(#[case] offset: BigUint) {
+    let program = vec![
+        Operation::Push0,              // value
+        Operation::Push((32, offset)), // offset
+        Operation::Mstore,
+    ];
+
+    // Even with plenty of gas, the memory range must not wrap around to a small one
+    let result = run_program_get_result_with_gas(program, u64::MAX / 2);
+    assert!(result.is_halt());
+}
+
+#[test]
+fn mload_past_the_addressable_memory_halts() {
+    let program = vec![
+        Operation::Push((4, BigUint::from(u32::MAX - 15))), // offset
+        Operation::Mload,
+    ];
+
+    let result = run_program_get_result_with_gas(program, u64::MAX / 2);
+    assert!(result.is_halt());
+}
+
+#[test]
+fn return_past_the_addressable_memory_halts() {
+    let program = vec![
+        Operation::Push((1, BigUint::from(32_u8))),         // size
+        Operation::Push((4, BigUint::from(u32::MAX - 15))), // offset
+        Operation::Return,
+    ];
+
+    let result = run_program_get_result_with_gas(program, u64::MAX / 2);
+    assert!(result.is_halt());
+}
+
 #[test]
 fn mstore_gas_cost_with_memory_extension() {
     let program = vec![
@@ -2196,6 +2959,36 @@ fn mstore_gas_cost_with_memory_extension() {
     run_program_assert_gas_exact(program, needed_gas as _);
 }
 
+#[test]
+fn mstore_gas_cost_without_memory_extension() {
+    // The second store doesn't extend the memory, but still has to pay the base cost
+    let single_store = vec![
+        Operation::Push((1_u8, BigUint::from(10_u8))), // value
+        Operation::Push0,                              // offset
+        Operation::Mstore,
+    ];
+    let double_store = [single_store.clone(), single_store].concat();
+
+    let dynamic_gas = gas_cost::memory_expansion_cost(0, 32);
+    let needed_gas = gas_cost::PUSHN * 2 + gas_cost::PUSH0 * 2 + gas_cost::MSTORE * 2 + dynamic_gas;
+    run_program_assert_gas_exact(double_store, needed_gas as _);
+}
+
+#[test]
+fn mstore8_gas_cost_without_memory_extension() {
+    let single_store = vec![
+        Operation::Push((1_u8, BigUint::from(10_u8))), // value
+        Operation::Push0,                              // offset
+        Operation::Mstore8,
+    ];
+    let double_store = [single_store.clone(), single_store].concat();
+
+    let dynamic_gas = gas_cost::memory_expansion_cost(0, 32);
+    let needed_gas =
+        gas_cost::PUSHN * 2 + gas_cost::PUSH0 * 2 + gas_cost::MSTORE8 * 2 + dynamic_gas;
+    run_program_assert_gas_exact(double_store, needed_gas as _);
+}
+
 #[test]
 fn mstore8_gas_cost_with_memory_extension() {
     let program = vec![
@@ -2219,6 +3012,20 @@ fn mload_gas_cost_with_memory_extension() {
     run_program_assert_gas_exact(program, needed_gas as _);
 }
 
+#[test]
+fn mload_twice_charges_base_gas_each_time() {
+    // Only the first load extends the memory
+    let program = vec![
+        Operation::Push0, // offset
+        Operation::Mload,
+        Operation::Push0, // offset
+        Operation::Mload,
+    ];
+    let dynamic_gas = gas_cost::memory_expansion_cost(0, 32);
+    let needed_gas = gas_cost::PUSH0 * 2 + gas_cost::MLOAD * 2 + dynamic_gas;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
 #[test]
 fn mload_gas_cost_with_memory_extension2() {
     let program = vec![
@@ -2230,6 +3037,25 @@ fn mload_gas_cost_with_memory_extension2() {
     run_program_assert_gas_exact(program, needed_gas as _);
 }
 
+#[rstest]
+#[case(1, 3)]
+#[case(32, 98)]
+#[case(1024, 5_120)]
+fn mload_memory_expansion_gas(#[case] words: u16, #[case] expected_expansion_cost: i64) {
+    // The last word loaded is the one at `offset`, expanding memory to `words` words
+    let offset = (words - 1) * 32;
+    let program = vec![
+        Operation::Push((2_u8, BigUint::from(offset))),
+        Operation::Mload,
+    ];
+    assert_eq!(
+        gas_cost::memory_expansion_cost(0, words as u32 * 32),
+        expected_expansion_cost
+    );
+    let needed_gas = gas_cost::PUSHN + gas_cost::MLOAD + expected_expansion_cost;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
 #[test]
 #[ignore]
 fn mload_out_of_gas() {
@@ -2305,6 +3131,27 @@ fn mcopy_offset_equals_dest_offset() {
     run_program_assert_stack_top(program, 123_u8.into());
 }
 
+#[test]
+fn mcopy_reallocating_memory() {
+    // The copy destination is far beyond the current memory size, so the
+    // memory gets reallocated before being accessed.
+    let value = BigUint::from_bytes_be(&[0xab; 32]);
+    let dest_offset: u32 = 1 << 16;
+    let program = vec![
+        Operation::Push((32_u8, value.clone())),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1_u8, BigUint::from(32_u8))),
+        Operation::Push0,
+        Operation::Push((3_u8, BigUint::from(dest_offset))),
+        Operation::Mcopy,
+        Operation::Push((3_u8, BigUint::from(dest_offset))),
+        Operation::Mload,
+    ];
+
+    run_program_assert_stack_top(program, value);
+}
+
 #[test]
 fn mstore_mcopy_mload_with_zero_address_arbitrary_size() {
     let value = BigUint::from(1_u8) << 24;
@@ -2361,6 +3208,44 @@ fn log_with_gas_cost(#[case] n: u8) {
     run_program_assert_gas_exact(program, gas_needed as _);
 }
 
+/// Returns LOG`n` of `[0, size)` with `n` topics.
+fn log_program(n: u8, size: u8) -> Vec<Operation> {
+    let mut program = vec![Operation::Push((32_u8, BigUint::from(7_u8))); n as usize];
+    program.push(Operation::Push((1_u8, BigUint::from(size))));
+    program.push(Operation::Push0);
+    program.push(Operation::Log(n));
+    program
+}
+
+#[rstest]
+#[case(0)]
+#[case(1)]
+#[case(2)]
+#[case(3)]
+#[case(4)]
+fn log_gas_matches_the_formula(#[case] n: u8) {
+    // 40 bytes take two words of memory: 3 * 2 + 2^2 / 512 = 6
+    let size = 40;
+    let memory_expansion = 6;
+    let log_gas = 375 + 375 * n as u64 + 8 * size as u64 + memory_expansion;
+    let pushes_gas = 3 * n as u64 + 3 + 2;
+
+    run_program_assert_gas_exact(log_program(n, size), pushes_gas + log_gas);
+}
+
+#[rstest]
+#[case(1)]
+#[case(4)]
+fn log_halts_without_gas_for_the_topics_and_data(#[case] n: u8) {
+    // Enough for the static and memory costs, but not for the dynamic cost
+    let size = 32;
+    let pushes_gas = gas_cost::PUSHN * (n as i64 + 1) + gas_cost::PUSH0;
+    let initial_gas = pushes_gas + gas_cost::LOG + gas_cost::memory_expansion_cost(0, 32);
+
+    let result = run_program_get_result_with_gas(log_program(n, size), initial_gas as _);
+    assert!(result.is_halt());
+}
+
 #[test]
 fn log_with_stack_underflow() {
     for n in 0..5 {
@@ -2368,3 +3253,564 @@ fn log_with_stack_underflow() {
         run_program_assert_halt(program);
     }
 }
+
+#[rstest]
+#[case(Operation::Dup(0))]
+#[case(Operation::Dup(17))]
+#[case(Operation::Swap(0))]
+#[case(Operation::Swap(17))]
+fn out_of_range_dup_and_swap_halt(#[case] operation: Operation) {
+    // There's enough elements for DUP17 and SWAP17, so only the index makes them halt
+    let mut program = vec![Operation::Push0; 18];
+    program.push(operation);
+    run_program_assert_halt(program);
+}
+
+#[test]
+fn invalid_operation_fails_to_compile() {
+    let program = vec![
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Log(5),
+    ];
+    assert_compilation_fails(program);
+}
+
+#[test]
+fn compilation_error_carries_the_pc() {
+    let program = Program::from(vec![
+        Operation::Push((2_u8, BigUint::from(1_u8))),
+        Operation::Push0,
+        Operation::Log(5),
+    ]);
+    let output_file = NamedTempFile::new()
+        .expect("failed to generate tempfile")
+        .into_temp_path();
+
+    let result = Context::new().compile(&program, &output_file);
+
+    // PUSH2 takes 3 bytes and PUSH0 1 byte
+    assert!(matches!(
+        result,
+        Err(CodegenError::OperationError { pc: 4, .. })
+    ));
+}
+
+#[test]
+fn out_of_gas_halt_reports_no_gas_remaining() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Push((1_u8, BigUint::from(2_u8))),
+        Operation::Add,
+    ];
+    let initial_gas = (gas_cost::PUSHN * 2 + gas_cost::ADD - 1) as u64;
+
+    let result = run_program_get_result_with_gas(program, initial_gas);
+
+    assert!(result.is_halt());
+    let gas_used = initial_gas - result.gas_remaining();
+    assert_eq!(gas_used, initial_gas);
+}
+
+#[test]
+fn keccak256_empty_input() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::Keccak256];
+    let expected = BigUint::from_bytes_be(&[
+        0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03,
+        0xc0, 0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85,
+        0xa4, 0x70,
+    ]);
+    run_program_assert_stack_top(program, expected);
+}
+
+#[test]
+fn keccak256_of_empty_range_ignores_its_offset() {
+    let program = vec![
+        Operation::Push0,                                  // size
+        Operation::Push((32, BigUint::from(1_u8) << 255)), // offset
+        Operation::Keccak256,
+    ];
+    let expected = BigUint::from_bytes_be(&Keccak256::digest(b""));
+    run_program_assert_stack_top(program.clone(), expected);
+
+    let needed_gas = gas_cost::PUSH0 + gas_cost::PUSHN + gas_cost::KECCAK256;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[test]
+fn keccak256_memory_word() {
+    let mut word = [0_u8; 32];
+    word[31] = 0x2a;
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(0x2a_u8))),
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1_u8, BigUint::from(32_u8))),
+        Operation::Push0,
+        Operation::Keccak256,
+    ];
+    let expected = BigUint::from_bytes_be(&Keccak256::digest(word));
+    run_program_assert_stack_top(program, expected);
+}
+
+#[test]
+fn keccak256_with_gas() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(33_u8))),
+        Operation::Push0,
+        Operation::Keccak256,
+    ];
+    let dynamic_gas = gas_cost::KECCAK256_PER_WORD * 2 + gas_cost::memory_expansion_cost(0, 64);
+    let needed_gas = gas_cost::PUSHN + gas_cost::PUSH0 + gas_cost::KECCAK256 + dynamic_gas;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[test]
+fn keccak256_with_stack_underflow() {
+    let program = vec![Operation::Push0, Operation::Keccak256];
+    run_program_assert_halt(program);
+}
+
+#[test]
+fn sstore_then_sload() {
+    let (key, value) = (BigUint::from(7_u8), BigUint::from(42_u8));
+    let program = vec![
+        Operation::Push((1_u8, value.clone())),
+        Operation::Push((1_u8, key.clone())),
+        Operation::Sstore,
+        Operation::Push((1_u8, key)),
+        Operation::Sload,
+    ];
+    run_program_assert_stack_top(program, value);
+}
+
+#[test]
+fn sload_reads_preseeded_storage() {
+    let key = U256 { lo: 1, hi: 0 };
+    let value = U256 { lo: 3, hi: 5 };
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Sload,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1_u8, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    let storage = HashMap::from([(key, value)]);
+
+    let (result, _) = run_program_with_storage(program, storage, 1e7 as _);
+
+    assert!(result.is_success());
+    let expected = (BigUint::from(5_u8) << 128) + BigUint::from(3_u8);
+    assert_eq!(
+        BigUint::from_bytes_be(result.return_data().unwrap()),
+        expected
+    );
+}
+
+#[test]
+fn sload_of_unset_slot_is_zero() {
+    let program = vec![Operation::Push((1_u8, 9_u8.into())), Operation::Sload];
+    run_program_assert_stack_top(program, BigUint::ZERO);
+}
+
+#[test]
+fn sstore_writes_storage() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(42_u8))),
+        Operation::Push((1_u8, BigUint::from(7_u8))),
+        Operation::Sstore,
+    ];
+    let storage = HashMap::from([(U256 { lo: 8, hi: 0 }, U256 { lo: 1, hi: 0 })]);
+
+    let (result, storage) = run_program_with_storage(program, storage, 1e7 as _);
+
+    assert!(result.is_success());
+    assert_eq!(storage.len(), 2);
+    assert_eq!(storage[&U256 { lo: 7, hi: 0 }], U256 { lo: 42, hi: 0 });
+    assert_eq!(storage[&U256 { lo: 8, hi: 0 }], U256 { lo: 1, hi: 0 });
+}
+
+#[test]
+fn sload_cold_and_warm_gas() {
+    let program = vec![
+        Operation::Push0,
+        Operation::Sload,
+        Operation::Push0,
+        Operation::Sload,
+    ];
+    let needed_gas = gas_cost::PUSH0 * 2 + gas_cost::COLD_SLOAD + gas_cost::WARM_STORAGE_READ;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[test]
+fn sstore_cold_new_slot_gas() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+    let needed_gas =
+        gas_cost::PUSHN + gas_cost::PUSH0 + gas_cost::SSTORE_SET + gas_cost::COLD_SLOAD;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[test]
+fn sstore_dirty_slot_gas() {
+    // The second write to the same slot is charged as a warm access
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Push0,
+        Operation::Sstore,
+        Operation::Push((1_u8, BigUint::from(2_u8))),
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+    let needed_gas = gas_cost::PUSHN * 2
+        + gas_cost::PUSH0 * 2
+        + gas_cost::SSTORE_SET
+        + gas_cost::COLD_SLOAD
+        + gas_cost::WARM_STORAGE_READ;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[test]
+fn sstore_reset_preseeded_slot_gas() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(2_u8))),
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+    let storage = HashMap::from([(U256::default(), U256 { lo: 1, hi: 0 })]);
+    let needed_gas =
+        (gas_cost::PUSHN + gas_cost::PUSH0 + gas_cost::SSTORE_RESET + gas_cost::COLD_SLOAD) as u64;
+
+    let (result, _) = run_program_with_storage(program.clone(), storage.clone(), needed_gas);
+    assert!(result.is_success());
+    let (result, _) = run_program_with_storage(program, storage, needed_gas - 1);
+    assert!(result.is_halt());
+}
+
+#[test]
+fn sstore_clearing_slot_is_refunded() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::Sstore];
+    let storage = HashMap::from([(U256::default(), U256 { lo: 1, hi: 0 })]);
+    let initial_gas = 1e7 as u64;
+
+    let (result, storage) = run_program_with_storage(program, storage, initial_gas);
+
+    // The refund is capped to a fifth of the gas used
+    let gas_used = (gas_cost::PUSH0 * 2 + gas_cost::SSTORE_RESET + gas_cost::COLD_SLOAD) as u64;
+    let gas_refunded = gas_used / 5;
+    assert_eq!(storage[&U256::default()], U256::default());
+    assert_eq!(
+        result,
+        ExecutionResult::Success {
+            return_data: vec![],
+            gas_remaining: initial_gas - gas_used + gas_refunded,
+            gas_used: gas_used - gas_refunded,
+            gas_refunded,
+            logs: vec![],
+            opcode_counts: None,
+        }
+    );
+}
+
+#[test]
+fn intrinsic_gas_counts_towards_the_refund_cap() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::Sstore];
+    let initial_gas = 1e7 as u64;
+    let intrinsic_gas = gas_cost::TX_BASE_COST;
+    let mut context = SyscallContext::default().with_intrinsic_gas(intrinsic_gas);
+    context.storage = HashMap::from([(
+        (context.env.tx.to.clone(), U256::default()),
+        U256 { lo: 1, hi: 0 },
+    )]);
+
+    let result = run_program_with(program, context, initial_gas).get_result();
+
+    // A fifth of the gas used with the intrinsic gas covers the whole refund
+    let gas_used = (gas_cost::PUSH0 * 2 + gas_cost::SSTORE_RESET + gas_cost::COLD_SLOAD) as u64;
+    let gas_refunded = gas_cost::SSTORE_CLEARS_SCHEDULE as u64;
+    assert_eq!(
+        result,
+        ExecutionResult::Success {
+            return_data: vec![],
+            gas_remaining: initial_gas - gas_used + gas_refunded,
+            gas_used: intrinsic_gas + gas_used - gas_refunded,
+            gas_refunded,
+            logs: vec![],
+            opcode_counts: None,
+        }
+    );
+}
+
+#[test]
+fn sstore_restoring_slot_is_refunded() {
+    // Setting a new slot and clearing it again only charges a warm read in the end
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(1_u8))),
+        Operation::Push0,
+        Operation::Sstore,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+    let initial_gas = 1e7 as u64;
+
+    let (result, _) = run_program_with_storage(program, HashMap::new(), initial_gas);
+
+    let gas_used = (gas_cost::PUSHN
+        + gas_cost::PUSH0 * 3
+        + gas_cost::SSTORE_SET
+        + gas_cost::COLD_SLOAD
+        + gas_cost::WARM_STORAGE_READ) as u64;
+    let refund = (gas_cost::SSTORE_SET - gas_cost::WARM_STORAGE_READ) as u64;
+    let gas_refunded = refund.min(gas_used / 5);
+    assert!(result.is_success());
+    assert_eq!(
+        result.gas_remaining(),
+        initial_gas - gas_used + gas_refunded
+    );
+}
+
+#[test]
+fn tstore_then_tload() {
+    let (key, value) = (BigUint::from(7_u8), BigUint::from(42_u8));
+    let program = vec![
+        Operation::Push((1_u8, value.clone())),
+        Operation::Push((1_u8, key.clone())),
+        Operation::Tstore,
+        Operation::Push((1_u8, key)),
+        Operation::Tload,
+    ];
+    run_program_assert_stack_top(program, value);
+}
+
+#[test]
+fn tload_of_unset_slot_is_zero() {
+    let program = vec![Operation::Push((1_u8, 9_u8.into())), Operation::Tload];
+    run_program_assert_stack_top(program, BigUint::ZERO);
+}
+
+#[test]
+fn tstore_does_not_write_storage() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(42_u8))),
+        Operation::Push((1_u8, BigUint::from(7_u8))),
+        Operation::Tstore,
+    ];
+
+    let (result, storage) = run_program_with_storage(program, HashMap::new(), 1e7 as _);
+
+    assert!(result.is_success());
+    assert!(storage.is_empty());
+}
+
+#[test]
+fn tstore_and_tload_gas() {
+    let program = vec![
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Tstore,
+        Operation::Push0,
+        Operation::Tload,
+    ];
+    let needed_gas = gas_cost::PUSH0 * 3 + gas_cost::TSTORE + gas_cost::TLOAD;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[test]
+fn tstore_with_stack_underflow() {
+    let program = vec![Operation::Push0, Operation::Tstore];
+    run_program_assert_halt(program);
+}
+
+#[test]
+fn sstore_fails_without_sentry_gas() {
+    // The second SSTORE only costs 100 gas, but it requires more than
+    // 2300 gas to be left (EIP-2200)
+    let program = vec![
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Sstore,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+    let gas_before_second_sstore =
+        gas_cost::PUSH0 * 4 + gas_cost::WARM_STORAGE_READ + gas_cost::COLD_SLOAD;
+    let initial_gas = (gas_before_second_sstore + gas_cost::SSTORE_MIN_REMAINING_GAS) as u64;
+
+    let result = run_program_get_result_with_gas(program.clone(), initial_gas);
+    assert!(result.is_halt());
+    let result = run_program_get_result_with_gas(program, initial_gas + 1);
+    assert!(result.is_success());
+}
+
+#[test]
+fn sstore_without_gas_for_the_write_leaves_the_storage_untouched() {
+    // Setting a slot costs more than the 10_000 gas available, but that's above the sentry
+    let program = vec![
+        Operation::Push((1, 1_u8.into())),
+        Operation::Push0,
+        Operation::Sstore,
+    ];
+
+    let context = run_program_with(program, SyscallContext::default(), 10_000);
+
+    assert!(context.get_result().is_halt());
+    assert!(context.storage.is_empty());
+}
+
+#[test]
+fn sstore_with_stack_underflow() {
+    let program = vec![Operation::Push0, Operation::Sstore];
+    run_program_assert_halt(program);
+}
+
+#[test]
+fn calldatacopy_gas() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(33_u8))),
+        Operation::Push0,
+        Operation::Push0,
+        Operation::CalldataCopy,
+    ];
+    // two words are copied, and the memory is extended by two words
+    let dynamic_gas = gas_cost::COPY_PER_WORD * 2 + 3 * 2;
+    let needed_gas = gas_cost::PUSHN + gas_cost::PUSH0 * 2 + gas_cost::CALLDATACOPY + dynamic_gas;
+
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[test]
+fn calldatacopy_of_empty_range_does_not_expand_memory() {
+    let program = vec![
+        Operation::Push0,                                  // size
+        Operation::Push0,                                  // offset
+        Operation::Push((32, BigUint::from(1_u8) << 255)), // destination offset
+        Operation::CalldataCopy,
+    ];
+    let needed_gas = gas_cost::PUSH0 * 2 + gas_cost::PUSHN + gas_cost::CALLDATACOPY;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[rstest]
+#[case::calldatacopy(vec![Operation::CalldataCopy])]
+#[case::codecopy(vec![Operation::CodeCopy])]
+#[case::returndatacopy(vec![Operation::ReturnDataCopy])]
+#[case::extcodecopy(vec![Operation::Push0, Operation::ExtCodeCopy])]
+fn copy_with_the_largest_size_halts(#[case] copy: Vec<Operation>) {
+    // size + 31 would wrap around to a single word, and the size doesn't fit in 32 bits
+    let size = BigUint::from_bytes_be(&[0xff; 32]);
+    let mut program = vec![
+        Operation::Push((32, size)), // size
+        Operation::Push0,            // offset
+        Operation::Push0,            // destination offset
+    ];
+    program.extend(copy);
+
+    let result = run_program_get_result_with_gas(program, u64::MAX / 2);
+    assert!(result.is_halt());
+}
+
+#[test]
+fn calldatacopy_with_stack_underflow() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::CalldataCopy];
+    run_program_assert_halt(program);
+}
+
+#[test]
+fn codecopy_whole_program() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(9_u8))),
+        Operation::Push0,
+        Operation::Push0,
+        Operation::CodeCopy,
+        Operation::Push((1_u8, BigUint::from(9_u8))),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    let bytecode = Program::from(program.clone()).to_bytecode();
+    assert_eq!(bytecode.len(), 9);
+
+    run_program_assert_result(program, &bytecode);
+}
+
+#[test]
+fn codecopy_past_end_of_code_is_zero_padded() {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(32_u8))),
+        Operation::Push((1_u8, BigUint::from(4_u8))),
+        Operation::Push0,
+        Operation::CodeCopy,
+        Operation::Push((1_u8, BigUint::from(32_u8))),
+        Operation::Push0,
+        Operation::Return,
+    ];
+    let bytecode = Program::from(program.clone()).to_bytecode();
+    let mut expected = [0_u8; 32];
+    expected[..bytecode.len() - 4].copy_from_slice(&bytecode[4..]);
+
+    run_program_assert_result(program, &expected);
+}
+
+#[test]
+fn codecopy_with_stack_underflow() {
+    let program = vec![Operation::Push0, Operation::Push0, Operation::CodeCopy];
+    run_program_assert_halt(program);
+}
+
+#[test]
+fn returndatasize_without_calls_is_zero() {
+    let program = vec![Operation::ReturnDataSize];
+    run_program_assert_stack_top(program, BigUint::ZERO);
+}
+
+#[test]
+fn returndatacopy_empty() {
+    let program = vec![
+        Operation::Push0,
+        Operation::Push0,
+        Operation::Push0,
+        Operation::ReturnDataCopy,
+    ];
+    let needed_gas = gas_cost::PUSH0 * 3 + gas_cost::RETURNDATACOPY;
+    run_program_assert_gas_exact(program, needed_gas as _);
+}
+
+#[rstest]
+#[case::size_past_end(0, 1)]
+#[case::offset_past_end(1, 0)]
+fn returndatacopy_out_of_bounds(#[case] offset: u8, #[case] size: u8) {
+    let program = vec![
+        Operation::Push((1_u8, BigUint::from(size))),
+        Operation::Push((1_u8, BigUint::from(offset))),
+        Operation::Push0,
+        Operation::ReturnDataCopy,
+    ];
+    run_program_assert_halt(program);
+}
+
+#[test]
+fn returndatacopy_with_stack_underflow() {
+    let program = vec![
+        Operation::Push0,
+        Operation::Push0,
+        Operation::ReturnDataCopy,
+    ];
+    run_program_assert_halt(program);
+}
+
+#[test]
+fn invalid_halts() {
+    let program = vec![Operation::Push((1, 1_u8.into())), Operation::Invalid];
+    run_program_assert_halt(program);
+}
@@ -0,0 +1,229 @@
+//! Differential tests: every program runs both through this EVM and through `revm`,
+//! and both must agree on the outcome, the return data, the gas used and the logs.
+
+use evm_mlir::{
+    program::{Operation, Program},
+    syscall::ExecutionResult,
+    Env,
+};
+use num_bigint::BigUint;
+use revm::{
+    db::{CacheDB, EmptyDB},
+    primitives::{
+        AccountInfo, Address as RevmAddress, Bytecode, Bytes, ExecutionResult as RevmResult,
+        Output, SpecId, TransactTo,
+    },
+    Evm as Revm,
+};
+
+const GAS_LIMIT: u64 = 1_000_000;
+/// The intrinsic cost of a call without calldata, which revm charges and
+/// [`evm_mlir::execute`] doesn't
+const CALL_INTRINSIC_GAS: u64 = 21_000;
+const CONTRACT_ADDRESS: RevmAddress = RevmAddress::repeat_byte(0x10);
+const CALLER_ADDRESS: RevmAddress = RevmAddress::repeat_byte(0x20);
+
+/// A comparable summary of either EVM's result.
+#[derive(Debug, PartialEq)]
+enum Outcome {
+    Success {
+        return_data: Vec<u8>,
+        gas_used: u64,
+        logs: Vec<(Vec<[u8; 32]>, Vec<u8>)>,
+    },
+    Revert {
+        return_data: Vec<u8>,
+        gas_used: u64,
+    },
+    Halt,
+}
+
+fn run_evm_mlir(bytecode: &[u8]) -> Outcome {
+    let env = Env::builder().gas_limit(GAS_LIMIT).build();
+    let initial_gas = GAS_LIMIT - CALL_INTRINSIC_GAS;
+    let result = evm_mlir::execute(bytecode, env, initial_gas).expect("failed to compile program");
+
+    match result {
+        ExecutionResult::Success {
+            return_data,
+            gas_used,
+            logs,
+            ..
+        } => Outcome::Success {
+            return_data,
+            gas_used: gas_used + CALL_INTRINSIC_GAS,
+            logs: logs
+                .iter()
+                .map(|log| (log.topics_be_bytes(), log.data.clone()))
+                .collect(),
+        },
+        ExecutionResult::Revert {
+            return_data,
+            gas_used,
+            ..
+        } => Outcome::Revert {
+            return_data,
+            gas_used: gas_used + CALL_INTRINSIC_GAS,
+        },
+        ExecutionResult::Halt { .. } => Outcome::Halt,
+    }
+}
+
+fn run_revm(bytecode: &[u8]) -> Outcome {
+    let code = Bytecode::new_raw(Bytes::copy_from_slice(bytecode));
+    let mut db = CacheDB::new(EmptyDB::default());
+    db.insert_account_info(
+        CONTRACT_ADDRESS,
+        AccountInfo {
+            code_hash: code.hash_slow(),
+            code: Some(code),
+            ..Default::default()
+        },
+    );
+
+    let mut evm = Revm::builder()
+        .with_db(db)
+        .with_spec_id(SpecId::CANCUN)
+        .modify_tx_env(|tx| {
+            tx.caller = CALLER_ADDRESS;
+            tx.transact_to = TransactTo::Call(CONTRACT_ADDRESS);
+            tx.gas_limit = GAS_LIMIT;
+        })
+        .build();
+    let result = evm
+        .transact()
+        .expect("revm rejected the transaction")
+        .result;
+
+    match result {
+        RevmResult::Success {
+            output: Output::Call(return_data),
+            gas_used,
+            logs,
+            ..
+        } => Outcome::Success {
+            return_data: return_data.to_vec(),
+            gas_used,
+            logs: logs
+                .iter()
+                .map(|log| {
+                    let topics = log.data.topics().iter().map(|topic| topic.0).collect();
+                    (topics, log.data.data.to_vec())
+                })
+                .collect(),
+        },
+        RevmResult::Success { output, .. } => panic!("unexpected output for a call: {output:?}"),
+        RevmResult::Revert { output, gas_used } => Outcome::Revert {
+            return_data: output.to_vec(),
+            gas_used,
+        },
+        RevmResult::Halt { .. } => Outcome::Halt,
+    }
+}
+
+fn assert_same_outcome(operations: Vec<Operation>) {
+    let bytecode = Program::from(operations).to_bytecode();
+    assert_eq!(
+        run_evm_mlir(&bytecode),
+        run_revm(&bytecode),
+        "outcomes differ for bytecode {bytecode:02x?}"
+    );
+}
+
+/// Returns a program applying `operation` to `operands`, the first one being the
+/// topmost in the stack, and returning the result as a 32-byte word.
+fn operation_program(operation: Operation, operands: &[BigUint]) -> Vec<Operation> {
+    let mut program: Vec<_> = operands
+        .iter()
+        .rev()
+        .map(|operand| Operation::Push((32, operand.clone())))
+        .collect();
+    program.extend([
+        operation,
+        Operation::Push0,
+        Operation::Mstore,
+        Operation::Push((1, 32_u8.into())),
+        Operation::Push0,
+        Operation::Return,
+    ]);
+    program
+}
+
+/// Interesting 256-bit values: the edges of both the unsigned and the signed range.
+fn corpus_values() -> Vec<BigUint> {
+    let max = BigUint::from_bytes_be(&[0xff; 32]);
+    let int_min = BigUint::from(1_u8) << 255;
+    vec![
+        BigUint::ZERO,
+        BigUint::from(1_u8),
+        BigUint::from(2_u8),
+        BigUint::from(31_u8),
+        BigUint::from(0x7f_u8),
+        BigUint::from(0x80_u8),
+        BigUint::from(u64::MAX),
+        &int_min - 1_u8,
+        int_min,
+        &max - 1_u8,
+        max,
+    ]
+}
+
+#[test]
+fn binary_arithmetic_matches_revm() {
+    let operations = [
+        Operation::Add,
+        Operation::Mul,
+        Operation::Sub,
+        Operation::Div,
+        Operation::Sdiv,
+        Operation::Mod,
+        Operation::SMod,
+        Operation::Exp,
+        Operation::SignExtend,
+    ];
+    let values = corpus_values();
+
+    for operation in operations {
+        for a in &values {
+            for b in &values {
+                assert_same_outcome(operation_program(
+                    operation.clone(),
+                    &[a.clone(), b.clone()],
+                ));
+            }
+        }
+    }
+}
+
+#[test]
+fn ternary_arithmetic_matches_revm() {
+    // Every combination of the whole corpus would take too long to compile
+    let max = BigUint::from_bytes_be(&[0xff; 32]);
+    let values = [
+        BigUint::ZERO,
+        BigUint::from(1_u8),
+        BigUint::from(7_u8),
+        &max - 1_u8,
+        max,
+    ];
+
+    for operation in [Operation::Addmod, Operation::Mulmod] {
+        for a in &values {
+            for b in &values {
+                for den in &values {
+                    assert_same_outcome(operation_program(
+                        operation.clone(),
+                        &[a.clone(), b.clone(), den.clone()],
+                    ));
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn stack_underflow_matches_revm() {
+    for operation in [Operation::Add, Operation::Div, Operation::Addmod] {
+        assert_same_outcome(vec![operation]);
+    }
+}
@@ -9,13 +9,13 @@ fn snailtracer() {
     let program = Program::from_bytecode(SNAILTRACER_BYTECODE);
 
     let mut env = Env::default();
-    env.tx.calldata = vec![48, 98, 123, 124];
+    env.tx.calldata = vec![48, 98, 123, 124].into();
     env.tx.gas_limit = 999_999;
-    env.tx.from = Address([0; 20]);
-    env.tx.from.0[0] = 16;
+    env.tx.caller = Address([0; 20]);
+    env.tx.caller.0[0] = 16;
     env.tx.to = Address([0; 20]);
 
     let evm = Evm::new(env, program.expect("Error parsing opcodes"));
 
-    let _ = evm.transact();
+    let _ = evm.transact().unwrap();
 }